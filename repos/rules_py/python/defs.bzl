@@ -3,8 +3,10 @@
 
 load("@rules_rust//rust:defs.bzl", _rust_binary = "rust_binary")
 load("@rules_py//python/private:py_binary.bzl", _py_binary = "py_binary", _py_binary_skeleton = "py_binary_skeleton")
+load("@rules_py//python/private:frozen_modules.bzl", _py_frozen_modules = "py_frozen_modules")
 load("@rules_py//python/private:runtime.bzl", _python_runtime = "python_runtime")
 
+py_frozen_modules = _py_frozen_modules
 python_runtime = _python_runtime
 
 # TODO: Would it be useful to turn py_binary from only emitting a rust_binary to
@@ -69,7 +71,13 @@ python_runtime = _python_runtime
 #       bring along a Python runtime, and instead have a py_launcher rule that
 #       wraps the py_binary and does the rust stuff?
 
-def py_binary(name, runtime = None):
+def py_binary(
+        name,
+        runtime = None,
+        source_roots = None,
+        frozen_modules = None,
+        packed_resources = None,
+        app_packed_resources = None):
     # Generally speaking, the way most of the various Python rules in other
     # projects work is they will generate some kind of a wrapper script using
     # some kind of interpreted language (rules_python uses a non-hermetic
@@ -198,7 +206,32 @@ def py_binary(name, runtime = None):
     #
     # This generated project is also where all of the magic happens to setup the
     # Python interpreter with everything it needs to know to run this project.
-    _py_binary_skeleton(name = "%s._wrapper" % name)
+    #
+    # Resources in packed_resources (the standard library and other
+    # dependencies) and app_packed_resources (the application itself) are
+    # embedded and imported with oxidized_importer. If packed_resources is
+    # given, nothing is imported from the filesystem.
+    #
+    # In development mode (see //python:development_mode), the wrapper imports
+    # the application from the workspace source roots, which default to this
+    # package, instead of from app_packed_resources.
+    #
+    # If frozen_modules (see py_frozen_modules) is given, the modules in it are
+    # embedded and registered as frozen modules, so they can be imported before
+    # the interpreter can find anything on the filesystem.
+    _py_binary_skeleton(
+        name = "%s._wrapper" % name,
+        source_roots = source_roots if source_roots != None else [native.package_name()],
+        frozen_modules = frozen_modules,
+        packed_resources = packed_resources or [],
+        app_packed_resources = app_packed_resources or [],
+    )
+
+    native.filegroup(
+        name = "%s._embedded_data" % name,
+        srcs = [":%s._wrapper" % name],
+        output_group = "embedded_data",
+    )
 
     # Take our generated rust files for our binary wrapper, and feed them into
     # rust_binary to ultimately compile our binary.
@@ -206,6 +239,7 @@ def py_binary(name, runtime = None):
         name = "%s._bin" % name,
         crate_name = name,
         srcs = [":%s._wrapper" % name],
+        compile_data = [":%s._embedded_data" % name],
         edition = "2021",
         # TODO: We're hardcoding @python here, but it should come through the
         #       runtime instead.
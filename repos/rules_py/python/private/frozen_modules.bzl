@@ -0,0 +1,52 @@
+"""
+"""
+
+def _py_frozen_modules_impl(ctx):
+    output = ctx.actions.declare_file("%s.pyr" % ctx.label.name)
+
+    args = ctx.actions.args()
+    if not ctx.attr.default_modules:
+        args.add("--no-default-modules")
+    args.add_all(ctx.attr.modules, before_each = "--module")
+    args.add("--output", output)
+    args.add_all(ctx.files.srcs)
+
+    ctx.actions.run(
+        executable = ctx.executable._extract_frozen_modules,
+        arguments = [args],
+        inputs = ctx.files.srcs,
+        outputs = [output],
+        mnemonic = "PyFrozenModules",
+        progress_message = "Extracting frozen modules for %{label}",
+    )
+
+    return [DefaultInfo(files = depset([output]))]
+
+py_frozen_modules = rule(
+    implementation = _py_frozen_modules_impl,
+    doc = """Extracts the bytecode of modules CPython imports while it initializes.
+
+The output is packed resources data that a py_binary can register as frozen
+modules, so interpreter startup doesn't need the standard library on the
+filesystem.
+""",
+    attrs = {
+        "srcs": attr.label_list(
+            doc = "Packed resources files to extract module bytecode from, in order of preference",
+            allow_files = True,
+            mandatory = True,
+        ),
+        "default_modules": attr.bool(
+            doc = "Whether to extract the modules needed by a default interpreter configuration",
+            default = True,
+        ),
+        "modules": attr.string_list(
+            doc = "Additional modules to extract, e.g. codecs for non UTF-8 locales",
+        ),
+        "_extract_frozen_modules": attr.label(
+            default = "@rules_py//third_party/crates:python-packed-resources__extract-frozen-modules",
+            executable = True,
+            cfg = "exec",
+        ),
+    },
+)
@@ -2,13 +2,44 @@
 """
 
 load("@bazel_skylib//lib:paths.bzl", "paths")
+load("@bazel_skylib//rules:common_settings.bzl", "BuildSettingInfo")
 load("@aspect_bazel_lib//lib:paths.bzl", "relative_file", "to_repository_relative_path")
 load("@aspect_bazel_lib//lib:copy_file.bzl", "copy_file_action")
 load("//python/private:runtime.bzl", "PythonRuntimeInfo")
 
+def _embed(ctx, src, name, embedded_data):
+    """Copies src next to the wrapper sources, returning the expression embedding it."""
+    dest = ctx.actions.declare_file(paths.join(ctx.attr.name, "src", name))
+    copy_file_action(ctx, src, dest)
+    embedded_data.append(dest)
+    return 'include_bytes!("%s")' % name
+
 def _py_binary_skeleton_impl(ctx):
     outfiles = []
 
+    substitutions = {
+        "{DEVELOPMENT_MODE}": "true" if ctx.attr._development_mode[BuildSettingInfo].value else "false",
+        "{SOURCE_ROOTS}": ", ".join(['"%s"' % root for root in ctx.attr.source_roots]),
+        "{WORKSPACE_NAME}": ctx.workspace_name,
+        "{FROZEN_MODULES}": "None",
+    }
+
+    # Embedded data lives in the binary next to the wrapper sources, in its own
+    # output group since rust_binary only accepts .rs srcs.
+    embedded_data = []
+
+    if ctx.file.frozen_modules:
+        substitutions["{FROZEN_MODULES}"] = "Some(%s)" % _embed(ctx, ctx.file.frozen_modules, "frozen_modules.pyr", embedded_data)
+
+    substitutions["{PACKED_RESOURCES}"] = ", ".join([
+        _embed(ctx, src, "packed_resources_%d.pyr" % i, embedded_data)
+        for i, src in enumerate(ctx.files.packed_resources)
+    ])
+    substitutions["{APP_PACKED_RESOURCES}"] = ", ".join([
+        _embed(ctx, src, "app_packed_resources_%d.pyr" % i, embedded_data)
+        for i, src in enumerate(ctx.files.app_packed_resources)
+    ])
+
     for file in ctx.files._wrapper_skel:
         # Determine the relative path of our file, which is a little convulted because we
         # bury it a level deeper than expected.
@@ -24,18 +55,38 @@ def _py_binary_skeleton_impl(ctx):
         ctx.actions.expand_template(
             template = file,
             output = ofile,
-            substitutions = {},
+            substitutions = substitutions,
         )
 
         outfiles.append(ofile)
 
     return [
         DefaultInfo(files = depset(outfiles)),
+        OutputGroupInfo(embedded_data = depset(embedded_data)),
     ]
 
 py_binary_skeleton = rule(
     implementation = _py_binary_skeleton_impl,
     attrs = {
+        "app_packed_resources": attr.label_list(
+            doc = "Packed resources files holding the application, which development mode doesn't load",
+            allow_files = True,
+        ),
+        "frozen_modules": attr.label(
+            doc = "Packed resources data holding modules to register as frozen modules, see py_frozen_modules",
+            allow_single_file = True,
+        ),
+        "packed_resources": attr.label_list(
+            doc = "Packed resources files holding the standard library and other dependencies",
+            allow_files = True,
+        ),
+        "source_roots": attr.string_list(
+            doc = "Workspace relative directories to import from in development mode",
+        ),
+        "_development_mode": attr.label(
+            default = "@rules_py//python:development_mode",
+            providers = [BuildSettingInfo],
+        ),
         "_wrapper_skel": attr.label(
             default = "@rules_py//python/private/wrapper-skel",
             doc = "The skeleton of template files to use to generate the wrapper files",
@@ -1,12 +1,100 @@
 use pyembed::{
-    MainPythonInterpreter, OxidizedPythonInterpreterConfig, PythonInterpreterConfig,
-    PythonInterpreterProfile,
+    MainPythonInterpreter, OxidizedPythonInterpreterConfig, PackedResourcesSource,
+    PythonInterpreterConfig, PythonInterpreterProfile,
 };
+use std::path::PathBuf;
+
+// The following values are substituted when the wrapper is generated.
+
+/// Whether development mode was enabled at build time with
+/// `--//python:development_mode`.
+const DEVELOPMENT_MODE: bool = {DEVELOPMENT_MODE};
+
+/// Workspace relative directories holding the application's sources.
+const SOURCE_ROOTS: &[&str] = &[{SOURCE_ROOTS}];
+
+/// Name of the workspace the sources live in, used to find them in runfiles.
+const WORKSPACE_NAME: &str = "{WORKSPACE_NAME}";
+
+/// Packed resources data holding modules to register as frozen modules.
+///
+/// These are the modules CPython imports while it initializes, so startup
+/// doesn't depend on the standard library being on the filesystem.
+const FROZEN_MODULES: Option<&[u8]> = {FROZEN_MODULES};
+
+/// Packed resources data holding the standard library and other dependencies.
+const PACKED_RESOURCES: &[&[u8]] = &[{PACKED_RESOURCES}];
+
+/// Packed resources data holding the application.
+///
+/// Development mode imports the application from its sources instead.
+const APP_PACKED_RESOURCES: &[&[u8]] = &[{APP_PACKED_RESOURCES}];
+
+/// Environment variable enabling development mode at run time.
+///
+/// This is read before the interpreter is configured, so it works even though
+/// the interpreter is isolated from the environment.
+const DEVELOPMENT_MODE_ENV: &str = "RULES_PY_DEVELOPMENT_MODE";
+
+/// Environment variable holding the path to write a startup profile to.
+///
+/// Paths ending in `.json` receive a JSON report, everything else receives
+/// folded stacks suitable for flamegraph tools.
+const PROFILE_ENV: &str = "RULES_PY_PROFILE";
+
+/// Environment variable holding a directory to write loaded modules manifests to.
+///
+/// Manifests from representative runs can be used to trim unused modules from
+/// the packaged application.
+const MODULES_MANIFEST_DIR_ENV: &str = "RULES_PY_MODULES_MANIFEST_DIR";
+
+fn development_mode() -> bool {
+    DEVELOPMENT_MODE
+        || matches!(
+            std::env::var(DEVELOPMENT_MODE_ENV).as_deref(),
+            Ok("1") | Ok("true")
+        )
+}
+
+/// Resolve the paths to import application code from in development mode.
+///
+/// `bazel run` tells us where the workspace is, so we import from the sources
+/// themselves. Otherwise (e.g. under `bazel test`) we fall back to the runfiles,
+/// which are symlinks back to the sources.
+fn development_module_search_paths() -> Vec<PathBuf> {
+    let workspace = std::env::var_os("BUILD_WORKSPACE_DIRECTORY")
+        .filter(|x| !x.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("$RUNFILES").join(WORKSPACE_NAME));
+
+    SOURCE_ROOTS
+        .iter()
+        .map(|root| {
+            if root.is_empty() {
+                workspace.clone()
+            } else {
+                workspace.join(root)
+            }
+        })
+        .collect()
+}
 
 fn main() {
     // The following code is in a block so the MainPythonInterpreter is destroyed in an
     // orderly manner, before process exit.
     let exit_code = {
+        let development_mode = development_mode();
+
+        let packed_resources = PACKED_RESOURCES
+            .iter()
+            .chain(if development_mode {
+                [].iter()
+            } else {
+                APP_PACKED_RESOURCES.iter()
+            })
+            .map(|data| PackedResourcesSource::Memory(data))
+            .collect::<Vec<_>>();
+
         // Load the Python configuration
         let config = OxidizedPythonInterpreterConfig {
             interpreter_config: PythonInterpreterConfig {
@@ -17,8 +105,14 @@ fn main() {
                 // Equivilant to the -I flag
                 isolated: Some(true),
                 // We want to explicitly control our sys.path, not let Python make
-                // it's own decisions about what it should be.
-                module_search_paths: Some(vec![]),
+                // it's own decisions about what it should be. In development mode
+                // that is the workspace sources, so edits take effect without a
+                // rebuild.
+                module_search_paths: Some(if development_mode {
+                    development_module_search_paths()
+                } else {
+                    vec![]
+                }),
                 // I think we don't actually need the site module, since we've
                 // already correctly setup the Python interpreter. Not importing it
                 // should also speed things up.
@@ -32,9 +126,18 @@ fn main() {
                 ..Default::default()
             },
 
-            // We bundle at least the standard library (if not the entire app)
-            // using OxidizedImporter, so we'll need to turn it on.
-            // oxidized_importer: true,
+            // When we bundle the standard library (if not the entire app), we
+            // import it using OxidizedImporter, so we'll need to turn it on.
+            oxidized_importer: !packed_resources.is_empty(),
+
+            // Development mode imports the application from the filesystem, as
+            // does a binary not bundling the standard library. Otherwise
+            // everything is imported from memory.
+            filesystem_importer: development_mode || PACKED_RESOURCES.is_empty(),
+            packed_resources,
+            frozen_modules: FROZEN_MODULES,
+            profile_startup_env: Some(PROFILE_ENV.to_string()),
+            write_modules_manifest_directory_env: Some(MODULES_MANIFEST_DIR_ENV.to_string()),
             ..Default::default()
         };
 
@@ -294,6 +294,7 @@ _NORMAL_DEPENDENCIES = {
     "": {
         _COMMON_CONDITION: {
             "pyembed": "//third_party/crates/pyembed-0.24.0:pyembed",
+            "python-packaging": "//third_party/crates/python-packaging-0.16.0:python_packaging",
             "python-packed-resources": "//third_party/crates/python-packed-resources-0.12.0:python_packed_resources",
         },
     },
@@ -5,17 +5,22 @@
 //! Data structures for configuring a Python interpreter.
 
 use {
-    crate::NewInterpreterError,
-    oxidized_importer::{PackedResourcesSource, PythonResourcesState},
+    crate::{runfiles::Runfiles, NewInterpreterError},
+    once_cell::unsync::OnceCell,
+    oxidized_importer::{PackedResourcesLayer, PackedResourcesSource, PythonResourcesState},
     pyo3::ffi as pyffi,
-    python_packaging::interpreter::{
-        MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterConfig,
-        PythonInterpreterProfile, TerminfoResolution,
+    python_packaging::{
+        interpreter::{
+            MemoryAllocatorBackend, MultiprocessingStartMethod, PythonInterpreterConfig,
+            PythonInterpreterProfile, TerminfoResolution,
+        },
+        module_util::glob_matches,
     },
     std::{
+        collections::HashSet,
         ffi::{CString, OsString},
         ops::Deref,
-        path::PathBuf,
+        path::{Component, Path, PathBuf},
     },
 };
 
@@ -106,8 +111,8 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// [PythonInterpreterConfig::profile] always set to [PythonInterpreterProfile::Python].
     ///
     /// [Self::resolve()] behavior: most fields are copied verbatim.
-    /// [PythonInterpreterConfig::module_search_paths] entries have the special token
-    /// `$ORIGIN` expanded to the resolved value of [Self::origin].
+    /// [PythonInterpreterConfig::module_search_paths] entries have path tokens
+    /// expanded. See [Self::resolve()] for the supported tokens.
     pub interpreter_config: PythonInterpreterConfig,
 
     /// Memory allocator backend to use.
@@ -283,13 +288,117 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// Default value: `vec![]`
     ///
     /// [Self::resolve()] behavior: [PackedResourcesSource::MemoryMappedPath] members
-    /// have the special string `$ORIGIN` expanded to the string value that
-    /// [Self::origin] resolves to.
+    /// have path tokens expanded. See [Self::resolve()] for the supported tokens.
     ///
     /// This field is ignored during serialization.
     #[cfg_attr(feature = "serialization", serde(skip))]
     pub packed_resources: Vec<PackedResourcesSource<'a>>,
 
+    /// Packed resources data layers having explicit priorities.
+    ///
+    /// Layers are loaded after [Self::packed_resources], which behave as required
+    /// layers having priority `0`. When multiple layers define a resource, the
+    /// definition from the layer with the highest priority wins. e.g. an optional
+    /// layer at `$ORIGIN/overlay.pyr` with priority `1` can override modules
+    /// embedded in the executable without rebuilding it.
+    ///
+    /// This field has no effect if `oxidized_importer=false`.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// [Self::resolve()] behavior: [PackedResourcesSource::MemoryMappedPath] sources
+    /// have path tokens expanded. See [Self::resolve()] for the supported tokens.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub packed_resources_layers: Vec<PackedResourcesLayer<'a>>,
+
+    /// Whether to report shadowed resources during interpreter initialization.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true] and [Self::oxidized_importer]
+    /// is [true], every resource definition shadowed by another packed resources
+    /// source or layer is printed to stderr.
+    pub report_shadowed_resources: bool,
+
+    /// Glob patterns of packed resources files to load as plugins.
+    ///
+    /// Wildcards (`*` and `?`) may only appear in the final path component.
+    /// e.g. `$ORIGIN/plugins/*.pyr`. [Self::resolve()] fails for patterns
+    /// having them elsewhere.
+    ///
+    /// Plugins are loaded after [Self::packed_resources] and before
+    /// [Self::packed_resources_layers], in the order they are resolved to.
+    /// A plugin that fails to load is reported on stderr and skipped.
+    ///
+    /// This field has no effect if `oxidized_importer=false`.
+    ///
+    /// Default value: `vec![]`
+    ///
+    /// [Self::resolve()] behavior: path tokens are expanded as described by
+    /// [Self::resolve()] and patterns are replaced by the paths of files they
+    /// match, sorted by path within each pattern. Paths matched by multiple patterns
+    /// are retained once. Paths whose file names aren't allowed by
+    /// [Self::packed_resources_plugins_allow] and [Self::packed_resources_plugins_deny]
+    /// are removed.
+    pub packed_resources_plugins: Vec<PathBuf>,
+
+    /// File name glob patterns of plugins that may be loaded.
+    ///
+    /// If [Some], only plugins whose file names match at least one pattern
+    /// are loaded.
+    ///
+    /// Default value: [None]
+    pub packed_resources_plugins_allow: Option<Vec<String>>,
+
+    /// File name glob patterns of plugins that may not be loaded.
+    ///
+    /// Takes precedence over [Self::packed_resources_plugins_allow].
+    ///
+    /// Default value: `vec![]`
+    pub packed_resources_plugins_deny: Vec<String>,
+
+    /// Whether modules imported from memory get a virtual `__file__`.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true] and [Self::oxidized_importer]
+    /// is [true], source and bytecode modules imported from memory have `__file__`
+    /// set to a path under the current executable, e.g. `/usr/bin/myapp/foo/bar.py`.
+    /// No file exists at that path. But paths derived from it, like
+    /// `os.path.join(os.path.dirname(__file__), "data.json")`, can be read via
+    /// the loader's `get_data()` if they refer to package resources.
+    pub virtual_file_paths: bool,
+
+    /// Whether `open()` serves files embedded in the executable.
+    ///
+    /// Default value: [false]
+    ///
+    /// Interpreter initialization behavior: if [true] and [Self::oxidized_importer]
+    /// is [true], `builtins.open()` and `io.open()` are replaced by a wrapper
+    /// serving read-only opens of paths under the current executable. Embedded
+    /// `File` resources, in-memory module source and in-memory package resources
+    /// are served. All other opens go to the real filesystem. This complements
+    /// [Self::virtual_file_paths] for code opening files next to its modules.
+    pub virtual_filesystem: bool,
+
+    /// Directory to reuse resources materialized to the filesystem from.
+    ///
+    /// In-memory resources are written to files when a filesystem path to them
+    /// is requested, e.g. via `importlib.resources.as_file()`.
+    ///
+    /// Default value: [None]
+    ///
+    /// [Self::resolve()] behavior: path tokens are expanded. See [Self::resolve()]
+    /// for the supported tokens.
+    ///
+    /// Interpreter initialization behavior: if [None], resources are written to
+    /// a temporary directory which is removed when the interpreter exits. If set,
+    /// resources are written to a location under this directory derived from a
+    /// hash of their content. These files are kept and reused by later runs.
+    pub resources_cache_dir: Option<PathBuf>,
+
     /// Extra extension modules to make available to the interpreter.
     ///
     /// The values will effectively be passed to ``PyImport_ExtendInitTab()``.
@@ -304,6 +413,28 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     #[cfg_attr(feature = "serialization", serde(skip))]
     pub extra_extension_modules: Option<Vec<ExtensionModule>>,
 
+    /// Packed resources data holding modules to register as frozen modules.
+    ///
+    /// Frozen modules are importable as soon as interpreter initialization
+    /// begins, before the custom meta path importer is installed. Registering the
+    /// modules CPython imports during initialization, like `encodings`, allows
+    /// startup without a standard library on the filesystem. The
+    /// `extract-frozen-modules` tool of the `python-packed-resources` crate
+    /// produces such data from packed resources.
+    ///
+    /// Only modules having in-memory bytecode are registered.
+    ///
+    /// Default value: [None]
+    ///
+    /// Interpreter initialization behavior: `PyImport_FrozenModules` will be
+    /// replaced by an array holding the modules in this data followed by the
+    /// interpreter's original frozen modules. The modules in this data take
+    /// precedence.
+    ///
+    /// This field is ignored during serialization.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    pub frozen_modules: Option<&'a [u8]>,
+
     /// Command line arguments to initialize `sys.argv` with.
     ///
     /// Default value: [None]
@@ -343,6 +474,21 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     /// will automatically call into the `multiprocessing` module instead of running
     /// the configured code.
     ///
+    /// The following invocations are recognized:
+    ///
+    /// * `--multiprocessing-fork [key=value]...`, used by the `spawn` start method
+    ///   when `sys.frozen` is set.
+    /// * `-c "from multiprocessing.spawn import spawn_main; ..." --multiprocessing-fork`,
+    ///   used by the `spawn` start method otherwise.
+    /// * `-c "from multiprocessing.forkserver import main; ..."`, used by the
+    ///   `forkserver` start method. Modules requested via
+    ///   `multiprocessing.set_forkserver_preload()` are imported by the server.
+    /// * `-c "from multiprocessing.resource_tracker import main; ..."`, used by the
+    ///   `spawn` and `forkserver` start methods.
+    ///
+    /// Interpreter flags preceding `-c` are ignored. Arguments to the called
+    /// function must be Python literals.
+    ///
     /// Enabling this has the same effect as calling `multiprocessing.freeze_support()`
     /// in your application code's `__main__` and replaces the need to do so.
     ///
@@ -402,8 +548,8 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     ///
     /// Default value: [None]
     ///
-    /// [Self::resolve()] behavior: the token `$ORIGIN` is expanded to the
-    /// resolved value of [Self::origin].
+    /// [Self::resolve()] behavior: path tokens are expanded. See [Self::resolve()]
+    /// for the supported tokens.
     ///
     /// Interpreter initialization behavior: if set, the `TCL_LIBRARY` environment
     /// variable will be set for the current process.
@@ -421,6 +567,43 @@ pub struct OxidizedPythonInterpreterConfig<'a> {
     ///
     /// Default value: [None]
     pub write_modules_directory_env: Option<String>,
+
+    /// Environment variable holding the directory to write a loaded modules manifest.
+    ///
+    /// If this value is set and the environment variable it refers to is set,
+    /// [Self::oxidized_importer] records the modules it imports and the package
+    /// resources read through its resource readers. On interpreter shutdown,
+    /// a `modules-<random>.manifest` file is written to the directory specified
+    /// holding, for each module in import order, the module name, whether it
+    /// came from memory or a path relative to the origin, the packed resources
+    /// layer it was indexed from, and whether it was imported during interpreter
+    /// startup. The package resources that were read follow.
+    ///
+    /// Unlike [Self::write_modules_directory_env], the manifest is also written
+    /// if the interpreter was finalized by running it.
+    ///
+    /// Manifests can be consumed by `PythonResourceCollector::retain_loaded_modules()`
+    /// to remove modules an application doesn't use from packaged resources.
+    ///
+    /// Default value: [None]
+    pub write_modules_manifest_directory_env: Option<String>,
+
+    /// Environment variable holding the path to write a startup profile to.
+    ///
+    /// If this value is set and the environment variable it refers to is set
+    /// to a non-empty value, the duration of each phase of interpreter
+    /// initialization is recorded. If [Self::oxidized_importer] is enabled,
+    /// the time `OxidizedFinder` spends finding, loading, and executing each
+    /// module is recorded as well, along with whether the module came from
+    /// memory, a path relative to the origin, or was built-in or frozen.
+    ///
+    /// The report is written when the interpreter is finalized. If the path ends
+    /// in `.json`, the report is a JSON document. Otherwise it is in the
+    /// "folded stacks" format accepted by flamegraph tools like
+    /// `inferno-flamegraph`. Durations are in microseconds.
+    ///
+    /// Default value: [None]
+    pub profile_startup_env: Option<String>,
 }
 
 impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
@@ -444,7 +627,16 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             oxidized_importer: false,
             filesystem_importer: true,
             packed_resources: vec![],
+            packed_resources_layers: vec![],
+            report_shadowed_resources: false,
+            packed_resources_plugins: vec![],
+            packed_resources_plugins_allow: None,
+            packed_resources_plugins_deny: vec![],
+            virtual_file_paths: false,
+            virtual_filesystem: false,
+            resources_cache_dir: None,
             extra_extension_modules: None,
+            frozen_modules: None,
             argv: None,
             argvb: false,
             multiprocessing_auto_dispatch: true,
@@ -454,12 +646,38 @@ impl<'a> Default for OxidizedPythonInterpreterConfig<'a> {
             terminfo_resolution: TerminfoResolution::Dynamic,
             tcl_library: None,
             write_modules_directory_env: None,
+            write_modules_manifest_directory_env: None,
+            profile_startup_env: None,
         }
     }
 }
 
 impl<'a> OxidizedPythonInterpreterConfig<'a> {
     /// Create a new type with all values resolved.
+    ///
+    /// Paths in fields documenting it have the following tokens expanded:
+    ///
+    /// `$ORIGIN`
+    ///    The resolved value of [Self::origin].
+    ///
+    /// `$EXE_DIR`
+    ///    The directory of the resolved value of [Self::exe]. Unlike `$ORIGIN`,
+    ///    this can't be overridden.
+    ///
+    /// `$CACHE_DIR`
+    ///    The current user's cache directory. `%LOCALAPPDATA%` on Windows,
+    ///    `$HOME/Library/Caches` on macOS, and `$XDG_CACHE_HOME` or `$HOME/.cache`
+    ///    elsewhere.
+    ///
+    /// `$RUNFILES`
+    ///    Only recognized at the beginning of a path. The remainder of the path is
+    ///    resolved in the Bazel runfiles tree of the executable. The runfiles are
+    ///    found via the `RUNFILES_MANIFEST_FILE` or `RUNFILES_DIR` environment
+    ///    variables or a `.runfiles_manifest` file or `.runfiles` directory next to
+    ///    the executable. When a manifest is used, the path must be a file listed
+    ///    in it or be within a directory listed in it.
+    ///
+    /// An error is returned if a used token can't be resolved.
     pub fn resolve(
         self,
     ) -> Result<ResolvedOxidizedPythonInterpreterConfig<'a>, NewInterpreterError> {
@@ -478,6 +696,10 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
                 .map_err(|_| NewInterpreterError::Simple("could not obtain current executable"))?
         };
 
+        // Runfiles are discovered next to the executable as invoked, which may be
+        // a symlink into a runfiles tree.
+        let raw_exe = exe.clone();
+
         // We always canonicalize the current executable because we use path
         // comparisons in the path hooks importer to assess whether a given sys.path
         // entry is this executable.
@@ -494,20 +716,51 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
                 .to_path_buf()
         };
 
-        let origin_string = origin.display().to_string();
+        let tokens = PathTokens {
+            origin: origin.display().to_string(),
+            exe_dir: exe
+                .parent()
+                .map(|p| p.display().to_string())
+                .unwrap_or_default(),
+            exes: [raw_exe, exe.clone()],
+            runfiles: OnceCell::new(),
+        };
 
-        let packed_resources = self
-            .packed_resources
-            .into_iter()
-            .map(|entry| match entry {
+        let resolve_source = |entry| -> Result<_, NewInterpreterError> {
+            Ok(match entry {
                 PackedResourcesSource::Memory(_) => entry,
                 PackedResourcesSource::MemoryMappedPath(p) => {
-                    PackedResourcesSource::MemoryMappedPath(PathBuf::from(
-                        p.display().to_string().replace("$ORIGIN", &origin_string),
-                    ))
+                    PackedResourcesSource::MemoryMappedPath(tokens.expand(&p)?)
                 }
             })
-            .collect::<Vec<_>>();
+        };
+
+        let packed_resources = self
+            .packed_resources
+            .into_iter()
+            .map(resolve_source)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let packed_resources_layers = self
+            .packed_resources_layers
+            .into_iter()
+            .map(|layer| {
+                Ok(PackedResourcesLayer {
+                    source: resolve_source(layer.source)?,
+                    ..layer
+                })
+            })
+            .collect::<Result<Vec<_>, NewInterpreterError>>()?;
+
+        let packed_resources_plugins = resolve_plugin_paths(
+            &self
+                .packed_resources_plugins
+                .iter()
+                .map(|p| tokens.expand(p))
+                .collect::<Result<Vec<_>, _>>()?,
+            self.packed_resources_plugins_allow.as_deref(),
+            &self.packed_resources_plugins_deny,
+        )?;
 
         let module_search_paths = self
             .interpreter_config
@@ -515,16 +768,22 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
             .as_ref()
             .map(|x| {
                 x.iter()
-                    .map(|p| {
-                        PathBuf::from(p.display().to_string().replace("$ORIGIN", &origin_string))
-                    })
-                    .collect::<Vec<_>>()
-            });
+                    .map(|p| tokens.expand(p))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
 
         let tcl_library = self
             .tcl_library
             .as_ref()
-            .map(|x| PathBuf::from(x.display().to_string().replace("$ORIGIN", &origin_string)));
+            .map(|x| tokens.expand(x))
+            .transpose()?;
+
+        let resources_cache_dir = self
+            .resources_cache_dir
+            .as_ref()
+            .map(|x| tokens.expand(x))
+            .transpose()?;
 
         Ok(ResolvedOxidizedPythonInterpreterConfig {
             inner: Self {
@@ -536,13 +795,188 @@ impl<'a> OxidizedPythonInterpreterConfig<'a> {
                 },
                 argv,
                 packed_resources,
+                packed_resources_layers,
+                packed_resources_plugins,
                 tcl_library,
+                resources_cache_dir,
                 ..self
             },
         })
     }
 }
 
+/// Expands tokens in paths as documented by [OxidizedPythonInterpreterConfig::resolve()].
+struct PathTokens {
+    origin: String,
+    exe_dir: String,
+    /// Executable paths to discover runfiles next to, in order of preference.
+    exes: [PathBuf; 2],
+    runfiles: OnceCell<Option<Runfiles>>,
+}
+
+impl PathTokens {
+    fn runfiles(&self) -> Result<&Runfiles, NewInterpreterError> {
+        self.runfiles
+            .get_or_try_init(|| {
+                for exe in &self.exes {
+                    if let Some(runfiles) = Runfiles::discover(
+                        exe,
+                        std::env::var_os("RUNFILES_MANIFEST_FILE"),
+                        std::env::var_os("RUNFILES_DIR"),
+                    )? {
+                        return Ok(Some(runfiles));
+                    }
+                }
+
+                Ok(None)
+            })
+            .map_err(NewInterpreterError::Dynamic)?
+            .as_ref()
+            .ok_or(NewInterpreterError::Simple(
+                "$RUNFILES is used but no runfiles could be found",
+            ))
+    }
+
+    fn expand(&self, path: &Path) -> Result<PathBuf, NewInterpreterError> {
+        let mut value = path
+            .display()
+            .to_string()
+            .replace("$ORIGIN", &self.origin)
+            .replace("$EXE_DIR", &self.exe_dir);
+
+        if value.contains("$CACHE_DIR") {
+            let cache_dir = user_cache_dir().ok_or(NewInterpreterError::Simple(
+                "$CACHE_DIR is used but the user cache directory could not be determined",
+            ))?;
+
+            value = value.replace("$CACHE_DIR", &cache_dir.display().to_string());
+        }
+
+        if let Some(rest) = value.strip_prefix("$RUNFILES") {
+            if rest.is_empty() || rest.starts_with(['/', '\\']) {
+                let relative = rest.trim_start_matches(['/', '\\']).replace('\\', "/");
+
+                return match self.runfiles()? {
+                    Runfiles::Directory(directory) if relative.is_empty() => Ok(directory.clone()),
+                    runfiles => runfiles.rlocation(&relative).ok_or_else(|| {
+                        NewInterpreterError::Dynamic(format!(
+                            "unable to locate {} in runfiles manifest",
+                            relative
+                        ))
+                    }),
+                };
+            }
+        }
+
+        Ok(PathBuf::from(value))
+    }
+}
+
+/// Resolve the directory holding the current user's cached data.
+fn user_cache_dir() -> Option<PathBuf> {
+    let non_empty = |key| std::env::var_os(key).filter(|x| !x.is_empty());
+
+    if cfg!(target_family = "windows") {
+        non_empty("LOCALAPPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| PathBuf::from(home).join("Library").join("Caches"))
+    } else {
+        non_empty("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|p| p.is_absolute())
+            .or_else(|| non_empty("HOME").map(|home| PathBuf::from(home).join(".cache")))
+    }
+}
+
+/// Expand plugin glob patterns to paths of files, applying allow and deny lists.
+fn resolve_plugin_paths(
+    patterns: &[PathBuf],
+    allow: Option<&[String]>,
+    deny: &[String],
+) -> Result<Vec<PathBuf>, NewInterpreterError> {
+    let mut paths = vec![];
+
+    for pattern in patterns {
+        let file_pattern = pattern
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let dir = pattern.parent().unwrap_or_else(|| Path::new(""));
+
+        // Verbatim path prefixes on Windows contain `?`, so only look at names.
+        if dir.components().any(|component| match component {
+            Component::Normal(name) => name.to_string_lossy().contains(['*', '?']),
+            _ => false,
+        }) {
+            return Err(NewInterpreterError::Dynamic(format!(
+                "packed resources plugin pattern {} has wildcards outside its final path component",
+                pattern.display()
+            )));
+        }
+
+        if !file_pattern.contains(['*', '?']) {
+            paths.push(pattern.clone());
+            continue;
+        }
+
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(NewInterpreterError::Dynamic(format!(
+                    "error reading plugins directory {}: {}",
+                    dir.display(),
+                    e
+                )))
+            }
+        };
+
+        let mut matches = vec![];
+        for entry in entries {
+            let entry = entry.map_err(|e| {
+                NewInterpreterError::Dynamic(format!(
+                    "error reading plugins directory {}: {}",
+                    dir.display(),
+                    e
+                ))
+            })?;
+
+            if glob_matches(&file_pattern, &entry.file_name().to_string_lossy())
+                && entry.path().is_file()
+            {
+                matches.push(pattern.with_file_name(entry.file_name()));
+            }
+        }
+
+        matches.sort();
+        paths.extend(matches);
+    }
+
+    let mut seen = HashSet::new();
+
+    Ok(paths
+        .into_iter()
+        .filter(|path| seen.insert(path.clone()))
+        .filter(|path| {
+            let name = path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            allow.map_or(true, |allow| {
+                allow.iter().any(|pattern| glob_matches(pattern, &name))
+            }) && !deny.iter().any(|pattern| glob_matches(pattern, &name))
+        })
+        .collect())
+}
+
 /// An `OxidizedPythonInterpreterConfig` that has fields resolved.
 pub struct ResolvedOxidizedPythonInterpreterConfig<'a> {
     inner: OxidizedPythonInterpreterConfig<'a>,
@@ -620,7 +1054,7 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
                 PackedResourcesSource::Memory(data) => {
                     state
                         .index_data(data)
-                        .map_err(NewInterpreterError::Simple)?;
+                        .map_err(NewInterpreterError::Dynamic)?;
                 }
                 PackedResourcesSource::MemoryMappedPath(path) => {
                     state
@@ -630,6 +1064,22 @@ impl<'a, 'config: 'a> TryFrom<&ResolvedOxidizedPythonInterpreterConfig<'config>>
             }
         }
 
+        for path in &config.packed_resources_plugins {
+            if let Err(e) = state.index_path_memory_mapped(path) {
+                eprintln!(
+                    "error loading packed resources plugin {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+
+        for layer in &config.packed_resources_layers {
+            state
+                .index_layer(layer)
+                .map_err(NewInterpreterError::Dynamic)?;
+        }
+
         state
             .index_interpreter_builtins()
             .map_err(NewInterpreterError::Simple)?;
@@ -663,6 +1113,145 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_packed_resources_layers_origin() -> Result<()> {
+        let mut config = OxidizedPythonInterpreterConfig {
+            origin: Some(PathBuf::from("/other/origin")),
+            ..Default::default()
+        };
+
+        config.packed_resources_layers.push(PackedResourcesLayer {
+            name: "overlay".to_string(),
+            priority: 1,
+            optional: true,
+            ..PackedResourcesLayer::new(PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                "$ORIGIN/overlay.pyr",
+            )))
+        });
+
+        let resolved = config.resolve()?;
+
+        assert_eq!(
+            resolved.packed_resources_layers,
+            vec![PackedResourcesLayer {
+                name: "overlay".to_string(),
+                source: PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                    "/other/origin/overlay.pyr"
+                )),
+                priority: 1,
+                optional: true,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_path_tokens() -> Result<()> {
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(PathBuf::from("/other/origin")),
+            tcl_library: Some(PathBuf::from("$EXE_DIR/tcl8.6")),
+            resources_cache_dir: Some(PathBuf::from("$ORIGIN/cache")),
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.tcl_library,
+            Some(resolved.exe().parent().unwrap().join("tcl8.6"))
+        );
+        assert_eq!(
+            resolved.resources_cache_dir,
+            Some(PathBuf::from("/other/origin/cache"))
+        );
+
+        if let Some(cache_dir) = user_cache_dir() {
+            let mut config = OxidizedPythonInterpreterConfig::default();
+            config
+                .packed_resources
+                .push(PackedResourcesSource::MemoryMappedPath(PathBuf::from(
+                    "$CACHE_DIR/app/resources",
+                )));
+
+            let resolved = config.resolve()?;
+            assert_eq!(
+                resolved.packed_resources,
+                vec![PackedResourcesSource::MemoryMappedPath(
+                    cache_dir.join("app/resources")
+                )]
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_resources_plugins() -> Result<()> {
+        let origin = std::env::temp_dir().join(format!(
+            "pyembed-test-packed-resources-plugins-{}",
+            std::process::id()
+        ));
+        let plugins = origin.join("plugins");
+        std::fs::create_dir_all(plugins.join("dir.pyr"))?;
+
+        for name in ["b.pyr", "a.pyr", "c.pyr", "denied.pyr", "other.txt"] {
+            std::fs::write(plugins.join(name), b"")?;
+        }
+
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(origin.clone()),
+            packed_resources_plugins: vec![
+                PathBuf::from("$ORIGIN/plugins/c.pyr"),
+                PathBuf::from("$ORIGIN/plugins/*.pyr"),
+                PathBuf::from("$ORIGIN/plugins/*.txt"),
+                PathBuf::from("$ORIGIN/missing/*.pyr"),
+            ],
+            packed_resources_plugins_deny: vec!["denied*".to_string()],
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.packed_resources_plugins,
+            vec![
+                plugins.join("c.pyr"),
+                plugins.join("a.pyr"),
+                plugins.join("b.pyr"),
+                plugins.join("other.txt"),
+            ]
+        );
+
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(origin.clone()),
+            packed_resources_plugins: vec![PathBuf::from("$ORIGIN/plugins/*")],
+            packed_resources_plugins_allow: Some(vec!["*.pyr".to_string()]),
+            packed_resources_plugins_deny: vec!["b.pyr".to_string()],
+            ..Default::default()
+        };
+
+        let resolved = config.resolve()?;
+        assert_eq!(
+            resolved.packed_resources_plugins,
+            vec![
+                plugins.join("a.pyr"),
+                plugins.join("c.pyr"),
+                plugins.join("denied.pyr"),
+            ]
+        );
+
+        let config = OxidizedPythonInterpreterConfig {
+            origin: Some(origin.clone()),
+            packed_resources_plugins: vec![PathBuf::from("$ORIGIN/*/a.pyr")],
+            ..Default::default()
+        };
+
+        assert!(config.resolve().is_err());
+
+        std::fs::remove_dir_all(&origin)?;
+
+        Ok(())
+    }
+
     #[test]
     fn test_packed_resources_explicit_origin() -> Result<()> {
         let mut config = OxidizedPythonInterpreterConfig {
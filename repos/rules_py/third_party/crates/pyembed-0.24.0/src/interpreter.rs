@@ -9,26 +9,34 @@ use {
         config::{OxidizedPythonInterpreterConfig, ResolvedOxidizedPythonInterpreterConfig},
         conversion::osstring_to_bytes,
         error::NewInterpreterError,
+        multiprocessing::MultiprocessingInvocation,
         osutils::resolve_terminfo_dirs,
         pyalloc::PythonMemoryAllocator,
+        startup_profile::StartupProfile,
     },
     once_cell::sync::Lazy,
     oxidized_importer::{
-        install_path_hook, remove_external_importers, replace_meta_path_importers, ImporterState,
-        OxidizedFinder, PyInit_oxidized_importer, PythonResourcesState, OXIDIZED_IMPORTER_NAME,
-        OXIDIZED_IMPORTER_NAME_STR,
+        install_path_hook, remove_external_importers, replace_meta_path_importers, ImportProfile,
+        ImporterState, OxidizedFinder, PyInit_oxidized_importer, PythonResourcesState,
+        OXIDIZED_IMPORTER_NAME, OXIDIZED_IMPORTER_NAME_STR,
     },
     pyo3::{
-        exceptions::PyRuntimeError, ffi as pyffi, prelude::*, types::PyDict, AsPyPointer,
-        PyTypeInfo,
+        exceptions::PySystemExit, ffi as pyffi, prelude::*, types::PyDict, AsPyPointer, PyTypeInfo,
+    },
+    python_packaging::{
+        interpreter::{MultiprocessingStartMethod, TerminfoResolution},
+        loaded_modules::{LoadedModule, LoadedModulesManifest},
     },
-    python_packaging::interpreter::{MultiprocessingStartMethod, TerminfoResolution},
     std::{
         collections::BTreeSet,
-        env, fs,
+        env,
+        ffi::CString,
+        fs,
         io::Write,
-        os::raw::c_char,
+        os::raw::{c_char, c_int},
         path::{Path, PathBuf},
+        sync::Arc,
+        time::Instant,
     },
 };
 
@@ -92,6 +100,12 @@ pub struct MainPythonInterpreter<'interpreter, 'resources: 'interpreter> {
     pub(crate) allocator: Option<PythonMemoryAllocator>,
     /// File to write containing list of modules when the interpreter finalizes.
     write_modules_path: Option<PathBuf>,
+    /// File to write a loaded modules manifest to when the interpreter finalizes.
+    write_modules_manifest_path: Option<PathBuf>,
+    /// Records imports serviced by `OxidizedFinder`.
+    import_profile: Option<Arc<ImportProfile>>,
+    /// Startup timings to report when the interpreter finishes running.
+    startup_profile: Option<StartupProfile>,
 }
 
 impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
@@ -101,8 +115,35 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     pub fn new(
         config: OxidizedPythonInterpreterConfig<'resources>,
     ) -> Result<MainPythonInterpreter<'interpreter, 'resources>, NewInterpreterError> {
+        let start = Instant::now();
+
+        let startup_profile_path = config
+            .profile_startup_env
+            .as_ref()
+            .and_then(std::env::var_os)
+            .filter(|x| !x.is_empty())
+            .map(PathBuf::from);
+
+        let import_profile = if startup_profile_path.is_some()
+            || config
+                .write_modules_manifest_directory_env
+                .as_ref()
+                .map_or(false, |key| std::env::var_os(key).is_some())
+        {
+            Some(Arc::new(ImportProfile::default()))
+        } else {
+            None
+        };
+
+        let mut startup_profile = startup_profile_path
+            .map(|path| StartupProfile::new(path, import_profile.clone().unwrap_or_default()));
+
         let config: ResolvedOxidizedPythonInterpreterConfig<'resources> = config.try_into()?;
 
+        if let Some(profile) = startup_profile.as_mut() {
+            profile.record_phase("resolve_config", start);
+        }
+
         match config.terminfo_resolution {
             TerminfoResolution::Dynamic => {
                 if let Some(v) = resolve_terminfo_dirs() {
@@ -120,6 +161,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             interpreter_guard: None,
             allocator: None,
             write_modules_path: None,
+            write_modules_manifest_path: None,
+            import_profile,
+            startup_profile,
         };
 
         res.init()?;
@@ -149,7 +193,10 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             std::env::set_var("TCL_LIBRARY", tcl_library);
         }
 
+        let start = Instant::now();
+
         set_pyimport_inittab(&self.config);
+        set_pyimport_frozen_modules(&self.config)?;
 
         // Pre-configure Python.
         let pre_config = pyffi::PyPreConfig::try_from(&self.config)?;
@@ -198,6 +245,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
         }
 
+        self.record_startup_phase("pre_init", start);
+        let start = Instant::now();
+
         let mut py_config: pyffi::PyConfig = (&self.config).try_into()?;
 
         // Enable multi-phase initialization. This allows us to initialize
@@ -215,6 +265,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         // The GIL is held.
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
 
+        self.record_startup_phase("core_init", start);
+        let start = Instant::now();
+
         // At this point, the core of Python is initialized.
         // importlib._bootstrap has been loaded. But not
         // importlib._bootstrap_external. This is where we work our magic to
@@ -226,6 +279,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         // The GIL is still held after calling into PyO3.
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
 
+        self.record_startup_phase("inject_importer", start);
+        let start = Instant::now();
+
         // Now proceed with the Python main initialization. This will initialize
         // importlib. And if the custom importlib bytecode was registered above,
         // our extension module will get imported and initialized.
@@ -240,6 +296,9 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
         // The GIL is held after finishing initialization.
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 1);
 
+        self.record_startup_phase("init_main", start);
+        let start = Instant::now();
+
         // We release the GIL so we can have pyo3's GIL handling take over from
         // an "empty" state. This mirrors what pyo3's prepare_freethreaded_python() does.
         unsafe {
@@ -248,12 +307,43 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
 
         self.write_modules_path =
             self.with_gil(|py| self.init_post_main(py, oxidized_finder_loaded))?;
+        self.write_modules_manifest_path = self.with_gil(|py| {
+            modules_file_path(
+                py,
+                self.config.write_modules_manifest_directory_env.as_deref(),
+                ".manifest",
+            )
+        })?;
 
         debug_assert_eq!(unsafe { pyffi::PyGILState_Check() }, 0);
 
+        self.record_startup_phase("post_main", start);
+
+        if let Some(profile) = &self.import_profile {
+            profile.end_startup();
+        }
+
         Ok(())
     }
 
+    /// Record the completion of a startup phase if startup profiling is enabled.
+    fn record_startup_phase(&mut self, name: &'static str, start: Instant) {
+        if let Some(profile) = self.startup_profile.as_mut() {
+            profile.record_phase(name, start);
+        }
+    }
+
+    /// Write the startup profile report, if enabled and not yet written.
+    ///
+    /// This only consults Rust state and is safe to call after finalization.
+    fn write_startup_profile(&mut self) {
+        if let Some(profile) = self.startup_profile.take() {
+            if let Err(msg) = profile.write() {
+                eprintln!("{}", msg);
+            }
+        }
+    }
+
     /// Inject OxidizedFinder into Python's importing mechanism.
     ///
     /// This function is meant to be called as part of multi-phase interpreter initialization
@@ -268,33 +358,46 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
 
         let resources_state = Box::new(PythonResourcesState::try_from(&self.config)?);
 
+        if self.config.report_shadowed_resources {
+            for shadowed in resources_state.shadowed_resources() {
+                eprintln!("{}", shadowed);
+            }
+        }
+
         let oxidized_importer = py.import(OXIDIZED_IMPORTER_NAME_STR).map_err(|err| {
             NewInterpreterError::new_from_pyerr(py, err, "import of oxidized importer module")
         })?;
 
-        let cb = |importer_state: &mut ImporterState| match self.config.multiprocessing_start_method
-        {
-            MultiprocessingStartMethod::None => {}
-            MultiprocessingStartMethod::Fork
-            | MultiprocessingStartMethod::ForkServer
-            | MultiprocessingStartMethod::Spawn => {
-                importer_state.set_multiprocessing_set_start_method(Some(
-                    self.config.multiprocessing_start_method.to_string(),
-                ));
-            }
-            MultiprocessingStartMethod::Auto => {
-                // Windows uses "spawn" because "fork" isn't available.
-                // Everywhere else uses "fork." The default on macOS is "spawn." This
-                // is due to https://bugs.python.org/issue33725, which only affects
-                // Python framework builds. Our assumption is we aren't using a Python
-                // framework, so "spawn" is safe.
-                let method = if cfg!(target_family = "windows") {
-                    "spawn"
-                } else {
-                    "fork"
-                };
-
-                importer_state.set_multiprocessing_set_start_method(Some(method.to_string()));
+        let import_profile = self.import_profile.clone();
+
+        let cb = |importer_state: &mut ImporterState| {
+            importer_state.set_import_profile(import_profile);
+            importer_state.set_virtual_file_paths(self.config.virtual_file_paths);
+            importer_state.set_resources_cache_dir(self.config.resources_cache_dir.clone());
+
+            match self.config.multiprocessing_start_method {
+                MultiprocessingStartMethod::None => {}
+                MultiprocessingStartMethod::Fork
+                | MultiprocessingStartMethod::ForkServer
+                | MultiprocessingStartMethod::Spawn => {
+                    importer_state.set_multiprocessing_set_start_method(Some(
+                        self.config.multiprocessing_start_method.to_string(),
+                    ));
+                }
+                MultiprocessingStartMethod::Auto => {
+                    // Windows uses "spawn" because "fork" isn't available.
+                    // Everywhere else uses "fork." The default on macOS is "spawn." This
+                    // is due to https://bugs.python.org/issue33725, which only affects
+                    // Python framework builds. Our assumption is we aren't using a Python
+                    // framework, so "spawn" is safe.
+                    let method = if cfg!(target_family = "windows") {
+                        "spawn"
+                    } else {
+                        "fork"
+                    };
+
+                    importer_state.set_multiprocessing_set_start_method(Some(method.to_string()));
+                }
             }
         };
 
@@ -392,6 +495,12 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
                     "installing OxidizedFinder in sys.path_hooks",
                 )
             })?;
+
+            if self.config.virtual_filesystem {
+                finder.call_method0("install_open_hook").map_err(|err| {
+                    NewInterpreterError::new_from_pyerr(py, err, "installing open() hook")
+                })?;
+            }
         }
 
         if self.config.argvb {
@@ -450,44 +559,7 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
             }
         }
 
-        let write_modules_path = if let Some(key) = &self.config.write_modules_directory_env {
-            if let Ok(path) = std::env::var(key) {
-                let path = PathBuf::from(path);
-
-                std::fs::create_dir_all(&path).map_err(|e| {
-                    NewInterpreterError::Dynamic(format!(
-                        "error creating directory for loaded modules files: {}",
-                        e
-                    ))
-                })?;
-
-                // We use Python's uuid module to generate a filename. This avoids
-                // a dependency on a Rust crate, which cuts down on dependency bloat.
-                let uuid_mod = py.import("uuid").map_err(|e| {
-                    NewInterpreterError::new_from_pyerr(py, e, "importing uuid module")
-                })?;
-                let uuid4 = uuid_mod.getattr("uuid4").map_err(|e| {
-                    NewInterpreterError::new_from_pyerr(py, e, "obtaining uuid.uuid4")
-                })?;
-                let uuid = uuid4.call0().map_err(|e| {
-                    NewInterpreterError::new_from_pyerr(py, e, "calling uuid.uuid4()")
-                })?;
-                let uuid_str = uuid
-                    .str()
-                    .map_err(|e| {
-                        NewInterpreterError::new_from_pyerr(py, e, "converting uuid to str")
-                    })?
-                    .to_string();
-
-                Some(path.join(format!("modules-{}", uuid_str)))
-            } else {
-                None
-            }
-        } else {
-            None
-        };
-
-        Ok(write_modules_path)
+        modules_file_path(py, self.config.write_modules_directory_env.as_deref(), "")
     }
 
     /// Proxy for [Python::with_gil()].
@@ -524,70 +596,40 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
 
     /// Run in "multiprocessing worker" mode.
     ///
-    /// This should be called when `sys.argv[1] == "--multiprocessing-fork"`. It
-    /// will parse arguments for the worker from `sys.argv` and call into the
+    /// This should be called when [Self::is_multiprocessing()] is true. It will
+    /// parse arguments for the worker from `sys.argv` and call into the
     /// `multiprocessing` module to perform work.
+    ///
+    /// Returns the exit code of the process, as given to `sys.exit()`.
     pub fn run_multiprocessing(&self) -> PyResult<i32> {
         // This code effectively reimplements multiprocessing.spawn.freeze_support(),
-        // except entirely in the Rust domain. This function effectively verifies
-        // `sys.argv[1] == "--multiprocessing-fork"` then parsed key=value arguments
-        // from arguments that follow. The keys are well-defined and guaranteed to
-        // be ASCII. The values are either ``None`` or an integer. This enables us
-        // to parse the arguments purely from Rust.
-
-        let argv = self.config.resolve_sys_argv().to_vec();
-
-        if argv.len() < 2 {
-            panic!("run_multiprocessing() called prematurely; sys.argv does not indicate multiprocessing mode");
-        }
+        // except entirely in the Rust domain. It also services the `-c` commands
+        // used to start processes when `sys.frozen` isn't set, for the forkserver
+        // and for the resource tracker. See [MultiprocessingInvocation].
 
-        self.with_gil(|py| {
-            let kwargs = PyDict::new(py);
-
-            for arg in argv.iter().skip(2) {
-                let arg = arg.to_string_lossy();
-
-                let mut parts = arg.splitn(2, '=');
-
-                let key = parts
-                    .next()
-                    .ok_or_else(|| PyRuntimeError::new_err("invalid multiprocessing argument"))?;
-                let value = parts
-                    .next()
-                    .ok_or_else(|| PyRuntimeError::new_err("invalid multiprocessing argument"))?;
-
-                let value = if value == "None" {
-                    py.None()
-                } else {
-                    let v = value.parse::<isize>().map_err(|e| {
-                        PyRuntimeError::new_err(format!(
-                            "unable to convert multiprocessing argument to integer: {}",
-                            e
-                        ))
-                    })?;
-
-                    v.into_py(py)
-                };
+        let invocation = MultiprocessingInvocation::from_argv(self.config.resolve_sys_argv())
+            .unwrap_or_else(|| {
+                panic!("run_multiprocessing() called prematurely; sys.argv does not indicate multiprocessing mode")
+            });
 
-                kwargs.set_item(key, value)?;
+        self.with_gil(|py| match invocation.run(py) {
+            Ok(()) => Ok(0),
+            Err(e) if e.is_instance_of::<PySystemExit>(py) => {
+                system_exit_code(py, e.value(py).getattr("code")?)
             }
-
-            let spawn_module = py.import("multiprocessing.spawn")?;
-            spawn_module.getattr("spawn_main")?.call1((kwargs,))?;
-
-            Ok(0)
+            Err(e) => Err(e),
         })
     }
 
     /// Whether the Python interpreter is in "multiprocessing worker" mode.
     ///
     /// The `multiprocessing` module can work by spawning new processes
-    /// with arguments `--multiprocessing-fork [key=value] ...`. This function
-    /// detects if the current Python interpreter is configured for said execution.
+    /// with arguments `--multiprocessing-fork [key=value] ...`, or with `-c`
+    /// commands calling into `multiprocessing` (e.g. to run the forkserver).
+    /// This function detects if the current Python interpreter is configured
+    /// for said execution.
     pub fn is_multiprocessing(&self) -> bool {
-        let argv = self.config.resolve_sys_argv();
-
-        argv.len() >= 2 && argv[1] == "--multiprocessing-fork"
+        MultiprocessingInvocation::from_argv(self.config.resolve_sys_argv()).is_some()
     }
 
     /// Runs the Python interpreter.
@@ -615,6 +657,23 @@ impl<'interpreter, 'resources> MainPythonInterpreter<'interpreter, 'resources> {
     }
 }
 
+/// Resolve the process exit code for a `SystemExit` exception's `code`.
+///
+/// This mirrors how `python` handles an uncaught `SystemExit`.
+fn system_exit_code(py: Python, code: &PyAny) -> PyResult<i32> {
+    if code.is_none() {
+        Ok(0)
+    } else if let Ok(code) = code.extract::<i32>() {
+        Ok(code)
+    } else {
+        py.import("sys")?
+            .getattr("stderr")?
+            .call_method1("write", (format!("{}\n", code.str()?),))?;
+
+        Ok(1)
+    }
+}
+
 static mut ORIGINAL_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
 static mut REPLACED_BUILTIN_EXTENSIONS: Option<Vec<pyffi::_inittab>> = None;
 
@@ -688,6 +747,157 @@ fn set_pyimport_inittab(config: &OxidizedPythonInterpreterConfig) {
     }
 }
 
+static mut ORIGINAL_FROZEN_MODULES: Option<*const pyffi::_frozen> = None;
+/// Replacement `PyImport_FrozenModules` array and the names and bytecode it points to.
+static mut REPLACED_FROZEN_MODULES: Option<(Vec<pyffi::_frozen>, Vec<(CString, Vec<u8>)>)> = None;
+
+/// Set PyImport_FrozenModules from config options.
+///
+/// As with [set_pyimport_inittab()], the original array is remembered so
+/// that each interpreter initialization starts from it.
+fn set_pyimport_frozen_modules(
+    config: &OxidizedPythonInterpreterConfig,
+) -> Result<(), NewInterpreterError> {
+    let original = unsafe { *ORIGINAL_FROZEN_MODULES.get_or_insert(pyffi::PyImport_FrozenModules) };
+
+    let data = match config.frozen_modules {
+        Some(data) => data,
+        None => {
+            unsafe {
+                pyffi::PyImport_FrozenModules = original;
+            }
+
+            return Ok(());
+        }
+    };
+
+    let mut storage = vec![];
+    let mut packages = vec![];
+
+    let resources = python_packed_resources::load_resources(data)
+        .map_err(|e| NewInterpreterError::Dynamic(format!("parsing frozen modules: {}", e)))?;
+
+    for resource in resources {
+        let resource = resource
+            .map_err(|e| NewInterpreterError::Dynamic(format!("parsing frozen modules: {}", e)))?;
+
+        if let Some(bytecode) = resource.in_memory_bytecode {
+            let name = CString::new(resource.name.as_bytes()).map_err(|_| {
+                NewInterpreterError::Dynamic(format!(
+                    "invalid frozen module name: {}",
+                    resource.name
+                ))
+            })?;
+
+            storage.push((name, bytecode.into_owned()));
+            packages.push(resource.is_python_package);
+        }
+    }
+
+    let mut modules = storage
+        .iter()
+        .zip(packages)
+        .map(|((name, code), is_package)| {
+            let size = c_int::try_from(code.len())
+                .map_err(|_| NewInterpreterError::Simple("frozen module bytecode is too large"))?;
+
+            // A negative size denotes a package. Python 3.11+ still honors this,
+            // so the fields it added can be left zeroed.
+            #[allow(clippy::needless_update)]
+            Ok(pyffi::_frozen {
+                name: name.as_ptr(),
+                code: code.as_ptr(),
+                size: if is_package { -size } else { size },
+                ..unsafe { std::mem::zeroed() }
+            })
+        })
+        .collect::<Result<Vec<_>, NewInterpreterError>>()?;
+
+    // Python 3.11+ leaves the array NULL unless an embedder sets it. Its own
+    // frozen modules are consulted separately.
+    if !original.is_null() {
+        for i in 0.. {
+            let record = unsafe { *original.offset(i) };
+
+            if record.name.is_null() {
+                break;
+            }
+
+            modules.push(record);
+        }
+    }
+
+    // Add sentinel record with NULLs.
+    modules.push(unsafe { std::mem::zeroed() });
+
+    unsafe {
+        REPLACED_FROZEN_MODULES = Some((modules, storage));
+        pyffi::PyImport_FrozenModules = REPLACED_FROZEN_MODULES.as_ref().unwrap().0.as_ptr();
+    }
+
+    Ok(())
+}
+
+/// Resolve the path of a file to write loaded modules information to.
+///
+/// `key` names an environment variable holding a directory. If it is set, the
+/// directory is created and a path to a `modules-<UUID><suffix>` file in it is
+/// returned.
+fn modules_file_path(
+    py: Python,
+    key: Option<&str>,
+    suffix: &str,
+) -> Result<Option<PathBuf>, NewInterpreterError> {
+    let path = match key.map(std::env::var) {
+        Some(Ok(path)) => PathBuf::from(path),
+        _ => return Ok(None),
+    };
+
+    std::fs::create_dir_all(&path).map_err(|e| {
+        NewInterpreterError::Dynamic(format!(
+            "error creating directory for loaded modules files: {}",
+            e
+        ))
+    })?;
+
+    // We use Python's uuid module to generate a filename. This avoids
+    // a dependency on a Rust crate, which cuts down on dependency bloat.
+    let uuid_mod = py
+        .import("uuid")
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "importing uuid module"))?;
+    let uuid4 = uuid_mod
+        .getattr("uuid4")
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "obtaining uuid.uuid4"))?;
+    let uuid = uuid4
+        .call0()
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "calling uuid.uuid4()"))?;
+    let uuid_str = uuid
+        .str()
+        .map_err(|e| NewInterpreterError::new_from_pyerr(py, e, "converting uuid to str"))?
+        .to_string();
+
+    Ok(Some(path.join(format!("modules-{}{}", uuid_str, suffix))))
+}
+
+/// Write a manifest of modules and package resources recorded by an [ImportProfile].
+fn write_modules_manifest_to_path(profile: &ImportProfile, path: &Path) -> Result<(), String> {
+    let manifest = LoadedModulesManifest {
+        modules: profile
+            .modules()
+            .into_iter()
+            .map(|m| LoadedModule {
+                name: m.name,
+                source: m.source.map(|x| x.as_str().to_string()),
+                layer: m.layer,
+                startup: m.startup,
+            })
+            .collect(),
+        resources: profile.resources().into_iter().collect(),
+    };
+
+    std::fs::write(path, manifest.to_manifest_string()).map_err(|e| e.to_string())
+}
+
 /// Write loaded Python modules to a directory.
 ///
 /// Given a Python interpreter and a path to a directory, this will create a
@@ -727,6 +937,16 @@ fn write_modules_to_path(py: Python, path: &Path) -> Result<(), &'static str> {
 
 impl<'interpreter, 'resources> Drop for MainPythonInterpreter<'interpreter, 'resources> {
     fn drop(&mut self) {
+        self.write_startup_profile();
+
+        if let (Some(profile), Some(path)) =
+            (&self.import_profile, &self.write_modules_manifest_path)
+        {
+            if let Err(msg) = write_modules_manifest_to_path(profile, path) {
+                eprintln!("error writing modules manifest: {}", msg);
+            }
+        }
+
         // Interpreter may have been finalized already. Possibly through our invocation
         // of Py_RunMain(). Possibly something out-of-band beyond our control. We don't
         // muck with the interpreter after finalization because this will likely result
@@ -59,8 +59,11 @@ mod conversion;
 mod error;
 mod interpreter;
 mod interpreter_config;
+mod multiprocessing;
 mod osutils;
 mod pyalloc;
+mod runfiles;
+mod startup_profile;
 pub mod technotes;
 #[cfg(test)]
 mod test;
@@ -76,7 +79,9 @@ pub use {
         interpreter::MainPythonInterpreter,
         pyalloc::PythonMemoryAllocator,
     },
-    oxidized_importer::{PackedResourcesSource, PythonResourcesState},
+    oxidized_importer::{
+        PackedResourcesLayer, PackedResourcesSource, PythonResourcesState, ShadowedResource,
+    },
     python_packaging::{
         interpreter::{
             Allocator, BytesWarning, CheckHashPycsMode, CoerceCLocale, MemoryAllocatorBackend,
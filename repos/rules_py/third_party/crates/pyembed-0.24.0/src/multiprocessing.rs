@@ -0,0 +1,352 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Servicing processes started by `multiprocessing`.
+
+`multiprocessing` (and `concurrent.futures`, which builds on it) starts helper
+processes by executing `sys.executable` with arguments meant for the `python`
+executable. An embedded interpreter doesn't process those arguments the way
+`python` does, so we recognize them and call into `multiprocessing` ourselves.
+*/
+
+use {
+    pyo3::{
+        exceptions::PyRuntimeError,
+        prelude::*,
+        types::{PyDict, PyTuple, PyType},
+    },
+    std::ffi::OsString,
+};
+
+/// Argument marking a process spawned by `multiprocessing.spawn`.
+const MULTIPROCESSING_FORK: &str = "--multiprocessing-fork";
+
+/// Entrypoints `multiprocessing` runs via `-c`, as (module, function) pairs.
+const COMMAND_ENTRYPOINTS: &[(&str, &str)] = &[
+    // The spawn start method when sys.frozen isn't set.
+    ("multiprocessing.spawn", "spawn_main"),
+    // The forkserver start method.
+    ("multiprocessing.forkserver", "main"),
+    // The process tracking named resources for the spawn and forkserver start
+    // methods.
+    ("multiprocessing.resource_tracker", "main"),
+];
+
+/// Interpreter flags taking a value in the following argument.
+const FLAGS_WITH_VALUE: &[&str] = &["-X"];
+
+/// How `multiprocessing` invoked the current process.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum MultiprocessingInvocation {
+    /// `<exe> --multiprocessing-fork [key=value]...`
+    ///
+    /// Used by the spawn start method when `sys.frozen` is set. Holds the
+    /// `key=value` arguments. Values are either `None` or an integer.
+    FrozenSpawn(Vec<String>),
+
+    /// `<exe> [flags]... -c "from <module> import <function>; <function>(<arguments>)"`
+    ///
+    /// Used by the spawn start method otherwise (followed by `--multiprocessing-fork`),
+    /// the forkserver start method and the resource tracker. Interpreter flags
+    /// reproduce the parent's `sys.flags` and are ignored: the embedded
+    /// interpreter's configuration applies.
+    Command {
+        module: &'static str,
+        function: &'static str,
+        /// Source of the arguments to `function`. They are Python literals.
+        arguments: String,
+        /// Arguments following the command. `python` exposes them as
+        /// `sys.argv[1:]` and spawned processes check for `--multiprocessing-fork`.
+        trailing: Vec<String>,
+    },
+}
+
+impl MultiprocessingInvocation {
+    /// Recognize a `multiprocessing` invocation from process arguments.
+    ///
+    /// Returns [None] if the arguments don't look like one.
+    pub(crate) fn from_argv(argv: &[OsString]) -> Option<Self> {
+        let args = argv
+            .iter()
+            .skip(1)
+            .map(|arg| arg.to_str())
+            .collect::<Option<Vec<_>>>();
+
+        // multiprocessing only ever passes ASCII arguments.
+        let args = args?;
+
+        if args.first() == Some(&MULTIPROCESSING_FORK) {
+            return Some(Self::FrozenSpawn(
+                args[1..].iter().map(|arg| arg.to_string()).collect(),
+            ));
+        }
+
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            if arg == "-c" {
+                let command = args.next()?;
+                let trailing = args.collect::<Vec<_>>();
+
+                return match trailing.as_slice() {
+                    [] | [MULTIPROCESSING_FORK] => parse_command(
+                        command,
+                        trailing.into_iter().map(|arg| arg.to_string()).collect(),
+                    ),
+                    _ => None,
+                };
+            } else if FLAGS_WITH_VALUE.contains(&arg) {
+                args.next();
+            } else if !is_interpreter_flag(arg) {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    /// Run the `multiprocessing` code this process was started for.
+    pub(crate) fn run(&self, py: Python) -> PyResult<()> {
+        match self {
+            Self::FrozenSpawn(values) => {
+                let kwargs = PyDict::new(py);
+
+                for arg in values {
+                    let (key, value) = arg.split_once('=').ok_or_else(|| {
+                        PyRuntimeError::new_err("invalid multiprocessing argument")
+                    })?;
+
+                    let value = if value == "None" {
+                        None
+                    } else {
+                        Some(value.parse::<isize>().map_err(|e| {
+                            PyRuntimeError::new_err(format!(
+                                "unable to convert multiprocessing argument to integer: {}",
+                                e
+                            ))
+                        })?)
+                    };
+
+                    kwargs.set_item(key, value)?;
+                }
+
+                py.import("multiprocessing.spawn")?
+                    .getattr("spawn_main")?
+                    .call((), Some(kwargs))?;
+            }
+            Self::Command {
+                module,
+                function,
+                arguments,
+                trailing,
+            } => {
+                let (args, kwargs) = evaluate_arguments(py, arguments)?;
+
+                // Mirror what `python -c` sets.
+                let argv = std::iter::once("-c")
+                    .chain(trailing.iter().map(|arg| arg.as_str()))
+                    .collect::<Vec<_>>();
+                py.import("sys")?.setattr("argv", argv)?;
+
+                py.import(*module)?
+                    .getattr(*function)?
+                    .call(args, Some(kwargs))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Whether an argument is an interpreter flag `subprocess._args_from_interpreter_flags()` emits.
+fn is_interpreter_flag(arg: &str) -> bool {
+    match arg.strip_prefix('-') {
+        // -W joins its value.
+        Some(flags) if flags.starts_with('W') => flags.len() > 1,
+        Some(flags) => {
+            !flags.is_empty()
+                && flags.chars().all(|c| {
+                    matches!(
+                        c,
+                        'b' | 'B' | 'd' | 'E' | 'I' | 'O' | 'P' | 'q' | 's' | 'S' | 'v'
+                    )
+                })
+        }
+        None => false,
+    }
+}
+
+/// Parse the `-c` command of [MultiprocessingInvocation::Command].
+fn parse_command(command: &str, trailing: Vec<String>) -> Option<MultiprocessingInvocation> {
+    COMMAND_ENTRYPOINTS.iter().find_map(|&(module, function)| {
+        let call = command
+            .strip_prefix(&format!("from {} import {};", module, function))?
+            .trim();
+        let arguments = call
+            .strip_prefix(function)?
+            .strip_prefix('(')?
+            .strip_suffix(')')?;
+
+        Some(MultiprocessingInvocation::Command {
+            module,
+            function,
+            arguments: arguments.to_string(),
+            trailing: trailing.clone(),
+        })
+    })
+}
+
+/// Evaluate the arguments of a [MultiprocessingInvocation::Command] call.
+///
+/// Arguments are parsed with `ast` and must be literals. So arguments crafted
+/// to look like a `multiprocessing` invocation can't run arbitrary code.
+fn evaluate_arguments<'p>(py: Python<'p>, arguments: &str) -> PyResult<(&'p PyTuple, &'p PyDict)> {
+    let ast = py.import("ast")?;
+    let literal_eval = ast.getattr("literal_eval")?;
+
+    let call = ast
+        .getattr("parse")?
+        .call1((format!("f({})", arguments), "<multiprocessing>", "eval"))?
+        .getattr("body")?;
+
+    // Arguments like `1)(2` turn the expression into something else.
+    if !call.is_instance(ast.getattr("Call")?.downcast::<PyType>()?)?
+        || !call
+            .getattr("func")?
+            .is_instance(ast.getattr("Name")?.downcast::<PyType>()?)?
+    {
+        return Err(PyRuntimeError::new_err("invalid multiprocessing command"));
+    }
+
+    let args = call
+        .getattr("args")?
+        .iter()?
+        .map(|arg| literal_eval.call1((arg?,)))
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let kwargs = PyDict::new(py);
+    for keyword in call.getattr("keywords")?.iter()? {
+        let keyword = keyword?;
+        let value = literal_eval.call1((keyword.getattr("value")?,))?;
+        let name = keyword.getattr("arg")?;
+
+        // `**mapping` has no name.
+        if name.is_none() {
+            kwargs.call_method1("update", (value,))?;
+        } else {
+            kwargs.set_item(name, value)?;
+        }
+    }
+
+    Ok((PyTuple::new(py, args), kwargs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn argv(args: &[&str]) -> Vec<OsString> {
+        std::iter::once("/usr/bin/app")
+            .chain(args.iter().copied())
+            .map(OsString::from)
+            .collect()
+    }
+
+    #[test]
+    fn test_frozen_spawn() {
+        assert_eq!(
+            MultiprocessingInvocation::from_argv(&argv(&[
+                "--multiprocessing-fork",
+                "tracker_fd=5",
+                "pipe_handle=None"
+            ])),
+            Some(MultiprocessingInvocation::FrozenSpawn(vec![
+                "tracker_fd=5".to_string(),
+                "pipe_handle=None".to_string()
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_spawn_command() {
+        assert_eq!(
+            MultiprocessingInvocation::from_argv(&argv(&[
+                "-I",
+                "-B",
+                "-c",
+                "from multiprocessing.spawn import spawn_main; spawn_main(tracker_fd=5, pipe_handle=7)",
+                "--multiprocessing-fork"
+            ])),
+            Some(MultiprocessingInvocation::Command {
+                module: "multiprocessing.spawn",
+                function: "spawn_main",
+                arguments: "tracker_fd=5, pipe_handle=7".to_string(),
+                trailing: vec!["--multiprocessing-fork".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_forkserver_command() {
+        assert_eq!(
+            MultiprocessingInvocation::from_argv(&argv(&[
+                "-OO",
+                "-Wdefault",
+                "-X",
+                "dev",
+                "-c",
+                "from multiprocessing.forkserver import main; main(3, 4, ['__main__'], **{'sys_path': ['/app']})",
+            ])),
+            Some(MultiprocessingInvocation::Command {
+                module: "multiprocessing.forkserver",
+                function: "main",
+                arguments: "3, 4, ['__main__'], **{'sys_path': ['/app']}".to_string(),
+                trailing: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_resource_tracker_command() {
+        assert_eq!(
+            MultiprocessingInvocation::from_argv(&argv(&[
+                "-c",
+                "from multiprocessing.resource_tracker import main;main(6)",
+            ])),
+            Some(MultiprocessingInvocation::Command {
+                module: "multiprocessing.resource_tracker",
+                function: "main",
+                arguments: "6".to_string(),
+                trailing: vec![],
+            })
+        );
+    }
+
+    #[test]
+    fn test_not_multiprocessing() {
+        for args in [
+            vec![],
+            vec!["script.py"],
+            vec!["-c", "print('hello')"],
+            vec!["-m", "multiprocessing.forkserver"],
+            vec![
+                "--verbose",
+                "-c",
+                "from multiprocessing.resource_tracker import main;main(6)",
+            ],
+            vec![
+                "-c",
+                "from multiprocessing.resource_tracker import main;main(6)",
+                "extra",
+            ],
+            vec![
+                "-c",
+                "from multiprocessing.resource_tracker import main;print(6)",
+            ],
+            vec!["-c"],
+        ] {
+            assert_eq!(MultiprocessingInvocation::from_argv(&argv(&args)), None);
+        }
+    }
+}
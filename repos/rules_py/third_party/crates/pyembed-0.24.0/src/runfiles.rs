@@ -0,0 +1,183 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Resolution of paths in Bazel runfiles trees.
+
+Bazel makes the runfiles of a binary available either as a directory tree
+or as a manifest file mapping runfiles-relative paths to absolute paths.
+The latter is common on Windows, where symlink trees are often disabled.
+*/
+
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+/// A runfiles tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Runfiles {
+    /// Runfiles are materialized in a directory.
+    Directory(PathBuf),
+
+    /// Runfiles are described by a manifest mapping relative paths to absolute paths.
+    Manifest(HashMap<String, PathBuf>),
+}
+
+impl Runfiles {
+    /// Discover runfiles for an executable.
+    ///
+    /// `manifest_file` and `directory` are the values of the `RUNFILES_MANIFEST_FILE`
+    /// and `RUNFILES_DIR` environment variables, which take precedence in that
+    /// order. Otherwise a `<exe>.runfiles_manifest` file or `<exe>.runfiles`
+    /// directory next to the executable is used.
+    ///
+    /// Returns [None] if no runfiles could be found.
+    pub fn discover(
+        exe: &Path,
+        manifest_file: Option<OsString>,
+        directory: Option<OsString>,
+    ) -> Result<Option<Self>, String> {
+        if let Some(path) = manifest_file.filter(|x| !x.is_empty()) {
+            return Self::from_manifest_file(Path::new(&path)).map(Some);
+        }
+
+        if let Some(path) = directory.filter(|x| !x.is_empty()) {
+            return Ok(Some(Self::Directory(PathBuf::from(path))));
+        }
+
+        let mut manifest = exe.as_os_str().to_os_string();
+        manifest.push(".runfiles_manifest");
+        let manifest = PathBuf::from(manifest);
+
+        if manifest.is_file() {
+            return Self::from_manifest_file(&manifest).map(Some);
+        }
+
+        let mut directory = exe.as_os_str().to_os_string();
+        directory.push(".runfiles");
+        let directory = PathBuf::from(directory);
+
+        if directory.is_dir() {
+            return Ok(Some(Self::Directory(directory)));
+        }
+
+        Ok(None)
+    }
+
+    /// Parse a runfiles manifest file.
+    pub fn from_manifest_file(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path)
+            .map_err(|e| format!("error reading runfiles manifest {}: {}", path.display(), e))?;
+
+        Ok(Self::Manifest(
+            data.lines()
+                .filter_map(|line| line.split_once(' '))
+                .map(|(key, value)| (key.to_string(), PathBuf::from(value)))
+                .collect(),
+        ))
+    }
+
+    /// Resolve the absolute path of a runfiles-relative path.
+    ///
+    /// Manifests list files, so a path within a directory listed in the manifest
+    /// is resolved relative to the longest such directory.
+    ///
+    /// Returns [None] if a manifest doesn't list the path.
+    pub fn rlocation(&self, path: &str) -> Option<PathBuf> {
+        match self {
+            Self::Directory(directory) => Some(directory.join(path)),
+            Self::Manifest(entries) => {
+                if let Some(value) = entries.get(path) {
+                    return Some(value.clone());
+                }
+
+                let mut prefix = path;
+                while let Some((parent, _)) = prefix.rsplit_once('/') {
+                    if let Some(value) = entries.get(parent) {
+                        return Some(value.join(&path[parent.len() + 1..]));
+                    }
+
+                    prefix = parent;
+                }
+
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> std::io::Result<PathBuf> {
+        let path = std::env::temp_dir().join(format!(
+            "pyembed-test-runfiles-{}-{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&path)?;
+
+        Ok(path)
+    }
+
+    #[test]
+    fn test_rlocation_manifest() {
+        let runfiles = Runfiles::Manifest(
+            [
+                ("ws/pkg/file.pyr", "/abs/file.pyr"),
+                ("ws/data", "/abs/data"),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), PathBuf::from(v)))
+            .collect(),
+        );
+
+        assert_eq!(
+            runfiles.rlocation("ws/pkg/file.pyr"),
+            Some(PathBuf::from("/abs/file.pyr"))
+        );
+        assert_eq!(
+            runfiles.rlocation("ws/data/sub/x.txt"),
+            Some(PathBuf::from("/abs/data/sub/x.txt"))
+        );
+        assert_eq!(runfiles.rlocation("ws/pkg/other"), None);
+    }
+
+    #[test]
+    fn test_discover() -> Result<(), String> {
+        let dir = temp_dir("discover").map_err(|e| e.to_string())?;
+        let exe = dir.join("app");
+        let manifest = dir.join("app.runfiles_manifest");
+
+        std::fs::write(&manifest, "ws/lib.pyr /abs/lib.pyr\nws/empty\n")
+            .map_err(|e| e.to_string())?;
+
+        // Environment variables take precedence.
+        assert_eq!(
+            Runfiles::discover(&exe, None, Some("/runfiles".into()))?,
+            Some(Runfiles::Directory(PathBuf::from("/runfiles")))
+        );
+
+        let runfiles = Runfiles::discover(&exe, None, None)?.unwrap();
+        assert_eq!(
+            runfiles.rlocation("ws/lib.pyr"),
+            Some(PathBuf::from("/abs/lib.pyr"))
+        );
+
+        std::fs::remove_file(&manifest).map_err(|e| e.to_string())?;
+        assert_eq!(Runfiles::discover(&exe, None, None)?, None);
+
+        std::fs::create_dir_all(dir.join("app.runfiles")).map_err(|e| e.to_string())?;
+        assert_eq!(
+            Runfiles::discover(&exe, None, None)?,
+            Some(Runfiles::Directory(dir.join("app.runfiles")))
+        );
+
+        std::fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+
+        Ok(())
+    }
+}
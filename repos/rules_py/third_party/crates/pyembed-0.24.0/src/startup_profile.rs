@@ -0,0 +1,234 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*! Profiling of interpreter startup.
+
+A [StartupProfile] records how long each phase of interpreter initialization
+took and, via an [ImportProfile] installed on `OxidizedFinder`, how long
+individual module imports took. The combined report is written either as
+JSON or as "folded stacks," which can be rendered by flamegraph tools
+such as `inferno-flamegraph` or `flamegraph.pl`.
+*/
+
+use {
+    oxidized_importer::{ImportProfile, ModuleImportTiming},
+    std::{
+        fmt::Write,
+        path::PathBuf,
+        sync::Arc,
+        time::{Duration, Instant},
+    },
+};
+
+/// Records timings of interpreter startup.
+#[derive(Debug)]
+pub(crate) struct StartupProfile {
+    /// File to write the report to.
+    path: PathBuf,
+    /// Startup phases and their durations, in order of execution.
+    phases: Vec<(&'static str, Duration)>,
+    /// Timings of imports serviced by `OxidizedFinder`.
+    imports: Arc<ImportProfile>,
+}
+
+impl StartupProfile {
+    pub fn new(path: PathBuf, imports: Arc<ImportProfile>) -> Self {
+        Self {
+            path,
+            phases: vec![],
+            imports,
+        }
+    }
+
+    /// Record the completion of a startup phase that began at `start`.
+    pub fn record_phase(&mut self, name: &'static str, start: Instant) {
+        self.phases.push((name, start.elapsed()));
+    }
+
+    /// Render the report.
+    ///
+    /// Paths ending in `.json` receive JSON. Everything else receives folded stacks.
+    pub fn render(&self) -> String {
+        let modules = self.imports.modules();
+
+        if self.path.extension().map_or(false, |x| x == "json") {
+            render_json(&self.phases, &modules)
+        } else {
+            render_folded(&self.phases, &modules)
+        }
+    }
+
+    /// Write the report to its destination file.
+    pub fn write(&self) -> Result<(), String> {
+        std::fs::write(&self.path, self.render()).map_err(|e| {
+            format!(
+                "error writing startup profile {}: {}",
+                self.path.display(),
+                e
+            )
+        })
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut res = String::with_capacity(s.len() + 2);
+    res.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            c if (c as u32) < 0x20 => {
+                write!(res, "\\u{:04x}", c as u32).unwrap();
+            }
+            c => res.push(c),
+        }
+    }
+
+    res.push('"');
+    res
+}
+
+/// Render a report as a JSON document.
+///
+/// Durations are expressed in microseconds.
+fn render_json(phases: &[(&'static str, Duration)], modules: &[ModuleImportTiming]) -> String {
+    let phases = phases
+        .iter()
+        .map(|(name, duration)| {
+            format!(
+                "{{\"name\": {}, \"duration_us\": {}}}",
+                json_string(name),
+                duration.as_micros()
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let modules = modules
+        .iter()
+        .map(|m| {
+            format!(
+                "{{\"name\": {}, \"source\": {}, \"find_us\": {}, \"load_us\": {}, \"exec_us\": {}, \"parents\": [{}]}}",
+                json_string(&m.name),
+                m.source
+                    .map_or_else(|| "null".to_string(), |x| json_string(x.as_str())),
+                m.find.as_micros(),
+                m.load.as_micros(),
+                m.exec.as_micros(),
+                m.parents
+                    .iter()
+                    .map(|x| json_string(x))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })
+        .collect::<Vec<_>>();
+
+    format!(
+        "{{\n  \"phases\": [\n    {}\n  ],\n  \"modules\": [\n    {}\n  ]\n}}\n",
+        phases.join(",\n    "),
+        modules.join(",\n    ")
+    )
+}
+
+/// Render a report as folded stacks.
+///
+/// Each line holds a `;` delimited stack and the self time of its leaf frame in
+/// microseconds. Module frames nest under the modules whose execution imported
+/// them and carry the execution time not attributed to those imports. Time spent
+/// finding and loading a module is attributed to `find` and `load` frames
+/// under it.
+fn render_folded(phases: &[(&'static str, Duration)], modules: &[ModuleImportTiming]) -> String {
+    let mut res = String::new();
+
+    for (name, duration) in phases {
+        writeln!(res, "startup;{} {}", name, duration.as_micros()).unwrap();
+    }
+
+    for module in modules {
+        let stack = std::iter::once("imports")
+            .chain(module.parents.iter().map(|x| x.as_str()))
+            .chain(std::iter::once(module.name.as_str()))
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let children = modules
+            .iter()
+            .filter(|m| m.parents.last() == Some(&module.name))
+            .map(|m| m.find + m.load + m.exec)
+            .sum::<Duration>();
+
+        for (frame, duration) in [
+            (None, module.exec.saturating_sub(children)),
+            (Some("find"), module.find),
+            (Some("load"), module.load),
+        ] {
+            if duration.is_zero() {
+                continue;
+            }
+
+            if let Some(frame) = frame {
+                writeln!(res, "{};{} {}", stack, frame, duration.as_micros()).unwrap();
+            } else {
+                writeln!(res, "{} {}", stack, duration.as_micros()).unwrap();
+            }
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, oxidized_importer::ModuleSource};
+
+    fn timing(name: &str, parents: &[&str], find: u64, exec: u64) -> ModuleImportTiming {
+        ModuleImportTiming {
+            name: name.to_string(),
+            source: Some(ModuleSource::Memory),
+            layer: None,
+            startup: true,
+            find: Duration::from_micros(find),
+            load: Duration::ZERO,
+            exec: Duration::from_micros(exec),
+            parents: parents.iter().map(|x| x.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn test_render_folded() {
+        let phases = [
+            ("core_init", Duration::from_micros(100)),
+            ("init_main", Duration::from_micros(250)),
+        ];
+        let modules = [
+            timing("encodings", &[], 5, 40),
+            timing("encodings.aliases", &["encodings"], 3, 10),
+        ];
+
+        assert_eq!(
+            render_folded(&phases, &modules),
+            "startup;core_init 100\n\
+             startup;init_main 250\n\
+             imports;encodings 27\n\
+             imports;encodings;find 5\n\
+             imports;encodings;encodings.aliases 10\n\
+             imports;encodings;encodings.aliases;find 3\n"
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let phases = [("pre_init", Duration::from_micros(7))];
+        let mut module = timing("foo\"bar", &["parent"], 1, 2);
+        module.source = None;
+
+        assert_eq!(
+            render_json(&phases, &[module]),
+            "{\n  \"phases\": [\n    {\"name\": \"pre_init\", \"duration_us\": 7}\n  ],\n  \
+             \"modules\": [\n    {\"name\": \"foo\\\"bar\", \"source\": null, \"find_us\": 1, \
+             \"load_us\": 0, \"exec_us\": 2, \"parents\": [\"parent\"]}\n  ]\n}\n"
+        );
+    }
+}
@@ -7,6 +7,7 @@ use {
     crate::MainPythonInterpreter,
     anyhow::Result,
     pyo3::prelude::*,
+    python_packaging::loaded_modules::LoadedModulesManifest,
     rusty_fork::rusty_fork_test,
 };
 
@@ -59,6 +60,44 @@ rusty_fork_test! {
 
     }
 
+    /// Imports are recorded in a loaded modules manifest.
+    #[test]
+    fn loaded_modules_manifest() {
+        let dir = std::env::temp_dir().join(format!(
+            "pyembed-test-modules-manifest-{}",
+            std::process::id()
+        ));
+        std::env::set_var("PYEMBED_TEST_MODULES_MANIFEST", &dir);
+
+        let mut config = default_interpreter_config();
+        config.oxidized_importer = true;
+        config.write_modules_manifest_directory_env =
+            Some("PYEMBED_TEST_MODULES_MANIFEST".to_string());
+        let interp = MainPythonInterpreter::new(config).unwrap();
+
+        interp.with_gil(|py| {
+            py.import("errno").unwrap();
+        });
+        std::mem::drop(interp);
+
+        let entries = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(entries.len(), 1);
+        assert!(entries[0].to_string_lossy().ends_with(".manifest"));
+
+        let manifest = LoadedModulesManifest::from_path(&entries[0]).unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let errno = manifest
+            .modules
+            .iter()
+            .find(|m| m.name == "errno")
+            .unwrap();
+        assert_eq!(errno.source.as_deref(), Some("builtin"));
+    }
+
     /// find_spec() returns None on missing module.
     #[test]
     fn find_spec_missing() {
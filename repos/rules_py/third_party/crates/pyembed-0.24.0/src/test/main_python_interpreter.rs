@@ -3,10 +3,12 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    super::{default_interpreter_config, run_py_test},
-    crate::MainPythonInterpreter,
+    super::{default_interpreter_config, PYTHON_INTERPRETER_PATH},
+    crate::{MainPythonInterpreter, PackedResourcesSource},
     pyo3::ffi as pyffi,
+    python_packed_resources::Resource,
     rusty_fork::rusty_fork_test,
+    std::borrow::Cow,
 };
 
 rusty_fork_test! {
@@ -36,7 +38,175 @@ rusty_fork_test! {
     }
 
     #[test]
-    fn multiprocessing_py() {
-        run_py_test("test_multiprocessing.py").unwrap()
+    fn startup_profile() {
+        let path = std::env::temp_dir().join(format!(
+            "pyembed-test-startup-profile-{}.folded",
+            std::process::id()
+        ));
+        std::env::set_var("PYEMBED_TEST_PROFILE", &path);
+
+        let mut config = default_interpreter_config();
+        config.profile_startup_env = Some("PYEMBED_TEST_PROFILE".to_string());
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        std::mem::drop(interp);
+
+        let report = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let phases = report
+            .lines()
+            .filter_map(|line| line.strip_prefix("startup;"))
+            .filter_map(|line| line.split_once(' '))
+            .map(|(phase, _)| phase)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            phases,
+            vec![
+                "resolve_config",
+                "pre_init",
+                "core_init",
+                "inject_importer",
+                "init_main",
+                "post_main"
+            ]
+        );
+    }
+
+    #[test]
+    fn frozen_modules() {
+        let output = std::process::Command::new(PYTHON_INTERPRETER_PATH)
+            .arg("-c")
+            .arg("import marshal, sys; sys.stdout.buffer.write(marshal.dumps(compile('value = 42', 'frozen_test', 'exec')))")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let mut data = Vec::new();
+        python_packed_resources::write_packed_resources_v4(
+            &[Resource {
+                name: Cow::Borrowed("frozen_test"),
+                is_python_module: true,
+                in_memory_bytecode: Some(Cow::Owned(output.stdout)),
+                ..Resource::default()
+            }],
+            &mut data,
+            None,
+        )
+        .unwrap();
+
+        let mut config = default_interpreter_config();
+        config.frozen_modules = Some(&data);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            let imp = py.import("_imp").unwrap();
+            assert!(imp
+                .call_method1("is_frozen", ("frozen_test",))
+                .unwrap()
+                .extract::<bool>()
+                .unwrap());
+
+            let module = py.import("frozen_test").unwrap();
+            assert_eq!(module.getattr("value").unwrap().extract::<i64>().unwrap(), 42);
+
+            // The interpreter's own frozen modules remain available.
+            assert!(imp
+                .call_method1("is_frozen", ("zipimport",))
+                .unwrap()
+                .extract::<bool>()
+                .unwrap());
+        });
+    }
+
+    #[test]
+    fn frozen_modules_startup_without_stdlib() {
+        // Compile the bootstrap modules with the system interpreter.
+        let bootstrap = python_packed_resources::DEFAULT_BOOTSTRAP_MODULES
+            .iter()
+            .map(|name| {
+                let output = std::process::Command::new(PYTHON_INTERPRETER_PATH)
+                    .arg("-c")
+                    .arg("import importlib.util, marshal, sys; spec = importlib.util.find_spec(sys.argv[1]); sys.stdout.buffer.write(bytes([spec.submodule_search_locations is not None]) + marshal.dumps(spec.loader.get_code(sys.argv[1])))")
+                    .arg(name)
+                    .output()
+                    .unwrap();
+                assert!(output.status.success());
+
+                Resource {
+                    name: Cow::Borrowed(*name),
+                    is_python_module: true,
+                    is_python_package: output.stdout[0] != 0,
+                    in_memory_bytecode: Some(Cow::Owned(output.stdout[1..].to_vec())),
+                    ..Resource::default()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut packed = Vec::new();
+        python_packed_resources::write_packed_resources_v4(&bootstrap, &mut packed, None).unwrap();
+        let mut frozen = Vec::new();
+        python_packed_resources::write_frozen_modules(
+            &[&packed],
+            python_packed_resources::DEFAULT_BOOTSTRAP_MODULES,
+            &mut frozen,
+        )
+        .unwrap();
+
+        // Decoding source needs the standard library, so the application
+        // provides bytecode.
+        let output = std::process::Command::new(PYTHON_INTERPRETER_PATH)
+            .arg("-c")
+            .arg("import marshal, sys; sys.stdout.buffer.write(marshal.dumps(compile('value = 42', 'frozen_app', 'exec')))")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let mut app = Vec::new();
+        python_packed_resources::write_packed_resources_v4(
+            &[Resource {
+                name: Cow::Borrowed("frozen_app"),
+                is_python_module: true,
+                in_memory_bytecode: Some(Cow::Owned(output.stdout)),
+                ..Resource::default()
+            }],
+            &mut app,
+            None,
+        )
+        .unwrap();
+
+        // A home without a lib/ directory, so nothing can come from the filesystem.
+        let home = std::env::temp_dir().join(format!(
+            "pyembed-test-frozen-startup-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&home).unwrap();
+
+        let mut config = default_interpreter_config();
+        config.interpreter_config.home = Some(home.clone());
+        config.interpreter_config.module_search_paths = Some(vec![]);
+        config.filesystem_importer = false;
+        config.oxidized_importer = true;
+        config.packed_resources = vec![PackedResourcesSource::Memory(&app)];
+        config.frozen_modules = Some(&frozen);
+
+        let interp = MainPythonInterpreter::new(config).unwrap();
+        interp.with_gil(|py| {
+            let imp = py.import("_imp").unwrap();
+            assert!(imp
+                .call_method1("is_frozen", ("encodings",))
+                .unwrap()
+                .extract::<bool>()
+                .unwrap());
+
+            let module = py.import("frozen_app").unwrap();
+            assert_eq!(module.getattr("value").unwrap().extract::<i64>().unwrap(), 42);
+
+            assert!(py.import("json").is_err());
+        });
+        std::mem::drop(interp);
+
+        std::fs::remove_dir(&home).unwrap();
     }
 }
@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests servicing processes `multiprocessing` starts via `sys.executable`.
+
+use {
+    super::{default_interpreter_config, PYTHON_INTERPRETER_PATH},
+    crate::MainPythonInterpreter,
+    rusty_fork::rusty_fork_test,
+    std::{
+        ffi::OsString,
+        io::Write,
+        os::unix::{
+            ffi::OsStringExt,
+            io::IntoRawFd,
+            net::{UnixListener, UnixStream},
+        },
+    },
+};
+
+/// Construct an interpreter whose `sys.argv` is `args` following the executable.
+fn interpreter_with_args<'a>(args: &[String]) -> MainPythonInterpreter<'a, 'a> {
+    let mut config = default_interpreter_config();
+    config.argv = Some(
+        std::iter::once(PYTHON_INTERPRETER_PATH.to_string())
+            .chain(args.iter().cloned())
+            .map(OsString::from)
+            .collect(),
+    );
+
+    MainPythonInterpreter::new(config).unwrap()
+}
+
+rusty_fork_test! {
+    #[test]
+    fn resource_tracker() {
+        // The tracker runs until the pipe to it is closed.
+        let (tracker_r, tracker_w) = UnixStream::pair().unwrap();
+        std::mem::drop(tracker_w);
+
+        let interp = interpreter_with_args(&[
+            "-I".to_string(),
+            "-c".to_string(),
+            format!(
+                "from multiprocessing.resource_tracker import main;main({})",
+                tracker_r.into_raw_fd()
+            ),
+        ]);
+
+        assert!(interp.is_multiprocessing());
+        assert_eq!(interp.run(), 0);
+    }
+
+    #[test]
+    fn forkserver() {
+        let td = std::env::temp_dir().join(format!(
+            "pyembed-test-forkserver-{}",
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&td).unwrap();
+        let listener = UnixListener::bind(td.join("socket")).unwrap();
+
+        // The forkserver takes ownership of the descriptors passed to it and
+        // exits once the pipe to it is closed.
+        let (alive_r, alive_w) = UnixStream::pair().unwrap();
+        std::mem::drop(alive_w);
+
+        let interp = interpreter_with_args(&[
+            "-B".to_string(),
+            "-c".to_string(),
+            format!(
+                "from multiprocessing.forkserver import main; main({}, {}, ['json'], **{{}})",
+                listener.into_raw_fd(),
+                alive_r.into_raw_fd()
+            ),
+        ]);
+
+        assert!(interp.is_multiprocessing());
+        assert_eq!(interp.run_multiprocessing().unwrap(), 0);
+
+        // Preloaded modules are imported.
+        interp.with_gil(|py| {
+            let modules = py.import("sys").unwrap().getattr("modules").unwrap();
+            assert!(modules.contains("json").unwrap());
+        });
+
+        std::mem::drop(interp);
+        std::fs::remove_dir_all(&td).unwrap();
+    }
+
+    #[test]
+    fn spawn() {
+        let td = std::env::temp_dir().join(format!(
+            "pyembed-test-spawn-{}",
+            std::process::id()
+        ));
+        let created = td.join("created");
+        std::fs::create_dir_all(&td).unwrap();
+
+        // Have a regular Python pickle what multiprocessing.spawn sends to a
+        // new process: preparation data followed by the process object.
+        let output = std::process::Command::new(PYTHON_INTERPRETER_PATH)
+            .arg("-c")
+            .arg(format!(
+                "import io, multiprocessing, os, sys\n\
+                 from multiprocessing import context, reduction, spawn\n\
+                 context.set_spawning_popen(object())\n\
+                 p = multiprocessing.Process(target=os.mkdir, args=({:?},))\n\
+                 fh = io.BytesIO()\n\
+                 reduction.dump(spawn.get_preparation_data('test'), fh)\n\
+                 reduction.dump(p, fh)\n\
+                 sys.stdout.buffer.write(fh.getvalue())",
+                created.display().to_string()
+            ))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let (pipe_r, mut pipe_w) = UnixStream::pair().unwrap();
+        pipe_w.write_all(&output.stdout).unwrap();
+        std::mem::drop(pipe_w);
+
+        let (tracker_r, tracker_w) = UnixStream::pair().unwrap();
+
+        // Use the arguments multiprocessing itself starts processes with.
+        let output = std::process::Command::new(PYTHON_INTERPRETER_PATH)
+            .arg("-c")
+            .arg("import os, sys\n\
+                  from multiprocessing import spawn\n\
+                  args = spawn.get_command_line(tracker_fd=int(sys.argv[1]), pipe_handle=int(sys.argv[2]))\n\
+                  sys.stdout.buffer.write(b'\\0'.join(map(os.fsencode, args[1:])))")
+            .arg(tracker_w.into_raw_fd().to_string())
+            .arg(pipe_r.into_raw_fd().to_string())
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+
+        let args = output
+            .stdout
+            .split(|c| *c == 0)
+            .map(|arg| OsString::from_vec(arg.to_vec()).into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(args.last().map(String::as_str), Some("--multiprocessing-fork"));
+
+        let interp = interpreter_with_args(&args);
+
+        assert!(interp.is_multiprocessing());
+        assert_eq!(interp.run(), 0);
+        assert!(created.is_dir());
+
+        std::mem::drop(tracker_r);
+        std::fs::remove_dir_all(&td).unwrap();
+    }
+
+    #[test]
+    fn command_arguments_must_be_literals() {
+        let interp = interpreter_with_args(&[
+            "-c".to_string(),
+            "from multiprocessing.resource_tracker import main;main(__import__('os').getpid())"
+                .to_string(),
+        ]);
+
+        assert!(interp.is_multiprocessing());
+        assert!(interp.run_multiprocessing().is_err());
+    }
+}
@@ -5,7 +5,7 @@
 use {
     crate::OxidizedPythonInterpreterConfig,
     anyhow::{anyhow, Result},
-    oxidized_importer::{PackedResourcesSource, PyTempDir, PythonResourcesState},
+    oxidized_importer::{PackedResourcesSource, PyTempDir, PythonResourcesState, ShadowedResource},
     python_packed_resources::Resource,
     rusty_fork::rusty_fork_test,
 };
@@ -46,6 +46,203 @@ fn multiple_resource_blobs() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn layered_resource_blobs() -> Result<()> {
+    let module = |name: &str, source: Option<Vec<u8>>, bytecode: Option<Vec<u8>>| Resource {
+        name: name.to_string().into(),
+        is_python_module: true,
+        in_memory_source: source.map(|x| x.into()),
+        in_memory_bytecode: bytecode.map(|x| x.into()),
+        ..Default::default()
+    };
+
+    let serialize = |resources: Vec<Resource<'static, u8>>| -> Result<Vec<u8>> {
+        let mut state = PythonResourcesState::default();
+        for resource in resources {
+            state.add_resource(resource).unwrap();
+        }
+        state.serialize_resources(true, true)
+    };
+
+    let embedded = serialize(vec![
+        module("foo", Some(vec![1]), Some(vec![2])),
+        module("bar", Some(vec![3]), None),
+    ])?;
+    let overlay = serialize(vec![module("foo", Some(vec![4]), None)])?;
+    let fallback = serialize(vec![
+        module("bar", None, Some(vec![5])),
+        module("baz", Some(vec![6]), None),
+    ])?;
+
+    let mut resources = PythonResourcesState::default();
+    resources
+        .index_layer_data(&embedded, "embedded".to_string(), 0)
+        .map_err(|e| anyhow!(e))?;
+    // A higher priority layer replaces definitions entirely, even if indexed
+    // before a lower priority layer.
+    resources
+        .index_layer_data(&overlay, "overlay".to_string(), 1)
+        .map_err(|e| anyhow!(e))?;
+    resources
+        .index_layer_data(&fallback, "fallback".to_string(), -1)
+        .map_err(|e| anyhow!(e))?;
+
+    let data = resources.serialize_resources(true, true)?;
+    let indexed = python_packed_resources::load_resources(&data)
+        .map_err(|e| anyhow!(e))?
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow!(e))?;
+    assert_eq!(
+        indexed,
+        vec![
+            module("bar", Some(vec![3]), None),
+            module("baz", Some(vec![6]), None),
+            module("foo", Some(vec![4]), None),
+        ]
+    );
+    assert_eq!(resources.resource_layer("foo"), Some("overlay"));
+    assert_eq!(resources.resource_layer("bar"), Some("embedded"));
+    assert_eq!(resources.resource_layer("baz"), Some("fallback"));
+    assert_eq!(resources.resource_layer("missing"), None);
+
+    assert_eq!(
+        resources.shadowed_resources(),
+        &[
+            ShadowedResource {
+                name: "foo".to_string(),
+                layer: "overlay".to_string(),
+                shadowed_layer: "embedded".to_string(),
+            },
+            ShadowedResource {
+                name: "bar".to_string(),
+                layer: "embedded".to_string(),
+                shadowed_layer: "fallback".to_string(),
+            },
+        ]
+    );
+    assert_eq!(
+        resources.shadowed_resources()[0].to_string(),
+        "foo from embedded is shadowed by overlay"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn layered_resource_blobs_lossless() -> Result<()> {
+    let module = |name: &str, source: Option<Vec<u8>>, bytecode: Option<Vec<u8>>| Resource {
+        name: name.to_string().into(),
+        is_python_module: true,
+        in_memory_source: source.map(|x| x.into()),
+        in_memory_bytecode: bytecode.map(|x| x.into()),
+        ..Default::default()
+    };
+
+    let serialize = |resources: Vec<Resource<'static, u8>>| -> Result<Vec<u8>> {
+        let mut state = PythonResourcesState::default();
+        for resource in resources {
+            state.add_resource(resource).unwrap();
+        }
+        state.serialize_resources(true, true)
+    };
+
+    let base = serialize(vec![
+        module("foo", Some(vec![1]), None),
+        module("bar", Some(vec![2]), None),
+    ])?;
+    let extra = serialize(vec![
+        module("foo", None, Some(vec![3])),
+        module("bar", Some(vec![4]), None),
+    ])?;
+    let fallback = serialize(vec![module("foo", Some(vec![1]), Some(vec![3]))])?;
+
+    let mut resources = PythonResourcesState::default();
+    resources
+        .index_layer_data(&base, "base".to_string(), 0)
+        .map_err(|e| anyhow!(e))?;
+    // foo gains a field and bar has its source overridden.
+    resources
+        .index_layer_data(&extra, "extra".to_string(), 0)
+        .map_err(|e| anyhow!(e))?;
+    // foo is identical to the definition already indexed.
+    resources
+        .index_layer_data(&fallback, "fallback".to_string(), -1)
+        .map_err(|e| anyhow!(e))?;
+
+    assert_eq!(
+        resources.shadowed_resources(),
+        &[ShadowedResource {
+            name: "bar".to_string(),
+            layer: "extra".to_string(),
+            shadowed_layer: "base".to_string(),
+        }]
+    );
+
+    Ok(())
+}
+
+/// Corrupt packed resources data so its first resource parses but a later one doesn't.
+fn partially_malformed(data: &[u8]) -> Vec<u8> {
+    (0..data.len())
+        .map(|i| {
+            let mut corrupt = data.to_vec();
+            corrupt[i] ^= 0xff;
+            corrupt
+        })
+        .find(
+            |corrupt| match python_packed_resources::load_resources(corrupt) {
+                Ok(mut resources) => {
+                    matches!(resources.next(), Some(Ok(_)))
+                        && resources.any(|resource| resource.is_err())
+                }
+                Err(_) => false,
+            },
+        )
+        .expect("a single byte corruption fails partway through parsing")
+}
+
+#[test]
+fn malformed_layer_indexes_nothing() -> Result<()> {
+    let module = |name: &str, source: u8| Resource {
+        name: name.to_string().into(),
+        is_python_module: true,
+        in_memory_source: Some(vec![source].into()),
+        ..Default::default()
+    };
+
+    let mut state = PythonResourcesState::default();
+    for resource in [module("foo", 1), module("bar", 2)] {
+        state.add_resource(resource).unwrap();
+    }
+    let data = partially_malformed(&state.serialize_resources(true, true)?);
+
+    let mut state = PythonResourcesState::default();
+    state.add_resource(module("foo", 3)).unwrap();
+    let before = state.serialize_resources(true, true)?;
+
+    assert!(state
+        .index_layer_data(&data, "malformed".to_string(), 1)
+        .is_err());
+    assert_eq!(state.serialize_resources(true, true)?, before);
+    assert!(state.shadowed_resources().is_empty());
+    assert_eq!(state.resource_layer("foo"), None);
+
+    // Packed resources plugins are memory mapped and skipped if they fail to
+    // load. The mapping is released on error, so nothing may borrow from it.
+    let path = std::env::temp_dir().join(format!(
+        "pyembed-test-malformed-layer-{}.pyr",
+        std::process::id()
+    ));
+    std::fs::write(&path, &data)?;
+    let result = state.index_path_memory_mapped(&path);
+    std::fs::remove_file(&path)?;
+
+    assert!(result.is_err());
+    assert_eq!(state.serialize_resources(true, true)?, before);
+
+    Ok(())
+}
+
 #[test]
 fn test_memory_mapped_file_resources() -> Result<()> {
     let current_dir = std::env::current_exe()?
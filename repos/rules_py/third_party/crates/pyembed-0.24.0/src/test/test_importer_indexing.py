@@ -2,6 +2,8 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import _imp
+import importlib.machinery
 import os
 import pathlib
 import sys
@@ -55,6 +57,16 @@ class TestImporterConstruction(unittest.TestCase):
         f = OxidizedFinder()
         f.index_interpreter_frozen_modules()
 
+    @unittest.skipUnless(
+        hasattr(_imp, "_frozen_module_names"), "frozen modules indexed before 3.11"
+    )
+    def test_find_spec_interpreter_frozen(self):
+        f = OxidizedFinder()
+
+        spec = f.find_spec("_frozen_importlib", None)
+        self.assertIs(spec.loader, importlib.machinery.FrozenImporter)
+        self.assertIsNone(f.find_spec("_frozen_importlib_missing", None))
+
     def test_index_bytes_bad(self):
         f = OxidizedFinder()
 
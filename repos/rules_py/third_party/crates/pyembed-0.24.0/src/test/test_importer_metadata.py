@@ -291,14 +291,119 @@ class TestImporterMetadata(unittest.TestCase):
         self.assertIsInstance(requires, list)
         self.assertEqual(requires, ["foo"])
 
+    def _write_package(self):
+        package_path = self.td / "my_package"
+        package_path.mkdir()
+
+        with (package_path / "__init__.py").open("wb") as fh:
+            fh.write(b"value = 1\n")
+
+        with (package_path / "data.txt").open("wb") as fh:
+            fh.write(b"data")
+
     def test_distribution_locate_file(self):
         self._write_metadata()
+        self._write_package()
         f = self._finder_from_td()
 
         dist = list(f.find_distributions())[0]
 
-        with self.assertRaises(AttributeError):
-            dist.locate_file("METADATA")
+        path = dist.locate_file("my_package/data.txt")
+        self.assertEqual(path.name, "data.txt")
+        self.assertTrue(path.exists())
+        self.assertEqual(path.read_bytes(), b"data")
+        self.assertEqual(
+            os.fspath(path),
+            os.path.join(f.path_hook_base_str, "my_package", "data.txt"),
+        )
+
+        path = dist.locate_file(
+            pathlib.PurePosixPath("my_package-1.0.dist-info/METADATA")
+        )
+        with path.open(encoding="utf-8") as fh:
+            self.assertEqual(fh.readline(), "Name: my_package\n")
+
+        path = dist.locate_file("my_package/missing.txt")
+        self.assertFalse(path.exists())
+        with self.assertRaises(FileNotFoundError):
+            path.read_text()
+
+    def test_files_record(self):
+        self._write_metadata()
+        self._write_package()
+
+        with (self.td / "my_package-1.0.dist-info" / "RECORD").open("w") as fh:
+            fh.write("my_package/__init__.py,sha256=abc,10\n")
+            fh.write("my_package/data.txt,,\n")
+            fh.write("my_package-1.0.dist-info/RECORD,,\n")
+
+        f = self._finder_from_td()
+
+        dist = list(f.find_distributions())[0]
+
+        files = dist.files
+        self.assertEqual(
+            [str(p) for p in files],
+            [
+                "my_package/__init__.py",
+                "my_package/data.txt",
+                "my_package-1.0.dist-info/RECORD",
+            ],
+        )
+        self.assertIsInstance(files[0], importlib.metadata.PackagePath)
+        self.assertEqual(files[0].hash.mode, "sha256")
+        self.assertEqual(files[0].hash.value, "abc")
+        self.assertEqual(files[0].size, 10)
+        self.assertIsNone(files[1].hash)
+        self.assertIsNone(files[1].size)
+
+        self.assertEqual(files[0].read_text(), "value = 1\n")
+        self.assertEqual(files[1].read_binary(), b"data")
+        self.assertTrue(files[2].read_text().startswith("my_package/__init__.py"))
+
+    def test_files_derived(self):
+        self._write_metadata()
+        self._write_package()
+        f = self._finder_from_td()
+
+        dist = list(f.find_distributions())[0]
+
+        files = dist.files
+        self.assertEqual(
+            [str(p) for p in files],
+            [
+                "my_package/__init__.py",
+                "my_package/data.txt",
+                "my_package-1.0.dist-info/METADATA",
+            ],
+        )
+        self.assertEqual(files[1].read_text(), "data")
+
+    def test_packages_distributions(self):
+        self._write_metadata()
+        self._write_package()
+        f = self._finder_from_td()
+
+        sys.meta_path = [f]
+        sys.path = []
+
+        self.assertEqual(
+            importlib.metadata.packages_distributions(),
+            {"my_package": ["my_package"]},
+        )
+
+        with (self.td / "my_package-1.0.dist-info" / "top_level.txt").open("w") as fh:
+            fh.write("other\n")
+
+        # Creating a finder inspects sys.meta_path, so restore it first.
+        sys.meta_path[:] = self.old_finders
+        f = self._finder_from_td()
+        sys.meta_path = [f]
+
+        self.assertEqual(
+            importlib.metadata.packages_distributions(),
+            {"other": ["my_package"]},
+        )
 
     def test_distribution_from_name(self):
         self._write_metadata()
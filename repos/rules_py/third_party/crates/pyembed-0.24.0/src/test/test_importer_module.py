@@ -2,6 +2,7 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import sys
 import unittest
 
 SYMBOL_ATTRIBUTES = {
@@ -11,12 +12,21 @@ SYMBOL_ATTRIBUTES = {
         "entry_points",
         "files",
         "from_name",
+        "locate_file",
         "metadata",
         "name",
         "read_text",
         "requires",
         "version",
     },
+    "OxidizedDistributionFile": {
+        "__fspath__",
+        "exists",
+        "name",
+        "open",
+        "read_bytes",
+        "read_text",
+    },
     "OxidizedFinder": {
         "add_resource",
         "add_resources",
@@ -36,6 +46,7 @@ SYMBOL_ATTRIBUTES = {
         "index_interpreter_builtin_extension_modules",
         "index_interpreter_frozen_modules",
         "indexed_resources",
+        "install_open_hook",
         "invalidate_caches",
         "iter_modules",
         "multiprocessing_set_start_method",
@@ -43,7 +54,15 @@ SYMBOL_ATTRIBUTES = {
         "path_hook",
         "path_hook_base_str",
         "pkg_resources_import_auto_register",
+        "resource_layer",
+        "resources_cache_dir",
         "serialize_indexed_resources",
+        "shadowed_resources",
+        "virtual_file_paths",
+    },
+    "OxidizedOpen": {
+        "__call__",
+        "wrapped",
     },
     "OxidizedPathEntryFinder": {
         "_package",
@@ -95,10 +114,17 @@ SYMBOL_ATTRIBUTES = {
         "add_filesystem_relative",
         "add_in_memory",
         "allowed_locations",
+        "analyze_compatibility",
+        "bytecode_filename_prefix",
         "oxidize",
+        "prune_unreachable_modules",
+        "relocate_packages",
+        "retain_loaded_modules",
+        "size_report",
     },
     "OxidizedResourceReader": {
         "contents",
+        "files",
         "is_resource",
         "open_resource",
         "resource_path",
@@ -213,6 +239,9 @@ class TestImporterModule(unittest.TestCase):
 
             expected = extra | expected
 
+            if sys.version_info >= (3, 11):
+                expected.add("__getstate__")
+
             attrs = set(dir(o))
             self.assertEqual(attrs, expected, "attributes on %s" % symbol)
 
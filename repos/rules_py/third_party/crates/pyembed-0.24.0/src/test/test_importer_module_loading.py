@@ -5,6 +5,7 @@
 import importlib.machinery
 import importlib.util
 import importlib
+import linecache
 import marshal
 import os
 import pathlib
@@ -14,6 +15,7 @@ import unittest
 
 from oxidized_importer import (
     OxidizedFinder,
+    OxidizedResource,
     OxidizedResourceCollector,
     find_resources_in_path,
 )
@@ -182,6 +184,76 @@ class TestImporterModuleLoading(unittest.TestCase):
         self.assertIn("dotinit.bar", sys.modules)
         self.assertNotIn("dotinit.__init__", sys.modules)
 
+    def test_virtual_file_paths(self):
+        p = self._make_package("my_package")
+
+        with (p / "data.json").open("wb") as fh:
+            fh.write(b"{}")
+
+        with (p / "mod.py").open("wb") as fh:
+            fh.write(b"")
+
+        collector = OxidizedResourceCollector(allowed_locations=["in-memory"])
+        for r in find_resources_in_path(self.td):
+            collector.add_in_memory(r)
+
+        f = OxidizedFinder(virtual_file_paths=True)
+        f.add_resources(collector.oxidize()[0])
+        self.assertTrue(f.virtual_file_paths)
+
+        base = os.path.join(f.path_hook_base_str, "my_package")
+
+        spec = f.find_spec("my_package", None)
+        self.assertEqual(spec.origin, os.path.join(base, "__init__.py"))
+        self.assertTrue(spec.has_location)
+
+        spec = f.find_spec("my_package.mod", None)
+        self.assertEqual(spec.origin, os.path.join(base, "mod.py"))
+
+        m = importlib.util.module_from_spec(spec)
+        self.assertEqual(m.__file__, os.path.join(base, "mod.py"))
+        self.assertEqual(f.get_filename("my_package.mod"), m.__file__)
+        self.assertEqual(
+            f.get_data(os.path.join(os.path.dirname(m.__file__), "data.json")),
+            b"{}",
+        )
+
+    def _import_with_virtual_filename(self, name, virtual_file_paths):
+        # Like bytecode compiled by a collector with bytecode_filename_prefix.
+        source = "def f():\n    return 42\n"
+        filename = "<exe>/%s.py" % name.replace(".", "/")
+
+        resource = OxidizedResource()
+        resource.is_module = True
+        resource.name = name
+        resource.in_memory_source = source.encode("utf-8")
+        resource.in_memory_bytecode = marshal.dumps(compile(source, filename, "exec"))
+
+        f = OxidizedFinder(virtual_file_paths=virtual_file_paths)
+        f.add_resource(resource)
+        sys.meta_path.insert(0, f)
+
+        return importlib.import_module(name)
+
+    def test_bytecode_filename_prefix(self):
+        m = self._import_with_virtual_filename("prefix_filenames", False)
+
+        self.assertFalse(hasattr(m, "__file__"))
+        filename = m.f.__code__.co_filename
+        self.assertEqual(filename, "<exe>/prefix_filenames.py")
+        self.assertEqual(
+            linecache.getlines(filename), ["def f():\n", "    return 42\n"]
+        )
+
+    def test_bytecode_filename_prefix_virtual_file_paths(self):
+        m = self._import_with_virtual_filename("virtual_filenames", True)
+
+        # Code refers to __file__ rather than the prefix given at build time.
+        self.assertEqual(m.f.__code__.co_filename, m.__file__)
+        self.assertEqual(
+            linecache.getlines(m.__file__), ["def f():\n", "    return 42\n"]
+        )
+
 
 if __name__ == "__main__":
     unittest.main()
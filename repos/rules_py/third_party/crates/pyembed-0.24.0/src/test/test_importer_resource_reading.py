@@ -2,6 +2,7 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import errno
 import io
 import os
 import pathlib
@@ -88,8 +89,10 @@ class TestImporterResourceReading(unittest.TestCase):
         self.assertIsInstance(contents, list)
         self.assertEqual(contents, ["resource.txt"])
 
-        with self.assertRaises(FileNotFoundError):
-            r.resource_path("resource.txt")
+        # In-memory resources are materialized on the filesystem.
+        path = pathlib.Path(r.resource_path("resource.txt"))
+        self.assertEqual(path.name, "resource.txt")
+        self.assertEqual(path.read_bytes(), b"my resource")
 
         with self.assertRaises(FileNotFoundError):
             r.open_resource("missing")
@@ -125,6 +128,103 @@ class TestImporterResourceReading(unittest.TestCase):
         self.assertEqual(r.open_resource("child0/a.txt").getvalue(), b"a")
         self.assertEqual(r.open_resource("child1/b.txt").getvalue(), b"b")
 
+    def test_open_hook(self):
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        with (p / "mod.py").open("wb") as fh:
+            fh.write(b"value = 1\n")
+
+        f = self._finder_from_td()
+
+        import builtins
+
+        original_open = builtins.open
+        original_io_open = io.open
+
+        try:
+            f.install_open_hook()
+            f.install_open_hook()
+
+            self.assertIs(builtins.open.wrapped, original_open)
+            self.assertIs(io.open, builtins.open)
+
+            base = pathlib.Path(f.path_hook_base_str) / "my_package"
+
+            with open(base / "resource.txt", "rb") as fh:
+                self.assertEqual(fh.read(), b"my resource")
+
+            self.assertEqual((base / "resource.txt").read_text(), "my resource")
+            self.assertEqual((base / "mod.py").read_text(), "value = 1\n")
+
+            for path in (base / "missing.txt", base / "missing" / "file.txt"):
+                for mode in ("rb", "r"):
+                    with self.assertRaises(FileNotFoundError) as e:
+                        open(path, mode)
+
+                    self.assertEqual(e.exception.errno, errno.ENOENT)
+                    self.assertEqual(e.exception.strerror, os.strerror(errno.ENOENT))
+                    self.assertEqual(e.exception.filename, str(path))
+
+            # Writes and paths outside the executable go to the filesystem.
+            with self.assertRaises(OSError):
+                open(base / "resource.txt", "wb")
+
+            self.assertEqual((p / "resource.txt").read_bytes(), b"my resource")
+        finally:
+            builtins.open = original_open
+            io.open = original_io_open
+
+    def test_materialize(self):
+        import importlib.resources
+
+        p = self._make_package("my_package")
+
+        with (p / "resource.txt").open("wb") as fh:
+            fh.write(b"my resource")
+
+        (p / "subdir").mkdir()
+        with (p / "subdir" / "child.txt").open("wb") as fh:
+            fh.write(b"child")
+
+        f = self._finder_from_td()
+        r = f.get_resource_reader("my_package")
+
+        path = pathlib.Path(r.resource_path("resource.txt"))
+        self.assertEqual(path.name, "resource.txt")
+        self.assertEqual(path.read_bytes(), b"my resource")
+
+        with self.assertRaises(FileNotFoundError):
+            r.resource_path("missing.txt")
+
+        files = r.files()
+        self.assertTrue(files.is_dir())
+        self.assertEqual(
+            sorted(x.name for x in files.iterdir()), ["resource.txt", "subdir"]
+        )
+        self.assertEqual((files / "subdir" / "child.txt").read_text(), "child")
+        self.assertTrue(files.joinpath("subdir", "child.txt").is_file())
+
+        with importlib.resources.as_file(files / "subdir") as path:
+            self.assertEqual((path / "child.txt").read_bytes(), b"child")
+
+        # Materialized files are reused across finders with a cache directory.
+        cache_dir = self.td / "cache"
+        paths = []
+        for _ in range(2):
+            f = OxidizedFinder(resources_cache_dir=cache_dir)
+            f.add_resources(self._finder_from_td().indexed_resources())
+            self.assertEqual(f.resources_cache_dir, str(cache_dir))
+
+            r = f.get_resource_reader("my_package")
+            paths.append(pathlib.Path(r.resource_path("resource.txt")))
+
+        self.assertEqual(paths[0], paths[1])
+        self.assertTrue(paths[0].is_relative_to(cache_dir))
+        self.assertEqual(paths[0].read_bytes(), b"my resource")
+
 
 if __name__ == "__main__":
     unittest.main()
@@ -2,8 +2,11 @@
 # License, v. 2.0. If a copy of the MPL was not distributed with this
 # file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+import concurrent.futures
+import os
 import sys
 import multiprocessing
+import multiprocessing.spawn
 import unittest
 
 
@@ -11,31 +14,79 @@ def multiply(x):
     return x * x
 
 
-# We can't run on Windows because the "spawn" start method relies on
-# spawning a new process. That process needs to recognize the
-# --multiprocessing-fork argument. The choices of executables to spawn
-# are the python.exe (which is the default in the test environment) and
-# the Rust test executable. Neither of which recognize
-# --multiprocessing-fork. So we're out of luck for testing "spawn" start
-# methods.
-@unittest.skipIf(sys.platform == "win32", "spawn method not supported")
+def is_imported(name):
+    return name in sys.modules
+
+
+# This runs in an interpreter embedded in the multiprocessing Rust test
+# executable, once with sys.frozen set and once without. That executable
+# services the processes multiprocessing starts via sys.executable. Detecting
+# them relies on Linux specifics.
+@unittest.skipUnless(sys.platform == "linux", "embedded binary requires Linux")
 class TestMultiprocessing(unittest.TestCase):
-    @classmethod
-    def setUpClass(cls):
-        sys.frozen = True
+    def test_embedded_binary(self):
+        self.assertEqual(sys.executable, os.readlink("/proc/self/exe"))
+        self.assertEqual(
+            multiprocessing.spawn.get_executable(), os.fsencode(sys.executable)
+        )
 
-        if sys.platform == "win32":
-            multiprocessing.set_start_method("spawn", force=True)
+        # Processes are spawned the way they are for frozen applications only
+        # if sys.frozen is set.
+        frozen = os.environ["PYEMBED_TEST_EMBEDDED_BINARY"] == "frozen"
+        self.assertEqual(getattr(sys, "frozen", False), frozen)
+
+        args = multiprocessing.spawn.get_command_line()
+        if frozen:
+            self.assertEqual(args[1:], ["--multiprocessing-fork"])
         else:
-            multiprocessing.set_start_method("fork", force=True)
+            self.assertIn("-c", args)
+            self.assertEqual(args[-1], "--multiprocessing-fork")
 
-    def test_pool(self):
-        with multiprocessing.Pool(4) as p:
-            p.map(multiply, range(1024))
+    def _test_pool(self, context):
+        with context.Pool(4) as p:
+            self.assertEqual(
+                p.map(multiply, range(1024)), [x * x for x in range(1024)]
+            )
 
         # Do it twice to ensure state isn't funky.
-        with multiprocessing.Pool(4) as p:
-            p.map(multiply, range(1024))
+        with context.Pool(4) as p:
+            self.assertEqual(
+                p.map(multiply, range(1024)), [x * x for x in range(1024)]
+            )
+
+    def _test_process_pool_executor(self, context):
+        with concurrent.futures.ProcessPoolExecutor(4, mp_context=context) as e:
+            results = list(e.map(multiply, range(8)))
+
+        self.assertEqual(results, [x * x for x in range(8)])
+
+    def test_fork(self):
+        context = multiprocessing.get_context("fork")
+
+        self._test_pool(context)
+        self._test_process_pool_executor(context)
+
+    def test_spawn(self):
+        context = multiprocessing.get_context("spawn")
+
+        self._test_pool(context)
+        self._test_process_pool_executor(context)
+
+        with context.Pool(1) as p:
+            self.assertFalse(p.apply(is_imported, ("colorsys",)))
+
+    def test_forkserver(self):
+        context = multiprocessing.get_context("forkserver")
+        context.set_forkserver_preload(["colorsys"])
+
+        self._test_pool(context)
+        self._test_process_pool_executor(context)
+
+        # Workers are forked from the server, which imported the preloaded
+        # modules.
+        self.assertNotIn("colorsys", sys.modules)
+        with context.Pool(1) as p:
+            self.assertTrue(p.apply(is_imported, ("colorsys",)))
 
 
 if __name__ == "__main__":
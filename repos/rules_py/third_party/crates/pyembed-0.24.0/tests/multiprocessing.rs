@@ -0,0 +1,115 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Tests `multiprocessing` end to end from an embedded interpreter.
+//!
+//! `multiprocessing` starts processes via `sys.executable` with arguments the
+//! test harness would reject. So this executable doesn't use the harness and
+//! acts as the embedded binary itself: it runs `test_multiprocessing.py` in a
+//! child process per case and services the processes `multiprocessing` starts
+//! from there.
+
+use {
+    pyembed::{MainPythonInterpreter, OxidizedPythonInterpreterConfig},
+    std::{
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+const PYTHON_INTERPRETER_PATH: &str = env!("PYTHON_INTERPRETER_PATH");
+
+/// Environment variable making this executable act as the embedded binary.
+///
+/// Its value is the name of the case being run. Processes `multiprocessing`
+/// starts inherit it.
+const EMBEDDED_BINARY_ENV: &str = "PYEMBED_TEST_EMBEDDED_BINARY";
+
+/// Cases to run, as (name, whether `sys.frozen` is set) pairs.
+///
+/// When `sys.frozen` is set, the spawn start method runs `<exe> --multiprocessing-fork`
+/// instead of a `-c` command.
+const CASES: &[(&str, bool)] = &[("regular", false), ("frozen", true)];
+
+/// Obtain the configuration of the embedded interpreter for a case.
+///
+/// The standard library is that of the Python interpreter the tests are built
+/// against.
+fn embedded_interpreter_config<'a>(sys_frozen: bool) -> OxidizedPythonInterpreterConfig<'a> {
+    let exe = std::env::current_exe().unwrap();
+
+    let mut config = OxidizedPythonInterpreterConfig::default();
+    config.oxidized_importer = true;
+    config.sys_frozen = sys_frozen;
+    config.set_missing_path_configuration = false;
+    config.argv = Some(
+        std::iter::once(exe.clone().into_os_string())
+            .chain(std::env::args_os().skip(1))
+            .collect(),
+    );
+    config.interpreter_config.parse_argv = Some(false);
+    config.interpreter_config.executable = Some(exe);
+    config.interpreter_config.home = Path::new(PYTHON_INTERPRETER_PATH)
+        .parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf);
+    config.interpreter_config.site_import = Some(false);
+    config.interpreter_config.user_site_directory = Some(false);
+    config.interpreter_config.buffered_stdio = Some(false);
+
+    config
+}
+
+/// Run the embedded interpreter for a case and exit.
+///
+/// Without arguments, this runs the Python tests. Otherwise `multiprocessing`
+/// started this process.
+fn run_embedded_binary(case: &str) -> ! {
+    let (_, sys_frozen) = CASES
+        .iter()
+        .find(|(name, _)| *name == case)
+        .unwrap_or_else(|| panic!("unknown case: {}", case));
+
+    let mut config = embedded_interpreter_config(*sys_frozen);
+
+    let is_test = std::env::args_os().len() == 1;
+    if is_test {
+        config.interpreter_config.run_filename =
+            Some(PathBuf::from(env!("PYEMBED_TESTS_DIR")).join("test_multiprocessing.py"));
+    }
+
+    let interp = MainPythonInterpreter::new(config).unwrap();
+    assert!(is_test || interp.is_multiprocessing());
+
+    std::process::exit(interp.run());
+}
+
+fn main() {
+    if let Some(case) = std::env::var_os(EMBEDDED_BINARY_ENV) {
+        run_embedded_binary(&case.to_string_lossy());
+    }
+
+    let exe = std::env::current_exe().unwrap();
+
+    let mut failed = false;
+
+    for (name, _) in CASES {
+        let status = Command::new(&exe)
+            .env(EMBEDDED_BINARY_ENV, name)
+            .status()
+            .unwrap();
+
+        println!(
+            "test multiprocessing::{} ... {}",
+            name,
+            if status.success() { "ok" } else { "FAILED" }
+        );
+
+        failed |= !status.success();
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,209 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Recording of module import timings.
+*/
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Where the code of an imported module came from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModuleSource {
+    /// Source, bytecode, or extension module data held in memory.
+    Memory,
+
+    /// A file at a path relative to the origin.
+    RelativePath,
+
+    /// An extension module built-in to the interpreter.
+    Builtin,
+
+    /// A module frozen into the interpreter.
+    Frozen,
+}
+
+impl ModuleSource {
+    /// Obtain a short string identifying this source.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Memory => "memory",
+            Self::RelativePath => "relative_path",
+            Self::Builtin => "builtin",
+            Self::Frozen => "frozen",
+        }
+    }
+}
+
+/// Timings for the import of a single module by `OxidizedFinder`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModuleImportTiming {
+    /// Fully qualified name of the module.
+    pub name: String,
+
+    /// Where the module came from.
+    pub source: Option<ModuleSource>,
+
+    /// Name of the packed resources layer the module was indexed from.
+    pub layer: Option<String>,
+
+    /// Whether the module was first imported before [ImportProfile::end_startup()].
+    pub startup: bool,
+
+    /// Time spent in `find_spec()`.
+    pub find: Duration,
+
+    /// Time spent in `create_module()`.
+    pub load: Duration,
+
+    /// Time spent in `exec_module()`, including imports of other modules.
+    pub exec: Duration,
+
+    /// Names of modules being executed when this module was executed, outermost first.
+    pub parents: Vec<String>,
+}
+
+impl ModuleImportTiming {
+    fn new(name: &str, startup: bool) -> Self {
+        Self {
+            name: name.to_string(),
+            source: None,
+            layer: None,
+            startup,
+            find: Duration::ZERO,
+            load: Duration::ZERO,
+            exec: Duration::ZERO,
+            parents: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct ImportProfileState {
+    modules: Vec<ModuleImportTiming>,
+    /// Index in `modules` of the most recent entry for each name.
+    latest: HashMap<String, usize>,
+    /// Names of modules currently being executed.
+    executing: Vec<String>,
+    /// Whether interpreter startup has completed.
+    startup_complete: bool,
+    /// Package resources that were read.
+    resources: BTreeSet<(String, String)>,
+}
+
+impl ImportProfileState {
+    fn entry(&mut self, name: &str) -> &mut ModuleImportTiming {
+        let index = match self.latest.get(name) {
+            Some(index) => *index,
+            None => {
+                self.modules
+                    .push(ModuleImportTiming::new(name, !self.startup_complete));
+                self.latest.insert(name.to_string(), self.modules.len() - 1);
+                self.modules.len() - 1
+            }
+        };
+
+        &mut self.modules[index]
+    }
+}
+
+/// Records timings of module imports serviced by an `OxidizedFinder`.
+///
+/// Package resources read through `OxidizedResourceReader` are recorded as well.
+///
+/// Instances are shared with the code that installed the finder, so results
+/// can be obtained without going through Python.
+#[derive(Debug, Default)]
+pub struct ImportProfile {
+    state: Mutex<ImportProfileState>,
+}
+
+impl ImportProfile {
+    /// Obtain timings of imported modules, in the order they were first found.
+    pub fn modules(&self) -> Vec<ModuleImportTiming> {
+        self.state.lock().unwrap().modules.clone()
+    }
+
+    /// Obtain `(package, resource name)` pairs of package resources that were read.
+    pub fn resources(&self) -> Vec<(String, String)> {
+        self.state
+            .lock()
+            .unwrap()
+            .resources
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    /// Mark the end of interpreter startup.
+    ///
+    /// Modules first imported afterwards aren't considered needed for startup.
+    pub fn end_startup(&self) {
+        self.state.lock().unwrap().startup_complete = true;
+    }
+
+    pub(crate) fn record_find(
+        &self,
+        name: &str,
+        source: ModuleSource,
+        layer: Option<&str>,
+        start: Instant,
+    ) {
+        let elapsed = start.elapsed();
+        let mut state = self.state.lock().unwrap();
+
+        // Finding a module again starts a new import. e.g. after removal from
+        // `sys.modules`.
+        if let Some(index) = state.latest.get(name) {
+            if state.modules[*index].exec != Duration::ZERO {
+                state.latest.remove(name);
+            }
+        }
+
+        let parents = state.executing.clone();
+        let entry = state.entry(name);
+        entry.source = Some(source);
+        entry.layer = layer.map(|x| x.to_string());
+        entry.find += elapsed;
+        entry.parents = parents;
+    }
+
+    pub(crate) fn record_resource(&self, package: &str, name: &str) {
+        self.state
+            .lock()
+            .unwrap()
+            .resources
+            .insert((package.to_string(), name.to_string()));
+    }
+
+    pub(crate) fn record_load(&self, name: &str, start: Instant) {
+        let elapsed = start.elapsed();
+        self.state.lock().unwrap().entry(name).load += elapsed;
+    }
+
+    pub(crate) fn begin_exec(&self, name: &str) -> Instant {
+        let mut state = self.state.lock().unwrap();
+
+        let parents = state.executing.clone();
+        state.entry(name).parents = parents;
+        state.executing.push(name.to_string());
+
+        Instant::now()
+    }
+
+    pub(crate) fn end_exec(&self, name: &str, start: Instant) {
+        let elapsed = start.elapsed();
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(position) = state.executing.iter().rposition(|x| x == name) {
+            state.executing.truncate(position);
+        }
+
+        state.entry(name).exec += elapsed;
+    }
+}
@@ -19,11 +19,14 @@ use {
     crate::{
         conversion::pyobject_to_pathbuf,
         get_module_state,
+        import_profile::ImportProfile,
+        open_hook::OxidizedOpen,
         path_entry_finder::OxidizedPathEntryFinder,
         pkg_resources::register_pkg_resources_with_module,
         python_resources::{
             pyobject_to_resource, ModuleFlavor, OxidizedResource, PythonResourcesState,
         },
+        resource_materializer::ResourceMaterializer,
         resource_reader::OxidizedResourceReader,
         OXIDIZED_IMPORTER_NAME_STR,
     },
@@ -35,7 +38,12 @@ use {
         AsPyPointer, FromPyPointer, PyNativeType, PyTraverseError, PyVisit,
     },
     python_packaging::resource::BytecodeOptimizationLevel,
-    std::sync::Arc,
+    std::{
+        collections::HashSet,
+        path::PathBuf,
+        sync::{Arc, Mutex},
+        time::Instant,
+    },
 };
 
 #[cfg(windows)]
@@ -255,6 +263,11 @@ pub struct ImporterState {
     pub(crate) builtin_importer: Py<PyAny>,
     /// `_frozen_importlib.FrozenImporter` meta path importer for frozen modules.
     pub(crate) frozen_importer: Py<PyAny>,
+    /// Names of the interpreter's own frozen modules that are enabled.
+    ///
+    /// Python 3.11+ doesn't expose these via `PyImport_FrozenModules`, so
+    /// they can't be indexed.
+    pub(crate) interpreter_frozen_modules: HashSet<String>,
     /// `importlib._bootstrap._call_with_frames_removed` function.
     pub(crate) call_with_frames_removed: Py<PyAny>,
     /// `importlib._bootstrap.ModuleSpec` class.
@@ -271,6 +284,16 @@ pub struct ImporterState {
     pub(crate) multiprocessing_set_start_method: Option<String>,
     /// Whether to automatically register ourself with `pkg_resources` when it is imported.
     pub(crate) pkg_resources_import_auto_register: bool,
+    /// Whether in-memory modules get a virtual `__file__` under the current executable.
+    pub(crate) virtual_file_paths: bool,
+    /// Records timings of imports, if enabled.
+    pub(crate) import_profile: Option<Arc<ImportProfile>>,
+    /// Writes resources to the filesystem for consumers requiring paths.
+    pub(crate) resource_materializer: ResourceMaterializer,
+    /// (filename, module name) of executed modules to register with `linecache`.
+    ///
+    /// Entries accumulate until `linecache` is imported.
+    pub(crate) linecache_pending: Mutex<Vec<(String, String)>>,
     /// Holds state about importable resources.
     ///
     /// This field is a PyCapsule and is a glorified wrapper around
@@ -301,26 +324,34 @@ impl ImporterState {
         let marshal_module = py.import("marshal")?;
 
         let imp_module = bootstrap_module.getattr("_imp")?;
-        let imp_module = imp_module.cast_as::<PyModule>()?.into_py(py);
+        let imp_module = imp_module.cast_as::<PyModule>()?;
+
+        // `_frozen_module_names()` lists frozen modules regardless of whether
+        // `-X frozen_modules` disables them. It is new in Python 3.11.
+        let mut interpreter_frozen_modules = HashSet::new();
+        if imp_module.hasattr("_frozen_module_names")? {
+            for name in imp_module
+                .call_method0("_frozen_module_names")?
+                .extract::<Vec<String>>()?
+            {
+                if imp_module
+                    .call_method1("is_frozen", (name.as_str(),))?
+                    .is_true()?
+                {
+                    interpreter_frozen_modules.insert(name);
+                }
+            }
+        }
+
+        let imp_module = imp_module.into_py(py);
         let sys_module = bootstrap_module.getattr("sys")?;
         let sys_module = sys_module.cast_as::<PyModule>()?;
-        let meta_path_object = sys_module.getattr("meta_path")?;
-
-        // We should be executing as part of
-        // _frozen_importlib_external._install_external_importers().
-        // _frozen_importlib._install() should have already been called and set up
-        // sys.meta_path with [BuiltinImporter, FrozenImporter]. Those should be the
-        // only meta path importers present.
-
-        let meta_path = meta_path_object.cast_as::<PyList>()?;
-        if meta_path.len() < 2 {
-            return Err(PyValueError::new_err(
-                "sys.meta_path does not contain 2 values",
-            ));
-        }
 
-        let builtin_importer = meta_path.get_item(0)?.into_py(py);
-        let frozen_importer = meta_path.get_item(1)?.into_py(py);
+        // These are also the first entries of `sys.meta_path` when we're
+        // installed during interpreter initialization. Finders constructed
+        // later can't rely on that, as `sys.meta_path` may have changed.
+        let builtin_importer = bootstrap_module.getattr("BuiltinImporter")?.into_py(py);
+        let frozen_importer = bootstrap_module.getattr("FrozenImporter")?.into_py(py);
 
         let marshal_loads = marshal_module.getattr("loads")?.into_py(py);
         let call_with_frames_removed = bootstrap_module
@@ -381,6 +412,7 @@ impl ImporterState {
             marshal_loads,
             builtin_importer,
             frozen_importer,
+            interpreter_frozen_modules,
             call_with_frames_removed,
             module_spec_type,
             decode_source,
@@ -389,6 +421,10 @@ impl ImporterState {
             multiprocessing_set_start_method: None,
             // TODO value should come from config.
             pkg_resources_import_auto_register: true,
+            virtual_file_paths: false,
+            import_profile: None,
+            linecache_pending: Mutex::new(vec![]),
+            resource_materializer: ResourceMaterializer::new(None),
             resources_state: capsule,
         })
     }
@@ -448,6 +484,24 @@ impl ImporterState {
     pub fn set_multiprocessing_set_start_method(&mut self, value: Option<String>) {
         self.multiprocessing_set_start_method = value;
     }
+
+    /// Set whether in-memory modules get a virtual `__file__` under the current executable.
+    pub fn set_virtual_file_paths(&mut self, value: bool) {
+        self.virtual_file_paths = value;
+    }
+
+    /// Set the directory to reuse materialized resources from across runs.
+    ///
+    /// If `None`, resources are materialized to a temporary directory removed
+    /// at interpreter exit.
+    pub fn set_resources_cache_dir(&mut self, value: Option<PathBuf>) {
+        self.resource_materializer = ResourceMaterializer::new(value);
+    }
+
+    /// Set the instance to record timings of imports into.
+    pub fn set_import_profile(&mut self, profile: Option<Arc<ImportProfile>>) {
+        self.import_profile = profile;
+    }
 }
 
 impl Drop for ImporterState {
@@ -523,6 +577,7 @@ impl OxidizedFinder {
     ) -> PyResult<&'p PyAny> {
         let py = slf.py();
         let finder = slf.borrow();
+        let start = Instant::now();
 
         let module = match finder
             .state
@@ -530,15 +585,25 @@ impl OxidizedFinder {
             .resolve_importable_module(&fullname, finder.state.optimize_level)
         {
             Some(module) => module,
+            None if finder.state.interpreter_frozen_modules.contains(&fullname) => {
+                return Ok(finder
+                    .state
+                    .frozen_importer
+                    .call_method(py, "find_spec", (fullname.as_str(), path, target), None)?
+                    .into_ref(py));
+            }
             None => return Ok(py.None().into_ref(py)),
         };
 
-        match module.flavor {
+        let source = module.source();
+
+        let spec = match module.flavor {
             ModuleFlavor::Extension | ModuleFlavor::SourceBytecode => module.resolve_module_spec(
                 py,
                 finder.state.module_spec_type.clone_ref(py).into_ref(py),
                 slf,
                 finder.state.optimize_level,
+                finder.state.virtual_file_paths,
             ),
             ModuleFlavor::Builtin => {
                 // BuiltinImporter.find_spec() always returns None if `path` is defined.
@@ -546,15 +611,26 @@ impl OxidizedFinder {
                 Ok(finder
                     .state
                     .builtin_importer
-                    .call_method(py, "find_spec", (fullname,), None)?
+                    .call_method(py, "find_spec", (fullname.as_str(),), None)?
                     .into_ref(py))
             }
             ModuleFlavor::Frozen => Ok(finder
                 .state
                 .frozen_importer
-                .call_method(py, "find_spec", (fullname, path, target), None)?
+                .call_method(py, "find_spec", (fullname.as_str(), path, target), None)?
                 .into_ref(py)),
+        };
+
+        if let Some(profile) = &finder.state.import_profile {
+            profile.record_find(
+                &fullname,
+                source,
+                finder.state.get_resources_state().resource_layer(&fullname),
+                start,
+            );
         }
+
+        spec
     }
 
     fn find_module<'p>(
@@ -581,126 +657,29 @@ impl OxidizedFinder {
     // Start of importlib.abc.Loader interface.
 
     fn create_module(slf: &PyCell<Self>, spec: &PyAny) -> PyResult<Py<PyAny>> {
-        let py = slf.py();
-        let finder = slf.borrow();
-        let state = &finder.state;
-
-        let name = spec.getattr("name")?;
-        let key = name.extract::<String>()?;
-
-        let module = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
-            Some(module) => module,
-            None => return Ok(py.None()),
-        };
+        let start = Instant::now();
+        let result = Self::create_module_inner(slf, spec);
 
-        // Extension modules need special module creation logic.
-        if module.flavor == ModuleFlavor::Extension {
-            // We need a custom implementation of create_module() for in-memory shared
-            // library extensions because if we wait until `exec_module()` to
-            // initialize the module object, this can confuse some CPython
-            // internals. A side-effect of initializing extension modules is
-            // populating `sys.modules` and this made `LazyLoader` unhappy.
-            // If we ever implement our own lazy module importer, we could
-            // potentially work around this and move all extension module
-            // initialization into `exec_module()`.
-            if let Some(library_data) = &module.in_memory_extension_module_shared_library() {
-                let sys_modules = state.sys_module.getattr(py, "modules")?;
-
-                extension_module_shared_library_create_module(
-                    state.get_resources_state(),
-                    py,
-                    sys_modules.into_ref(py),
-                    spec,
-                    name,
-                    &key,
-                    library_data,
-                )
-            } else {
-                // Call `imp.create_dynamic()` for dynamic extension modules.
-                let create_dynamic = state.imp_module.getattr(py, "create_dynamic")?;
-
-                state
-                    .call_with_frames_removed
-                    .call(py, (&create_dynamic, spec), None)
-            }
-        } else {
-            Ok(py.None())
+        if let Some(profile) = &slf.borrow().state.import_profile {
+            profile.record_load(&spec.getattr("name")?.extract::<String>()?, start);
         }
+
+        result
     }
 
     fn exec_module(slf: &PyCell<Self>, module: &PyAny) -> PyResult<Py<PyAny>> {
-        let py = slf.py();
-        let finder = slf.borrow();
-        let state = &finder.state;
+        let profile = slf.borrow().state.import_profile.clone();
 
-        let name = module.getattr("__name__")?;
-        let key = name.extract::<String>()?;
+        if let Some(profile) = profile {
+            let name = module.getattr("__name__")?.extract::<String>()?;
+            let start = profile.begin_exec(&name);
+            let result = Self::exec_module_inner(slf, module);
+            profile.end_exec(&name, start);
 
-        let mut entry = match state
-            .get_resources_state()
-            .resolve_importable_module(&key, state.optimize_level)
-        {
-            Some(entry) => entry,
-            None => {
-                // Raising here might make more sense, as `find_spec()` shouldn't have returned
-                // an entry for something that we don't know how to handle.
-                return Ok(py.None());
-            }
-        };
-
-        if let Some(bytecode) = entry.resolve_bytecode(
-            py,
-            state.optimize_level,
-            state.decode_source.as_ref(py),
-            state.io_module.as_ref(py),
-        )? {
-            let code = state.marshal_loads.call(py, (bytecode,), None)?;
-            let dict = module.getattr("__dict__")?;
-
-            state
-                .call_with_frames_removed
-                .call(py, (&state.exec_fn, code, dict), None)
-        } else if entry.flavor == ModuleFlavor::Builtin {
-            state
-                .builtin_importer
-                .call_method(py, "exec_module", (module,), None)
-        } else if entry.flavor == ModuleFlavor::Frozen {
-            state
-                .frozen_importer
-                .call_method(py, "exec_module", (module,), None)
-        } else if entry.flavor == ModuleFlavor::Extension {
-            // `ExtensionFileLoader.exec_module()` simply calls `imp.exec_dynamic()`.
-            let exec_dynamic = state.imp_module.getattr(py, "exec_dynamic")?;
-
-            state
-                .call_with_frames_removed
-                .call(py, (&exec_dynamic, module), None)
+            result
         } else {
-            Ok(py.None())
-        }?;
-
-        // Perform import time side-effects for special modules.
-        match key.as_str() {
-            "multiprocessing" => {
-                if let Some(method) = state.multiprocessing_set_start_method.as_ref() {
-                    // We pass force=True to ensure the call doesn't fail.
-                    let kwargs = PyDict::new(py);
-                    kwargs.set_item("force", true)?;
-                    module.call_method("set_start_method", (method,), Some(kwargs))?;
-                }
-            }
-            "pkg_resources" => {
-                if state.pkg_resources_import_auto_register {
-                    register_pkg_resources_with_module(py, module)?;
-                }
-            }
-            _ => {}
+            Self::exec_module_inner(slf, module)
         }
-
-        Ok(py.None())
     }
 
     // End of importlib.abc.Loader interface.
@@ -805,7 +784,7 @@ impl OxidizedFinder {
             .ok_or_else(|| make_error("unknown module"))?;
 
         module
-            .resolve_origin(slf.py())
+            .resolve_origin(slf.py(), state.virtual_file_paths)
             .map_err(|_| make_error("unable to resolve origin"))?
             .ok_or_else(|| make_error("no origin"))
     }
@@ -902,10 +881,20 @@ impl OxidizedFinder {
 
     // Additional methods provided for convenience.
 
-    /// OxidizedFinder.__new__(relative_path_origin=None))
+    /// OxidizedFinder.__new__(relative_path_origin=None, virtual_file_paths=False,
+    /// resources_cache_dir=None))
     #[new]
-    #[args(relative_path_origin = "None")]
-    fn new(py: Python, relative_path_origin: Option<&PyAny>) -> PyResult<Self> {
+    #[args(
+        relative_path_origin = "None",
+        virtual_file_paths = "false",
+        resources_cache_dir = "None"
+    )]
+    fn new(
+        py: Python,
+        relative_path_origin: Option<&PyAny>,
+        virtual_file_paths: bool,
+        resources_cache_dir: Option<&PyAny>,
+    ) -> PyResult<Self> {
         // We need to obtain an ImporterState instance. This requires handles on a
         // few items...
 
@@ -921,13 +910,14 @@ impl OxidizedFinder {
             resources_state.set_origin(pyobject_to_pathbuf(py, py_origin)?);
         }
 
+        let mut state = ImporterState::new(py, m, bootstrap_module, resources_state)?;
+        state.set_virtual_file_paths(virtual_file_paths);
+        if let Some(py_cache_dir) = resources_cache_dir {
+            state.set_resources_cache_dir(Some(pyobject_to_pathbuf(py, py_cache_dir)?));
+        }
+
         Ok(OxidizedFinder {
-            state: Arc::new(ImporterState::new(
-                py,
-                m,
-                bootstrap_module,
-                resources_state,
-            )?),
+            state: Arc::new(state),
         })
     }
 
@@ -963,6 +953,20 @@ impl OxidizedFinder {
         Ok(self.state.pkg_resources_import_auto_register)
     }
 
+    #[getter]
+    fn virtual_file_paths(&self) -> bool {
+        self.state.virtual_file_paths
+    }
+
+    #[getter]
+    fn resources_cache_dir<'p>(&self, py: Python<'p>) -> &'p PyAny {
+        self.state
+            .resource_materializer
+            .cache_dir()
+            .into_py(py)
+            .into_ref(py)
+    }
+
     fn path_hook(slf: &PyCell<Self>, path: &PyAny) -> PyResult<OxidizedPathEntryFinder> {
         Self::path_hook_inner(slf, path).map_err(|inner| {
             let err = PyImportError::new_err("error running OxidizedFinder.path_hook");
@@ -988,6 +992,23 @@ impl OxidizedFinder {
         Ok(())
     }
 
+    fn install_open_hook(&self, py: Python) -> PyResult<()> {
+        let builtins = py.import("builtins")?;
+        let open = builtins.getattr("open")?;
+
+        // Don't wrap ourselves if installed multiple times.
+        if open.is_instance_of::<OxidizedOpen>()? {
+            return Ok(());
+        }
+
+        let hook = PyCell::new(py, OxidizedOpen::new(py, self.state.clone(), open)?)?;
+
+        builtins.setattr("open", hook)?;
+        py.import("io")?.setattr("open", hook)?;
+
+        Ok(())
+    }
+
     fn index_file_memory_mapped(&self, py: Python, path: &PyAny) -> PyResult<()> {
         let path = pyobject_to_pathbuf(py, path)?;
 
@@ -1032,6 +1053,31 @@ impl OxidizedFinder {
         resources_state.resources_as_py_list(py)
     }
 
+    fn resource_layer(&self, name: &str) -> Option<String> {
+        self.state
+            .get_resources_state()
+            .resource_layer(name)
+            .map(|layer| layer.to_string())
+    }
+
+    fn shadowed_resources<'p>(&self, py: Python<'p>) -> &'p PyList {
+        PyList::new(
+            py,
+            self.state
+                .get_resources_state()
+                .shadowed_resources()
+                .iter()
+                .map(|shadowed| {
+                    (
+                        shadowed.name.as_str(),
+                        shadowed.layer.as_str(),
+                        shadowed.shadowed_layer.as_str(),
+                    )
+                        .to_object(py)
+                }),
+        )
+    }
+
     fn add_resource(&self, resource: &OxidizedResource) -> PyResult<()> {
         let resources_state = self.state.get_resources_state_mut();
 
@@ -1075,6 +1121,148 @@ impl OxidizedFinder {
 }
 
 impl OxidizedFinder {
+    fn create_module_inner(slf: &PyCell<Self>, spec: &PyAny) -> PyResult<Py<PyAny>> {
+        let py = slf.py();
+        let finder = slf.borrow();
+        let state = &finder.state;
+
+        let name = spec.getattr("name")?;
+        let key = name.extract::<String>()?;
+
+        let module = match state
+            .get_resources_state()
+            .resolve_importable_module(&key, state.optimize_level)
+        {
+            Some(module) => module,
+            None => return Ok(py.None()),
+        };
+
+        // Extension modules need special module creation logic.
+        if module.flavor == ModuleFlavor::Extension {
+            // We need a custom implementation of create_module() for in-memory shared
+            // library extensions because if we wait until `exec_module()` to
+            // initialize the module object, this can confuse some CPython
+            // internals. A side-effect of initializing extension modules is
+            // populating `sys.modules` and this made `LazyLoader` unhappy.
+            // If we ever implement our own lazy module importer, we could
+            // potentially work around this and move all extension module
+            // initialization into `exec_module()`.
+            if let Some(library_data) = &module.in_memory_extension_module_shared_library() {
+                let sys_modules = state.sys_module.getattr(py, "modules")?;
+
+                extension_module_shared_library_create_module(
+                    state.get_resources_state(),
+                    py,
+                    sys_modules.into_ref(py),
+                    spec,
+                    name,
+                    &key,
+                    library_data,
+                )
+            } else {
+                // Call `imp.create_dynamic()` for dynamic extension modules.
+                let create_dynamic = state.imp_module.getattr(py, "create_dynamic")?;
+
+                state
+                    .call_with_frames_removed
+                    .call(py, (&create_dynamic, spec), None)
+            }
+        } else {
+            Ok(py.None())
+        }
+    }
+
+    fn exec_module_inner(slf: &PyCell<Self>, module: &PyAny) -> PyResult<Py<PyAny>> {
+        let py = slf.py();
+        let finder = slf.borrow();
+        let state = &finder.state;
+
+        let name = module.getattr("__name__")?;
+        let key = name.extract::<String>()?;
+
+        let mut entry = match state
+            .get_resources_state()
+            .resolve_importable_module(&key, state.optimize_level)
+        {
+            Some(entry) => entry,
+            None => {
+                // Raising here might make more sense, as `find_spec()` shouldn't have returned
+                // an entry for something that we don't know how to handle.
+                return Ok(py.None());
+            }
+        };
+
+        if let Some(bytecode) = entry.resolve_bytecode(
+            py,
+            state.optimize_level,
+            state.decode_source.as_ref(py),
+            state.io_module.as_ref(py),
+        )? {
+            let code = state.marshal_loads.call(py, (bytecode,), None)?;
+            let dict = module.getattr("__dict__")?;
+
+            if entry.flavor == ModuleFlavor::SourceBytecode {
+                // Like Python's source file loader, have code refer to
+                // `__file__`, so tracebacks and `linecache` lookups derived
+                // from `__file__` agree.
+                if let Some(origin) = entry.resolve_origin(py, state.virtual_file_paths)? {
+                    state
+                        .imp_module
+                        .call_method1(py, "_fix_co_filename", (&code, origin))?;
+                }
+
+                if entry.has_source() {
+                    let filename = code.getattr(py, "co_filename")?.extract::<String>(py)?;
+                    register_linecache_source(slf, filename, &key)?;
+                }
+            }
+
+            state
+                .call_with_frames_removed
+                .call(py, (&state.exec_fn, code, dict), None)
+        } else if entry.flavor == ModuleFlavor::Builtin {
+            state
+                .builtin_importer
+                .call_method(py, "exec_module", (module,), None)
+        } else if entry.flavor == ModuleFlavor::Frozen {
+            state
+                .frozen_importer
+                .call_method(py, "exec_module", (module,), None)
+        } else if entry.flavor == ModuleFlavor::Extension {
+            // `ExtensionFileLoader.exec_module()` simply calls `imp.exec_dynamic()`.
+            let exec_dynamic = state.imp_module.getattr(py, "exec_dynamic")?;
+
+            state
+                .call_with_frames_removed
+                .call(py, (&exec_dynamic, module), None)
+        } else {
+            Ok(py.None())
+        }?;
+
+        // Perform import time side-effects for special modules.
+        match key.as_str() {
+            "multiprocessing" => {
+                if let Some(method) = state.multiprocessing_set_start_method.as_ref() {
+                    // We pass force=True to ensure the call doesn't fail.
+                    let kwargs = PyDict::new(py);
+                    kwargs.set_item("force", true)?;
+                    module.call_method("set_start_method", (method,), Some(kwargs))?;
+                }
+            }
+            "pkg_resources" => {
+                if state.pkg_resources_import_auto_register {
+                    register_pkg_resources_with_module(py, module)?;
+                }
+            }
+            "linecache" => {
+                flush_linecache_pending(slf, module)?;
+            }
+            _ => {}
+        }
+
+        Ok(py.None())
+    }
+
     fn path_hook_inner(
         slf: &PyCell<Self>,
         path_original: &PyAny,
@@ -1198,78 +1386,57 @@ impl OxidizedFinder {
     }
 }
 
-/// Path-like object facilitating Python resource access.
+/// Register a lazy `linecache` entry serving a module's source for `filename`.
 ///
-/// This implements importlib.abc.Traversable.
-#[pyclass(module = "oxidized_importer")]
-pub(crate) struct PyOxidizerTraversable {
-    state: Arc<ImporterState>,
-    path: String,
-}
-
-#[pymethods]
-impl PyOxidizerTraversable {
-    /// Yield Traversable objects in self.
-    fn iterdir(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Read contents of self as bytes.
-    fn read_bytes(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Read contents of self as text.
-    fn read_text(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return True if self is a dir.
-    fn is_dir(&self) -> PyResult<bool> {
-        // We are a directory if the current path is a known package.
-        // TODO We may need to expand this definition in the future to cover
-        // virtual subdirectories in addressable resources. But this will require
-        // changes to the resources data format to capture said annotations.
-        if let Some(entry) = self
-            .state
-            .get_resources_state()
-            .resolve_importable_module(&self.path, self.state.optimize_level)
-        {
-            if entry.is_package {
-                return Ok(true);
-            }
-        }
-
-        Ok(false)
-    }
-
-    /// Return True if self is a file.
-    fn is_file(&self) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
-
-    /// Return Traversable child in self.
-    #[allow(unused)]
-    fn joinpath(&self, child: &PyAny) -> PyResult<&PyAny> {
-        unimplemented!()
+/// `filename` is the `co_filename` of the module's code, which may be a
+/// virtual path that doesn't exist on the filesystem. If `linecache` isn't
+/// imported yet, the entry is registered once it is.
+fn register_linecache_source(
+    finder: &PyCell<OxidizedFinder>,
+    filename: String,
+    name: &str,
+) -> PyResult<()> {
+    let py = finder.py();
+    let state = &finder.borrow().state;
+
+    let linecache = state
+        .sys_module
+        .as_ref(py)
+        .getattr("modules")?
+        .get_item("linecache")
+        .ok()
+        .filter(|module| module.hasattr("cache").unwrap_or(false));
+
+    state
+        .linecache_pending
+        .lock()
+        .unwrap()
+        .push((filename, name.to_string()));
+
+    if let Some(linecache) = linecache {
+        flush_linecache_pending(finder, linecache)
+    } else {
+        Ok(())
     }
+}
 
-    /// Return Traversable child in self.
-    #[allow(unused)]
-    fn __truediv__(&self, child: &PyAny) -> PyResult<&PyAny> {
-        unimplemented!()
+/// Add pending entries to `linecache.cache`.
+///
+/// Each entry is a lazy entry resolving source via `get_source()`.
+fn flush_linecache_pending(finder: &PyCell<OxidizedFinder>, linecache: &PyAny) -> PyResult<()> {
+    let py = finder.py();
+    let pending = std::mem::take(&mut *finder.borrow().state.linecache_pending.lock().unwrap());
+
+    let partial = py.import("functools")?.getattr("partial")?;
+    let get_source = finder.getattr("get_source")?;
+    let cache = linecache.getattr("cache")?;
+
+    for (filename, name) in pending {
+        let get_lines = partial.call1((get_source, name))?;
+        cache.set_item(filename, PyTuple::new(py, [get_lines]))?;
     }
 
-    /// mode may be 'r' or 'rb' to open as text or binary. Return a handle
-    /// suitable for reading (same as pathlib.Path.open).
-    ///
-    /// When opening as text, accepts encoding parameters such as those
-    /// accepted by io.TextIOWrapper.
-    #[allow(unused)]
-    #[args(py_args = "*", py_kwargs = "**")]
-    fn open(&self, py_args: &PyTuple, py_kwargs: Option<&PyDict>) -> PyResult<&PyAny> {
-        unimplemented!()
-    }
+    Ok(())
 }
 
 /// Replace all meta path importers with an OxidizedFinder instance and return it.
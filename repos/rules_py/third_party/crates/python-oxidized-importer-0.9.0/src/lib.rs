@@ -5,10 +5,12 @@
 //! oxidized_importer Python extension.
 
 mod conversion;
+mod import_profile;
 #[allow(clippy::needless_option_as_deref)]
 mod importer;
 #[cfg(windows)]
 mod memory_dll;
+mod open_hook;
 mod package_metadata;
 #[allow(clippy::needless_option_as_deref)]
 mod path_entry_finder;
@@ -17,6 +19,7 @@ mod pkg_resources;
 mod python_resource_collector;
 mod python_resource_types;
 mod python_resources;
+mod resource_materializer;
 mod resource_reader;
 mod resource_scanning;
 #[cfg(feature = "zipimport")]
@@ -24,12 +27,15 @@ mod resource_scanning;
 mod zip_import;
 
 pub use crate::{
+    import_profile::{ImportProfile, ModuleImportTiming, ModuleSource},
     importer::{
         install_path_hook, remove_external_importers, replace_meta_path_importers, ImporterState,
         OxidizedFinder,
     },
     python_resource_collector::PyTempDir,
-    python_resources::{PackedResourcesSource, PythonResourcesState},
+    python_resources::{
+        PackedResourcesLayer, PackedResourcesSource, PythonResourcesState, ShadowedResource,
+    },
 };
 
 #[cfg(feature = "zipimport")]
@@ -191,10 +197,12 @@ fn module_init(py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(register_pkg_resources, m)?)?;
 
     m.add_class::<crate::package_metadata::OxidizedDistribution>()?;
+    m.add_class::<crate::package_metadata::OxidizedDistributionFile>()?;
     m.add_class::<OxidizedFinder>()?;
     m.add_class::<OxidizedResource>()?;
     m.add_class::<crate::python_resource_collector::OxidizedResourceCollector>()?;
     m.add_class::<OxidizedResourceReader>()?;
+    m.add_class::<crate::open_hook::OxidizedOpen>()?;
     m.add_class::<OxidizedPathEntryFinder>()?;
     m.add_class::<OxidizedPkgResourcesProvider>()?;
     m.add_class::<crate::python_resource_types::PythonModuleSource>()?;
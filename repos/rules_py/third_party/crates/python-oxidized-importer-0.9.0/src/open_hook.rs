@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Serving `open()` of virtual paths under the current executable.
+*/
+
+use {
+    crate::{importer::ImporterState, python_resources::ENOENT},
+    pyo3::{
+        exceptions::PyFileNotFoundError,
+        prelude::*,
+        types::{PyDict, PyString, PyTuple},
+    },
+    std::{path::Path, sync::Arc},
+};
+
+/// Obtain an argument of `open()` by position or keyword.
+fn open_argument<'p>(
+    args: &'p PyTuple,
+    kwargs: Option<&'p PyDict>,
+    index: usize,
+    name: &str,
+) -> Option<&'p PyAny> {
+    args.get_item(index)
+        .ok()
+        .or_else(|| kwargs.and_then(|kwargs| kwargs.get_item(name)))
+        .filter(|value| !value.is_none())
+}
+
+/// A replacement for `open()` serving virtual files read-only.
+///
+/// Reads of paths under the current executable are served from the resources
+/// of an [crate::OxidizedFinder]. See
+/// [crate::python_resources::PythonResourcesState::resolve_virtual_file_data()].
+/// Reads of unknown paths under the current executable raise `FileNotFoundError`.
+/// Everything else, including writes, is passed through to the original `open()`.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct OxidizedOpen {
+    state: Arc<ImporterState>,
+    /// The original `open()`.
+    open: Py<PyAny>,
+    /// `os.fspath()`.
+    fspath: Py<PyAny>,
+    /// `os.strerror()`.
+    strerror: Py<PyAny>,
+    /// `io.BytesIO`.
+    bytes_io: Py<PyAny>,
+    /// `io.TextIOWrapper`.
+    text_io_wrapper: Py<PyAny>,
+}
+
+impl OxidizedOpen {
+    pub(crate) fn new(py: Python, state: Arc<ImporterState>, open: &PyAny) -> PyResult<Self> {
+        let io_module = py.import("io")?;
+        let os_module = py.import("os")?;
+
+        Ok(Self {
+            state,
+            open: open.into_py(py),
+            fspath: os_module.getattr("fspath")?.into_py(py),
+            strerror: os_module.getattr("strerror")?.into_py(py),
+            bytes_io: io_module.getattr("BytesIO")?.into_py(py),
+            text_io_wrapper: io_module.getattr("TextIOWrapper")?.into_py(py),
+        })
+    }
+
+    /// Attempt to open a virtual file, returning `None` if `open()` should handle it.
+    fn open_virtual<'p>(
+        &self,
+        py: Python<'p>,
+        args: &'p PyTuple,
+        kwargs: Option<&'p PyDict>,
+    ) -> PyResult<Option<&'p PyAny>> {
+        let file = match open_argument(args, kwargs, 0, "file") {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        let mode = match open_argument(args, kwargs, 1, "mode") {
+            Some(mode) => mode.extract::<String>()?,
+            None => "r".to_string(),
+        };
+
+        // Only plain reads are served. Let open() deal with everything else,
+        // including rejecting invalid modes.
+        if !mode.contains('r') || mode.chars().any(|c| !matches!(c, 'r' | 'b' | 't')) {
+            return Ok(None);
+        }
+
+        // File descriptors and bytes paths are never virtual.
+        if file.extract::<i64>().is_ok() {
+            return Ok(None);
+        }
+        let path = self.fspath.call1(py, (file,))?.into_ref(py);
+        let path = match path.downcast::<PyString>() {
+            Ok(path) => path.to_str()?,
+            Err(_) => return Ok(None),
+        };
+
+        let resources_state = self.state.get_resources_state();
+
+        // The executable itself is a real file.
+        if !Path::new(path).starts_with(resources_state.current_exe())
+            || Path::new(path) == resources_state.current_exe()
+        {
+            return Ok(None);
+        }
+
+        // Nothing exists on the filesystem below the executable, so report a
+        // missing virtual file like open() would report a missing file.
+        let data = match resources_state.resolve_virtual_file_data(py, Path::new(path))? {
+            Some(data) => data,
+            None => {
+                return Err(PyFileNotFoundError::new_err((
+                    ENOENT,
+                    self.strerror.call1(py, (ENOENT,))?,
+                    path.to_string(),
+                )))
+            }
+        };
+
+        let buffer = self.bytes_io.call1(py, (data,))?.into_ref(py);
+        buffer.setattr("name", path)?;
+
+        if mode.contains('b') {
+            return Ok(Some(buffer));
+        }
+
+        let wrapper_kwargs = PyDict::new(py);
+        for (index, name) in [(3, "encoding"), (4, "errors"), (5, "newline")] {
+            if let Some(value) = open_argument(args, kwargs, index, name) {
+                wrapper_kwargs.set_item(name, value)?;
+            }
+        }
+
+        Ok(Some(
+            self.text_io_wrapper
+                .call(py, (buffer,), Some(wrapper_kwargs))?
+                .into_ref(py),
+        ))
+    }
+}
+
+#[pymethods]
+impl OxidizedOpen {
+    #[args(py_args = "*", py_kwargs = "**")]
+    fn __call__<'p>(
+        &self,
+        py: Python<'p>,
+        py_args: &'p PyTuple,
+        py_kwargs: Option<&'p PyDict>,
+    ) -> PyResult<&'p PyAny> {
+        if let Some(file) = self.open_virtual(py, py_args, py_kwargs)? {
+            Ok(file)
+        } else {
+            Ok(self.open.call(py, py_args, py_kwargs)?.into_ref(py))
+        }
+    }
+
+    /// The original `open()` calls are passed through to.
+    #[getter]
+    fn wrapped<'p>(&self, py: Python<'p>) -> &'p PyAny {
+        self.open.clone_ref(py).into_ref(py)
+    }
+}
@@ -9,11 +9,11 @@ use {
         python_resources::{name_at_package_hierarchy, name_within_package_hierarchy},
     },
     pyo3::{
-        exceptions::{PyIOError, PyNotImplementedError, PyValueError},
+        exceptions::{PyFileNotFoundError, PyIOError, PyValueError},
         prelude::*,
         types::{PyBytes, PyDict, PyList, PyString, PyTuple, PyType},
     },
-    std::{collections::BTreeMap, sync::Arc},
+    std::{collections::BTreeMap, path::PathBuf, sync::Arc},
 };
 
 // Emulates importlib.metadata.Distribution._discover_resolvers().
@@ -34,6 +34,20 @@ fn discover_resolvers(py: Python) -> PyResult<&PyList> {
     Ok(PyList::new(py, resolvers))
 }
 
+/// Obtain the distribution resource name a distribution file path refers to.
+///
+/// Paths of distribution resources start with the `.dist-info` or `.egg-info`
+/// directory they would be installed to.
+fn distribution_resource_name(path: &str) -> Option<&str> {
+    let (directory, name) = path.split_once('/')?;
+
+    if directory.ends_with(".dist-info") || directory.ends_with(".egg-info") {
+        Some(name)
+    } else {
+        None
+    }
+}
+
 /// A importlib.metadata.Distribution allowing access to package distribution data.
 #[pyclass(module = "oxidized_importer")]
 pub(crate) struct OxidizedDistribution {
@@ -47,6 +61,53 @@ impl OxidizedDistribution {
     }
 }
 
+impl OxidizedDistribution {
+    /// Derive names of files in this distribution from indexed resources.
+    ///
+    /// Used when the distribution doesn't have a `RECORD`.
+    fn derived_file_names(&self, py: Python) -> PyResult<Vec<String>> {
+        let resources_state = self.state.get_resources_state();
+
+        let top_level = self.read_text(py, "top_level.txt".into())?;
+        let packages = if top_level.is_none() {
+            vec![self.package.clone()]
+        } else {
+            top_level
+                .extract::<String>()?
+                .split_whitespace()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        let mut names = vec![];
+
+        for package in packages {
+            names.extend(resources_state.package_file_names(&package));
+        }
+
+        let distribution_names =
+            resources_state.package_distribution_resources_list_directory(&self.package, "");
+
+        if !distribution_names.is_empty() {
+            let directory = format!(
+                "{}-{}.dist-info",
+                self._normalized_name(py)?.extract::<String>()?,
+                self.metadata(py)?
+                    .get_item("Version")?
+                    .extract::<String>()?
+            );
+
+            names.extend(
+                distribution_names
+                    .into_iter()
+                    .map(|name| format!("{}/{}", directory, name)),
+            );
+        }
+
+        Ok(names)
+    }
+}
+
 #[pymethods]
 impl OxidizedDistribution {
     #[allow(unused)]
@@ -227,9 +288,85 @@ impl OxidizedDistribution {
         }
     }
 
+    /// Files in this distribution, as `importlib.metadata.PackagePath` instances.
+    ///
+    /// Entries come from the `RECORD` distribution resource. Without one, they
+    /// are derived from the indexed modules and resources of the distribution's
+    /// top-level packages.
     #[getter]
-    fn files(&self) -> PyResult<()> {
-        Err(PyNotImplementedError::new_err(()))
+    fn files<'p>(slf: &'p PyCell<Self>, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let importlib_metadata = py.import("importlib.metadata")?;
+        let package_path_type = importlib_metadata.getattr("PackagePath")?;
+        let file_hash_type = importlib_metadata.getattr("FileHash")?;
+
+        let this = slf.borrow();
+        let record = this.read_text(py, "RECORD".into())?;
+
+        let entries = if record.is_none() {
+            this.derived_file_names(py)?
+                .into_iter()
+                .map(|name| (name, None, None))
+                .collect::<Vec<_>>()
+        } else {
+            let reader = py
+                .import("csv")?
+                .getattr("reader")?
+                .call1((record.call_method0("splitlines")?,))?;
+
+            let mut entries = vec![];
+            for row in reader.iter()? {
+                let mut row = row?.extract::<Vec<String>>()?.into_iter();
+
+                if let Some(name) = row.next() {
+                    entries.push((name, row.next(), row.next()));
+                }
+            }
+
+            entries
+        };
+
+        let files = PyList::empty(py);
+
+        for (name, hash, size) in entries {
+            let path = package_path_type.call1((name,))?;
+
+            let hash = match hash.filter(|hash| !hash.is_empty()) {
+                Some(hash) => file_hash_type.call1((hash,))?,
+                None => py.None().into_ref(py),
+            };
+            let size = size
+                .filter(|size| !size.is_empty())
+                .map(|size| {
+                    size.parse::<u64>()
+                        .map_err(|e| PyValueError::new_err(format!("invalid size: {}", e)))
+                })
+                .transpose()?;
+
+            path.setattr("hash", hash)?;
+            path.setattr("size", size)?;
+            path.setattr("dist", slf)?;
+
+            files.append(path)?;
+        }
+
+        Ok(files)
+    }
+
+    /// Resolve a path relative to the distribution's installation directory.
+    ///
+    /// Returns an [OxidizedDistributionFile] serving data of indexed resources.
+    fn locate_file(&self, py: Python, path: &PyAny) -> PyResult<OxidizedDistributionFile> {
+        let path = py
+            .import("os")?
+            .getattr("fspath")?
+            .call1((path,))?
+            .extract::<String>()?;
+
+        Ok(OxidizedDistributionFile {
+            state: self.state.clone(),
+            package: self.package.clone(),
+            path: path.replace('\\', "/"),
+        })
     }
 
     #[getter]
@@ -265,6 +402,119 @@ impl OxidizedDistribution {
     }
 }
 
+/// A file in an [OxidizedDistribution], as returned by its `locate_file()`.
+///
+/// Its filesystem path is virtual, under the current executable, like the
+/// virtual `__file__` of modules imported from memory. Reads are served from
+/// distribution resources, in-memory module source and package resources.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct OxidizedDistributionFile {
+    state: Arc<ImporterState>,
+    package: String,
+    /// POSIX path relative to the distribution's installation directory.
+    path: String,
+}
+
+impl OxidizedDistributionFile {
+    fn virtual_path(&self) -> PathBuf {
+        self.state
+            .get_resources_state()
+            .current_exe()
+            .join(&self.path)
+    }
+
+    /// Resolve the content of this file, if it is known.
+    fn resolve_data<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyAny>> {
+        let resources_state = self.state.get_resources_state();
+
+        if let Some(name) = distribution_resource_name(&self.path) {
+            if let Some(data) = resources_state
+                .resolve_package_distribution_resource(&self.package, name)
+                .map_err(|e| PyIOError::new_err(format!("error when resolving resource: {}", e)))?
+            {
+                return Ok(Some(PyBytes::new(py, &data)));
+            }
+        }
+
+        resources_state.resolve_virtual_file_data(py, &self.virtual_path())
+    }
+}
+
+#[pymethods]
+impl OxidizedDistributionFile {
+    #[getter]
+    fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or_default()
+    }
+
+    fn __fspath__(&self) -> PathBuf {
+        self.virtual_path()
+    }
+
+    fn __str__(&self) -> String {
+        self.virtual_path().display().to_string()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<OxidizedDistributionFile {}>", self.path)
+    }
+
+    /// Whether the content of this file is known.
+    fn exists(&self, py: Python) -> PyResult<bool> {
+        Ok(self.resolve_data(py)?.is_some())
+    }
+
+    fn read_bytes<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        self.resolve_data(py)?
+            .ok_or_else(|| PyFileNotFoundError::new_err(self.__str__()))
+    }
+
+    #[args(encoding = "None")]
+    fn read_text<'p>(&self, py: Python<'p>, encoding: Option<&str>) -> PyResult<&'p PyAny> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("encoding", encoding)?;
+
+        self.open(py, PyTuple::new(py, ["r"]), Some(kwargs))?
+            .call_method0("read")
+    }
+
+    /// Open the file for reading, in mode 'r' or 'rb'.
+    ///
+    /// When opening as text, accepts arguments of io.TextIOWrapper.
+    #[args(py_args = "*", py_kwargs = "**")]
+    fn open<'p>(
+        &self,
+        py: Python<'p>,
+        py_args: &'p PyTuple,
+        py_kwargs: Option<&'p PyDict>,
+    ) -> PyResult<&'p PyAny> {
+        let (mode, wrapper_args) = match py_args.get_item(0) {
+            Ok(mode) => (mode.extract::<&str>()?, py_args.get_slice(1, py_args.len())),
+            Err(_) => ("r", py_args),
+        };
+
+        if !matches!(mode, "r" | "rb") {
+            return Err(PyValueError::new_err(format!("invalid mode: {}", mode)));
+        }
+
+        let io = py.import("io")?;
+        let buffer = io.getattr("BytesIO")?.call1((self.read_bytes(py)?,))?;
+
+        if mode == "rb" {
+            return Ok(buffer);
+        }
+
+        let args = PyTuple::new(
+            py,
+            std::iter::once(buffer)
+                .chain(wrapper_args.iter())
+                .collect::<Vec<_>>(),
+        );
+
+        io.getattr("TextIOWrapper")?.call(args, py_kwargs)
+    }
+}
+
 /// Find package metadata distributions given search criteria.
 pub(crate) fn find_distributions<'p>(
     py: Python<'p>,
@@ -18,11 +18,13 @@ use {
         exceptions::{PyTypeError, PyValueError},
         ffi as pyffi,
         prelude::*,
-        types::{PyBytes, PyList, PyTuple},
+        types::{PyBytes, PyDict, PyList, PyTuple},
         AsPyPointer,
     },
     python_packaging::{
         bytecode::BytecodeCompiler,
+        import_graph::ImportGraphPruningPolicy,
+        loaded_modules::LoadedModulesManifest,
         location::{AbstractResourceLocation, ConcreteResourceLocation},
         resource_collection::{CompiledResourcesCollection, PythonResourceCollector},
     },
@@ -106,6 +108,21 @@ impl OxidizedResourceCollector {
         Ok(PyList::new(py, &values))
     }
 
+    #[getter]
+    fn bytecode_filename_prefix(&self) -> Option<String> {
+        self.collector
+            .borrow()
+            .bytecode_filename_prefix()
+            .map(|x| x.to_string())
+    }
+
+    #[setter]
+    fn set_bytecode_filename_prefix(&self, value: Option<String>) {
+        self.collector
+            .borrow_mut()
+            .set_bytecode_filename_prefix(value);
+    }
+
     fn add_in_memory(&self, resource: &PyAny) -> PyResult<()> {
         let mut collector = self.collector.borrow_mut();
         let typ = resource.get_type();
@@ -265,6 +282,136 @@ impl OxidizedResourceCollector {
         }
     }
 
+    #[args(startup_only = "false")]
+    fn retain_loaded_modules(&self, py: Python, paths: &PyAny, startup_only: bool) -> PyResult<()> {
+        let manifests = paths
+            .iter()?
+            .map(|path| {
+                let path = pyobject_to_pathbuf(py, path?)?;
+
+                LoadedModulesManifest::from_path(&path)
+                    .map_err(|e| PyValueError::new_err(format!("{:?}", e)))
+            })
+            .collect::<PyResult<Vec<_>>>()?;
+
+        self.collector
+            .borrow_mut()
+            .retain_loaded_modules(&manifests, startup_only)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[args(retain = "None")]
+    fn prune_unreachable_modules<'p>(
+        &self,
+        py: Python<'p>,
+        entry_points: Vec<String>,
+        retain: Option<Vec<String>>,
+    ) -> PyResult<&'p PyDict> {
+        let policy = ImportGraphPruningPolicy {
+            entry_points,
+            retain: retain.unwrap_or_default(),
+        };
+
+        let report = self
+            .collector
+            .borrow_mut()
+            .prune_unreachable_modules(&policy)
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        let res = PyDict::new(py);
+        res.set_item("removed", report.removed.into_iter().collect::<Vec<_>>())?;
+        res.set_item(
+            "dynamic_only",
+            report.dynamic_only.into_iter().collect::<Vec<_>>(),
+        )?;
+        res.set_item(
+            "unresolved_dynamic_importers",
+            report
+                .unresolved_dynamic_importers
+                .into_iter()
+                .collect::<Vec<_>>(),
+        )?;
+
+        Ok(res)
+    }
+
+    fn analyze_compatibility<'p>(&self, py: Python<'p>) -> PyResult<&'p PyDict> {
+        let report = self
+            .collector
+            .borrow()
+            .analyze_compatibility()
+            .map_err(|e| PyValueError::new_err(e.to_string()))?;
+
+        let res = PyDict::new(py);
+
+        for (package, modules) in report.packages {
+            let findings = modules
+                .into_iter()
+                .flat_map(|(module, findings)| {
+                    findings.into_iter().map(move |finding| {
+                        (
+                            module.clone(),
+                            finding.kind.as_str(),
+                            finding.line,
+                            finding.text,
+                        )
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            res.set_item(package, findings)?;
+        }
+
+        Ok(res)
+    }
+
+    fn relocate_packages(
+        &self,
+        py: Python,
+        packages: Vec<String>,
+        prefix: String,
+    ) -> PyResult<Vec<String>> {
+        let cache_tag = py
+            .import("sys")?
+            .getattr("implementation")?
+            .getattr("cache_tag")?
+            .extract::<String>()?;
+
+        self.collector
+            .borrow_mut()
+            .relocate_packages(&packages, &prefix, &cache_tag)
+            .map_err(|e| PyValueError::new_err(e.to_string()))
+    }
+
+    #[args(python_exe = "None", text = "false")]
+    fn size_report(&self, py: Python, python_exe: Option<&PyAny>, text: bool) -> PyResult<String> {
+        let python_exe = match python_exe {
+            Some(p) => p,
+            None => {
+                let sys_module = py.import("sys")?;
+                sys_module.getattr("executable")?
+            }
+        };
+        let python_exe = pyobject_to_pathbuf(py, python_exe)?;
+        let temp_dir = PyTempDir::new(py)?;
+
+        let mut compiler = BytecodeCompiler::new(&python_exe, temp_dir.path()).map_err(|e| {
+            PyValueError::new_err(format!("error constructing bytecode compiler: {:?}", e))
+        })?;
+
+        let report = self
+            .collector
+            .borrow()
+            .size_report(&mut compiler)
+            .map_err(|e| PyValueError::new_err(format!("error computing size report: {:?}", e)))?;
+
+        Ok(if text {
+            report.to_text()
+        } else {
+            report.to_report_string()
+        })
+    }
+
     #[args(python_exe = "None")]
     fn oxidize<'p>(&self, py: Python<'p>, python_exe: Option<&PyAny>) -> PyResult<&'p PyTuple> {
         let python_exe = match python_exe {
@@ -7,10 +7,13 @@ Management of Python resources.
 */
 
 use {
-    crate::conversion::{
-        path_to_pathlib_path, pyobject_optional_resources_map_to_owned_bytes,
-        pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
-        pyobject_to_pathbuf_optional,
+    crate::{
+        conversion::{
+            path_to_pathlib_path, pyobject_optional_resources_map_to_owned_bytes,
+            pyobject_optional_resources_map_to_pathbuf, pyobject_to_owned_bytes_optional,
+            pyobject_to_pathbuf_optional,
+        },
+        import_profile::ModuleSource,
     },
     anyhow::Result,
     pyo3::{
@@ -33,7 +36,69 @@ use {
     },
 };
 
-const ENOENT: c_int = 2;
+pub(crate) const ENOENT: c_int = 2;
+
+/// Bytecode optimization levels to try for a requested level, in order of preference.
+///
+/// The requested level is preferred. Otherwise the closest level is used so
+/// that a module packaged at a single optimization level can be imported
+/// regardless of the interpreter's optimization level.
+fn optimize_level_preference(
+    optimize_level: BytecodeOptimizationLevel,
+) -> [BytecodeOptimizationLevel; 3] {
+    match optimize_level {
+        BytecodeOptimizationLevel::Zero => [
+            BytecodeOptimizationLevel::Zero,
+            BytecodeOptimizationLevel::One,
+            BytecodeOptimizationLevel::Two,
+        ],
+        BytecodeOptimizationLevel::One => [
+            BytecodeOptimizationLevel::One,
+            BytecodeOptimizationLevel::Two,
+            BytecodeOptimizationLevel::Zero,
+        ],
+        BytecodeOptimizationLevel::Two => [
+            BytecodeOptimizationLevel::Two,
+            BytecodeOptimizationLevel::One,
+            BytecodeOptimizationLevel::Zero,
+        ],
+    }
+}
+
+/// Whether an entry has bytecode at a given optimization level.
+fn has_bytecode<X>(entry: &Resource<X>, optimize_level: BytecodeOptimizationLevel) -> bool
+where
+    [X]: ToOwned<Owned = Vec<X>>,
+{
+    match optimize_level {
+        BytecodeOptimizationLevel::Zero => {
+            entry.in_memory_bytecode.is_some() || entry.relative_path_module_bytecode.is_some()
+        }
+        BytecodeOptimizationLevel::One => {
+            entry.in_memory_bytecode_opt1.is_some()
+                || entry.relative_path_module_bytecode_opt1.is_some()
+        }
+        BytecodeOptimizationLevel::Two => {
+            entry.in_memory_bytecode_opt2.is_some()
+                || entry.relative_path_module_bytecode_opt2.is_some()
+        }
+    }
+}
+
+/// Resolve the optimization level of bytecode to use for an entry.
+///
+/// Returns `None` if the entry has no bytecode at any level.
+fn resolve_optimize_level<X>(
+    entry: &Resource<X>,
+    optimize_level: BytecodeOptimizationLevel,
+) -> Option<BytecodeOptimizationLevel>
+where
+    [X]: ToOwned<Owned = Vec<X>>,
+{
+    optimize_level_preference(optimize_level)
+        .into_iter()
+        .find(|level| has_bytecode(entry, *level))
+}
 
 /// Determines whether an entry represents an importable Python module.
 ///
@@ -44,17 +109,7 @@ where
 {
     entry.in_memory_source.is_some()
         || entry.relative_path_module_source.is_some()
-        || match optimize_level {
-            BytecodeOptimizationLevel::Zero => {
-                entry.in_memory_bytecode.is_some() || entry.relative_path_module_bytecode.is_some()
-            }
-            BytecodeOptimizationLevel::One => {
-                entry.in_memory_bytecode_opt1.is_some() || entry.in_memory_bytecode_opt1.is_some()
-            }
-            BytecodeOptimizationLevel::Two => {
-                entry.in_memory_bytecode_opt2.is_some() || entry.in_memory_bytecode_opt2.is_some()
-            }
-        }
+        || resolve_optimize_level(entry, optimize_level).is_some()
 }
 
 /// Whether a resource name matches a package target.
@@ -94,6 +149,15 @@ pub(crate) fn name_within_package_hierarchy(fullname: &str, package_target: Opti
     }
 }
 
+/// Where the data of a package resource lives.
+#[derive(Debug)]
+pub enum PackageResourceData<'a> {
+    /// Data held in memory.
+    Memory(&'a [u8]),
+    /// Data in a file on the filesystem.
+    Path(PathBuf),
+}
+
 /// Describes the type of an importable Python module.
 #[derive(Debug, PartialEq, Eq)]
 pub enum ModuleFlavor {
@@ -173,6 +237,9 @@ impl<'a> ImportablePythonModule<'a, u8> {
     /// (e.g. the case of frozen modules).
     ///
     /// The returned `PyObject` will be an instance of `memoryview`.
+    ///
+    /// Bytecode at `optimize_level` is preferred. If the module only has bytecode
+    /// at other levels, the closest level is used instead of compiling source.
     pub fn resolve_bytecode(
         &mut self,
         py: Python,
@@ -180,6 +247,9 @@ impl<'a> ImportablePythonModule<'a, u8> {
         decode_source: &PyAny,
         io_module: &PyModule,
     ) -> PyResult<Option<Py<PyAny>>> {
+        let optimize_level =
+            resolve_optimize_level(self.resource, optimize_level).unwrap_or(optimize_level);
+
         if let Some(data) = match optimize_level {
             BytecodeOptimizationLevel::Zero => &self.resource.in_memory_bytecode,
             BytecodeOptimizationLevel::One => &self.resource.in_memory_bytecode_opt1,
@@ -235,13 +305,53 @@ impl<'a> ImportablePythonModule<'a, u8> {
         }
     }
 
+    /// Whether source code is available for this module.
+    pub fn has_source(&self) -> bool {
+        self.resource.in_memory_source.is_some()
+            || self.resource.relative_path_module_source.is_some()
+    }
+
+    /// Where this module's code comes from.
+    pub fn source(&self) -> ModuleSource {
+        match self.flavor {
+            ModuleFlavor::Builtin => ModuleSource::Builtin,
+            ModuleFlavor::Frozen => ModuleSource::Frozen,
+            ModuleFlavor::Extension => {
+                if self
+                    .resource
+                    .in_memory_extension_module_shared_library
+                    .is_some()
+                {
+                    ModuleSource::Memory
+                } else {
+                    ModuleSource::RelativePath
+                }
+            }
+            ModuleFlavor::SourceBytecode => {
+                if self.resource.in_memory_source.is_some()
+                    || self.resource.in_memory_bytecode.is_some()
+                    || self.resource.in_memory_bytecode_opt1.is_some()
+                    || self.resource.in_memory_bytecode_opt2.is_some()
+                {
+                    ModuleSource::Memory
+                } else {
+                    ModuleSource::RelativePath
+                }
+            }
+        }
+    }
+
     /// Resolve the `importlib.machinery.ModuleSpec` for this module.
+    ///
+    /// If `virtual_file_paths` is true, in-memory modules get a virtual origin.
+    /// See [Self::resolve_origin()].
     pub fn resolve_module_spec<'p>(
         &self,
         py: Python,
         module_spec_type: &'p PyAny,
         loader: &PyAny,
         optimize_level: BytecodeOptimizationLevel,
+        virtual_file_paths: bool,
     ) -> PyResult<&'p PyAny> {
         let name = PyString::new(py, &self.resource.name);
 
@@ -250,9 +360,10 @@ impl<'a> ImportablePythonModule<'a, u8> {
 
         // If we pass `origin=` and set `spec.has_location = True`, `__file__`
         // will be set on the module. This is appropriate for modules backed by
-        // the filesystem.
+        // the filesystem. In-memory modules only get one if virtual file paths
+        // are enabled.
 
-        let origin = self.resolve_origin(py)?;
+        let origin = self.resolve_origin(py, virtual_file_paths)?;
         if let Some(origin) = &origin {
             kwargs.set_item("origin", origin)?;
         }
@@ -322,14 +433,53 @@ impl<'a> ImportablePythonModule<'a, u8> {
     /// Resolve the value of a `ModuleSpec` origin.
     ///
     /// The value gets turned into `__file__`
-    pub fn resolve_origin<'p>(&self, py: Python<'p>) -> PyResult<Option<&'p PyAny>> {
-        Ok(if let Some(path) = self.origin_path() {
+    ///
+    /// If `virtual_file_paths` is true, source and bytecode modules not backed by
+    /// the filesystem get a path under the current executable, e.g.
+    /// `/usr/bin/myapp/foo/bar.py`. This mirrors the `__path__` of in-memory
+    /// packages, so paths derived from `__file__` resolve to in-memory
+    /// package resources via `get_data()`.
+    pub fn resolve_origin<'p>(
+        &self,
+        py: Python<'p>,
+        virtual_file_paths: bool,
+    ) -> PyResult<Option<&'p PyAny>> {
+        let path = self.origin_path().or_else(|| {
+            if virtual_file_paths {
+                self.virtual_origin_path()
+            } else {
+                None
+            }
+        });
+
+        Ok(if let Some(path) = path {
             Some(path.into_py(py).into_ref(py))
         } else {
             None
         })
     }
 
+    /// Obtain a virtual path to this module's source under the current executable.
+    fn virtual_origin_path(&self) -> Option<PathBuf> {
+        if self.flavor != ModuleFlavor::SourceBytecode || self.resource.is_python_namespace_package
+        {
+            return None;
+        }
+
+        let parts = self.resource.name.split('.').collect::<Vec<_>>();
+        let mut path = self.current_exe.to_path_buf();
+
+        if self.is_package {
+            path.extend(&parts);
+            path.push("__init__.py");
+        } else {
+            path.extend(&parts[0..parts.len() - 1]);
+            path.push(format!("{}.py", parts[parts.len() - 1]));
+        }
+
+        Some(path)
+    }
+
     /// Resolve the value of a `ModuleSpec` `cached` attribute.
     ///
     /// The value gets turned into `__cached__`.
@@ -339,7 +489,8 @@ impl<'a> ImportablePythonModule<'a, u8> {
         optimize_level: BytecodeOptimizationLevel,
     ) -> PyResult<Option<&'p PyAny>> {
         let path = match self.flavor {
-            ModuleFlavor::SourceBytecode => self.bytecode_path(optimize_level),
+            ModuleFlavor::SourceBytecode => resolve_optimize_level(self.resource, optimize_level)
+                .and_then(|level| self.bytecode_path(level)),
             _ => None,
         };
 
@@ -402,6 +553,77 @@ impl<'a> From<&'a [u8]> for PackedResourcesSource<'a> {
     }
 }
 
+/// A layer of packed resources data having an explicit priority.
+///
+/// When multiple layers define a resource with the same name, the
+/// definition from the layer with the highest priority is used and the
+/// others are shadowed. Definitions from layers having equal priority are
+/// merged, with fields from the layer indexed last taking precedence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PackedResourcesLayer<'a> {
+    /// Name identifying this layer in diagnostics.
+    pub name: String,
+
+    /// Where resources data comes from.
+    pub source: PackedResourcesSource<'a>,
+
+    /// Priority of this layer. Higher values win.
+    pub priority: i32,
+
+    /// Whether to silently ignore a [PackedResourcesSource::MemoryMappedPath]
+    /// that doesn't exist.
+    pub optional: bool,
+}
+
+impl<'a> PackedResourcesLayer<'a> {
+    /// Construct a required layer having the default priority.
+    ///
+    /// The layer is named after its source.
+    pub fn new(source: PackedResourcesSource<'a>) -> Self {
+        let name = match &source {
+            PackedResourcesSource::Memory(_) => "<memory>".to_string(),
+            PackedResourcesSource::MemoryMappedPath(path) => path.display().to_string(),
+        };
+
+        Self {
+            name,
+            source,
+            priority: 0,
+            optional: false,
+        }
+    }
+}
+
+/// Describes a resource whose definition in one layer was shadowed by another layer.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShadowedResource {
+    /// Name of the resource.
+    pub name: String,
+
+    /// Name of the layer whose definition is used.
+    pub layer: String,
+
+    /// Name of the layer whose definition was shadowed.
+    pub shadowed_layer: String,
+}
+
+impl std::fmt::Display for ShadowedResource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} from {} is shadowed by {}",
+            self.name, self.shadowed_layer, self.layer
+        )
+    }
+}
+
+/// A layer that resources have been indexed from.
+#[derive(Clone, Debug)]
+struct IndexedLayer {
+    name: String,
+    priority: i32,
+}
+
 /// Defines Python resources available for import.
 #[derive(Debug)]
 pub struct PythonResourcesState<'a, X>
@@ -427,6 +649,17 @@ where
 
     /// Holds memory mapped file instances that resources data came from.
     backing_mmaps: Vec<memmap2::Mmap>,
+
+    /// Layers that resources data has been indexed from, in indexing order.
+    layers: Vec<IndexedLayer>,
+
+    /// Index into `layers` of the layer serving each resource.
+    ///
+    /// Resources that didn't come from packed resources data aren't present.
+    resource_layers: HashMap<Cow<'a, str>, usize>,
+
+    /// Resource definitions shadowed by other layers.
+    shadowed_resources: Vec<ShadowedResource>,
 }
 
 impl<'a> Default for PythonResourcesState<'a, u8> {
@@ -437,6 +670,9 @@ impl<'a> Default for PythonResourcesState<'a, u8> {
             resources: HashMap::new(),
             backing_py_objects: vec![],
             backing_mmaps: vec![],
+            layers: vec![],
+            resource_layers: HashMap::new(),
+            shadowed_resources: vec![],
         }
     }
 }
@@ -483,24 +719,103 @@ impl<'a> PythonResourcesState<'a, u8> {
     /// on the incoming entry will overwrite fields on the existing entry.
     ///
     /// If an entry doesn't exist, the resource will be inserted as-is.
-    pub fn index_data(&mut self, data: &'a [u8]) -> Result<(), &'static str> {
-        let resources = python_packed_resources::load_resources(data)?;
+    pub fn index_data(&mut self, data: &'a [u8]) -> Result<(), String> {
+        let name = format!("<memory #{}>", self.layers.len());
+
+        self.index_layer_data(data, name, 0)
+    }
+
+    /// Load resources from a blob as a layer with a name and priority.
+    ///
+    /// Entries defined by a layer of lower priority are replaced. Entries defined
+    /// by a layer of equal priority are merged, as [Self::index_data()] does.
+    /// Incoming entries are ignored if a layer of higher priority defines them.
+    ///
+    /// Definitions whose data is lost this way are recorded and can be obtained
+    /// via [Self::shadowed_resources()]: replaced or ignored definitions that
+    /// differ from the definition used, and merged definitions having a field
+    /// value overridden by the other definition.
+    ///
+    /// If the data is malformed, an error is returned and nothing is indexed.
+    pub fn index_layer_data(
+        &mut self,
+        data: &'a [u8],
+        name: String,
+        priority: i32,
+    ) -> Result<(), String> {
+        // Validate every resource before indexing any of them. Otherwise a failure
+        // partway through would leave indexed resources borrowing from data the
+        // caller is free to release after we return an error. The data is parsed
+        // again to index it rather than holding all parsed resources at once.
+        python_packed_resources::load_resources(data)
+            .map_err(|e| e.to_string())?
+            .try_for_each(|resource| resource.map(|_| ()))
+            .map_err(|e| e.to_string())?;
+
+        let resources = python_packed_resources::load_resources(data).map_err(|e| e.to_string())?;
+
+        let layer = self.layers.len();
+        self.layers.push(IndexedLayer {
+            name: name.clone(),
+            priority,
+        });
 
         // Reserve space for expected number of incoming items so we can avoid extra
         // allocations.
         self.resources.reserve(resources.expected_resources_count());
 
         for resource in resources {
-            let resource = resource?;
+            let resource = resource.map_err(|e| e.to_string())?;
+            let resource_name = resource.name.clone();
+
+            let existing_layer = self
+                .resource_layers
+                .get(&resource.name)
+                .map(|i| &self.layers[*i]);
 
             match self.resources.entry(resource.name.clone()) {
                 Entry::Occupied(existing) => {
-                    existing.into_mut().merge_from(resource)?;
+                    if let Some(existing_layer) = existing_layer {
+                        let shadowed = if existing_layer.priority == priority {
+                            !python_packed_resources::conflicting_fields(existing.get(), &resource)
+                                .is_empty()
+                        } else {
+                            existing.get() != &resource
+                        };
+
+                        if shadowed {
+                            let (layer, shadowed_layer) = if existing_layer.priority > priority {
+                                (existing_layer.name.clone(), name.clone())
+                            } else {
+                                (name.clone(), existing_layer.name.clone())
+                            };
+
+                            self.shadowed_resources.push(ShadowedResource {
+                                name: resource.name.to_string(),
+                                layer,
+                                shadowed_layer,
+                            });
+                        }
+
+                        match existing_layer.priority.cmp(&priority) {
+                            std::cmp::Ordering::Greater => continue,
+                            std::cmp::Ordering::Less => {
+                                *existing.into_mut() = resource;
+                            }
+                            std::cmp::Ordering::Equal => {
+                                existing.into_mut().merge_from(resource)?;
+                            }
+                        }
+                    } else {
+                        existing.into_mut().merge_from(resource)?;
+                    }
                 }
                 Entry::Vacant(vacant) => {
                     vacant.insert(resource);
                 }
             }
+
+            self.resource_layers.insert(resource_name, layer);
         }
 
         Ok(())
@@ -509,18 +824,53 @@ impl<'a> PythonResourcesState<'a, u8> {
     /// Load resources data from a filesystem path using memory mapped I/O.
     pub fn index_path_memory_mapped(&mut self, path: impl AsRef<Path>) -> Result<(), String> {
         let path = path.as_ref();
+
+        self.index_layer_path_memory_mapped(path, path.display().to_string(), 0)
+    }
+
+    /// Load resources data from a filesystem path as a layer with a name and priority.
+    ///
+    /// See [Self::index_layer_data()] for how layers interact.
+    pub fn index_layer_path_memory_mapped(
+        &mut self,
+        path: impl AsRef<Path>,
+        name: String,
+        priority: i32,
+    ) -> Result<(), String> {
+        let path = path.as_ref();
         let f = std::fs::File::open(path).map_err(|e| e.to_string())?;
 
         let mapped = unsafe { memmap2::Mmap::map(&f) }.map_err(|e| e.to_string())?;
 
         let data = unsafe { std::slice::from_raw_parts::<u8>(mapped.as_ptr(), mapped.len()) };
 
-        self.index_data(data)?;
+        // Nothing borrows from the mapping if indexing fails. So it is safe to
+        // unmap it on error.
+        self.index_layer_data(data, name, priority)?;
         self.backing_mmaps.push(mapped);
 
         Ok(())
     }
 
+    /// Load resources from a [PackedResourcesLayer].
+    ///
+    /// Optional layers whose path doesn't exist are ignored.
+    pub fn index_layer(&mut self, layer: &PackedResourcesLayer<'a>) -> Result<(), String> {
+        match &layer.source {
+            PackedResourcesSource::Memory(data) => {
+                self.index_layer_data(data, layer.name.clone(), layer.priority)
+            }
+            PackedResourcesSource::MemoryMappedPath(path) => {
+                if layer.optional && !path.exists() {
+                    return Ok(());
+                }
+
+                self.index_layer_path_memory_mapped(path, layer.name.clone(), layer.priority)
+                    .map_err(|e| format!("error loading {}: {}", layer.name, e))
+            }
+        }
+    }
+
     /// Load resources from packed data stored in a PyObject.
     ///
     /// The `PyObject` must conform to the buffer protocol.
@@ -571,6 +921,12 @@ impl<'a> PythonResourcesState<'a, u8> {
 
     /// Load `frozen` modules from the Python interpreter.
     pub fn index_interpreter_frozen_modules(&mut self) -> Result<(), &'static str> {
+        // Python 3.11+ keeps its own frozen modules elsewhere and leaves this
+        // NULL unless the embedder registers frozen modules.
+        if unsafe { pyffi::PyImport_FrozenModules }.is_null() {
+            return Ok(());
+        }
+
         for i in 0.. {
             let record = unsafe { pyffi::PyImport_FrozenModules.offset(i) };
 
@@ -626,6 +982,7 @@ impl<'a> PythonResourcesState<'a, u8> {
         &mut self,
         resource: Resource<'resource, u8>,
     ) -> Result<(), &'static str> {
+        self.resource_layers.remove(&resource.name);
         self.resources.insert(resource.name.clone(), resource);
 
         Ok(())
@@ -776,6 +1133,89 @@ impl<'a> PythonResourcesState<'a, u8> {
         Ok(None)
     }
 
+    /// Obtain the data of a single named resource in a package.
+    ///
+    /// Returns `None` if the resource does not exist.
+    pub fn get_package_resource_data(
+        &self,
+        package: &str,
+        resource_name: &str,
+    ) -> Option<PackageResourceData<'_>> {
+        let entry = self.resources.get(package)?;
+
+        if let Some(resources) = &entry.in_memory_package_resources {
+            if let Some(data) = resources.get(resource_name) {
+                return Some(PackageResourceData::Memory(data));
+            }
+        }
+
+        if let Some(resources) = &entry.relative_path_package_resources {
+            if let Some(path) = resources.get(resource_name) {
+                return Some(PackageResourceData::Path(self.origin.join(path)));
+            }
+        }
+
+        None
+    }
+
+    /// Attempt to resolve the content of a virtual file under the current executable.
+    ///
+    /// Virtual files are `File` resources with embedded data (keyed by their path
+    /// relative to the current executable), the source of in-memory modules (at
+    /// their virtual `__file__`), and in-memory package resources (next to their
+    /// package's virtual `__path__`).
+    ///
+    /// Returns `Ok(None)` if the path isn't under the current executable or doesn't
+    /// refer to a known virtual file.
+    pub fn resolve_virtual_file_data<'p>(
+        &self,
+        py: Python<'p>,
+        path: &Path,
+    ) -> PyResult<Option<&'p PyAny>> {
+        let relative_path = match path.strip_prefix(&self.current_exe) {
+            Ok(relative_path) => relative_path,
+            Err(_) => return Ok(None),
+        };
+
+        // Internally resources are normalized to POSIX separators.
+        let name = relative_path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        let name_ref: &str = &name;
+
+        if let Some(entry) = self.resources.get(name_ref) {
+            if entry.is_utf8_filename_data {
+                if let Some(data) = &entry.file_data_embedded {
+                    return Ok(Some(PyBytes::new(py, data).into()));
+                }
+            }
+        }
+
+        if let Some(stem) = name.strip_suffix(".py") {
+            let (module_name, is_package) = match stem.strip_suffix("/__init__") {
+                Some(package) => (package.replace('/', "."), true),
+                None => (stem.replace('/', "."), false),
+            };
+            let module_name_ref: &str = &module_name;
+
+            if let Some(entry) = self.resources.get(module_name_ref) {
+                if entry.is_python_package == is_package {
+                    if let Some(source) = &entry.in_memory_source {
+                        return Ok(Some(PyBytes::new(py, source).into()));
+                    }
+                }
+            }
+        }
+
+        match self.resolve_resource_data_from_path(py, &path.to_string_lossy()) {
+            Ok(data) => Ok(Some(data)),
+            Err(err) if err.is_instance_of::<PyOSError>(py) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Determines whether a specific package + name pair is a known Python package resource.
     pub fn is_package_resource(&self, package: &str, resource_name: &str) -> bool {
         if let Some(entry) = self.resources.get(package) {
@@ -903,6 +1343,43 @@ impl<'a> PythonResourcesState<'a, u8> {
         entries.into_iter().collect::<Vec<_>>()
     }
 
+    /// Resolve names of all package resources in a directory, recursively.
+    ///
+    /// The empty string refers to the root directory of the package.
+    pub fn package_resources_walk_directory(&self, package: &str, name: &str) -> Vec<String> {
+        let name = name.replace('\\', "/");
+
+        let prefix = if name.is_empty() || name.ends_with('/') {
+            name
+        } else {
+            format!("{}/", name)
+        };
+
+        let mut entries = BTreeSet::new();
+
+        if let Some(entry) = self.resources.get(package) {
+            if let Some(resources) = &entry.in_memory_package_resources {
+                entries.extend(
+                    resources
+                        .keys()
+                        .filter(|path| path.starts_with(&prefix))
+                        .map(|path| path.to_string()),
+                );
+            }
+
+            if let Some(resources) = &entry.relative_path_package_resources {
+                entries.extend(
+                    resources
+                        .keys()
+                        .filter(|path| path.starts_with(&prefix))
+                        .map(|path| path.to_string()),
+                );
+            }
+        }
+
+        entries.into_iter().collect::<Vec<_>>()
+    }
+
     /// Attempt to resolve a PyBytes for resource data given a relative path.
     ///
     /// Raises OSerror on failure.
@@ -1185,9 +1662,9 @@ impl<'a> PythonResourcesState<'a, u8> {
 
         let filter_map_resource = |path: &'slf Cow<'slf, str>| -> Option<&'slf str> {
             match &prefix {
-                Some(prefix) => {
-                    path.strip_prefix(prefix).filter(|&name| !name.contains('/'))
-                }
+                Some(prefix) => path
+                    .strip_prefix(prefix)
+                    .filter(|&name| !name.contains('/')),
                 None => {
                     // Empty string input matches root directory.
                     if path.contains('/') {
@@ -1214,6 +1691,51 @@ impl<'a> PythonResourcesState<'a, u8> {
         entries.into_iter().collect::<Vec<_>>()
     }
 
+    /// Resolve paths of the files making up a package and its descendants.
+    ///
+    /// Paths are POSIX style and relative to the directory the package would
+    /// be installed to. They consist of the `.py` files of modules and package
+    /// resources. The names are returned in sorted order.
+    pub fn package_file_names(&self, package: &str) -> Vec<String> {
+        let prefix = format!("{}.", package);
+
+        let mut names = BTreeSet::new();
+
+        for resource in self
+            .resources
+            .values()
+            .filter(|r| r.name == package || r.name.starts_with(&prefix))
+        {
+            let package_dir = resource.name.replace('.', "/");
+
+            if resource.is_python_module && !resource.is_python_namespace_package {
+                names.insert(if resource.is_python_package {
+                    format!("{}/__init__.py", package_dir)
+                } else {
+                    format!("{}.py", package_dir)
+                });
+            }
+
+            if let Some(resources) = &resource.in_memory_package_resources {
+                names.extend(
+                    resources
+                        .keys()
+                        .map(|name| format!("{}/{}", package_dir, name)),
+                );
+            }
+
+            if let Some(resources) = &resource.relative_path_package_resources {
+                names.extend(
+                    resources
+                        .keys()
+                        .map(|name| format!("{}/{}", package_dir, name)),
+                );
+            }
+        }
+
+        names.into_iter().collect::<Vec<_>>()
+    }
+
     /// Resolve content of a shared library to load from memory.
     pub fn resolve_in_memory_shared_library_data(&self, name: &str) -> Option<&[u8]> {
         if let Some(entry) = &self.resources.get(name) {
@@ -1227,6 +1749,21 @@ impl<'a> PythonResourcesState<'a, u8> {
         }
     }
 
+    /// Obtain the name of the layer serving a named resource.
+    ///
+    /// Returns [None] if the resource isn't known or didn't come from packed
+    /// resources data.
+    pub fn resource_layer(&self, name: &str) -> Option<&str> {
+        self.resource_layers
+            .get(name)
+            .map(|i| self.layers[*i].name.as_str())
+    }
+
+    /// Obtain resource definitions that were shadowed by other layers, in indexing order.
+    pub fn shadowed_resources(&self) -> &[ShadowedResource] {
+        &self.shadowed_resources
+    }
+
     /// Convert indexed resources to a [PyList].
     pub fn resources_as_py_list<'p>(&self, py: Python<'p>) -> PyResult<&'p PyList> {
         let mut resources = self.resources.values().collect::<Vec<_>>();
@@ -1264,7 +1801,7 @@ impl<'a> PythonResourcesState<'a, u8> {
 
         let mut buffer = Vec::new();
 
-        python_packed_resources::write_packed_resources_v3(&resources, &mut buffer, None)?;
+        python_packed_resources::write_packed_resources_v4(&resources, &mut buffer, None)?;
 
         Ok(buffer)
     }
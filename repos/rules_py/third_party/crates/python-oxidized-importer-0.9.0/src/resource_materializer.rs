@@ -0,0 +1,167 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/*!
+Materializing resource data as files on the filesystem.
+
+Some consumers of resources insist on a real filesystem path. e.g.
+`importlib.resources.as_file()` and APIs handing paths to native code.
+This module writes resource data out to files to satisfy them.
+*/
+
+use {
+    pyo3::{
+        prelude::*,
+        types::{IntoPyDict, PyBytes},
+    },
+    std::{
+        borrow::Cow,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// Removes a directory at interpreter exit.
+///
+/// Only the process that created the directory removes it, so forked
+/// children exiting don't pull files from under their parent.
+#[pyclass(module = "oxidized_importer")]
+struct TemporaryDirectoryCleanup {
+    path: PathBuf,
+    pid: u32,
+}
+
+#[pymethods]
+impl TemporaryDirectoryCleanup {
+    fn __call__(&self) {
+        if std::process::id() == self.pid {
+            // Errors are ignored: there's nobody left to report them to.
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Writes resource data to files so it can be accessed by path.
+///
+/// By default files are written to a temporary directory that is created on
+/// first use and removed at interpreter exit.
+///
+/// If a cache directory is set, files are written to a location under it
+/// derived from a hash of their content instead. These files are not removed
+/// and are reused by later runs materializing the same content.
+pub(crate) struct ResourceMaterializer {
+    /// Directory to reuse materialized files from across runs.
+    cache_dir: Option<PathBuf>,
+    /// Temporary directory holding materialized files, once created.
+    temp_dir: Mutex<Option<PathBuf>>,
+}
+
+impl ResourceMaterializer {
+    pub(crate) fn new(cache_dir: Option<PathBuf>) -> Self {
+        Self {
+            cache_dir,
+            temp_dir: Mutex::new(None),
+        }
+    }
+
+    /// The directory materialized files are reused from across runs, if any.
+    pub(crate) fn cache_dir(&self) -> Option<&Path> {
+        self.cache_dir.as_deref()
+    }
+
+    /// Write files to the filesystem.
+    ///
+    /// `files` are pairs of relative paths and their content. Returns the
+    /// directory the relative paths were resolved against.
+    ///
+    /// Files that already exist are not written again. Resources are immutable,
+    /// so existing files already hold the expected content.
+    pub(crate) fn materialize(
+        &self,
+        py: Python,
+        files: &[(PathBuf, Cow<[u8]>)],
+    ) -> PyResult<PathBuf> {
+        let root = match &self.cache_dir {
+            Some(cache_dir) => cache_dir.join(content_digest(py, files)?),
+            None => self.temp_dir(py)?,
+        };
+
+        for (path, data) in files {
+            let dest = root.join(path);
+
+            if dest.exists() {
+                continue;
+            }
+
+            if let Some(parent) = dest.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            // Write to a temporary file and rename it into place so concurrent
+            // processes sharing a cache directory never see partial files.
+            let mut temp_name = dest.file_name().unwrap_or_default().to_os_string();
+            temp_name.push(format!(".{}.tmp", std::process::id()));
+            let temp_path = dest.with_file_name(temp_name);
+
+            std::fs::write(&temp_path, data)?;
+            std::fs::rename(&temp_path, &dest)?;
+        }
+
+        Ok(root)
+    }
+
+    /// Obtain the temporary directory, creating it if needed.
+    fn temp_dir(&self, py: Python) -> PyResult<PathBuf> {
+        let mut temp_dir = self.temp_dir.lock().unwrap();
+
+        if let Some(path) = temp_dir.as_ref() {
+            return Ok(path.clone());
+        }
+
+        let path = py
+            .import("tempfile")?
+            .getattr("mkdtemp")?
+            .call(
+                (),
+                Some([("prefix", "oxidized_importer-")].into_py_dict(py)),
+            )?
+            .extract::<PathBuf>()?;
+
+        let cleanup = PyCell::new(
+            py,
+            TemporaryDirectoryCleanup {
+                path: path.clone(),
+                pid: std::process::id(),
+            },
+        )?;
+        py.import("atexit")?
+            .getattr("register")?
+            .call1((cleanup,))?;
+
+        temp_dir.replace(path.clone());
+
+        Ok(path)
+    }
+}
+
+/// Compute a hex digest identifying a set of files and their content.
+fn content_digest(py: Python, files: &[(PathBuf, Cow<[u8]>)]) -> PyResult<String> {
+    let hasher = py.import("hashlib")?.getattr("sha256")?.call0()?;
+
+    for (path, data) in files {
+        let path = path.to_string_lossy();
+
+        // Prefix variable length fields with their length so different sets of
+        // files can't produce the same input.
+        for field in [path.as_bytes(), data.as_ref()] {
+            hasher.call_method1(
+                "update",
+                (PyBytes::new(py, &(field.len() as u64).to_le_bytes()),),
+            )?;
+            hasher.call_method1("update", (PyBytes::new(py, field),))?;
+        }
+    }
+
+    hasher.call_method0("hexdigest")?.extract()
+}
@@ -3,11 +3,98 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use {
-    crate::importer::ImporterState,
-    pyo3::{exceptions::PyFileNotFoundError, prelude::*},
-    std::sync::Arc,
+    crate::{importer::ImporterState, python_resources::PackageResourceData},
+    pyo3::{
+        exceptions::{PyFileNotFoundError, PyNotADirectoryError, PyValueError},
+        prelude::*,
+        types::{PyBytes, PyDict, PyList, PyString, PyTuple},
+        PyTypeInfo,
+    },
+    std::{borrow::Cow, collections::BTreeSet, path::PathBuf, sync::Arc},
 };
 
+/// Obtain a filesystem path holding a package resource or resource directory.
+///
+/// Resources backed by files are returned as-is. In-memory resources are
+/// materialized to files first.
+fn materialize_resource(
+    py: Python,
+    state: &ImporterState,
+    package: &str,
+    name: &str,
+) -> PyResult<PathBuf> {
+    let resources_state = state.get_resources_state();
+
+    // Materialized files are laid out like packages on the filesystem so
+    // resources can find their siblings.
+    let package_dir = PathBuf::from(package.replace('.', "/"));
+
+    let names = if let Some(data) = resources_state.get_package_resource_data(package, name) {
+        if let PackageResourceData::Path(path) = data {
+            return Ok(path);
+        }
+
+        vec![name.to_string()]
+    } else if name.is_empty() || resources_state.is_package_resource_directory(package, name) {
+        resources_state.package_resources_walk_directory(package, name)
+    } else {
+        return Err(PyFileNotFoundError::new_err("resource not found"));
+    };
+
+    let mut files = vec![];
+    for name in names {
+        let data = match resources_state.get_package_resource_data(package, &name) {
+            Some(PackageResourceData::Memory(data)) => Cow::Borrowed(data),
+            Some(PackageResourceData::Path(path)) => Cow::Owned(std::fs::read(path)?),
+            None => continue,
+        };
+
+        files.push((package_dir.join(name), data));
+    }
+
+    let root = state.resource_materializer.materialize(py, &files)?;
+
+    if name.is_empty() {
+        // The package may not have any resources to create its directory.
+        let path = root.join(package_dir);
+        std::fs::create_dir_all(&path)?;
+
+        Ok(path)
+    } else {
+        Ok(root.join(package_dir).join(name))
+    }
+}
+
+/// Register our `importlib.resources.as_file()` handler for [PyOxidizerTraversable].
+///
+/// `as_file()` only knows how to provide paths for `pathlib.Path` and falls
+/// back to a temporary file for everything else, which doesn't work for
+/// directories.
+fn register_as_file(py: Python) -> PyResult<()> {
+    let as_file = py.import("importlib.resources")?.getattr("as_file")?;
+
+    // as_file() is a functools.singledispatch function. Don't assume it stays
+    // that way.
+    if !as_file.hasattr("register")? {
+        return Ok(());
+    }
+
+    let traversable_type = PyOxidizerTraversable::type_object(py);
+
+    if !as_file
+        .getattr("registry")?
+        .call_method1("__contains__", (traversable_type,))?
+        .is_true()?
+    {
+        as_file.call_method1(
+            "register",
+            (traversable_type, traversable_type.getattr("_as_file")?),
+        )?;
+    }
+
+    Ok(())
+}
+
 /// Implements in-memory reading of resource data.
 ///
 /// Implements importlib.abc.ResourceReader.
@@ -34,6 +121,10 @@ impl OxidizedResourceReader {
             &self.package,
             resource,
         )? {
+            if let Some(profile) = &self.state.import_profile {
+                profile.record_resource(&self.package, resource);
+            }
+
             Ok(file)
         } else {
             Err(PyFileNotFoundError::new_err("resource not found"))
@@ -42,13 +133,20 @@ impl OxidizedResourceReader {
 
     /// Returns the file system path to the resource.
     ///
-    /// If the resource does not concretely exist on the file system, raise
-    /// FileNotFoundError.
-    #[allow(unused)]
-    fn resource_path(&self, resource: &PyAny) -> PyResult<()> {
-        Err(PyFileNotFoundError::new_err(
-            "in-memory resources do not have filesystem paths",
-        ))
+    /// In-memory resources are materialized to files first. If the resource
+    /// does not exist, raise FileNotFoundError.
+    fn resource_path<'p>(&self, py: Python<'p>, resource: &str) -> PyResult<&'p PyAny> {
+        if !self
+            .state
+            .get_resources_state()
+            .is_package_resource(&self.package, resource)
+        {
+            return Err(PyFileNotFoundError::new_err("resource not found"));
+        }
+
+        let path = materialize_resource(py, &self.state, &self.package, resource)?;
+
+        Ok(path.into_py(py).into_ref(py))
     }
 
     /// Returns True if the named name is considered a resource. FileNotFoundError
@@ -80,4 +178,227 @@ impl OxidizedResourceReader {
             .get_resources_state()
             .package_resource_names(py, &self.package)
     }
+
+    /// Returns a Traversable for the package's resources.
+    ///
+    /// Implements importlib.resources.abc.TraversableResources.
+    fn files(&self, py: Python) -> PyResult<PyOxidizerTraversable> {
+        register_as_file(py)?;
+
+        Ok(PyOxidizerTraversable {
+            state: self.state.clone(),
+            package: self.package.clone(),
+            name: "".to_string(),
+        })
+    }
+}
+
+/// Path-like object facilitating Python resource access.
+///
+/// This implements importlib.abc.Traversable.
+#[pyclass(module = "oxidized_importer")]
+pub(crate) struct PyOxidizerTraversable {
+    state: Arc<ImporterState>,
+    package: String,
+    /// Name of the resource or resource directory within the package.
+    ///
+    /// The empty string refers to the package itself.
+    name: String,
+}
+
+impl PyOxidizerTraversable {
+    fn child(&self, name: &str) -> Self {
+        let name = name.trim_matches('/');
+
+        Self {
+            state: self.state.clone(),
+            package: self.package.clone(),
+            name: if self.name.is_empty() {
+                name.to_string()
+            } else if name.is_empty() {
+                self.name.clone()
+            } else {
+                format!("{}/{}", self.name, name)
+            },
+        }
+    }
+
+    fn is_dir_inner(&self) -> bool {
+        self.name.is_empty()
+            || self
+                .state
+                .get_resources_state()
+                .is_package_resource_directory(&self.package, &self.name)
+    }
+}
+
+#[pymethods]
+impl PyOxidizerTraversable {
+    /// The base name of this object without any parent references.
+    #[getter]
+    fn name(&self) -> &str {
+        if self.name.is_empty() {
+            self.package.rsplit('.').next().unwrap_or_default()
+        } else {
+            self.name.rsplit('/').next().unwrap_or_default()
+        }
+    }
+
+    /// Yield Traversable objects in self.
+    fn iterdir<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        if !self.is_dir_inner() {
+            return Err(PyNotADirectoryError::new_err(self.name.clone()));
+        }
+
+        let prefix = if self.name.is_empty() {
+            "".to_string()
+        } else {
+            format!("{}/", self.name)
+        };
+
+        // Subdirectories are only known through the resources within them.
+        let names = self
+            .state
+            .get_resources_state()
+            .package_resources_walk_directory(&self.package, &self.name)
+            .into_iter()
+            .filter_map(|name| {
+                name.strip_prefix(&prefix)
+                    .and_then(|name| name.split('/').next())
+                    .map(|name| name.to_string())
+            })
+            .collect::<BTreeSet<_>>();
+
+        let children = names
+            .iter()
+            .map(|name| Py::new(py, self.child(name)))
+            .collect::<PyResult<Vec<_>>>()?;
+
+        PyList::new(py, children).call_method0("__iter__")
+    }
+
+    /// Read contents of self as bytes.
+    fn read_bytes<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        match self
+            .state
+            .get_resources_state()
+            .get_package_resource_data(&self.package, &self.name)
+        {
+            Some(PackageResourceData::Memory(data)) => Ok(PyBytes::new(py, data)),
+            Some(PackageResourceData::Path(path)) => Ok(PyBytes::new(py, &std::fs::read(path)?)),
+            None => Err(PyFileNotFoundError::new_err("resource not found")),
+        }
+    }
+
+    /// Read contents of self as text.
+    #[args(encoding = "None")]
+    fn read_text<'p>(&self, py: Python<'p>, encoding: Option<&str>) -> PyResult<&'p PyAny> {
+        let kwargs = PyDict::new(py);
+        kwargs.set_item("encoding", encoding)?;
+
+        self.open(py, PyTuple::new(py, ["r"]), Some(kwargs))?
+            .call_method0("read")
+    }
+
+    /// Return True if self is a dir.
+    fn is_dir(&self) -> bool {
+        self.is_dir_inner()
+    }
+
+    /// Return True if self is a file.
+    fn is_file(&self) -> bool {
+        !self.name.is_empty()
+            && self
+                .state
+                .get_resources_state()
+                .is_package_resource(&self.package, &self.name)
+    }
+
+    /// Return Traversable child in self.
+    #[args(py_args = "*")]
+    fn joinpath(&self, py: Python, py_args: &PyTuple) -> PyResult<Self> {
+        let fspath = py.import("os")?.getattr("fspath")?;
+
+        let mut names = vec![];
+        for child in py_args {
+            names.push(fspath.call1((child,))?.downcast::<PyString>()?.to_str()?);
+        }
+
+        Ok(self.child(&names.join("/")))
+    }
+
+    /// Return Traversable child in self.
+    fn __truediv__(&self, py: Python, child: &PyAny) -> PyResult<Self> {
+        self.joinpath(py, PyTuple::new(py, [child]))
+    }
+
+    /// mode may be 'r' or 'rb' to open as text or binary. Return a handle
+    /// suitable for reading (same as pathlib.Path.open).
+    ///
+    /// When opening as text, accepts encoding parameters such as those
+    /// accepted by io.TextIOWrapper.
+    #[args(py_args = "*", py_kwargs = "**")]
+    fn open<'p>(
+        &self,
+        py: Python<'p>,
+        py_args: &'p PyTuple,
+        py_kwargs: Option<&'p PyDict>,
+    ) -> PyResult<&'p PyAny> {
+        let (mode, wrapper_args) = match py_args.get_item(0) {
+            Ok(mode) => (mode.extract::<&str>()?, py_args.get_slice(1, py_args.len())),
+            Err(_) => ("r", py_args),
+        };
+
+        if !matches!(mode, "r" | "rb") {
+            return Err(PyValueError::new_err(format!("invalid mode: {}", mode)));
+        }
+
+        let file = match self.state.get_resources_state().get_package_resource_file(
+            py,
+            &self.package,
+            &self.name,
+        )? {
+            Some(file) => file,
+            None => return Err(PyFileNotFoundError::new_err("resource not found")),
+        };
+
+        if let Some(profile) = &self.state.import_profile {
+            profile.record_resource(&self.package, &self.name);
+        }
+
+        if mode == "rb" {
+            return Ok(file);
+        }
+
+        let args = PyTuple::new(
+            py,
+            std::iter::once(file)
+                .chain(wrapper_args.iter())
+                .collect::<Vec<_>>(),
+        );
+
+        py.import("io")?
+            .getattr("TextIOWrapper")?
+            .call(args, py_kwargs)
+    }
+
+    /// Handler for `importlib.resources.as_file()`.
+    ///
+    /// Returns a context manager providing a `pathlib.Path` to the resource or
+    /// resource directory, materializing in-memory resources as needed.
+    fn _as_file<'p>(&self, py: Python<'p>) -> PyResult<&'p PyAny> {
+        let path = materialize_resource(py, &self.state, &self.package, &self.name)?;
+        let path = py.import("pathlib")?.getattr("Path")?.call1((path,))?;
+
+        py.import("contextlib")?
+            .getattr("nullcontext")?
+            .call1((path,))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "<PyOxidizerTraversable package={} name={}>",
+            self.package, self.name
+        )
+    }
 }
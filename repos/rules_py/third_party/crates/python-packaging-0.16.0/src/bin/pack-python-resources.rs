@@ -0,0 +1,269 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Pack Python resources found in directories as directed by a packaging policy.
+
+Usage: `pack-python-resources --python PATH [--policy PATH] [--stdlib DIR] [--files-dir DIR] [--bytecode-filename-prefix PREFIX] --output PATH ROOT...`
+
+Each `ROOT` is scanned for Python resources like a `sys.path` entry. The
+resources are added as directed by the packaging policy loaded from
+`--policy`, or the default policy. Policy files ending in `.json` are parsed
+as JSON, all others as TOML.
+
+`--stdlib` is scanned like a `ROOT`, but its resources are marked as
+belonging to the standard library, so the policy's stdlib profile applies to
+them. Standard library modules the profile excludes but packed modules import
+are reported as warnings.
+
+Bytecode is compiled with the interpreter given by `--python`, which also
+determines the bytecode cache tag and module suffixes. Compiled bytecode
+refers to its source as `PREFIX/foo/bar.py`, with `PREFIX` given by
+`--bytecode-filename-prefix` (default `<exe>`). The importer replaces these
+filenames with `__file__` for modules that have one. Packed resources data
+is written to `--output`, copying file contents as it goes rather than
+holding them in memory. Resources the policy places on the filesystem are
+written below `--files-dir`, which is required if there are any.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::{
+        bytecode::BytecodeCompiler, filesystem_scanning::find_python_resources,
+        location::AbstractResourceLocation, module_util::PythonModuleSuffixes,
+        policy::PythonPackagingPolicy, resource::PythonResource,
+        resource_collection::PythonResourceCollector,
+    },
+    std::{
+        io::Write,
+        path::{Path, PathBuf},
+    },
+};
+
+const USAGE: &str = "usage: pack-python-resources --python PATH [--policy PATH] [--stdlib DIR] [--files-dir DIR] [--bytecode-filename-prefix PREFIX] --output PATH ROOT...";
+
+/// Default prefix of the filenames compiled bytecode refers to.
+const DEFAULT_BYTECODE_FILENAME_PREFIX: &str = "<exe>";
+
+/// Python code printing the bytecode cache tag and module suffixes of an interpreter.
+const INTERPRETER_INFO: &str = r#"
+import importlib.machinery as m, json, sys
+print(json.dumps({
+    "cache_tag": sys.implementation.cache_tag,
+    "source": m.SOURCE_SUFFIXES,
+    "bytecode": m.BYTECODE_SUFFIXES,
+    "debug_bytecode": getattr(m, "DEBUG_BYTECODE_SUFFIXES", []),
+    "optimized_bytecode": getattr(m, "OPTIMIZED_BYTECODE_SUFFIXES", []),
+    "extension": m.EXTENSION_SUFFIXES,
+}))
+"#;
+
+/// Resolve the bytecode cache tag and module suffixes of a Python interpreter.
+fn interpreter_info(python: &Path) -> Result<(String, PythonModuleSuffixes)> {
+    let output = std::process::Command::new(python)
+        .arg("-c")
+        .arg(INTERPRETER_INFO)
+        .output()
+        .with_context(|| format!("running {}", python.display()))?;
+
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} failed: {}",
+            python.display(),
+            String::from_utf8_lossy(&output.stderr)
+        ));
+    }
+
+    let info: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("parsing interpreter info")?;
+
+    let strings = |key: &str| -> Result<Vec<String>> {
+        serde_json::from_value(info[key].clone())
+            .with_context(|| format!("parsing interpreter info {}", key))
+    };
+
+    Ok((
+        serde_json::from_value(info["cache_tag"].clone())
+            .context("parsing interpreter info cache_tag")?,
+        PythonModuleSuffixes {
+            source: strings("source")?,
+            bytecode: strings("bytecode")?,
+            debug_bytecode: strings("debug_bytecode")?,
+            optimized_bytecode: strings("optimized_bytecode")?,
+            extension: strings("extension")?,
+        },
+    ))
+}
+
+/// Mark a resource as belonging to the standard library.
+fn mark_stdlib(resource: &mut PythonResource) {
+    match resource {
+        PythonResource::ModuleSource(m) => m.to_mut().is_stdlib = true,
+        PythonResource::ModuleBytecodeRequest(m) => m.to_mut().is_stdlib = true,
+        PythonResource::ModuleBytecode(m) => m.to_mut().is_stdlib = true,
+        PythonResource::PackageResource(r) => r.to_mut().is_stdlib = true,
+        PythonResource::ExtensionModule(m) => m.to_mut().is_stdlib = true,
+        _ => {}
+    }
+}
+
+fn run() -> Result<()> {
+    let mut python = None;
+    let mut policy_path = None;
+    let mut stdlib = None;
+    let mut files_dir = None;
+    let mut bytecode_filename_prefix = DEFAULT_BYTECODE_FILENAME_PREFIX.to_string();
+    let mut output = None;
+    let mut roots = vec![];
+
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--python") => {
+                python = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--python requires a value"))?,
+                ));
+            }
+            Some("--policy") => {
+                policy_path = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--policy requires a value"))?,
+                ));
+            }
+            Some("--stdlib") => {
+                stdlib = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--stdlib requires a value"))?,
+                ));
+            }
+            Some("--files-dir") => {
+                files_dir = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--files-dir requires a value"))?,
+                ));
+            }
+            Some("--bytecode-filename-prefix") => {
+                bytecode_filename_prefix = args
+                    .next()
+                    .ok_or_else(|| anyhow!("--bytecode-filename-prefix requires a value"))?
+                    .into_string()
+                    .map_err(|_| anyhow!("--bytecode-filename-prefix must be valid UTF-8"))?;
+            }
+            Some("--output") | Some("-o") => {
+                output = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--output requires a value"))?,
+                ));
+            }
+            Some("--help") | Some("-h") => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            Some(option) if option.starts_with('-') => {
+                return Err(anyhow!("unrecognized option {}\n{}", option, USAGE));
+            }
+            _ => roots.push(PathBuf::from(arg)),
+        }
+    }
+
+    let python = python.ok_or_else(|| anyhow!("{}", USAGE))?;
+    let output = output.ok_or_else(|| anyhow!("{}", USAGE))?;
+
+    let mut policy = match &policy_path {
+        Some(path) => PythonPackagingPolicy::from_path(path)?,
+        None => PythonPackagingPolicy::default(),
+    };
+
+    let (cache_tag, suffixes) = interpreter_info(&python)?;
+
+    let mut resources = vec![];
+    for (root, is_stdlib) in stdlib
+        .iter()
+        .map(|root| (root, true))
+        .chain(roots.iter().map(|root| (root, false)))
+    {
+        for resource in find_python_resources(
+            root,
+            &cache_tag,
+            &suffixes,
+            policy.file_scanner_emit_files(),
+            policy.file_scanner_classify_files(),
+        )? {
+            match resource.with_context(|| format!("scanning {}", root.display()))? {
+                // These only affect how sys.path is populated, which packed
+                // resources don't model.
+                PythonResource::EggFile(_) | PythonResource::PathExtension(_) => {}
+                mut resource => {
+                    if is_stdlib {
+                        mark_stdlib(&mut resource);
+                    }
+                    resources.push(resource);
+                }
+            }
+        }
+    }
+
+    let locations = vec![
+        AbstractResourceLocation::InMemory,
+        AbstractResourceLocation::RelativePath,
+    ];
+    let mut collector =
+        PythonResourceCollector::new(locations.clone(), locations, false, policy.allow_files());
+    collector.set_bytecode_filename_prefix(Some(bytecode_filename_prefix));
+    collector.add_python_resources_with_policy(&mut policy, &resources)?;
+
+    for (name, importers) in policy.find_excluded_stdlib_imports(resources.iter())? {
+        eprintln!(
+            "warning: {} is excluded by the {} stdlib profile but imported by {}",
+            name,
+            policy.stdlib_profile().as_ref(),
+            importers.into_iter().collect::<Vec<_>>().join(", ")
+        );
+    }
+
+    let scratch_dir =
+        std::env::temp_dir().join(format!("pack-python-resources-{}", std::process::id()));
+    std::fs::create_dir_all(&scratch_dir)
+        .with_context(|| format!("creating {}", scratch_dir.display()))?;
+    let compiled = BytecodeCompiler::new(&python, &scratch_dir)
+        .and_then(|mut compiler| collector.compile_resources_streaming(&mut compiler));
+    std::fs::remove_dir_all(&scratch_dir)
+        .with_context(|| format!("removing {}", scratch_dir.display()))?;
+    let compiled = compiled?;
+
+    if !compiled.extra_files.is_empty() {
+        let files_dir = files_dir.ok_or_else(|| {
+            anyhow!(
+                "the policy places resources on the filesystem; --files-dir is required\n{}",
+                USAGE
+            )
+        })?;
+
+        compiled
+            .extra_files_manifest()?
+            .materialize_files(&files_dir)
+            .with_context(|| format!("writing files to {}", files_dir.display()))?;
+    }
+
+    let mut writer = std::io::BufWriter::new(
+        std::fs::File::create(&output).with_context(|| format!("creating {}", output.display()))?,
+    );
+    compiled.write_packed_resources(&mut writer)?;
+    writer
+        .flush()
+        .with_context(|| format!("writing {}", output.display()))?;
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
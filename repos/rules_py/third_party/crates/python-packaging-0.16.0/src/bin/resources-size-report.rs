@@ -0,0 +1,158 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Report and compare the size of packed resources.
+
+Usage:
+
+* `resources-size-report report [--origin DIR] [--text] [--output PATH] INPUT`
+* `resources-size-report diff [--threshold BYTES] BEFORE AFTER`
+
+`report` attributes the bytes of a packed resources file to packages and
+distributions. Files installed relative to resources are resolved against
+`--origin`. The report is written in the machine-readable format unless
+`--text` is given.
+
+`diff` compares two machine-readable reports. With `--threshold`, it exits
+with status 2 if any package grew by more than that many bytes.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packaging::size_report::SizeReport,
+    std::path::PathBuf,
+};
+
+const USAGE: &str =
+    "usage: resources-size-report report [--origin DIR] [--text] [--output PATH] INPUT
+       resources-size-report diff [--threshold BYTES] BEFORE AFTER";
+
+fn report(mut args: impl Iterator<Item = std::ffi::OsString>) -> Result<i32> {
+    let mut origin = None;
+    let mut text = false;
+    let mut output = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--origin") => {
+                origin = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--origin requires a value"))?,
+                ));
+            }
+            Some("--text") => {
+                text = true;
+            }
+            Some("--output") | Some("-o") => {
+                output = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--output requires a value"))?,
+                ));
+            }
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(anyhow!("{}", USAGE)),
+        }
+    }
+
+    let input = input.ok_or_else(|| anyhow!("{}", USAGE))?;
+    let data = std::fs::read(&input).with_context(|| format!("reading {}", input.display()))?;
+
+    let report = SizeReport::from_packed_resources(&data, origin.as_deref())
+        .with_context(|| format!("analyzing {}", input.display()))?;
+
+    let rendered = if text {
+        report.to_text()
+    } else {
+        report.to_report_string()
+    };
+
+    if let Some(output) = output {
+        std::fs::write(&output, rendered)
+            .with_context(|| format!("writing {}", output.display()))?;
+    } else {
+        print!("{}", rendered);
+    }
+
+    Ok(0)
+}
+
+fn diff(mut args: impl Iterator<Item = std::ffi::OsString>) -> Result<i32> {
+    let mut threshold = None;
+    let mut paths = vec![];
+
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--threshold") => {
+                threshold = Some(
+                    args.next()
+                        .and_then(|v| v.into_string().ok())
+                        .ok_or_else(|| anyhow!("--threshold requires a value"))?
+                        .parse::<u64>()
+                        .context("parsing --threshold")?,
+                );
+            }
+            _ => paths.push(PathBuf::from(arg)),
+        }
+    }
+
+    let (before, after) = match paths.as_slice() {
+        [before, after] => (
+            SizeReport::from_path(before)?,
+            SizeReport::from_path(after)?,
+        ),
+        _ => return Err(anyhow!("{}", USAGE)),
+    };
+
+    let diff = before.diff(&after);
+    print!("{}", diff.to_text());
+
+    if let Some(threshold) = threshold {
+        let regressions = diff.regressions(threshold).collect::<Vec<_>>();
+
+        if !regressions.is_empty() {
+            eprintln!(
+                "packages grew by more than {} bytes: {}",
+                threshold,
+                regressions
+                    .iter()
+                    .map(|change| change.name.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            return Ok(2);
+        }
+    }
+
+    Ok(0)
+}
+
+fn run() -> Result<i32> {
+    let mut args = std::env::args_os().skip(1);
+
+    match args.next().as_ref().and_then(|x| x.to_str()) {
+        Some("report") => report(args),
+        Some("diff") => diff(args),
+        Some("--help") | Some("-h") => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        _ => Err(anyhow!("{}", USAGE)),
+    }
+}
+
+fn main() {
+    match run() {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("error: {:#}", e);
+            std::process::exit(1);
+        }
+    }
+}
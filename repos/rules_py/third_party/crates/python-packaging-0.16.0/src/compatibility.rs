@@ -0,0 +1,413 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Detection of Python code incompatible with in-memory importing.
+
+Modules imported from memory don't have files backing them. Code deriving
+filesystem paths from module attributes, or relying on APIs that only work
+with filesystem-based loaders, breaks when imported this way.
+
+Detection is a line-based heuristic over decoded source code. Comments are
+ignored but string literals aren't, so findings may be false positives. A
+statement spanning multiple lines is only detected if the relevant tokens
+appear on the same line.
+*/
+
+use {
+    crate::python_source::decode_python_source, once_cell::sync::Lazy, regex::Regex,
+    std::collections::BTreeMap,
+};
+
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+/// Describes a pattern that is incompatible with in-memory importing.
+///
+/// Serialization type: `string`. See [Self::as_str()] for values.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub enum CompatibilityIssueKind {
+    /// `__file__` is referenced.
+    ///
+    /// Modules imported from memory don't define `__file__`.
+    DunderFile,
+    /// A filesystem path is derived from `__file__`.
+    ///
+    /// Unlike [Self::DunderFile], this excludes uses like comparisons or
+    /// `hasattr()` checks, which don't need the module to be backed by a file.
+    DunderFilePath,
+    /// `__path__` is indexed or mutated to derive filesystem paths.
+    DunderPath,
+    /// A directory derived from a module's location is listed.
+    DirectoryListing,
+    /// `pkgutil.get_data()` is called.
+    ///
+    /// It derives paths from `__file__`.
+    PkgutilGetData,
+    /// `pkg_resources.resource_filename()` is called.
+    PkgResourcesFilename,
+    /// `importlib.resources.path()` is called.
+    ///
+    /// Resources in memory must be copied to temporary files.
+    ImportlibResourcesPath,
+    /// A shared library is loaded with `ctypes` from a path derived from a
+    /// module's location.
+    CtypesSiblingLibrary,
+    /// `sys.path` is mutated.
+    ///
+    /// This usually implies subsequent imports are expected to be serviced
+    /// from the filesystem.
+    SysPathMutation,
+}
+
+impl CompatibilityIssueKind {
+    /// All kinds.
+    pub const ALL: [CompatibilityIssueKind; 9] = [
+        Self::DunderFile,
+        Self::DunderFilePath,
+        Self::DunderPath,
+        Self::DirectoryListing,
+        Self::PkgutilGetData,
+        Self::PkgResourcesFilename,
+        Self::ImportlibResourcesPath,
+        Self::CtypesSiblingLibrary,
+        Self::SysPathMutation,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DunderFile => "dunder-file",
+            Self::DunderFilePath => "dunder-file-path",
+            Self::DunderPath => "dunder-path",
+            Self::DirectoryListing => "directory-listing",
+            Self::PkgutilGetData => "pkgutil-get-data",
+            Self::PkgResourcesFilename => "pkg-resources-filename",
+            Self::ImportlibResourcesPath => "importlib-resources-path",
+            Self::CtypesSiblingLibrary => "ctypes-sibling-library",
+            Self::SysPathMutation => "sys-path-mutation",
+        }
+    }
+}
+
+impl TryFrom<&str> for CompatibilityIssueKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+            .ok_or_else(|| format!("{} is not a valid compatibility issue kind", value))
+    }
+}
+
+impl TryFrom<String> for CompatibilityIssueKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+impl From<CompatibilityIssueKind> for String {
+    fn from(kind: CompatibilityIssueKind) -> Self {
+        kind.as_str().to_string()
+    }
+}
+
+/// An occurrence of a [CompatibilityIssueKind] in source code.
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct CompatibilityFinding {
+    pub kind: CompatibilityIssueKind,
+    /// 1-based line number.
+    pub line: usize,
+    /// The offending line, without surrounding whitespace.
+    pub text: String,
+}
+
+static RE_DUNDER_FILE: Lazy<Regex> = Lazy::new(|| Regex::new(r"\b__file__\b").unwrap());
+
+static RE_DUNDER_FILE_PATH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(os\.path\.\w+|(pathlib\.)?(Pure)?Path)\([^)]*\b__file__\b").unwrap()
+});
+
+static RE_DUNDER_PATH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b__path__\s*(\[|\+=|\.(append|extend|insert)\()|os\.path\.\w+\([^)]*\b__path__\b")
+        .unwrap()
+});
+
+static RE_MODULE_LOCATION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(__file__|__path__)\b|\bos\.path\.dirname\(").unwrap());
+
+static RE_DIRECTORY_LISTING: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(os\.(listdir|scandir|walk)|glob\.i?glob)\(|\.(iterdir|glob|rglob)\(").unwrap()
+});
+
+static RE_PKGUTIL_GET_DATA: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\bpkgutil\.get_data\(").unwrap());
+
+static RE_PKG_RESOURCES_FILENAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\b(pkg_resources\.)?resource_filename\(").unwrap());
+
+static RE_IMPORTLIB_RESOURCES_PATH: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(importlib\.resources|importlib_resources|resources)\.path\(").unwrap()
+});
+
+static RE_CTYPES_LOAD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b(CDLL|PyDLL|WinDLL|OleDLL|LoadLibrary|cdll\.LoadLibrary)\(").unwrap()
+});
+
+static RE_SYS_PATH_MUTATION: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"\bsys\.path\s*(\.(insert|append|extend|remove|pop|clear)\(|\[[^\]]*\]\s*=[^=]|\+=|=[^=])|\bsite\.addsitedir\(",
+    )
+    .unwrap()
+});
+
+/// Remove a trailing comment from a line of Python source.
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+            continue;
+        }
+
+        match (quote, c) {
+            (Some(_), '\\') => escaped = true,
+            (Some(q), c) if c == q => quote = None,
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '#') => return &line[..i],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+/// Find patterns incompatible with in-memory importing in a line of source.
+fn line_issues(line: &str) -> Vec<CompatibilityIssueKind> {
+    let mut res = vec![];
+
+    let location = RE_MODULE_LOCATION.is_match(line);
+
+    if RE_DUNDER_FILE.is_match(line) {
+        res.push(CompatibilityIssueKind::DunderFile);
+    }
+    if RE_DUNDER_FILE_PATH.is_match(line) {
+        res.push(CompatibilityIssueKind::DunderFilePath);
+    }
+    if RE_DUNDER_PATH.is_match(line) {
+        res.push(CompatibilityIssueKind::DunderPath);
+    }
+    if location && RE_DIRECTORY_LISTING.is_match(line) {
+        res.push(CompatibilityIssueKind::DirectoryListing);
+    }
+    if RE_PKGUTIL_GET_DATA.is_match(line) {
+        res.push(CompatibilityIssueKind::PkgutilGetData);
+    }
+    if RE_PKG_RESOURCES_FILENAME.is_match(line) {
+        res.push(CompatibilityIssueKind::PkgResourcesFilename);
+    }
+    if RE_IMPORTLIB_RESOURCES_PATH.is_match(line) {
+        res.push(CompatibilityIssueKind::ImportlibResourcesPath);
+    }
+    if location && RE_CTYPES_LOAD.is_match(line) {
+        res.push(CompatibilityIssueKind::CtypesSiblingLibrary);
+    }
+    if RE_SYS_PATH_MUTATION.is_match(line) {
+        res.push(CompatibilityIssueKind::SysPathMutation);
+    }
+
+    res
+}
+
+/// Find patterns incompatible with in-memory importing in Python source code.
+///
+/// Findings are ordered by line.
+pub fn find_compatibility_issues(source: &[u8]) -> Vec<CompatibilityFinding> {
+    let source = decode_python_source(source);
+
+    let mut res = vec![];
+
+    for (i, line) in source.lines().enumerate() {
+        let code = strip_comment(line);
+
+        for kind in line_issues(code) {
+            res.push(CompatibilityFinding {
+                kind,
+                line: i + 1,
+                text: line.trim().to_string(),
+            });
+        }
+    }
+
+    res
+}
+
+/// Findings of incompatibilities with in-memory importing for a collection of modules.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CompatibilityReport {
+    /// Findings by top-level package, then by module.
+    pub packages: BTreeMap<String, BTreeMap<String, Vec<CompatibilityFinding>>>,
+}
+
+impl CompatibilityReport {
+    /// Record findings for a module.
+    ///
+    /// Modules without findings aren't recorded.
+    pub fn add_module(&mut self, name: &str, findings: Vec<CompatibilityFinding>) {
+        if findings.is_empty() {
+            return;
+        }
+
+        let package = name.split('.').next().unwrap_or(name);
+
+        self.packages
+            .entry(package.to_string())
+            .or_default()
+            .entry(name.to_string())
+            .or_default()
+            .extend(findings);
+    }
+
+    /// Names of top-level packages having findings of any of the given kinds.
+    pub fn flagged_packages(&self, kinds: &[CompatibilityIssueKind]) -> Vec<String> {
+        self.packages
+            .iter()
+            .filter(|(_, modules)| {
+                modules
+                    .values()
+                    .flatten()
+                    .any(|finding| kinds.contains(&finding.kind))
+            })
+            .map(|(package, _)| package.clone())
+            .collect()
+    }
+
+    /// Whether no findings were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty()
+    }
+
+    /// Render findings as text, grouped by package.
+    pub fn to_text(&self) -> String {
+        let mut res = String::new();
+
+        for (package, modules) in &self.packages {
+            res.push_str(&format!("{}:\n", package));
+
+            for (module, findings) in modules {
+                for finding in findings {
+                    res.push_str(&format!(
+                        "  {}:{}: {}: {}\n",
+                        module,
+                        finding.line,
+                        finding.kind.as_str(),
+                        finding.text
+                    ));
+                }
+            }
+        }
+
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, CompatibilityIssueKind::*};
+
+    fn kinds(source: &str) -> Vec<CompatibilityIssueKind> {
+        find_compatibility_issues(source.as_bytes())
+            .into_iter()
+            .map(|f| f.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_find_compatibility_issues() {
+        assert_eq!(kinds("import os\n"), vec![]);
+        assert_eq!(kinds("# __file__\nx = '#' # sys.path.append(x)\n"), vec![]);
+
+        assert_eq!(
+            kinds("HERE = os.path.dirname(__file__)\n"),
+            vec![DunderFile, DunderFilePath]
+        );
+        assert_eq!(
+            kinds("HERE = pathlib.Path(__file__).parent\n"),
+            vec![DunderFile, DunderFilePath]
+        );
+        assert_eq!(kinds("if __file__ is None:\n"), vec![DunderFile]);
+        assert_eq!(
+            kinds("for p in os.listdir(os.path.dirname(__file__)):\n"),
+            vec![DunderFile, DunderFilePath, DirectoryListing]
+        );
+        assert_eq!(kinds("os.listdir('.')\n"), vec![]);
+        assert_eq!(
+            kinds("data = os.path.join(__path__[0], 'x')\n"),
+            vec![DunderPath]
+        );
+        assert_eq!(kinds("__path__.append(d)\n"), vec![DunderPath]);
+        assert_eq!(
+            kinds("__path__ = extend_path(__path__, __name__)\n"),
+            vec![]
+        );
+        assert_eq!(
+            kinds("pkgutil.get_data(__name__, 'data/x.json')\n"),
+            vec![PkgutilGetData]
+        );
+        assert_eq!(
+            kinds("pkg_resources.resource_filename(__name__, 'x')\n"),
+            vec![PkgResourcesFilename]
+        );
+        assert_eq!(
+            kinds("with importlib.resources.path('pkg', 'x') as p:\n"),
+            vec![ImportlibResourcesPath]
+        );
+        assert_eq!(
+            kinds("lib = ctypes.CDLL(os.path.join(os.path.dirname(__file__), 'x.so'))\n"),
+            vec![DunderFile, DunderFilePath, CtypesSiblingLibrary]
+        );
+        assert_eq!(kinds("lib = ctypes.CDLL('libc.so.6')\n"), vec![]);
+        assert_eq!(kinds("sys.path.insert(0, d)\n"), vec![SysPathMutation]);
+        assert_eq!(kinds("sys.path = [d] + sys.path\n"), vec![SysPathMutation]);
+        assert_eq!(kinds("if d in sys.path:\n"), vec![]);
+        assert_eq!(kinds("if sys.path == x:\n"), vec![]);
+
+        let findings = find_compatibility_issues(b"import sys\n  sys.path.append(d)\n");
+        assert_eq!(
+            findings,
+            vec![CompatibilityFinding {
+                kind: SysPathMutation,
+                line: 2,
+                text: "sys.path.append(d)".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let mut report = CompatibilityReport::default();
+        report.add_module("foo.bar", find_compatibility_issues(b"x = __file__\n"));
+        report.add_module("foo", find_compatibility_issues(b"sys.path.append(x)\n"));
+        report.add_module("baz", find_compatibility_issues(b"import os\n"));
+
+        assert_eq!(report.packages.keys().collect::<Vec<_>>(), vec!["foo"]);
+        assert_eq!(report.flagged_packages(&[DunderFile]), vec!["foo"]);
+        assert!(report.flagged_packages(&[PkgutilGetData]).is_empty());
+        assert_eq!(
+            report.to_text(),
+            "foo:\n  \
+             foo:1: sys-path-mutation: sys.path.append(x)\n  \
+             foo.bar:1: dunder-file: x = __file__\n"
+        );
+    }
+}
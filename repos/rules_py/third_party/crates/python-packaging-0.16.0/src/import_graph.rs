@@ -0,0 +1,1051 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Static analysis of imports between Python modules.
+
+Imports are found without executing code, either by scanning source code
+or by decoding the instructions in bytecode. The analysis is conservative:
+a name imported with `from package import name` is treated as a possible
+import of `package.name`, as it is impossible to know statically whether
+`name` is a submodule or an attribute.
+
+Besides `import` and `from` statements, calls to `importlib.import_module()`
+and `__import__()` whose module name is a string literal are recognized. These
+are recorded separately, as dynamic imports, since their presence is often
+conditional. Calls with non-literal arguments can't be resolved and are only
+counted.
+
+Bytecode analysis supports the bytecode of CPython 3.7 through 3.12.
+*/
+
+use {
+    crate::{
+        module_util::packages_from_module_name,
+        python_source::decode_python_source,
+        resource_collection::{PrePackagedResource, PythonModuleBytecodeProvider},
+    },
+    anyhow::{anyhow, Result},
+    byteorder::{ByteOrder, LittleEndian},
+    simple_file_manifest::FileData,
+    std::collections::{BTreeMap, BTreeSet, VecDeque},
+};
+
+/// Imports performed by a single module.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ModuleImports {
+    /// Absolute names imported by `import` and `from` statements.
+    ///
+    /// Names from `from` statements may refer to attributes instead of modules.
+    pub static_imports: BTreeSet<String>,
+
+    /// Absolute names passed as literals to `importlib.import_module()` or `__import__()`.
+    pub dynamic_imports: BTreeSet<String>,
+
+    /// Number of calls to import functions with names that couldn't be resolved.
+    pub unresolved_dynamic_imports: usize,
+}
+
+/// Resolve the package a module's relative imports are relative to.
+fn module_package(module: &str, is_package: bool) -> &str {
+    if is_package {
+        module
+    } else {
+        module.rsplit_once('.').map_or("", |(parent, _)| parent)
+    }
+}
+
+/// Resolve a possibly relative import to an absolute name.
+///
+/// Returns [None] if a relative import goes beyond the top-level package.
+fn resolve_name(package: &str, level: usize, name: &str) -> Option<String> {
+    if level == 0 {
+        return Some(name.to_string());
+    }
+
+    let mut base = package;
+    for _ in 1..level {
+        base = base.rsplit_once('.').map(|(parent, _)| parent)?;
+    }
+
+    if base.is_empty() {
+        None
+    } else if name.is_empty() {
+        Some(base.to_string())
+    } else {
+        Some(format!("{}.{}", base, name))
+    }
+}
+
+impl ModuleImports {
+    fn add_import(&mut self, package: &str, level: usize, name: &str, fromlist: &[String]) {
+        let base = match resolve_name(package, level, name) {
+            Some(base) => base,
+            None => return,
+        };
+
+        for item in fromlist {
+            if item != "*" {
+                self.static_imports.insert(format!("{}.{}", base, item));
+            }
+        }
+
+        self.static_imports.insert(base);
+    }
+
+    fn add_dynamic_import(&mut self, package: &str, name: &str) {
+        let level = name.chars().take_while(|c| *c == '.').count();
+
+        if let Some(name) = resolve_name(package, level, &name[level..]) {
+            self.dynamic_imports.insert(name);
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Name(String),
+    /// A string literal. [None] for byte strings and f-strings.
+    Str(Option<String>),
+    Op(char),
+    /// The end of a statement.
+    Newline,
+}
+
+/// Split Python source code into a simplified token stream.
+fn tokenize(source: &str) -> Vec<Token> {
+    let chars = source.chars().collect::<Vec<_>>();
+    let mut tokens = vec![];
+    let mut depth = 0usize;
+    let mut i = 0;
+
+    let end_statement = |tokens: &mut Vec<Token>| {
+        if !matches!(tokens.last(), None | Some(Token::Newline)) {
+            tokens.push(Token::Newline);
+        }
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '#' {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+        } else if c == '\\' && chars.get(i + 1) == Some(&'\n') {
+            i += 2;
+        } else if c == '\n' || ((c == ';' || c == ':') && depth == 0) {
+            if depth == 0 {
+                end_statement(&mut tokens);
+            }
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let word = chars[start..i].iter().collect::<String>();
+
+            if matches!(chars.get(i), Some('"') | Some('\''))
+                && word.len() <= 2
+                && word.chars().all(|c| "rRbBuUfF".contains(c))
+            {
+                let literal = !word.chars().any(|c| "bBfF".contains(c));
+                let (value, end) = read_string(&chars, i);
+                tokens.push(Token::Str(if literal { Some(value) } else { None }));
+                i = end;
+            } else {
+                tokens.push(Token::Name(word));
+            }
+        } else if c == '"' || c == '\'' {
+            let (value, end) = read_string(&chars, i);
+            tokens.push(Token::Str(Some(value)));
+            i = end;
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '.') {
+                i += 1;
+            }
+            tokens.push(Token::Op('0'));
+        } else {
+            match c {
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+            tokens.push(Token::Op(c));
+            i += 1;
+        }
+    }
+
+    end_statement(&mut tokens);
+
+    tokens
+}
+
+/// Read a string literal starting at the quote at `start`.
+///
+/// Returns the content of the string, with escape sequences left as is, and
+/// the index following the literal.
+fn read_string(chars: &[char], start: usize) -> (String, usize) {
+    let quote = chars[start];
+    let triple = chars.get(start + 1) == Some(&quote) && chars.get(start + 2) == Some(&quote);
+    let delimiter = if triple { 3 } else { 1 };
+
+    let mut i = start + delimiter;
+    let content_start = i;
+
+    while i < chars.len() {
+        if chars[i] == '\\' {
+            i += 2;
+        } else if chars[i] == quote
+            && (!triple || (chars.get(i + 1) == Some(&quote) && chars.get(i + 2) == Some(&quote)))
+        {
+            let value = chars[content_start..i].iter().collect();
+            return (value, i + delimiter);
+        } else if chars[i] == '\n' && !triple {
+            break;
+        } else {
+            i += 1;
+        }
+    }
+
+    let end = i.min(chars.len());
+    (chars[content_start..end].iter().collect(), end)
+}
+
+/// Read a dotted name at `tokens[i]`, returning it and the index after it.
+fn read_dotted_name(tokens: &[Token], mut i: usize) -> (String, usize) {
+    let mut name = String::new();
+
+    while let Some(Token::Name(part)) = tokens.get(i) {
+        name.push_str(part);
+        i += 1;
+
+        if tokens.get(i) == Some(&Token::Op('.')) {
+            name.push('.');
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    (name, i)
+}
+
+/// Find imports in Python source code.
+pub fn find_source_imports(module: &str, is_package: bool, source: &[u8]) -> ModuleImports {
+    let source = decode_python_source(source);
+    let tokens = tokenize(&source);
+    let package = module_package(module, is_package);
+    let mut imports = ModuleImports::default();
+
+    for statement in tokens.split(|t| t == &Token::Newline) {
+        match statement.first() {
+            Some(Token::Name(keyword)) if keyword == "import" => {
+                let mut i = 1;
+                while i < statement.len() {
+                    let (name, next) = read_dotted_name(statement, i);
+                    if !name.is_empty() {
+                        imports.add_import(package, 0, name.trim_end_matches('.'), &[]);
+                    }
+
+                    // Skip to the next comma separated name.
+                    i = next;
+                    while i < statement.len() && statement[i] != Token::Op(',') {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            Some(Token::Name(keyword)) if keyword == "from" => {
+                let mut i = 1;
+                let mut level = 0;
+                while statement.get(i) == Some(&Token::Op('.')) {
+                    level += 1;
+                    i += 1;
+                }
+
+                let (name, i) = if statement.get(i) == Some(&Token::Name("import".to_string())) {
+                    (String::new(), i)
+                } else {
+                    read_dotted_name(statement, i)
+                };
+                if statement.get(i) != Some(&Token::Name("import".to_string())) {
+                    continue;
+                }
+
+                let mut fromlist = vec![];
+                let mut expect_name = true;
+                for token in &statement[i + 1..] {
+                    match token {
+                        Token::Name(item) if expect_name => {
+                            fromlist.push(item.clone());
+                            expect_name = false;
+                        }
+                        Token::Op('*') => fromlist.push("*".to_string()),
+                        Token::Op(',') => expect_name = true,
+                        _ => {}
+                    }
+                }
+
+                imports.add_import(package, level, &name, &fromlist);
+            }
+            _ => {}
+        }
+
+        for (i, token) in statement.iter().enumerate() {
+            match token {
+                Token::Name(name) if name == "import_module" || name == "__import__" => {}
+                _ => continue,
+            }
+
+            if i > 0 && statement[i - 1] == Token::Name("def".to_string()) {
+                continue;
+            }
+
+            if statement.get(i + 1) != Some(&Token::Op('(')) {
+                continue;
+            }
+
+            match (statement.get(i + 2), statement.get(i + 3)) {
+                (Some(Token::Str(Some(name))), Some(Token::Op(')'))) => {
+                    imports.add_dynamic_import(package, name);
+                }
+                (Some(Token::Str(Some(name))), Some(Token::Op(','))) => {
+                    let base = match statement.get(i + 4) {
+                        Some(Token::Str(Some(base))) => base.as_str(),
+                        Some(Token::Name(x)) if x == "__package__" => package,
+                        Some(Token::Name(x)) if x == "__name__" => module,
+                        _ => package,
+                    };
+
+                    imports.add_dynamic_import(base, name);
+                }
+                _ => {
+                    imports.unresolved_dynamic_imports += 1;
+                }
+            }
+        }
+    }
+
+    imports
+}
+
+/// A value decoded from `marshal` data.
+#[derive(Clone, Debug)]
+enum MarshalValue {
+    Null,
+    None,
+    Int(i64),
+    Str(String),
+    Bytes(Vec<u8>),
+    Tuple(Vec<MarshalValue>),
+    Code(Box<CodeObject>),
+    Other,
+}
+
+/// The fields of a code object relevant to finding imports.
+#[derive(Clone, Debug)]
+struct CodeObject {
+    code: Vec<u8>,
+    consts: Vec<MarshalValue>,
+    names: Vec<String>,
+}
+
+/// Layout of serialized code objects.
+#[derive(Clone, Copy, Debug)]
+struct CodeLayout {
+    /// Number of 32-bit integers preceding the bytecode.
+    leading_ints: usize,
+    /// Number of objects following the line number.
+    trailing_objects: usize,
+}
+
+const CODE_LAYOUTS: [CodeLayout; 3] = [
+    // 3.11+: argcount, posonlyargcount, kwonlyargcount, stacksize, flags;
+    // linetable, exceptiontable.
+    CodeLayout {
+        leading_ints: 5,
+        trailing_objects: 2,
+    },
+    // 3.8 - 3.10: argcount, posonlyargcount, kwonlyargcount, nlocals, stacksize,
+    // flags; lnotab or linetable.
+    CodeLayout {
+        leading_ints: 6,
+        trailing_objects: 1,
+    },
+    // 3.7: argcount, kwonlyargcount, nlocals, stacksize, flags; lnotab.
+    CodeLayout {
+        leading_ints: 5,
+        trailing_objects: 1,
+    },
+];
+
+struct MarshalReader<'a> {
+    data: &'a [u8],
+    offset: usize,
+    refs: Vec<MarshalValue>,
+    layout: CodeLayout,
+}
+
+impl<'a> MarshalReader<'a> {
+    fn take(&mut self, size: usize) -> Result<&'a [u8]> {
+        let end = self
+            .offset
+            .checked_add(size)
+            .filter(|end| *end <= self.data.len())
+            .ok_or_else(|| anyhow!("unexpected end of marshal data"))?;
+
+        let data = &self.data[self.offset..end];
+        self.offset = end;
+
+        Ok(data)
+    }
+
+    fn read_u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn read_i32(&mut self) -> Result<i32> {
+        Ok(LittleEndian::read_i32(self.take(4)?))
+    }
+
+    fn read_size(&mut self) -> Result<usize> {
+        usize::try_from(self.read_i32()?).map_err(|_| anyhow!("negative size in marshal data"))
+    }
+
+    fn read_string(&mut self, size: usize) -> Result<String> {
+        Ok(String::from_utf8_lossy(self.take(size)?).into_owned())
+    }
+
+    fn read_values(&mut self, count: usize) -> Result<Vec<MarshalValue>> {
+        (0..count).map(|_| self.read_value()).collect()
+    }
+
+    fn read_value(&mut self) -> Result<MarshalValue> {
+        let code = self.read_u8()?;
+        let flag_ref = code & 0x80 != 0;
+
+        // Containers reserve their reference before reading their contents.
+        let index = self.refs.len();
+        if flag_ref {
+            self.refs.push(MarshalValue::None);
+        }
+
+        let value = match code & 0x7f {
+            b'0' => MarshalValue::Null,
+            b'N' => MarshalValue::None,
+            b'F' | b'T' | b'S' | b'.' => MarshalValue::Other,
+            b'i' => MarshalValue::Int(self.read_i32()? as i64),
+            b'I' => {
+                self.take(8)?;
+                MarshalValue::Other
+            }
+            b'l' => {
+                let digits = self.read_i32()?;
+                self.take(digits.unsigned_abs() as usize * 2)?;
+                MarshalValue::Other
+            }
+            b'f' => {
+                let size = self.read_u8()? as usize;
+                self.take(size)?;
+                MarshalValue::Other
+            }
+            b'g' => {
+                self.take(8)?;
+                MarshalValue::Other
+            }
+            b'x' => {
+                for _ in 0..2 {
+                    let size = self.read_u8()? as usize;
+                    self.take(size)?;
+                }
+                MarshalValue::Other
+            }
+            b'y' => {
+                self.take(16)?;
+                MarshalValue::Other
+            }
+            b's' => {
+                let size = self.read_size()?;
+                MarshalValue::Bytes(self.take(size)?.to_vec())
+            }
+            b'u' | b't' | b'a' | b'A' => {
+                let size = self.read_size()?;
+                MarshalValue::Str(self.read_string(size)?)
+            }
+            b'z' | b'Z' => {
+                let size = self.read_u8()? as usize;
+                MarshalValue::Str(self.read_string(size)?)
+            }
+            b'(' | b'[' | b'<' | b'>' => {
+                let count = self.read_size()?;
+                MarshalValue::Tuple(self.read_values(count)?)
+            }
+            b')' => {
+                let count = self.read_u8()? as usize;
+                MarshalValue::Tuple(self.read_values(count)?)
+            }
+            b'{' => {
+                loop {
+                    if let MarshalValue::Null = self.read_value()? {
+                        break;
+                    }
+                    self.read_value()?;
+                }
+                MarshalValue::Other
+            }
+            b'r' => {
+                let target = self.read_i32()? as usize;
+                self.refs
+                    .get(target)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("invalid reference in marshal data"))?
+            }
+            b'c' => MarshalValue::Code(Box::new(self.read_code()?)),
+            code => return Err(anyhow!("unknown marshal type: {:#x}", code)),
+        };
+
+        if flag_ref {
+            self.refs[index] = value.clone();
+        }
+
+        Ok(value)
+    }
+
+    fn read_code(&mut self) -> Result<CodeObject> {
+        self.take(self.layout.leading_ints * 4)?;
+
+        // code, consts, names, and 5 objects we don't care about.
+        let objects = self.read_values(8)?;
+        self.read_i32()?;
+        self.read_values(self.layout.trailing_objects)?;
+
+        let mut objects = objects.into_iter();
+
+        let code = match objects.next() {
+            Some(MarshalValue::Bytes(code)) => code,
+            _ => return Err(anyhow!("code object lacks bytecode")),
+        };
+        let consts = match objects.next() {
+            Some(MarshalValue::Tuple(consts)) => consts,
+            _ => return Err(anyhow!("code object lacks constants")),
+        };
+        let names = match objects.next() {
+            Some(MarshalValue::Tuple(names)) => names
+                .into_iter()
+                .map(|name| match name {
+                    MarshalValue::Str(name) => Ok(name),
+                    _ => Err(anyhow!("code object name is not a string")),
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => return Err(anyhow!("code object lacks names")),
+        };
+
+        Ok(CodeObject {
+            code,
+            consts,
+            names,
+        })
+    }
+}
+
+/// Decode the code object of a module from `marshal` data.
+fn read_module_code(bytecode: &[u8]) -> Result<CodeObject> {
+    let mut error = anyhow!("no code layouts to try");
+
+    // The layout of code objects varies by Python version. A layout reading the
+    // data exactly is assumed to be the correct one.
+    for layout in CODE_LAYOUTS {
+        let mut reader = MarshalReader {
+            data: bytecode,
+            offset: 0,
+            refs: vec![],
+            layout,
+        };
+
+        match reader.read_value() {
+            Ok(MarshalValue::Code(code)) if reader.offset == bytecode.len() => return Ok(*code),
+            Ok(_) => error = anyhow!("marshal data is not a code object"),
+            Err(e) => error = e,
+        }
+    }
+
+    Err(error)
+}
+
+const OP_CACHE: u8 = 0;
+const OP_LOAD_CONST: u8 = 100;
+const OP_LOAD_NAME: u8 = 101;
+const OP_LOAD_ATTR: u8 = 106;
+const OP_IMPORT_NAME: u8 = 108;
+const OP_LOAD_GLOBAL: u8 = 116;
+const OP_EXTENDED_ARG: u8 = 144;
+const OP_LOAD_METHOD: u8 = 160;
+
+fn find_code_imports(code: &CodeObject, package: &str, imports: &mut ModuleImports) {
+    let mut recent_consts = [None, None];
+    let mut import_function = false;
+    let mut extended = 0usize;
+
+    for instruction in code.code.chunks_exact(2) {
+        let (op, arg) = (instruction[0], extended | instruction[1] as usize);
+
+        if op == OP_EXTENDED_ARG {
+            extended = arg << 8;
+            continue;
+        }
+        extended = 0;
+
+        // Later versions encode flags in the low bits of name arguments.
+        let name_matches = |names: &[&str]| {
+            [arg, arg >> 1]
+                .iter()
+                .filter_map(|i| code.names.get(*i))
+                .any(|name| names.contains(&name.as_str()))
+        };
+
+        match op {
+            OP_CACHE => {}
+            OP_LOAD_CONST => {
+                if import_function {
+                    match code.consts.get(arg) {
+                        Some(MarshalValue::Str(name)) => imports.add_dynamic_import(package, name),
+                        _ => imports.unresolved_dynamic_imports += 1,
+                    }
+                    import_function = false;
+                }
+
+                recent_consts = [recent_consts[1], Some(arg)];
+            }
+            OP_IMPORT_NAME => {
+                let level = match recent_consts[0].and_then(|i| code.consts.get(i)) {
+                    Some(MarshalValue::Int(level)) => *level as usize,
+                    _ => 0,
+                };
+                let fromlist = match recent_consts[1].and_then(|i| code.consts.get(i)) {
+                    Some(MarshalValue::Tuple(items)) => items
+                        .iter()
+                        .filter_map(|item| match item {
+                            MarshalValue::Str(item) => Some(item.clone()),
+                            _ => None,
+                        })
+                        .collect(),
+                    _ => vec![],
+                };
+
+                if let Some(name) = code.names.get(arg) {
+                    imports.add_import(package, level, name, &fromlist);
+                }
+            }
+            OP_LOAD_NAME | OP_LOAD_GLOBAL | OP_LOAD_ATTR | OP_LOAD_METHOD
+                if name_matches(&["import_module", "__import__"]) =>
+            {
+                if import_function {
+                    imports.unresolved_dynamic_imports += 1;
+                }
+                import_function = true;
+            }
+            _ => {
+                if import_function {
+                    imports.unresolved_dynamic_imports += 1;
+                    import_function = false;
+                }
+            }
+        }
+    }
+
+    for value in &code.consts {
+        if let MarshalValue::Code(child) = value {
+            find_code_imports(child, package, imports);
+        }
+    }
+}
+
+/// Find imports in the bytecode of a module.
+///
+/// `bytecode` is `marshal` data without a `.pyc` header.
+pub fn find_bytecode_imports(
+    module: &str,
+    is_package: bool,
+    bytecode: &[u8],
+) -> Result<ModuleImports> {
+    let code = read_module_code(bytecode)?;
+    let mut imports = ModuleImports::default();
+
+    find_code_imports(&code, module_package(module, is_package), &mut imports);
+
+    Ok(imports)
+}
+
+/// A graph of imports between modules.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportGraph {
+    modules: BTreeMap<String, ModuleImports>,
+}
+
+/// The result of walking an [ImportGraph] from entry points.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportGraphReachability {
+    /// Modules reachable through `import` and `from` statements.
+    pub static_reachable: BTreeSet<String>,
+
+    /// Modules only reachable through calls to import functions with literal names.
+    pub dynamic_only: BTreeSet<String>,
+
+    /// Reachable modules calling import functions with names that couldn't be resolved.
+    ///
+    /// Modules imported by these calls may be missing from the reachable sets.
+    pub unresolved_dynamic_importers: BTreeSet<String>,
+}
+
+impl ImportGraphReachability {
+    /// Whether a module is reachable by any means.
+    pub fn is_reachable(&self, name: &str) -> bool {
+        self.static_reachable.contains(name) || self.dynamic_only.contains(name)
+    }
+}
+
+impl ImportGraph {
+    /// Record the imports of a module.
+    pub fn add_module(&mut self, name: &str, imports: ModuleImports) {
+        self.modules.insert(name.to_string(), imports);
+    }
+
+    /// Whether a module is known to the graph.
+    pub fn contains(&self, name: &str) -> bool {
+        self.modules.contains_key(name)
+    }
+
+    /// Obtain the imports of a module.
+    pub fn module_imports(&self, name: &str) -> Option<&ModuleImports> {
+        self.modules.get(name)
+    }
+
+    /// Construct an instance by analyzing Python module resources.
+    ///
+    /// Source code is preferred over bytecode. Extension modules and modules
+    /// lacking both are added without imports. Builtin and frozen modules and
+    /// non-module resources are ignored.
+    pub fn from_resources<'a>(
+        resources: impl IntoIterator<Item = &'a PrePackagedResource>,
+    ) -> Result<Self> {
+        let mut graph = Self::default();
+
+        for resource in resources {
+            if resource.is_extension_module {
+                graph.add_module(&resource.name, ModuleImports::default());
+            }
+
+            if !resource.is_module {
+                continue;
+            }
+
+            let source = resource.in_memory_source.as_ref().or(resource
+                .relative_path_module_source
+                .as_ref()
+                .map(|(_, source)| source));
+
+            let bytecode = [
+                &resource.in_memory_bytecode,
+                &resource.in_memory_bytecode_opt1,
+                &resource.in_memory_bytecode_opt2,
+            ]
+            .into_iter()
+            .flatten()
+            .chain(
+                [
+                    &resource.relative_path_bytecode,
+                    &resource.relative_path_bytecode_opt1,
+                    &resource.relative_path_bytecode_opt2,
+                ]
+                .into_iter()
+                .flatten()
+                .map(|(_, _, bytecode)| bytecode),
+            )
+            .next();
+
+            let imports = match (source, bytecode) {
+                (Some(source), _) => analyze_source(resource, source)?,
+                (None, Some(PythonModuleBytecodeProvider::FromSource(source))) => {
+                    analyze_source(resource, source)?
+                }
+                (None, Some(PythonModuleBytecodeProvider::Provided(bytecode))) => {
+                    find_bytecode_imports(
+                        &resource.name,
+                        resource.is_package,
+                        &bytecode.resolve_content()?,
+                    )
+                    .map_err(|e| anyhow!("analyzing bytecode of {}: {}", resource.name, e))?
+                }
+                (None, None) => ModuleImports::default(),
+            };
+
+            graph.add_module(&resource.name, imports);
+        }
+
+        Ok(graph)
+    }
+
+    /// Determine the modules reachable from entry point modules.
+    ///
+    /// Importing a module imports its parent packages, so these are reachable as
+    /// well. Imported names not in the graph are ignored.
+    pub fn reachable<'a>(
+        &self,
+        entry_points: impl IntoIterator<Item = &'a str>,
+    ) -> ImportGraphReachability {
+        let entry_points = entry_points.into_iter().collect::<Vec<_>>();
+
+        let static_reachable = self.walk(&entry_points, false);
+        let all = self.walk(&entry_points, true);
+
+        let unresolved_dynamic_importers = all
+            .iter()
+            .filter(|name| self.modules[*name].unresolved_dynamic_imports > 0)
+            .cloned()
+            .collect();
+
+        ImportGraphReachability {
+            dynamic_only: all.difference(&static_reachable).cloned().collect(),
+            static_reachable,
+            unresolved_dynamic_importers,
+        }
+    }
+
+    fn walk(&self, entry_points: &[&str], dynamic: bool) -> BTreeSet<String> {
+        let mut seen = BTreeSet::new();
+        let mut queue = entry_points
+            .iter()
+            .map(|x| x.to_string())
+            .collect::<VecDeque<_>>();
+
+        while let Some(name) = queue.pop_front() {
+            let candidates = packages_from_module_name(&name)
+                .into_iter()
+                .chain(std::iter::once(name));
+
+            for candidate in candidates {
+                if seen.contains(&candidate) {
+                    continue;
+                }
+
+                let imports = match self.modules.get(&candidate) {
+                    Some(imports) => imports,
+                    None => continue,
+                };
+
+                queue.extend(imports.static_imports.iter().cloned());
+                if dynamic {
+                    queue.extend(imports.dynamic_imports.iter().cloned());
+                }
+
+                seen.insert(candidate);
+            }
+        }
+
+        seen
+    }
+}
+
+fn analyze_source(resource: &PrePackagedResource, source: &FileData) -> Result<ModuleImports> {
+    Ok(find_source_imports(
+        &resource.name,
+        resource.is_package,
+        &source.resolve_content()?,
+    ))
+}
+
+/// Describes how to remove modules unreachable from an application's entry points.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportGraphPruningPolicy {
+    /// Modules the application starts executing from.
+    ///
+    /// Modules imported by the interpreter itself during startup, like `encodings`,
+    /// need to be listed here or in [Self::retain].
+    pub entry_points: Vec<String>,
+
+    /// Modules to retain regardless of reachability, along with their submodules.
+    pub retain: Vec<String>,
+}
+
+impl ImportGraphPruningPolicy {
+    /// Whether a module is retained regardless of reachability.
+    pub fn is_retained(&self, name: &str) -> bool {
+        self.retain.iter().any(|x| {
+            name == x || (name.starts_with(x.as_str()) && name[x.len()..].starts_with('.'))
+        })
+    }
+}
+
+/// Describes the outcome of pruning modules with an [ImportGraphPruningPolicy].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImportGraphPruneReport {
+    /// Names of removed modules.
+    pub removed: BTreeSet<String>,
+
+    /// Modules retained only because calls to import functions with literal names
+    /// reach them.
+    pub dynamic_only: BTreeSet<String>,
+
+    /// Retained modules calling import functions with names that couldn't be
+    /// resolved. Modules they import may have been removed.
+    pub unresolved_dynamic_importers: BTreeSet<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source_imports(module: &str, is_package: bool, source: &str) -> ModuleImports {
+        find_source_imports(module, is_package, source.as_bytes())
+    }
+
+    fn names(values: &[&str]) -> BTreeSet<String> {
+        values.iter().map(|x| x.to_string()).collect()
+    }
+
+    #[test]
+    fn test_source_import_statements() {
+        let imports = source_imports(
+            "pkg.sub.mod",
+            false,
+            "import os, os.path as p\n\
+             from . import sibling\n\
+             from ..up import (a,\n    b as c)\n\
+             from json import *\n\
+             if True: import sys; import re\n\
+             x = 'import fake'\n\
+             # import commented\n\
+             \"\"\"\n\
+             import docstring\n\
+             \"\"\"\n",
+        );
+
+        assert_eq!(
+            imports.static_imports,
+            names(&[
+                "json",
+                "os",
+                "os.path",
+                "pkg.sub",
+                "pkg.sub.sibling",
+                "pkg.up",
+                "pkg.up.a",
+                "pkg.up.b",
+                "re",
+                "sys",
+            ])
+        );
+        assert!(imports.dynamic_imports.is_empty());
+    }
+
+    #[test]
+    fn test_source_relative_package() {
+        let imports = source_imports("pkg", true, "from .child import x\nfrom ... import y\n");
+
+        assert_eq!(imports.static_imports, names(&["pkg.child", "pkg.child.x"]));
+    }
+
+    #[test]
+    fn test_source_dynamic_imports() {
+        let imports = source_imports(
+            "pkg.mod",
+            false,
+            "import importlib\n\
+             importlib.import_module('plugins.a')\n\
+             importlib.import_module('.b', __package__)\n\
+             __import__(\"c\")\n\
+             importlib.import_module(name)\n\
+             def import_module(name): pass\n",
+        );
+
+        assert_eq!(imports.static_imports, names(&["importlib"]));
+        assert_eq!(imports.dynamic_imports, names(&["c", "pkg.b", "plugins.a"]));
+        assert_eq!(imports.unresolved_dynamic_imports, 1);
+    }
+
+    /// Marshal data for a module compiled by CPython 3.11 from
+    /// `from .a import b`, `import importlib` and `importlib.import_module('c')`.
+    const BYTECODE_311: &[u8] =
+        b"\xe3\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x03\x00\x00\x00\x00\x00\x00\x00\
+        \xf3:\x00\x00\x00\x97\x00d\x00d\x01l\x00m\x01Z\x01\x01\x00d\x02d\x03l\x02Z\x02\x02\
+        \x00e\x02j\x03\x00\x00\x00\x00\x00\x00\x00\x00d\x04\xa6\x01\x00\x00\xab\x01\x00\x00\
+        \x00\x00\x00\x00\x00\x00\x01\x00d\x03S\x00)\x05\xe9\x01\x00\x00\x00)\x01\xda\x01b\
+        \xe9\x00\x00\x00\x00N\xda\x01c)\x04\xda\x01ar\x03\x00\x00\x00\xda\x09importlib\xda\
+        \x0dimport_module\xa9\x00\xf3\x00\x00\x00\x00\xda\x01x\xfa\x08<module>r\x0c\x00\x00\
+        \x00\x01\x00\x00\x00s<\x00\x00\x00\xf0\x03\x01\x01\x01\xd8\x00\x10\xd0\x00\x10\xd0\
+        \x00\x10\xd0\x00\x10\xd0\x00\x10\xd0\x00\x10\xd8\x00\x10\xd0\x00\x10\xd0\x00\x10\xd0\
+        \x00\x10\xd8\x00\x17\x80\x09\xd4\x00\x17\x98\x03\xd1\x00\x1c\xd4\x00\x1c\xd0\x00\x1c\
+        \xd0\x00\x1c\xd0\x00\x1cr\x0a\x00\x00\x00";
+
+    #[test]
+    fn test_bytecode_imports() -> Result<()> {
+        let imports = find_bytecode_imports("pkg.mod", false, BYTECODE_311)?;
+
+        assert_eq!(
+            imports.static_imports,
+            names(&["importlib", "pkg.a", "pkg.a.b"])
+        );
+        assert_eq!(imports.dynamic_imports, names(&["c"]));
+        assert_eq!(imports.unresolved_dynamic_imports, 0);
+
+        assert!(find_bytecode_imports("pkg.mod", false, &BYTECODE_311[0..20]).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reachable() {
+        let mut graph = ImportGraph::default();
+        graph.add_module("app", source_imports("app", true, ""));
+        graph.add_module(
+            "app.main",
+            source_imports(
+                "app.main",
+                false,
+                "from . import util\nimport os\nimportlib.import_module('app.plugin')\n\
+                 importlib.import_module(x)\n",
+            ),
+        );
+        graph.add_module("app.util", source_imports("app.util", false, "import json"));
+        graph.add_module(
+            "app.plugin",
+            source_imports("app.plugin", false, "import csv"),
+        );
+        graph.add_module("app.unused", source_imports("app.unused", false, ""));
+        graph.add_module("json", source_imports("json", true, ""));
+        graph.add_module("csv", source_imports("csv", false, ""));
+
+        let reachability = graph.reachable(["app.main"]);
+
+        assert_eq!(
+            reachability.static_reachable,
+            names(&["app", "app.main", "app.util", "json"])
+        );
+        assert_eq!(reachability.dynamic_only, names(&["app.plugin", "csv"]));
+        assert_eq!(
+            reachability.unresolved_dynamic_importers,
+            names(&["app.main"])
+        );
+        assert!(!reachability.is_reachable("app.unused"));
+    }
+
+    #[test]
+    fn test_pruning_policy_retain() {
+        let policy = ImportGraphPruningPolicy {
+            entry_points: vec![],
+            retain: vec!["encodings".to_string()],
+        };
+
+        assert!(policy.is_retained("encodings"));
+        assert!(policy.is_retained("encodings.utf_8"));
+        assert!(!policy.is_retained("encodingsx"));
+    }
+}
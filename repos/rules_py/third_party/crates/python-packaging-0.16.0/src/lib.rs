@@ -13,10 +13,13 @@ and packaging facilities.
 */
 
 pub mod bytecode;
+pub mod compatibility;
 pub mod filesystem_scanning;
+pub mod import_graph;
 pub mod interpreter;
 pub mod libpython;
 pub mod licensing;
+pub mod loaded_modules;
 pub mod location;
 pub mod module_util;
 pub mod package_metadata;
@@ -24,6 +27,7 @@ pub mod policy;
 pub mod python_source;
 pub mod resource;
 pub mod resource_collection;
+pub mod size_report;
 #[cfg(test)]
 mod testutil;
 #[cfg(feature = "wheel")]
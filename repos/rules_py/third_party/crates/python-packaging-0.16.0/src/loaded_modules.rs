@@ -0,0 +1,267 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Manifests of modules loaded by a running application.
+
+An embedded interpreter can record which modules it imported and which
+package resources it read. These records can be fed back into packaging
+to remove resources an application never uses.
+
+The manifest is a line-oriented, tab-delimited text format. The first line
+identifies the format. Subsequent lines are either modules, in import order:
+
+```text
+module <name> <source> <layer> <startup|runtime>
+```
+
+or package resources that were read:
+
+```text
+resource <package> <name>
+```
+
+`<source>` and `<layer>` are `-` when unknown.
+*/
+
+use {
+    crate::{module_util::packages_from_module_name, resource_collection::PrePackagedResource},
+    anyhow::{anyhow, Context, Result},
+    std::{collections::BTreeSet, fmt::Write, path::Path},
+};
+
+/// Header line identifying the manifest format.
+const HEADER: &str = "# loaded modules manifest v1";
+
+/// A module loaded by an application.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedModule {
+    /// Fully qualified name of the module.
+    pub name: String,
+
+    /// Where the module's code came from. e.g. `memory` or `relative_path`.
+    pub source: Option<String>,
+
+    /// Name of the packed resources layer the module was indexed from.
+    pub layer: Option<String>,
+
+    /// Whether the module was imported during interpreter startup.
+    pub startup: bool,
+}
+
+/// Describes modules and package resources used by a run of an application.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadedModulesManifest {
+    /// Loaded modules, in the order they were imported.
+    pub modules: Vec<LoadedModule>,
+
+    /// `(package, resource name)` pairs of package resources that were read.
+    pub resources: BTreeSet<(String, String)>,
+}
+
+fn optional_field(value: &str) -> Option<String> {
+    if value == "-" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+impl LoadedModulesManifest {
+    /// Parse a manifest from its serialized form.
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut lines = data.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("not a loaded modules manifest"));
+        }
+
+        let mut res = Self::default();
+
+        for (i, line) in lines.enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+
+            match fields.as_slice() {
+                ["module", name, source, layer, phase] => {
+                    let startup = match *phase {
+                        "startup" => true,
+                        "runtime" => false,
+                        _ => return Err(anyhow!("line {}: invalid phase: {}", i + 2, phase)),
+                    };
+
+                    res.modules.push(LoadedModule {
+                        name: name.to_string(),
+                        source: optional_field(source),
+                        layer: optional_field(layer),
+                        startup,
+                    });
+                }
+                ["resource", package, name] => {
+                    res.resources
+                        .insert((package.to_string(), name.to_string()));
+                }
+                _ => return Err(anyhow!("line {}: malformed entry: {}", i + 2, line)),
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Parse a manifest from a file.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let data =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+        Self::parse(&data).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Serialize the manifest.
+    pub fn to_manifest_string(&self) -> String {
+        let mut res = format!("{}\n", HEADER);
+
+        for module in &self.modules {
+            writeln!(
+                res,
+                "module\t{}\t{}\t{}\t{}",
+                module.name,
+                module.source.as_deref().unwrap_or("-"),
+                module.layer.as_deref().unwrap_or("-"),
+                if module.startup { "startup" } else { "runtime" }
+            )
+            .unwrap();
+        }
+
+        for (package, name) in &self.resources {
+            writeln!(res, "resource\t{}\t{}", package, name).unwrap();
+        }
+
+        res
+    }
+}
+
+/// Decides which resources to keep given the manifests of one or more runs.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LoadedModulesFilter {
+    /// Names of modules and packages that must be retained.
+    modules: BTreeSet<String>,
+}
+
+impl LoadedModulesFilter {
+    /// Construct an instance from manifests.
+    ///
+    /// A module is retained if any manifest records it. If `startup_only` is
+    /// true, only modules imported during interpreter startup are considered.
+    /// Parent packages of retained modules are always retained, as are packages
+    /// whose resources were read.
+    pub fn new<'a>(
+        manifests: impl IntoIterator<Item = &'a LoadedModulesManifest>,
+        startup_only: bool,
+    ) -> Self {
+        let mut modules = BTreeSet::new();
+
+        for manifest in manifests {
+            for module in &manifest.modules {
+                if startup_only && !module.startup {
+                    continue;
+                }
+
+                modules.extend(packages_from_module_name(&module.name));
+                modules.insert(module.name.clone());
+            }
+
+            for (package, _) in &manifest.resources {
+                modules.extend(packages_from_module_name(package));
+                modules.insert(package.clone());
+            }
+        }
+
+        Self { modules }
+    }
+
+    /// Whether a resource should be retained.
+    ///
+    /// Only Python modules are candidates for removal. Builtin and frozen modules
+    /// are provided by the interpreter and are always retained, as are non-module
+    /// resources like shared libraries and files.
+    pub fn retain(&self, resource: &PrePackagedResource) -> bool {
+        if !(resource.is_module || resource.is_extension_module) {
+            return true;
+        }
+
+        self.modules.contains(&resource.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = "# loaded modules manifest v1\n\
+        module\tfoo\tmemory\tapp\tstartup\n\
+        module\tfoo.bar\trelative_path\t-\truntime\n\
+        module\tbaz\t-\t-\truntime\n\
+        resource\tdata.pkg\tconfig.json\n";
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let manifest = LoadedModulesManifest::parse(MANIFEST)?;
+
+        assert_eq!(manifest.modules.len(), 3);
+        assert_eq!(
+            manifest.modules[1],
+            LoadedModule {
+                name: "foo.bar".to_string(),
+                source: Some("relative_path".to_string()),
+                layer: None,
+                startup: false,
+            }
+        );
+        assert!(manifest
+            .resources
+            .contains(&("data.pkg".to_string(), "config.json".to_string())));
+        assert_eq!(manifest.to_manifest_string(), MANIFEST);
+
+        assert!(LoadedModulesManifest::parse("module\tfoo\n").is_err());
+        assert!(LoadedModulesManifest::parse(&format!("{}\nmodule\tfoo\n", HEADER)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter() -> Result<()> {
+        let manifest = LoadedModulesManifest::parse(MANIFEST)?;
+
+        let module = |name: &str| PrePackagedResource {
+            name: name.to_string(),
+            is_module: true,
+            ..PrePackagedResource::default()
+        };
+
+        let filter = LoadedModulesFilter::new([&manifest], false);
+        assert!(filter.retain(&module("foo")));
+        assert!(filter.retain(&module("foo.bar")));
+        assert!(filter.retain(&module("data")));
+        assert!(filter.retain(&module("data.pkg")));
+        assert!(!filter.retain(&module("unused")));
+        assert!(filter.retain(&PrePackagedResource {
+            name: "libfoo".to_string(),
+            is_shared_library: true,
+            ..PrePackagedResource::default()
+        }));
+
+        let filter = LoadedModulesFilter::new([&manifest], true);
+        assert!(filter.retain(&module("foo")));
+        assert!(!filter.retain(&module("foo.bar")));
+        assert!(!filter.retain(&module("baz")));
+
+        Ok(())
+    }
+}
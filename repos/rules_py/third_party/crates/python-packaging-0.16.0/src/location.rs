@@ -8,6 +8,9 @@
 
 /*! Functionality related to resource locations. */
 
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
 /// Describes the location of a Python resource.
 ///
 /// The location is abstract because a concrete location (such as the
@@ -42,7 +45,13 @@ impl TryFrom<&str> for AbstractResourceLocation {
 }
 
 /// Describes the concrete location of a Python resource.
+/// Where a resource is placed.
+///
+/// Serialization type: `string`. Serialized values are `in-memory` and
+/// `filesystem-relative:<prefix>`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
 pub enum ConcreteResourceLocation {
     /// Resource is loaded from memory.
     InMemory,
@@ -101,6 +110,14 @@ impl TryFrom<&str> for ConcreteResourceLocation {
     }
 }
 
+impl TryFrom<String> for ConcreteResourceLocation {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, anyhow::Result};
@@ -109,6 +109,61 @@ pub fn resolve_path_for_module(
     module_path
 }
 
+/// Resolve a stable virtual filename for a module's source.
+///
+/// The filename is the `/` separated source path of the module under `prefix`,
+/// e.g. `<exe>/foo/bar.py`. It is meant for `co_filename` of compiled bytecode
+/// so tracebacks don't reference paths from the build environment.
+pub fn virtual_module_filename(prefix: &str, name: &str, is_package: bool) -> String {
+    let path = name.replace('.', "/");
+
+    let path = if is_package {
+        format!("{}/__init__.py", path)
+    } else {
+        format!("{}.py", path)
+    };
+
+    if prefix.is_empty() {
+        path
+    } else {
+        format!("{}/{}", prefix.trim_end_matches('/'), path)
+    }
+}
+
+/// Whether a glob pattern matches a value.
+///
+/// `*` matches any sequence of characters and `?` matches any single character.
+pub fn glob_matches(pattern: &str, value: &str) -> bool {
+    let pattern = pattern.chars().collect::<Vec<_>>();
+    let value = value.chars().collect::<Vec<_>>();
+
+    let (mut p, mut v) = (0, 0);
+    let mut backtrack = None;
+
+    while v < value.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, v));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == value[v] => {
+                p += 1;
+                v += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    v = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub fn is_package_from_path(path: &Path) -> bool {
     let file_name = path.file_name().unwrap().to_str().unwrap();
     file_name.starts_with("__init__.")
@@ -133,6 +188,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("foo", "foo"));
+        assert!(!glob_matches("foo", "foobar"));
+        assert!(glob_matches("foo*", "foobar"));
+        assert!(glob_matches("*bar", "foobar"));
+        assert!(glob_matches("f?o*r", "foobar"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("?", ""));
+        assert!(glob_matches("a*b*c", "axxbyyc"));
+        assert!(!glob_matches("a*b*c", "axxbyy"));
+        assert!(glob_matches("*.pyr", ".pyr"));
+        assert!(!glob_matches("*.pyr", "foo.pyr.bak"));
+    }
+
+    #[test]
+    fn test_virtual_module_filename() {
+        assert_eq!(
+            virtual_module_filename("<exe>", "foo", false),
+            "<exe>/foo.py"
+        );
+        assert_eq!(
+            virtual_module_filename("<exe>/", "foo.bar", true),
+            "<exe>/foo/bar/__init__.py"
+        );
+        assert_eq!(virtual_module_filename("", "foo.bar", false), "foo/bar.py");
+    }
+
     #[test]
     fn test_resolve_path_for_module() {
         assert_eq!(
@@ -12,17 +12,69 @@ Functionality for defining how Python resources should be packaged.
 
 use {
     crate::{
+        compatibility::{find_compatibility_issues, CompatibilityIssueKind},
+        import_graph::find_source_imports,
         licensing::{LicenseFlavor, SAFE_SYSTEM_LIBRARIES},
         location::ConcreteResourceLocation,
-        resource::{PythonExtensionModule, PythonExtensionModuleVariants, PythonResource},
+        module_util::glob_matches,
+        resource::{
+            BytecodeOptimizationLevel, PythonExtensionModule, PythonExtensionModuleVariants,
+            PythonResource,
+        },
         resource_collection::PythonResourceAddCollectionContext,
+        size_report::normalize_distribution_name,
     },
-    anyhow::Result,
-    std::collections::{HashMap, HashSet},
+    anyhow::{anyhow, Result},
+    std::collections::{BTreeMap, BTreeSet, HashMap, HashSet},
 };
 
+#[cfg(feature = "serialization")]
+use {
+    anyhow::Context,
+    serde::{Deserialize, Serialize},
+    std::path::Path,
+};
+
+/// Obtain a dotted name followed by the names of its parents.
+fn name_and_parents(name: &str) -> Vec<&str> {
+    let mut res = vec![name];
+    let mut current = name;
+
+    while let Some((parent, _)) = current.rsplit_once('.') {
+        res.push(parent);
+        current = parent;
+    }
+
+    res
+}
+
+/// Default patterns for [PythonPackagingPolicy::third_party_test_patterns()].
+pub const DEFAULT_THIRD_PARTY_TEST_PATTERNS: &[&str] = &["conftest", "test", "testing", "tests"];
+
+/// Obtain the name of the Python package a resource belongs to, if any.
+fn resource_package_name(resource: &PythonResource) -> Option<String> {
+    match resource {
+        PythonResource::ModuleSource(m) => Some(m.name.clone()),
+        PythonResource::ModuleBytecodeRequest(m) => Some(m.name.clone()),
+        PythonResource::ModuleBytecode(m) => Some(m.name.clone()),
+        PythonResource::PackageResource(r) => Some(r.leaf_package.clone()),
+        PythonResource::PackageDistributionResource(r) => {
+            Some(r.package.to_lowercase().replace('-', "_"))
+        }
+        PythonResource::ExtensionModule(em) => Some(em.name.clone()),
+        PythonResource::EggFile(_) | PythonResource::PathExtension(_) | PythonResource::File(_) => {
+            None
+        }
+    }
+}
+
 /// Denotes methods to filter extension modules.
+///
+/// Serialization type: `string`. Serialized values are `minimal`, `all`,
+/// `no-libraries`, and `no-copyleft`.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
 pub enum ExtensionModuleFilter {
     /// Only use the minimum set of extension modules needed to initialize an interpreter.
     Minimal,
@@ -47,6 +99,20 @@ impl TryFrom<&str> for ExtensionModuleFilter {
     }
 }
 
+impl TryFrom<String> for ExtensionModuleFilter {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+impl From<ExtensionModuleFilter> for String {
+    fn from(filter: ExtensionModuleFilter) -> Self {
+        filter.as_ref().to_string()
+    }
+}
+
 impl AsRef<str> for ExtensionModuleFilter {
     fn as_ref(&self) -> &str {
         match self {
@@ -92,8 +158,276 @@ impl AsRef<str> for ResourceHandlingMode {
     }
 }
 
+/// Standard library modules needed by the interpreter and common pure-Python code.
+///
+/// Together with [STDLIB_MINIMAL_EXCLUDED_MODULES], this defines
+/// [StdlibProfile::Minimal]. It includes everything these modules import
+/// unconditionally, so all of them can be imported.
+const STDLIB_MINIMAL_MODULES: &[&str] = &[
+    "__future__",
+    "_collections_abc",
+    "_compat_pickle",
+    "_compression",
+    "_opcode_metadata",
+    "_sitebuiltins",
+    "_weakrefset",
+    "abc",
+    "ast",
+    "base64",
+    "bisect",
+    "bz2",
+    "codecs",
+    "collections",
+    "contextlib",
+    "copy",
+    "copyreg",
+    "dataclasses",
+    "dis",
+    "encodings",
+    "enum",
+    "fnmatch",
+    "functools",
+    "genericpath",
+    "glob",
+    "heapq",
+    "importlib",
+    "inspect",
+    "io",
+    "ipaddress",
+    "keyword",
+    "linecache",
+    "locale",
+    "ntpath",
+    "numbers",
+    "opcode",
+    "operator",
+    "os",
+    "pathlib",
+    "posixpath",
+    "quopri",
+    "random",
+    "re",
+    "reprlib",
+    "selectors",
+    "shutil",
+    "signal",
+    "site",
+    "sre_compile",
+    "sre_constants",
+    "sre_parse",
+    "stat",
+    "string",
+    "stringprep",
+    "struct",
+    "subprocess",
+    "tempfile",
+    "textwrap",
+    "threading",
+    "token",
+    "tokenize",
+    "traceback",
+    "types",
+    "typing",
+    "urllib",
+    "warnings",
+    "weakref",
+    "zipfile",
+    "zipimport",
+];
+
+/// Submodules of [STDLIB_MINIMAL_MODULES] that [StdlibProfile::Minimal] excludes.
+///
+/// These aren't needed to start the interpreter and import modules outside
+/// the profile.
+const STDLIB_MINIMAL_EXCLUDED_MODULES: &[&str] = &[
+    "importlib.metadata",
+    "urllib.error",
+    "urllib.request",
+    "urllib.response",
+    "urllib.robotparser",
+];
+
+/// Standard library modules that only serve networked applications.
+///
+/// [StdlibProfile::Cli] excludes these. Modules other profiles need, like
+/// `email` for `importlib.metadata` and `urllib.parse` for `pathlib`, aren't
+/// listed. `xml.sax` is, because it unconditionally imports `urllib.request`.
+const STDLIB_NETWORK_MODULES: &[&str] = &[
+    "asynchat",
+    "asyncore",
+    "cgi",
+    "cgitb",
+    "ftplib",
+    "html",
+    "http",
+    "imaplib",
+    "mailbox",
+    "mailcap",
+    "nntplib",
+    "poplib",
+    "smtpd",
+    "smtplib",
+    "ssl",
+    "telnetlib",
+    "urllib.error",
+    "urllib.request",
+    "urllib.response",
+    "urllib.robotparser",
+    "wsgiref",
+    "xml.dom.pulldom",
+    "xml.sax",
+    "xmlrpc",
+];
+
+/// Standard library modules for GUIs, development tooling, and packaging.
+///
+/// All profiles except [StdlibProfile::Full] exclude these.
+const STDLIB_TOOLING_MODULES: &[&str] = &[
+    "distutils",
+    "ensurepip",
+    "idlelib",
+    "lib2to3",
+    "msilib",
+    "pydoc_data",
+    "tkinter",
+    "turtle",
+    "turtledemo",
+    "venv",
+];
+
+/// Whether a module name is one of the given modules or a submodule of them.
+fn module_in(name: &str, modules: &[&str]) -> bool {
+    modules.iter().any(|m| {
+        name == *m
+            || name
+                .strip_prefix(m)
+                .map(|rest| rest.starts_with('.'))
+                .unwrap_or_default()
+    })
+}
+
+/// Named sets of pure-Python standard library modules to package.
+///
+/// Serialization type: `string`. Serialized values are `minimal`, `cli`,
+/// `network`, and `full`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(try_from = "String", into = "String"))]
+pub enum StdlibProfile {
+    /// Only modules needed to start the interpreter and run simple programs.
+    Minimal,
+    /// All modules except networking, GUI, and development tooling modules.
+    Cli,
+    /// All modules except GUI and development tooling modules.
+    Network,
+    /// All modules.
+    Full,
+}
+
+impl StdlibProfile {
+    /// Whether a standard library module is part of this profile.
+    pub fn includes(&self, name: &str) -> bool {
+        match self {
+            Self::Minimal => {
+                module_in(name, STDLIB_MINIMAL_MODULES)
+                    && !module_in(name, STDLIB_MINIMAL_EXCLUDED_MODULES)
+            }
+            Self::Cli => {
+                !module_in(name, STDLIB_TOOLING_MODULES) && !module_in(name, STDLIB_NETWORK_MODULES)
+            }
+            Self::Network => !module_in(name, STDLIB_TOOLING_MODULES),
+            Self::Full => true,
+        }
+    }
+}
+
+impl TryFrom<&str> for StdlibProfile {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value {
+            "minimal" => Ok(Self::Minimal),
+            "cli" => Ok(Self::Cli),
+            "network" => Ok(Self::Network),
+            "full" => Ok(Self::Full),
+            _ => Err(format!(
+                "{} is not a valid stdlib profile; use \"minimal\", \"cli\", \"network\", or \"full\"",
+                value
+            )),
+        }
+    }
+}
+
+impl TryFrom<String> for StdlibProfile {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::try_from(value.as_str())
+    }
+}
+
+impl From<StdlibProfile> for String {
+    fn from(profile: StdlibProfile) -> Self {
+        profile.as_ref().to_string()
+    }
+}
+
+impl AsRef<str> for StdlibProfile {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::Minimal => "minimal",
+            Self::Cli => "cli",
+            Self::Network => "network",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// Serialization of optional locations whose [None] differs from the default.
+///
+/// [None] is represented as `"none"`, which isn't a valid location.
+#[cfg(feature = "serialization")]
+mod location_or_none {
+    use {
+        super::ConcreteResourceLocation,
+        serde::{Deserialize, Deserializer, Serializer},
+    };
+
+    const NONE: &str = "none";
+
+    pub fn serialize<S: Serializer>(
+        location: &Option<ConcreteResourceLocation>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match location {
+            Some(location) => serializer.serialize_str(&location.to_string()),
+            None => serializer.serialize_str(NONE),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<ConcreteResourceLocation>, D::Error> {
+        let value = String::deserialize(deserializer)?;
+
+        if value == NONE {
+            Ok(None)
+        } else {
+            ConcreteResourceLocation::try_from(value)
+                .map(Some)
+                .map_err(serde::de::Error::custom)
+        }
+    }
+}
+
 /// Defines how Python resources should be packaged.
+///
+/// With the `serialization` feature, instances can be loaded from TOML or
+/// JSON documents whose keys are the field names of this struct. Omitted
+/// keys take their default values and unknown keys are an error.
 #[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+#[cfg_attr(feature = "serialization", serde(default, deny_unknown_fields))]
 pub struct PythonPackagingPolicy {
     /// Which extension modules should be included.
     extension_module_filter: ExtensionModuleFilter,
@@ -107,6 +441,28 @@ pub struct PythonPackagingPolicy {
     /// Optional fallback location for resources should `resources_location` fail.
     resources_location_fallback: Option<ConcreteResourceLocation>,
 
+    /// Location for resources of packages that need to be backed by files.
+    ///
+    /// If set, resources of packages registered as file dependent are placed
+    /// here instead of `resources_location`.
+    ///
+    /// Serialized as `"none"` when unset, so it isn't confused with an
+    /// omitted key, which means the default location.
+    #[cfg_attr(feature = "serialization", serde(with = "location_or_none"))]
+    file_dependent_packages_location: Option<ConcreteResourceLocation>,
+
+    /// Kinds of compatibility issues that make a package file dependent when scanning.
+    file_dependent_issue_kinds: Vec<CompatibilityIssueKind>,
+
+    /// Top-level packages whose resources need to be backed by files.
+    file_dependent_packages: BTreeSet<String>,
+
+    /// Explicit resource locations for packages, as (glob pattern, location).
+    ///
+    /// The last matching entry wins and takes precedence over file dependent
+    /// package placement.
+    package_location_overrides: Vec<(String, ConcreteResourceLocation)>,
+
     /// Whether to allow in-memory shared library loading.
     ///
     /// If true, we will attempt to load Python extension modules
@@ -152,6 +508,12 @@ pub struct PythonPackagingPolicy {
     /// Whether to include Python module source for non-distribution modules.
     include_non_distribution_sources: bool,
 
+    /// Glob patterns for modules whose source should be kept.
+    ///
+    /// If non-empty, only matching modules have their source included,
+    /// regardless of the `include_*_sources` settings.
+    source_packages: Vec<String>,
+
     /// Whether to include package resource files.
     include_distribution_resources: bool,
 
@@ -178,6 +540,27 @@ pub struct PythonPackagingPolicy {
 
     /// Python modules for which bytecode should not be generated by default.
     no_bytecode_modules: HashSet<String>,
+
+    /// Bytecode optimization levels for packages, as (glob pattern, level).
+    ///
+    /// Matching modules only get bytecode at this level, regardless of the
+    /// `bytecode_optimize_level_*` settings. The last matching entry wins.
+    package_optimization_levels: Vec<(String, BytecodeOptimizationLevel)>,
+
+    /// Which pure-Python standard library modules should be included.
+    stdlib_profile: StdlibProfile,
+
+    /// Glob patterns for name and path components denoting tests in third-party resources.
+    third_party_test_patterns: Vec<String>,
+
+    /// Glob patterns for distribution names exempt from `third_party_test_patterns`.
+    third_party_test_exemptions: Vec<String>,
+
+    /// Distribution names of third-party top-level packages.
+    ///
+    /// This is derived from resources rather than configured.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    third_party_distributions: BTreeMap<String, String>,
 }
 
 impl Default for PythonPackagingPolicy {
@@ -187,6 +570,12 @@ impl Default for PythonPackagingPolicy {
             preferred_extension_module_variants: HashMap::new(),
             resources_location: ConcreteResourceLocation::InMemory,
             resources_location_fallback: None,
+            file_dependent_packages_location: Some(ConcreteResourceLocation::RelativePath(
+                "lib".to_string(),
+            )),
+            file_dependent_issue_kinds: vec![CompatibilityIssueKind::DunderFilePath],
+            file_dependent_packages: BTreeSet::new(),
+            package_location_overrides: vec![],
             allow_in_memory_shared_library_loading: false,
             allow_files: false,
             file_scanner_emit_files: false,
@@ -194,6 +583,7 @@ impl Default for PythonPackagingPolicy {
             include_classified_resources: true,
             include_distribution_sources: true,
             include_non_distribution_sources: true,
+            source_packages: vec![],
             include_distribution_resources: false,
             include_test: false,
             include_file_resources: false,
@@ -202,11 +592,150 @@ impl Default for PythonPackagingPolicy {
             bytecode_optimize_level_one: false,
             bytecode_optimize_level_two: false,
             no_bytecode_modules: HashSet::new(),
+            package_optimization_levels: vec![],
+            stdlib_profile: StdlibProfile::Full,
+            third_party_test_patterns: DEFAULT_THIRD_PARTY_TEST_PATTERNS
+                .iter()
+                .map(|x| x.to_string())
+                .collect(),
+            third_party_test_exemptions: vec![],
+            third_party_distributions: BTreeMap::new(),
         }
     }
 }
 
 impl PythonPackagingPolicy {
+    /// Parse an instance from a TOML document.
+    ///
+    /// The parsed instance is validated with [Self::validate()].
+    #[cfg(feature = "serialization")]
+    pub fn from_toml_str(s: &str) -> Result<Self> {
+        let policy: Self = toml::from_str(s).context("parsing packaging policy TOML")?;
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    /// Parse an instance from a JSON document.
+    ///
+    /// The parsed instance is validated with [Self::validate()].
+    #[cfg(feature = "serialization")]
+    pub fn from_json_str(s: &str) -> Result<Self> {
+        let policy: Self = serde_json::from_str(s).context("parsing packaging policy JSON")?;
+        policy.validate()?;
+
+        Ok(policy)
+    }
+
+    /// Load an instance from a file.
+    ///
+    /// Files with a `.json` extension are parsed as JSON. All others are
+    /// parsed as TOML.
+    #[cfg(feature = "serialization")]
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("reading packaging policy {}", path.display()))?;
+
+        if path.extension().map(|x| x == "json").unwrap_or_default() {
+            Self::from_json_str(&data)
+        } else {
+            Self::from_toml_str(&data)
+        }
+        .with_context(|| format!("loading packaging policy {}", path.display()))
+    }
+
+    /// Serialize the instance to a TOML document.
+    #[cfg(feature = "serialization")]
+    pub fn to_toml_string(&self) -> Result<String> {
+        // Going through a value orders plain values before tables, as TOML requires.
+        let value = toml::Value::try_from(self).context("serializing packaging policy")?;
+
+        toml::to_string(&value).context("serializing packaging policy to TOML")
+    }
+
+    /// Ensure settings are internally consistent.
+    ///
+    /// Settings that can't be represented by setters, like empty names,
+    /// are rejected.
+    pub fn validate(&self) -> Result<()> {
+        if self.resources_location_fallback.as_ref() == Some(&self.resources_location) {
+            return Err(anyhow!(
+                "resources_location_fallback must differ from resources_location ({})",
+                self.resources_location.to_string()
+            ));
+        }
+
+        for (field, names) in [
+            (
+                "no_bytecode_modules",
+                self.no_bytecode_modules.iter().collect::<Vec<_>>(),
+            ),
+            (
+                "file_dependent_packages",
+                self.file_dependent_packages.iter().collect::<Vec<_>>(),
+            ),
+            (
+                "third_party_test_patterns",
+                self.third_party_test_patterns.iter().collect::<Vec<_>>(),
+            ),
+            (
+                "third_party_test_exemptions",
+                self.third_party_test_exemptions.iter().collect::<Vec<_>>(),
+            ),
+            (
+                "package_location_overrides",
+                self.package_location_overrides
+                    .iter()
+                    .map(|(pattern, _)| pattern)
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "source_packages",
+                self.source_packages.iter().collect::<Vec<_>>(),
+            ),
+            (
+                "package_optimization_levels",
+                self.package_optimization_levels
+                    .iter()
+                    .map(|(pattern, _)| pattern)
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "broken_extensions",
+                self.broken_extensions
+                    .iter()
+                    .flat_map(|(triple, extensions)| {
+                        std::iter::once(triple).chain(extensions.iter())
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            (
+                "preferred_extension_module_variants",
+                self.preferred_extension_module_variants
+                    .iter()
+                    .flat_map(|(extension, variant)| [extension, variant])
+                    .collect::<Vec<_>>(),
+            ),
+        ] {
+            if names.iter().any(|name| name.is_empty()) {
+                return Err(anyhow!("{} must not contain empty names", field));
+            }
+        }
+
+        if let Some(name) = self
+            .file_dependent_packages
+            .iter()
+            .find(|x| x.contains('.'))
+        {
+            return Err(anyhow!(
+                "file_dependent_packages must contain top-level package names; got {}",
+                name
+            ));
+        }
+
+        Ok(())
+    }
+
     /// Obtain the active extension module filter for this instance.
     pub fn extension_module_filter(&self) -> &ExtensionModuleFilter {
         &self.extension_module_filter
@@ -217,6 +746,73 @@ impl PythonPackagingPolicy {
         self.extension_module_filter = filter;
     }
 
+    /// Obtain the active standard library profile.
+    pub fn stdlib_profile(&self) -> StdlibProfile {
+        self.stdlib_profile
+    }
+
+    /// Set the standard library profile to use.
+    ///
+    /// Standard library module sources, bytecode, and package resources
+    /// outside the profile are excluded. Extension modules are governed by
+    /// [Self::extension_module_filter()] instead.
+    pub fn set_stdlib_profile(&mut self, profile: StdlibProfile) {
+        self.stdlib_profile = profile;
+    }
+
+    /// Find standard library modules excluded by the stdlib profile that included code imports.
+    ///
+    /// Imports are found by static analysis of the source of modules the
+    /// policy includes. Only modules present in `resources` are considered
+    /// standard library modules.
+    ///
+    /// Returns a mapping of excluded module names to the names of modules
+    /// importing them.
+    pub fn find_excluded_stdlib_imports<'a>(
+        &self,
+        resources: impl Iterator<Item = &'a PythonResource<'a>>,
+    ) -> Result<BTreeMap<String, BTreeSet<String>>> {
+        let mut excluded = BTreeSet::new();
+        let mut imports = vec![];
+
+        for resource in resources {
+            let (name, source, is_package, is_stdlib) = match resource {
+                PythonResource::ModuleSource(m) => (&m.name, &m.source, m.is_package, m.is_stdlib),
+                PythonResource::ModuleBytecodeRequest(m) => {
+                    (&m.name, &m.source, m.is_package, m.is_stdlib)
+                }
+                _ => continue,
+            };
+
+            if is_stdlib && !self.stdlib_profile.includes(name) {
+                excluded.insert(name.clone());
+            } else if self.filter_python_resource(resource) {
+                imports.push((
+                    name.clone(),
+                    find_source_imports(name, is_package, &source.resolve_content()?),
+                ));
+            }
+        }
+
+        let mut res = BTreeMap::<String, BTreeSet<String>>::new();
+
+        for (importer, module_imports) in imports {
+            for name in module_imports
+                .static_imports
+                .iter()
+                .chain(module_imports.dynamic_imports.iter())
+            {
+                if excluded.contains(name) {
+                    res.entry(name.clone())
+                        .or_default()
+                        .insert(importer.clone());
+                }
+            }
+        }
+
+        Ok(res)
+    }
+
     /// Obtain the preferred extension module variants for this policy.
     ///
     /// The returned object is a mapping of extension name to its variant
@@ -253,6 +849,139 @@ impl PythonPackagingPolicy {
         self.resources_location_fallback = location;
     }
 
+    /// Where resources of file dependent packages should be placed.
+    ///
+    /// Defaults to `filesystem-relative:lib`. [None] means file dependent
+    /// packages aren't treated specially. In policy files, [None] is written
+    /// as `"none"`.
+    pub fn file_dependent_packages_location(&self) -> &Option<ConcreteResourceLocation> {
+        &self.file_dependent_packages_location
+    }
+
+    /// Set where resources of file dependent packages should be placed.
+    pub fn set_file_dependent_packages_location(
+        &mut self,
+        location: Option<ConcreteResourceLocation>,
+    ) {
+        self.file_dependent_packages_location = location;
+    }
+
+    /// Kinds of compatibility issues that make a package file dependent.
+    pub fn file_dependent_issue_kinds(&self) -> &[CompatibilityIssueKind] {
+        &self.file_dependent_issue_kinds
+    }
+
+    /// Set the kinds of compatibility issues that make a package file dependent.
+    ///
+    /// Defaults to [CompatibilityIssueKind::DunderFilePath].
+    pub fn set_file_dependent_issue_kinds(&mut self, kinds: Vec<CompatibilityIssueKind>) {
+        self.file_dependent_issue_kinds = kinds;
+    }
+
+    /// Top-level packages registered as file dependent.
+    pub fn file_dependent_packages(&self) -> &BTreeSet<String> {
+        &self.file_dependent_packages
+    }
+
+    /// Register a package as one whose resources need to be backed by files.
+    ///
+    /// Resources of the top-level package containing `name` are placed at
+    /// [Self::file_dependent_packages_location()], if set.
+    pub fn register_file_dependent_package(&mut self, name: &str) {
+        let package = name.split('.').next().unwrap_or(name);
+
+        self.file_dependent_packages.insert(package.to_string());
+    }
+
+    /// Scan module sources and register packages with compatibility issues as file dependent.
+    ///
+    /// Packages with modules having issues of the kinds in
+    /// [Self::file_dependent_issue_kinds()] are registered. Standard library
+    /// modules are ignored. This needs to
+    /// be called with all resources before deriving add contexts, so every
+    /// resource of a package is placed consistently.
+    /// [crate::resource_collection::PythonResourceCollector::add_python_resources_with_policy()]
+    /// takes care of this.
+    ///
+    /// Returns the names of newly registered packages.
+    pub fn register_file_dependent_packages_from_resources<'a>(
+        &mut self,
+        resources: impl Iterator<Item = &'a PythonResource<'a>>,
+    ) -> Result<Vec<String>> {
+        let mut res = vec![];
+
+        for resource in resources {
+            let (name, source) = match resource {
+                PythonResource::ModuleSource(m) if !m.is_stdlib => (&m.name, &m.source),
+                PythonResource::ModuleBytecodeRequest(m) if !m.is_stdlib => (&m.name, &m.source),
+                _ => continue,
+            };
+
+            let package = name.split('.').next().unwrap_or(name);
+            if self.file_dependent_packages.contains(package) {
+                continue;
+            }
+
+            let flagged = find_compatibility_issues(&source.resolve_content()?)
+                .iter()
+                .any(|finding| self.file_dependent_issue_kinds.contains(&finding.kind));
+
+            if flagged {
+                self.file_dependent_packages.insert(package.to_string());
+                res.push(package.to_string());
+            }
+        }
+
+        Ok(res)
+    }
+
+    /// Explicit resource locations for packages, as (glob pattern, location).
+    pub fn package_location_overrides(&self) -> &[(String, ConcreteResourceLocation)] {
+        &self.package_location_overrides
+    }
+
+    /// Set an explicit resource location for packages matching a glob pattern.
+    ///
+    /// The pattern is matched against package names and their parent
+    /// packages, so `foo` applies to `foo.bar` as well. `*` and `?` are
+    /// wildcards. When multiple patterns match, the last added wins.
+    ///
+    /// Overrides take precedence over file dependent package placement, in
+    /// either direction.
+    pub fn add_package_location_override(
+        &mut self,
+        pattern: &str,
+        location: ConcreteResourceLocation,
+    ) {
+        self.package_location_overrides
+            .push((pattern.to_string(), location));
+    }
+
+    /// Resolve the location for resources of a package.
+    fn package_location(&self, name: &str) -> Option<&ConcreteResourceLocation> {
+        let candidates = name_and_parents(name);
+
+        let overridden = self
+            .package_location_overrides
+            .iter()
+            .rev()
+            .find(|(pattern, _)| candidates.iter().any(|c| glob_matches(pattern, c)))
+            .map(|(_, location)| location);
+
+        if overridden.is_some() {
+            return overridden;
+        }
+
+        if self
+            .file_dependent_packages
+            .contains(*candidates.last().unwrap())
+        {
+            self.file_dependent_packages_location.as_ref()
+        } else {
+            None
+        }
+    }
+
     /// Whether to allow untyped `File` resources.
     pub fn allow_files(&self) -> bool {
         self.allow_files
@@ -323,6 +1052,24 @@ impl PythonPackagingPolicy {
         self.include_non_distribution_sources = include;
     }
 
+    /// Glob patterns for modules whose source should be kept.
+    pub fn source_packages(&self) -> &[String] {
+        &self.source_packages
+    }
+
+    /// Keep source for modules matching a glob pattern.
+    ///
+    /// The pattern is matched against module names and their parent
+    /// packages, so `foo` applies to `foo.bar` as well. `*` and `?` are
+    /// wildcards.
+    ///
+    /// Once a pattern is added, source is only kept for matching modules and
+    /// the `include_*_sources` settings no longer apply. This allows shipping
+    /// source for the packages worth debugging while stripping the rest.
+    pub fn add_source_package(&mut self, pattern: &str) {
+        self.source_packages.push(pattern.to_string());
+    }
+
     /// Get setting for whether to include test files.
     pub fn include_test(&self) -> bool {
         self.include_test
@@ -333,6 +1080,123 @@ impl PythonPackagingPolicy {
         self.include_test = include;
     }
 
+    /// Glob patterns identifying tests in resources outside the standard library.
+    pub fn third_party_test_patterns(&self) -> &[String] {
+        &self.third_party_test_patterns
+    }
+
+    /// Set glob patterns identifying tests in resources outside the standard library.
+    ///
+    /// Module sources, bytecode requests, package resources, and files of
+    /// third-party distributions are excluded if a component of their module
+    /// name or path matches a pattern. A `.py` extension is ignored, so
+    /// `conftest` matches `conftest.py`. Other file names are matched with
+    /// their extension, so data files like `templates/test.html` are kept.
+    ///
+    /// Only packages registered with
+    /// [Self::register_third_party_distribution()] are considered, so the
+    /// application's own tests are left alone. Defaults to
+    /// [DEFAULT_THIRD_PARTY_TEST_PATTERNS]. An empty list disables exclusion.
+    pub fn set_third_party_test_patterns(&mut self, patterns: Vec<String>) {
+        self.third_party_test_patterns = patterns;
+    }
+
+    /// Glob patterns for distribution names exempt from third-party test exclusion.
+    pub fn third_party_test_exemptions(&self) -> &[String] {
+        &self.third_party_test_exemptions
+    }
+
+    /// Exempt distributions from third-party test exclusion.
+    ///
+    /// This is for distributions that import their own test helpers at run
+    /// time, like `numpy` importing `numpy.testing`. The pattern is matched
+    /// against normalized distribution names, so `Foo-Bar` and `foo_bar` are
+    /// equivalent.
+    pub fn add_third_party_test_exemption(&mut self, pattern: &str) {
+        self.third_party_test_exemptions.push(pattern.to_string());
+    }
+
+    /// Distribution names of registered third-party top-level packages.
+    pub fn third_party_distributions(&self) -> &BTreeMap<String, String> {
+        &self.third_party_distributions
+    }
+
+    /// Register a top-level package as belonging to a third-party distribution.
+    pub fn register_third_party_distribution(&mut self, distribution: &str, package: &str) {
+        self.third_party_distributions
+            .insert(package.to_string(), distribution.to_string());
+    }
+
+    /// Register the top-level packages of distributions having metadata in resources.
+    ///
+    /// A distribution claims the packages its `top_level.txt` lists, or the
+    /// package named like its normalized name if it lacks one. This needs to
+    /// be called with all resources before deriving add contexts.
+    /// [crate::resource_collection::PythonResourceCollector::add_python_resources_with_policy()]
+    /// takes care of this.
+    pub fn register_third_party_distributions_from_resources<'a>(
+        &mut self,
+        resources: impl Iterator<Item = &'a PythonResource<'a>>,
+    ) -> Result<()> {
+        let mut distributions = BTreeMap::<&str, Option<Vec<u8>>>::new();
+
+        for resource in resources {
+            if let PythonResource::PackageDistributionResource(resource) = resource {
+                let entry = distributions.entry(&resource.package).or_default();
+
+                if resource.name == "top_level.txt" {
+                    *entry = Some(resource.data.resolve_content()?);
+                }
+            }
+        }
+
+        for (distribution, top_level_txt) in distributions {
+            match top_level_txt {
+                Some(data) => {
+                    for line in String::from_utf8_lossy(&data).lines() {
+                        let package = line.trim().split('/').next().unwrap_or_default();
+
+                        if !package.is_empty() {
+                            self.register_third_party_distribution(distribution, package);
+                        }
+                    }
+                }
+                None => self.register_third_party_distribution(
+                    distribution,
+                    &normalize_distribution_name(distribution),
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether a non-stdlib resource is a test according to third-party test patterns.
+    ///
+    /// `components` are the components of the resource's module name or
+    /// path, the first being its top-level package.
+    fn is_third_party_test(&self, components: &[&str]) -> bool {
+        let distribution = match components
+            .first()
+            .and_then(|package| self.third_party_distributions.get(*package))
+        {
+            Some(distribution) => normalize_distribution_name(distribution),
+            None => return false,
+        };
+
+        let is_test = components.iter().any(|component| {
+            self.third_party_test_patterns
+                .iter()
+                .any(|pattern| glob_matches(pattern, component))
+        });
+
+        is_test
+            && !self
+                .third_party_test_exemptions
+                .iter()
+                .any(|pattern| glob_matches(&normalize_distribution_name(pattern), &distribution))
+    }
+
     /// Get whether to classify `File` resources as include by default.
     pub fn include_file_resources(&self) -> bool {
         self.include_file_resources
@@ -383,6 +1247,41 @@ impl PythonPackagingPolicy {
         self.bytecode_optimize_level_two = value;
     }
 
+    /// Bytecode optimization levels for packages, as (glob pattern, level).
+    pub fn package_optimization_levels(&self) -> &[(String, BytecodeOptimizationLevel)] {
+        &self.package_optimization_levels
+    }
+
+    /// Set the bytecode optimization level for modules matching a glob pattern.
+    ///
+    /// The pattern is matched against module names and their parent
+    /// packages, so `foo` applies to `foo.bar` as well. `*` and `?` are
+    /// wildcards. When multiple patterns match, the last added wins.
+    ///
+    /// Matching modules get bytecode at only this level, which replaces the
+    /// global `bytecode_optimize_level_*` settings for them. This allows
+    /// keeping docstrings and asserts for packages that need them while
+    /// optimizing everything else.
+    pub fn add_package_optimization_level(
+        &mut self,
+        pattern: &str,
+        level: BytecodeOptimizationLevel,
+    ) {
+        self.package_optimization_levels
+            .push((pattern.to_string(), level));
+    }
+
+    /// Resolve the explicit bytecode optimization level for a module.
+    fn package_optimization_level(&self, name: &str) -> Option<BytecodeOptimizationLevel> {
+        let candidates = name_and_parents(name);
+
+        self.package_optimization_levels
+            .iter()
+            .rev()
+            .find(|(pattern, _)| candidates.iter().any(|c| glob_matches(pattern, c)))
+            .map(|(_, level)| *level)
+    }
+
     /// Set the resource handling mode of the policy.
     ///
     /// This is a convenience function for mapping a `ResourceHandlingMode`
@@ -446,6 +1345,13 @@ impl PythonPackagingPolicy {
         let include = self.filter_python_resource(resource);
 
         let store_source = match resource {
+            PythonResource::ModuleSource(ref module) if !self.source_packages.is_empty() => {
+                name_and_parents(&module.name).iter().any(|name| {
+                    self.source_packages
+                        .iter()
+                        .any(|pattern| glob_matches(pattern, name))
+                })
+            }
             PythonResource::ModuleSource(ref module) => {
                 if module.is_stdlib {
                     self.include_distribution_sources
@@ -456,38 +1362,34 @@ impl PythonPackagingPolicy {
             _ => false,
         };
 
-        let location = self.resources_location.clone();
+        let location = resource_package_name(resource)
+            .and_then(|name| self.package_location(&name).cloned())
+            .unwrap_or_else(|| self.resources_location.clone());
         let location_fallback = self.resources_location_fallback.clone();
 
-        let optimize_level_zero = match resource {
+        let (optimize_level_zero, optimize_level_one, optimize_level_two) = match resource {
             PythonResource::ModuleSource(module) => {
                 if self.no_bytecode_modules.contains(&*module.name) {
-                    false
-                } else {
-                    self.bytecode_optimize_level_zero
-                }
-            }
-            _ => self.bytecode_optimize_level_zero,
-        };
-        let optimize_level_one = match resource {
-            PythonResource::ModuleSource(module) => {
-                if self.no_bytecode_modules.contains(&*module.name) {
-                    false
+                    (false, false, false)
+                } else if let Some(level) = self.package_optimization_level(&module.name) {
+                    (
+                        level == BytecodeOptimizationLevel::Zero,
+                        level == BytecodeOptimizationLevel::One,
+                        level == BytecodeOptimizationLevel::Two,
+                    )
                 } else {
-                    self.bytecode_optimize_level_one
+                    (
+                        self.bytecode_optimize_level_zero,
+                        self.bytecode_optimize_level_one,
+                        self.bytecode_optimize_level_two,
+                    )
                 }
             }
-            _ => self.bytecode_optimize_level_one,
-        };
-        let optimize_level_two = match resource {
-            PythonResource::ModuleSource(module) => {
-                if self.no_bytecode_modules.contains(&*module.name) {
-                    false
-                } else {
-                    self.bytecode_optimize_level_two
-                }
-            }
-            _ => self.bytecode_optimize_level_two,
+            _ => (
+                self.bytecode_optimize_level_zero,
+                self.bytecode_optimize_level_one,
+                self.bytecode_optimize_level_two,
+            ),
         };
 
         PythonResourceAddCollectionContext {
@@ -521,6 +1423,57 @@ impl PythonPackagingPolicy {
             }
         }
 
+        let excluded_stdlib = match resource {
+            PythonResource::ModuleSource(module) => {
+                module.is_stdlib && !self.stdlib_profile.includes(&module.name)
+            }
+            PythonResource::ModuleBytecodeRequest(module) => {
+                module.is_stdlib && !self.stdlib_profile.includes(&module.name)
+            }
+            PythonResource::PackageResource(resource) => {
+                resource.is_stdlib && !self.stdlib_profile.includes(&resource.leaf_package)
+            }
+            _ => false,
+        };
+
+        if excluded_stdlib {
+            return false;
+        }
+
+        let module_test =
+            |name: &str| self.is_third_party_test(&name.split('.').collect::<Vec<_>>());
+        let path_test = |path: &str| {
+            let mut components = path
+                .split(['/', '\\'])
+                .filter(|c| !c.is_empty())
+                .collect::<Vec<_>>();
+            if let Some(leaf) = components.last_mut() {
+                *leaf = leaf.strip_suffix(".py").unwrap_or(leaf);
+            }
+
+            self.is_third_party_test(&components)
+        };
+
+        let third_party_test = match resource {
+            PythonResource::ModuleSource(module) if !module.is_stdlib => module_test(&module.name),
+            PythonResource::ModuleBytecodeRequest(module) if !module.is_stdlib => {
+                module_test(&module.name)
+            }
+            PythonResource::PackageResource(resource) if !resource.is_stdlib => {
+                path_test(&format!(
+                    "{}/{}",
+                    resource.leaf_package.replace('.', "/"),
+                    resource.relative_name
+                ))
+            }
+            PythonResource::File(file) => path_test(&file.path().to_string_lossy()),
+            _ => false,
+        };
+
+        if third_party_test {
+            return false;
+        }
+
         match resource {
             PythonResource::ModuleSource(module) => {
                 if !self.include_test && module.is_test {
@@ -683,7 +1636,38 @@ impl PythonPackagingPolicy {
 
 #[cfg(test)]
 mod tests {
-    use {super::*, simple_file_manifest::File};
+    use {
+        super::*,
+        crate::resource::{
+            PythonModuleSource, PythonPackageDistributionResource,
+            PythonPackageDistributionResourceFlavor, PythonPackageResource,
+        },
+        simple_file_manifest::{File, FileData},
+    };
+
+    fn module_source(name: &str, source: &str) -> PythonResource<'static> {
+        PythonModuleSource {
+            name: name.to_string(),
+            source: FileData::Memory(source.as_bytes().to_vec()),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: false,
+            is_test: false,
+        }
+        .into()
+    }
+
+    fn stdlib_source(name: &str, source: &str) -> PythonResource<'static> {
+        PythonModuleSource {
+            name: name.to_string(),
+            source: FileData::Memory(source.as_bytes().to_vec()),
+            is_package: false,
+            cache_tag: "cpython-39".to_string(),
+            is_stdlib: true,
+            is_test: false,
+        }
+        .into()
+    }
 
     #[test]
     fn test_add_collection_context_file() -> Result<()> {
@@ -703,4 +1687,740 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_file_dependent_packages() -> Result<()> {
+        let relative = ConcreteResourceLocation::RelativePath("lib".to_string());
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_file_dependent_packages_location(Some(relative.clone()));
+
+        let resources = [
+            module_source("foo", "import foo.data\n"),
+            module_source("foo.data", "HERE = os.path.dirname(__file__)\n"),
+            module_source("bar", "if __file__ is None:\n"),
+            stdlib_source("encodings", "HERE = os.path.dirname(__file__)\n"),
+            PythonPackageResource {
+                leaf_package: "foo.data".to_string(),
+                relative_name: "data.json".to_string(),
+                data: FileData::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into(),
+        ];
+
+        let registered =
+            policy.register_file_dependent_packages_from_resources(resources.iter())?;
+        assert_eq!(registered, vec!["foo"]);
+
+        let locations = resources
+            .iter()
+            .map(|r| policy.derive_add_collection_context(r).location)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            locations,
+            vec![
+                relative.clone(),
+                relative.clone(),
+                ConcreteResourceLocation::InMemory,
+                ConcreteResourceLocation::InMemory,
+                relative.clone(),
+            ]
+        );
+
+        // Without a location, registration has no effect.
+        policy.set_file_dependent_packages_location(None);
+        assert_eq!(
+            policy.derive_add_collection_context(&resources[0]).location,
+            ConcreteResourceLocation::InMemory
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_location_overrides() -> Result<()> {
+        let relative = ConcreteResourceLocation::RelativePath("lib".to_string());
+
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_file_dependent_packages_location(Some(relative.clone()));
+        policy.register_file_dependent_package("foo.bar");
+        policy.add_package_location_override("foo.bar", ConcreteResourceLocation::InMemory);
+        policy.add_package_location_override("baz*", relative.clone());
+
+        for (name, location) in [
+            ("foo", &relative),
+            ("foo.bar", &ConcreteResourceLocation::InMemory),
+            ("foo.bar.baz", &ConcreteResourceLocation::InMemory),
+            ("bazinga.x", &relative),
+            ("other", &ConcreteResourceLocation::InMemory),
+        ] {
+            assert_eq!(
+                &policy
+                    .derive_add_collection_context(&module_source(name, ""))
+                    .location,
+                location,
+                "{}",
+                name
+            );
+        }
+
+        // The last matching override wins.
+        policy.add_package_location_override("*", ConcreteResourceLocation::InMemory);
+        assert_eq!(
+            policy
+                .derive_add_collection_context(&module_source("bazinga", ""))
+                .location,
+            ConcreteResourceLocation::InMemory
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_package_optimization_levels() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+        policy.set_bytecode_optimize_level_zero(false);
+        policy.set_bytecode_optimize_level_two(true);
+        policy.add_package_optimization_level("click", BytecodeOptimizationLevel::Zero);
+        policy.add_package_optimization_level("pydantic*", BytecodeOptimizationLevel::Zero);
+        policy.register_no_bytecode_module("click.testing");
+
+        for (name, levels) in [
+            ("click", (true, false, false)),
+            ("click.core", (true, false, false)),
+            ("click.testing", (false, false, false)),
+            ("pydantic_core", (true, false, false)),
+            ("requests", (false, false, true)),
+        ] {
+            let context = policy.derive_add_collection_context(&module_source(name, ""));
+            assert_eq!(
+                (
+                    context.optimize_level_zero,
+                    context.optimize_level_one,
+                    context.optimize_level_two
+                ),
+                levels,
+                "{}",
+                name
+            );
+        }
+
+        // The last matching entry wins.
+        policy.add_package_optimization_level("*", BytecodeOptimizationLevel::One);
+        let context = policy.derive_add_collection_context(&module_source("click", ""));
+        assert!(!context.optimize_level_zero);
+        assert!(context.optimize_level_one);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_source_packages() {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(
+            policy
+                .derive_add_collection_context(&module_source("foo", ""))
+                .store_source
+        );
+
+        policy.add_source_package("myapp");
+        policy.add_source_package("plugin_*");
+
+        for (name, store_source) in [
+            ("myapp", true),
+            ("myapp.cli", true),
+            ("plugin_x.core", true),
+            ("foo", false),
+            ("myapplication", false),
+        ] {
+            assert_eq!(
+                policy
+                    .derive_add_collection_context(&module_source(name, ""))
+                    .store_source,
+                store_source,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut policy = PythonPackagingPolicy::default();
+        assert!(policy.validate().is_ok());
+
+        policy.set_resources_location_fallback(Some(ConcreteResourceLocation::InMemory));
+        assert!(policy.validate().is_err());
+        policy.set_resources_location_fallback(None);
+
+        policy.register_no_bytecode_module("");
+        assert!(policy.validate().is_err());
+    }
+
+    #[cfg(feature = "serialization")]
+    #[test]
+    fn test_serialization() -> Result<()> {
+        assert_eq!(
+            PythonPackagingPolicy::from_toml_str("")?,
+            PythonPackagingPolicy::default()
+        );
+
+        let policy = PythonPackagingPolicy::from_toml_str(
+            r#"
+            extension_module_filter = "no-copyleft"
+            resources_location = "in-memory"
+            resources_location_fallback = "filesystem-relative:lib"
+            include_test = true
+            bytecode_optimize_level_two = true
+            no_bytecode_modules = ["foo"]
+            file_dependent_packages_location = "filesystem-relative:lib"
+            file_dependent_issue_kinds = ["dunder-file", "pkgutil-get-data"]
+            package_location_overrides = [["bar*", "filesystem-relative:lib"]]
+            package_optimization_levels = [["click", "0"]]
+
+            [broken_extensions]
+            x86_64-unknown-linux-gnu = ["_tkinter"]
+            "#,
+        )?;
+
+        let mut expected = PythonPackagingPolicy::default();
+        expected.set_extension_module_filter(ExtensionModuleFilter::NoCopyleft);
+        expected.set_resources_location_fallback(Some(ConcreteResourceLocation::RelativePath(
+            "lib".to_string(),
+        )));
+        expected.set_include_test(true);
+        expected.set_bytecode_optimize_level_two(true);
+        expected.register_no_bytecode_module("foo");
+        expected.set_file_dependent_packages_location(Some(
+            ConcreteResourceLocation::RelativePath("lib".to_string()),
+        ));
+        expected.set_file_dependent_issue_kinds(vec![
+            CompatibilityIssueKind::DunderFile,
+            CompatibilityIssueKind::PkgutilGetData,
+        ]);
+        expected.add_package_location_override(
+            "bar*",
+            ConcreteResourceLocation::RelativePath("lib".to_string()),
+        );
+        expected.add_package_optimization_level("click", BytecodeOptimizationLevel::Zero);
+        expected.register_broken_extension("x86_64-unknown-linux-gnu", "_tkinter");
+
+        assert_eq!(policy, expected);
+        assert_eq!(
+            PythonPackagingPolicy::from_toml_str(&policy.to_toml_string()?)?,
+            policy
+        );
+
+        let mut disabled = PythonPackagingPolicy::default();
+        disabled.set_file_dependent_packages_location(None);
+        assert_eq!(
+            PythonPackagingPolicy::from_toml_str(&disabled.to_toml_string()?)?,
+            disabled
+        );
+        assert_eq!(
+            PythonPackagingPolicy::from_json_str(&serde_json::to_string(&disabled)?)?,
+            disabled
+        );
+        assert_eq!(
+            PythonPackagingPolicy::from_toml_str("file_dependent_packages_location = \"none\"")?,
+            disabled
+        );
+
+        assert_eq!(
+            PythonPackagingPolicy::from_json_str(
+                r#"{"include_test": true, "resources_location": "filesystem-relative:lib"}"#
+            )?
+            .resources_location(),
+            &ConcreteResourceLocation::RelativePath("lib".to_string())
+        );
+
+        for (document, message) in [
+            ("bogus = true", "unknown field `bogus`"),
+            (
+                "extension_module_filter = \"some\"",
+                "some is not a valid extension module filter",
+            ),
+            (
+                "resources_location = \"memory\"",
+                "memory is not a valid resource location",
+            ),
+            (
+                "resources_location_fallback = \"in-memory\"",
+                "resources_location_fallback must differ from resources_location",
+            ),
+            (
+                "file_dependent_packages = [\"foo.bar\"]",
+                "file_dependent_packages must contain top-level package names",
+            ),
+        ] {
+            let err = format!(
+                "{:#}",
+                PythonPackagingPolicy::from_toml_str(document).unwrap_err()
+            );
+            assert!(err.contains(message), "{}: {}", document, err);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdlib_profile() {
+        for (name, minimal, cli, network) in [
+            ("encodings.utf_8", true, true, true),
+            ("json", false, true, true),
+            ("http.client", false, false, true),
+            ("httpx", false, true, true),
+            ("tkinter", false, false, false),
+            ("lib2to3.pgen2", false, false, false),
+            ("importlib.util", true, true, true),
+            ("importlib.metadata", false, true, true),
+            ("urllib.parse", true, true, true),
+            ("urllib.request", false, false, true),
+            ("email.message", false, true, true),
+            ("pydoc", false, true, true),
+        ] {
+            assert_eq!(StdlibProfile::Minimal.includes(name), minimal, "{}", name);
+            assert_eq!(StdlibProfile::Cli.includes(name), cli, "{}", name);
+            assert_eq!(StdlibProfile::Network.includes(name), network, "{}", name);
+            assert!(StdlibProfile::Full.includes(name));
+        }
+
+        assert_eq!(StdlibProfile::try_from("cli"), Ok(StdlibProfile::Cli));
+        assert!(StdlibProfile::try_from("bogus").is_err());
+    }
+
+    #[test]
+    fn test_stdlib_profile_import_closure() {
+        // Unconditional module-level imports of the CPython 3.11 standard
+        // library, by the module names profiles are defined in terms of.
+        // Tooling modules aren't listed, as only the full profile has them.
+        let imports = [
+            ("_aix_support", "sysconfig"),
+            ("_bootsubprocess", "os"),
+            ("_collections_abc", "abc"),
+            ("_compression", "io"),
+            ("_markupbase", "re"),
+            ("_osx_support", "os re"),
+            ("_py_abc", "_weakrefset"),
+            ("_pydecimal", "contextvars numbers re"),
+            ("_pyio", "abc codecs io os stat"),
+            ("_strptime", "calendar datetime locale re"),
+            ("_threading_local", "contextlib threading weakref"),
+            ("_weakrefset", "types"),
+            ("aifc", "collections struct warnings"),
+            ("argparse", "gettext os re warnings"),
+            ("ast", "contextlib enum"),
+            ("asynchat", "asyncore collections warnings"),
+            (
+                "asyncio",
+                "ast code collections concurrent contextlib contextvars enum functools heapq \
+                 inspect io linecache logging os reprlib selectors signal socket stat struct \
+                 subprocess tempfile threading traceback types typing warnings weakref",
+            ),
+            ("asyncore", "os socket warnings"),
+            ("base64", "re struct"),
+            ("bdb", "fnmatch inspect os"),
+            ("bz2", "_compression io os"),
+            ("cProfile", "io profile"),
+            ("calendar", "datetime locale"),
+            ("cgi", "collections email html io locale os tempfile urllib warnings"),
+            (
+                "cgitb",
+                "html inspect keyword linecache os pydoc tempfile tokenize traceback warnings",
+            ),
+            ("chunk", "warnings"),
+            ("cmd", "string"),
+            ("code", "codeop traceback"),
+            ("codeop", "__future__ warnings"),
+            ("collections", "_collections_abc keyword operator reprlib"),
+            ("compileall", "filecmp functools importlib os pathlib py_compile struct"),
+            (
+                "concurrent",
+                "collections functools logging multiprocessing os queue threading traceback types \
+                 weakref",
+            ),
+            ("configparser", "collections functools io os re warnings"),
+            ("contextlib", "_collections_abc abc collections functools os types"),
+            ("copy", "copyreg types weakref"),
+            ("crypt", "collections random string warnings"),
+            ("csv", "io re"),
+            ("ctypes", "os re shutil struct subprocess types"),
+            ("curses", "os"),
+            ("dataclasses", "abc copy functools inspect keyword re types"),
+            ("datetime", "operator"),
+            ("dbm", "ast collections io os struct"),
+            ("difflib", "collections heapq re types"),
+            ("dis", "collections io opcode types"),
+            (
+                "doctest",
+                "__future__ collections difflib inspect io linecache os pdb re traceback unittest",
+            ),
+            (
+                "email",
+                "abc base64 calendar collections copy datetime functools io operator os quopri \
+                 random re socket string types urllib",
+            ),
+            ("encodings", "base64 bz2 codecs io quopri re stringprep"),
+            ("enum", "functools operator types"),
+            ("filecmp", "os stat types"),
+            ("fileinput", "io os types"),
+            ("fnmatch", "functools os posixpath re"),
+            ("fractions", "decimal numbers operator re"),
+            ("ftplib", "socket"),
+            ("functools", "abc collections reprlib types"),
+            ("genericpath", "os stat"),
+            ("getopt", "os"),
+            ("getpass", "contextlib io os warnings"),
+            ("gettext", "operator os re"),
+            ("glob", "contextlib fnmatch os re stat"),
+            ("graphlib", "types"),
+            ("gzip", "_compression io os struct"),
+            ("hmac", "hashlib warnings"),
+            ("html", "_markupbase re"),
+            (
+                "http",
+                "calendar collections copy datetime email enum html io mimetypes os posixpath re \
+                 shutil socket socketserver string threading types urllib urllib.request",
+            ),
+            ("imaplib", "calendar datetime io random re socket subprocess"),
+            ("imghdr", "os warnings"),
+            ("imp", "importlib os tokenize types warnings"),
+            (
+                "importlib",
+                "abc collections contextlib functools io operator os pathlib tempfile types typing \
+                 warnings zipfile",
+            ),
+            (
+                "importlib.metadata",
+                "abc collections contextlib csv email functools importlib operator os pathlib \
+                 posixpath re textwrap types typing warnings zipfile",
+            ),
+            (
+                "inspect",
+                "abc ast collections dis enum functools importlib keyword linecache operator os re \
+                 token tokenize types",
+            ),
+            ("io", "abc"),
+            ("ipaddress", "functools"),
+            ("json", "argparse codecs pathlib re"),
+            ("linecache", "functools os tokenize"),
+            ("locale", "_collections_abc encodings functools re"),
+            (
+                "logging",
+                "collections copy io os pickle queue re socket socketserver stat string struct \
+                 threading traceback types warnings weakref",
+            ),
+            ("lzma", "_compression io os"),
+            ("mailbox", "calendar contextlib copy email io os socket types warnings"),
+            ("mailcap", "os re warnings"),
+            ("mimetypes", "os posixpath urllib"),
+            ("modulefinder", "dis importlib io os"),
+            (
+                "multiprocessing",
+                "_weakrefset abc bisect collections copyreg ctypes functools io os pickle queue \
+                 runpy secrets selectors signal socket struct subprocess tempfile threading \
+                 traceback types warnings weakref",
+            ),
+            ("netrc", "os shlex stat"),
+            ("nntplib", "collections datetime email re socket warnings"),
+            ("ntpath", "genericpath os stat"),
+            ("numbers", "abc"),
+            ("optparse", "os textwrap"),
+            ("os", "_collections_abc abc stat"),
+            (
+                "pathlib",
+                "_collections_abc fnmatch functools io ntpath operator os posixpath re stat urllib \
+                 warnings",
+            ),
+            (
+                "pdb",
+                "bdb cmd code dis functools glob inspect io linecache os pprint re signal tokenize \
+                 traceback typing",
+            ),
+            ("pickle", "_compat_pickle codecs copyreg functools io re struct types"),
+            ("pickletools", "codecs io pickle re struct"),
+            ("pipes", "os re shlex tempfile warnings"),
+            ("pkgutil", "collections functools importlib os types warnings"),
+            ("platform", "collections functools os re"),
+            ("plistlib", "codecs datetime enum io os re struct xml"),
+            ("poplib", "re socket"),
+            ("posixpath", "genericpath os stat"),
+            ("pprint", "collections dataclasses io re types"),
+            ("profile", "io"),
+            ("pstats", "dataclasses enum functools os re typing"),
+            ("pty", "os tty"),
+            ("py_compile", "enum importlib os traceback"),
+            ("pyclbr", "ast importlib"),
+            (
+                "pydoc",
+                "__future__ collections importlib inspect io os pkgutil platform re reprlib \
+                 sysconfig tokenize traceback urllib warnings",
+            ),
+            ("queue", "collections heapq threading types"),
+            ("random", "_collections_abc bisect operator os warnings"),
+            ("re", "copyreg enum functools"),
+            ("rlcompleter", "inspect keyword re"),
+            ("runpy", "importlib io os"),
+            ("sched", "collections heapq threading"),
+            ("secrets", "base64 hmac random"),
+            ("selectors", "abc collections"),
+            ("shelve", "collections io pickle"),
+            ("shlex", "collections io os re"),
+            ("shutil", "collections fnmatch os stat"),
+            ("signal", "enum"),
+            ("site", "_sitebuiltins io os"),
+            ("smtpd", "asynchat asyncore collections email getopt os socket warnings"),
+            ("smtplib", "base64 copy datetime email hmac io re socket"),
+            ("sndhdr", "collections warnings"),
+            ("socket", "enum io os selectors"),
+            ("socketserver", "io os selectors socket threading"),
+            ("sqlite3", "collections datetime"),
+            ("sre_compile", "re warnings"),
+            ("sre_constants", "re warnings"),
+            ("sre_parse", "re warnings"),
+            ("ssl", "base64 collections enum os socket warnings"),
+            (
+                "statistics",
+                "bisect collections decimal fractions functools numbers operator random",
+            ),
+            ("string", "collections re"),
+            ("subprocess", "contextlib io locale os signal threading types warnings"),
+            ("sunau", "collections warnings"),
+            ("symtable", "weakref"),
+            ("sysconfig", "os"),
+            ("tabnanny", "os tokenize"),
+            ("tarfile", "copy io os re shutil stat struct warnings"),
+            ("telnetlib", "selectors socket warnings"),
+            ("tempfile", "functools io os random shutil types warnings weakref"),
+            ("textwrap", "re"),
+            ("threading", "_weakrefset functools os"),
+            ("tokenize", "codecs collections functools io re token"),
+            ("tomllib", "__future__ collections datetime functools re string types typing"),
+            ("trace", "dis inspect io linecache os pickle sysconfig threading token tokenize"),
+            ("traceback", "collections contextlib linecache textwrap"),
+            ("tracemalloc", "collections fnmatch functools linecache os pickle"),
+            ("typing", "abc collections contextlib functools operator re types warnings"),
+            (
+                "unittest",
+                "argparse asyncio collections contextlib contextvars difflib fnmatch functools \
+                 inspect io logging os pkgutil pprint re signal threading traceback types warnings \
+                 weakref",
+            ),
+            ("urllib", "collections functools ipaddress re types warnings"),
+            ("urllib.error", "io urllib urllib.response"),
+            (
+                "urllib.request",
+                "base64 bisect contextlib email hashlib http io os posixpath re socket string \
+                 tempfile urllib urllib.error urllib.response warnings",
+            ),
+            ("urllib.response", "tempfile"),
+            ("urllib.robotparser", "collections urllib urllib.request"),
+            ("uu", "os warnings"),
+            ("uuid", "enum os"),
+            ("wave", "collections struct"),
+            ("weakref", "_collections_abc _weakrefset"),
+            ("webbrowser", "os shlex shutil subprocess threading warnings"),
+            ("wsgiref", "collections http os platform posixpath re types typing urllib warnings"),
+            ("xdrlib", "functools io struct warnings"),
+            ("xml", "collections contextlib copy io re urllib warnings"),
+            ("xml.dom.pulldom", "xml xml.sax"),
+            ("xml.sax", "codecs io os urllib urllib.request xml"),
+            (
+                "xmlrpc",
+                "base64 datetime decimal functools html http inspect io os pydoc re socketserver \
+                 traceback urllib xml",
+            ),
+            ("zipapp", "contextlib os pathlib shutil stat zipfile"),
+            (
+                "zipfile",
+                "contextlib importlib io os pathlib posixpath shutil stat struct threading",
+            ),
+            (
+                "zoneinfo",
+                "bisect calendar collections datetime functools os re struct sysconfig weakref",
+            ),
+        ];
+
+        for profile in [
+            StdlibProfile::Minimal,
+            StdlibProfile::Cli,
+            StdlibProfile::Network,
+        ] {
+            for (importer, imported) in imports {
+                if !profile.includes(importer) {
+                    continue;
+                }
+
+                for name in imported.split(' ') {
+                    assert!(
+                        profile.includes(name),
+                        "{} profile includes {} but not its import {}",
+                        profile.as_ref(),
+                        importer,
+                        name
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stdlib_profile_add_collection_context() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let resources = [
+            stdlib_source("json", ""),
+            stdlib_source("http.client", "import email.parser\n"),
+            stdlib_source("email.parser", ""),
+            stdlib_source("tkinter", ""),
+            module_source("app", "import json\nimport http.client\nimport tkinter\n"),
+        ];
+
+        let included = |policy: &PythonPackagingPolicy| {
+            resources
+                .iter()
+                .filter(|r| policy.derive_add_collection_context(r).include)
+                .map(|r| r.full_name())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            included(&policy),
+            vec!["json", "http.client", "email.parser", "tkinter", "app"]
+        );
+        assert!(policy
+            .find_excluded_stdlib_imports(resources.iter())?
+            .is_empty());
+
+        policy.set_stdlib_profile(StdlibProfile::Cli);
+        assert_eq!(included(&policy), vec!["json", "email.parser", "app"]);
+
+        let excluded = policy.find_excluded_stdlib_imports(resources.iter())?;
+        assert_eq!(
+            excluded.keys().collect::<Vec<_>>(),
+            vec!["http.client", "tkinter"]
+        );
+        assert_eq!(
+            excluded["http.client"].iter().collect::<Vec<_>>(),
+            vec!["app"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_third_party_tests() -> Result<()> {
+        let mut policy = PythonPackagingPolicy::default();
+
+        let package_source = |name: &str| -> PythonResource {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: FileData::Memory(vec![]),
+                is_package: true,
+                cache_tag: "cpython-39".to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+        let package_resource = |leaf_package: &str, relative_name: &str| -> PythonResource {
+            PythonPackageResource {
+                leaf_package: leaf_package.to_string(),
+                relative_name: relative_name.to_string(),
+                data: FileData::Memory(vec![]),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+        let distribution_resource = |package: &str, name: &str, data: &str| -> PythonResource {
+            PythonPackageDistributionResource {
+                location: PythonPackageDistributionResourceFlavor::DistInfo,
+                package: package.to_string(),
+                version: "1.0".to_string(),
+                name: name.to_string(),
+                data: FileData::Memory(data.as_bytes().to_vec()),
+            }
+            .into()
+        };
+
+        let resources: Vec<PythonResource> = vec![
+            module_source("foo", ""),
+            package_source("foo.tests"),
+            module_source("foo.tests.test_bar", ""),
+            module_source("foo.conftest", ""),
+            module_source("foo.testing", ""),
+            package_source("numpy.testing"),
+            module_source("numpy.testing.overrides", ""),
+            module_source("testfixtures", ""),
+            module_source("app.tests.test_app", ""),
+            stdlib_source("unittest.test", ""),
+            package_resource("foo", "tests/data.json"),
+            package_resource("foo.tests", "data.json"),
+            package_resource("foo", "templates/test.html"),
+            File::new("bar/conftest.py", vec![]).into(),
+            File::new("bar/tests/data.txt", vec![]).into(),
+            File::new("bar/test.txt", vec![]).into(),
+            distribution_resource("Foo", "METADATA", ""),
+            distribution_resource("numpy", "top_level.txt", "numpy\n"),
+            distribution_resource("testfixtures", "METADATA", ""),
+            distribution_resource("bar-dist", "top_level.txt", "bar\n"),
+        ];
+
+        policy.set_include_file_resources(true);
+
+        let included = |policy: &PythonPackagingPolicy| {
+            resources
+                .iter()
+                .filter(|r| policy.derive_add_collection_context(r).include)
+                .map(|r| r.full_name())
+                .collect::<Vec<_>>()
+        };
+
+        // Packages not known to belong to a distribution aren't excluded.
+        assert!(included(&policy).contains(&"foo.tests".to_string()));
+
+        policy.register_third_party_distributions_from_resources(resources.iter())?;
+        assert_eq!(
+            policy
+                .third_party_distributions()
+                .iter()
+                .map(|(package, distribution)| (package.as_str(), distribution.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("bar", "bar-dist"),
+                ("foo", "Foo"),
+                ("numpy", "numpy"),
+                ("testfixtures", "testfixtures")
+            ]
+        );
+        assert_eq!(
+            included(&policy),
+            vec![
+                "foo",
+                "testfixtures",
+                "app.tests.test_app",
+                "unittest.test",
+                "foo.templates/test.html",
+                "bar/test.txt",
+                "Foo:METADATA",
+                "numpy:top_level.txt",
+                "testfixtures:METADATA",
+                "bar-dist:top_level.txt"
+            ]
+        );
+
+        policy.add_third_party_test_exemption("NumPy");
+        let included_with_exemption = included(&policy);
+        assert!(included_with_exemption.contains(&"numpy.testing".to_string()));
+        assert!(included_with_exemption.contains(&"numpy.testing.overrides".to_string()));
+        assert!(!included_with_exemption.contains(&"foo.testing".to_string()));
+
+        policy.set_third_party_test_patterns(vec![]);
+        assert_eq!(included(&policy).len(), resources.len());
+
+        Ok(())
+    }
 }
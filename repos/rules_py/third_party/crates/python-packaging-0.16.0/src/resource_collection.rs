@@ -13,19 +13,26 @@ use {
         bytecode::{
             compute_bytecode_header, BytecodeHeaderMode, CompileMode, PythonBytecodeCompiler,
         },
+        compatibility::{find_compatibility_issues, CompatibilityReport},
+        import_graph::{ImportGraph, ImportGraphPruneReport, ImportGraphPruningPolicy},
         libpython::LibPythonBuildContext,
         licensing::{LicensedComponent, LicensedComponents},
+        loaded_modules::{LoadedModulesFilter, LoadedModulesManifest},
         location::{AbstractResourceLocation, ConcreteResourceLocation},
-        module_util::{packages_from_module_name, resolve_path_for_module},
+        module_util::{
+            packages_from_module_name, resolve_path_for_module, virtual_module_filename,
+        },
+        policy::PythonPackagingPolicy,
         python_source::has_dunder_file,
         resource::{
             BytecodeOptimizationLevel, PythonExtensionModule, PythonModuleBytecode,
             PythonModuleBytecodeFromSource, PythonModuleSource, PythonPackageDistributionResource,
             PythonPackageResource, PythonResource, SharedLibrary,
         },
+        size_report::SizeReport,
     },
     anyhow::{anyhow, Context, Result},
-    python_packed_resources::Resource,
+    python_packed_resources::{FilePayload, PayloadKey, Resource, StreamingResource},
     simple_file_manifest::{File, FileData, FileEntry, FileManifest},
     std::{
         borrow::Cow,
@@ -109,26 +116,69 @@ impl PrePackagedResource {
         &self,
         compiler: &mut dyn PythonBytecodeCompiler,
     ) -> Result<(Resource<'a, u8>, Vec<FileInstall>)> {
+        let (resource, installs) = self.to_resource_with_file_payloads(compiler, false, None)?;
+
+        Ok((resource.resource, installs))
+    }
+
+    /// Convert the instance to a `StreamingResource`.
+    ///
+    /// This is like [Self::to_resource()] except in-memory payloads backed by
+    /// files aren't read. Instead, they are recorded as file payloads so their
+    /// content can be copied when packed resources data is written.
+    pub fn to_streaming_resource<'a>(
+        &self,
+        compiler: &mut dyn PythonBytecodeCompiler,
+    ) -> Result<(StreamingResource<'a>, Vec<FileInstall>)> {
+        self.to_resource_with_file_payloads(compiler, true, None)
+    }
+
+    /// `filename_prefix` controls the filename bytecode is compiled with. If
+    /// set, it is the module's [virtual_module_filename()] under this prefix.
+    /// Otherwise it is the module name.
+    fn to_resource_with_file_payloads<'a>(
+        &self,
+        compiler: &mut dyn PythonBytecodeCompiler,
+        stream_files: bool,
+        filename_prefix: Option<&str>,
+    ) -> Result<(StreamingResource<'a>, Vec<FileInstall>)> {
+        let filename = match filename_prefix {
+            Some(prefix) => virtual_module_filename(prefix, &self.name, self.is_package),
+            None => self.name.clone(),
+        };
+
         let mut installs = Vec::new();
+        let mut file_payloads = BTreeMap::new();
+
+        let mut payload = |key: PayloadKey, location: &FileData| -> Result<Cow<'a, [u8]>> {
+            match location {
+                FileData::Path(path) if stream_files => {
+                    file_payloads.insert(key, FilePayload::new(path)?);
+
+                    Ok(Cow::Owned(vec![]))
+                }
+                _ => Ok(Cow::Owned(location.resolve_content()?)),
+            }
+        };
 
         let resource = Resource {
             name: Cow::Owned(self.name.clone()),
             is_python_package: self.is_package,
             is_python_namespace_package: self.is_namespace_package,
             in_memory_source: if let Some(location) = &self.in_memory_source {
-                Some(Cow::Owned(location.resolve_content()?))
+                Some(payload(PayloadKey::InMemorySource, location)?)
             } else {
                 None
             },
             in_memory_bytecode: match &self.in_memory_bytecode {
                 Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve_content()?))
+                    Some(payload(PayloadKey::InMemoryBytecode, location)?)
                 }
                 Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(Cow::Owned(
                     compiler
                         .compile(
                             &location.resolve_content()?,
-                            &self.name,
+                            &filename,
                             BytecodeOptimizationLevel::Zero,
                             CompileMode::Bytecode,
                         )
@@ -138,13 +188,13 @@ impl PrePackagedResource {
             },
             in_memory_bytecode_opt1: match &self.in_memory_bytecode_opt1 {
                 Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve_content()?))
+                    Some(payload(PayloadKey::InMemoryBytecodeOpt1, location)?)
                 }
                 Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(Cow::Owned(
                     compiler
                         .compile(
                             &location.resolve_content()?,
-                            &self.name,
+                            &filename,
                             BytecodeOptimizationLevel::One,
                             CompileMode::Bytecode,
                         )
@@ -154,13 +204,13 @@ impl PrePackagedResource {
             },
             in_memory_bytecode_opt2: match &self.in_memory_bytecode_opt2 {
                 Some(PythonModuleBytecodeProvider::Provided(location)) => {
-                    Some(Cow::Owned(location.resolve_content()?))
+                    Some(payload(PayloadKey::InMemoryBytecodeOpt2, location)?)
                 }
                 Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(Cow::Owned(
                     compiler
                         .compile(
                             &location.resolve_content()?,
-                            &self.name,
+                            &filename,
                             BytecodeOptimizationLevel::Two,
                             CompileMode::Bytecode,
                         )
@@ -171,7 +221,10 @@ impl PrePackagedResource {
             in_memory_extension_module_shared_library: if let Some(location) =
                 &self.in_memory_extension_module_shared_library
             {
-                Some(Cow::Owned(location.resolve_content()?))
+                Some(payload(
+                    PayloadKey::InMemoryExtensionModuleSharedLibrary,
+                    location,
+                )?)
             } else {
                 None
             },
@@ -180,7 +233,7 @@ impl PrePackagedResource {
                 for (key, location) in resources {
                     res.insert(
                         Cow::Owned(key.clone()),
-                        Cow::Owned(location.resolve_content()?),
+                        payload(PayloadKey::InMemoryPackageResource(key.clone()), location)?,
                     );
                 }
                 Some(res)
@@ -194,7 +247,10 @@ impl PrePackagedResource {
                 for (key, location) in resources {
                     res.insert(
                         Cow::Owned(key.clone()),
-                        Cow::Owned(location.resolve_content()?),
+                        payload(
+                            PayloadKey::InMemoryDistributionResource(key.clone()),
+                            location,
+                        )?,
                     );
                 }
                 Some(res)
@@ -202,7 +258,7 @@ impl PrePackagedResource {
                 None
             },
             in_memory_shared_library: if let Some(location) = &self.in_memory_shared_library {
-                Some(Cow::Owned(location.resolve_content()?))
+                Some(payload(PayloadKey::InMemorySharedLibrary, location)?)
             } else {
                 None
             },
@@ -241,7 +297,7 @@ impl PrePackagedResource {
                         PythonModuleBytecodeProvider::FromSource(location) => compiler
                             .compile(
                                 &location.resolve_content()?,
-                                &self.name,
+                                &filename,
                                 BytecodeOptimizationLevel::Zero,
                                 CompileMode::PycUncheckedHash,
                             )
@@ -283,7 +339,7 @@ impl PrePackagedResource {
                         PythonModuleBytecodeProvider::FromSource(location) => compiler
                             .compile(
                                 &location.resolve_content()?,
-                                &self.name,
+                                &filename,
                                 BytecodeOptimizationLevel::One,
                                 CompileMode::PycUncheckedHash,
                             )
@@ -324,7 +380,7 @@ impl PrePackagedResource {
                     FileData::Memory(match provider {
                         PythonModuleBytecodeProvider::FromSource(location) => compiler.compile(
                             &location.resolve_content()?,
-                            &self.name,
+                            &filename,
                             BytecodeOptimizationLevel::Two,
                             CompileMode::PycUncheckedHash,
                         )?,
@@ -389,7 +445,7 @@ impl PrePackagedResource {
             is_utf8_filename_data: self.is_utf8_filename_data,
             file_executable: self.file_executable,
             file_data_embedded: if let Some(location) = &self.file_data_embedded {
-                Some(Cow::Owned(location.resolve_content()?))
+                Some(payload(PayloadKey::FileDataEmbedded, location)?)
             } else {
                 None
             },
@@ -408,7 +464,13 @@ impl PrePackagedResource {
             installs.push((PathBuf::from(prefix).join(filename), location.clone(), true));
         }
 
-        Ok((resource, installs))
+        Ok((
+            StreamingResource {
+                resource,
+                file_payloads,
+            },
+            installs,
+        ))
     }
 }
 
@@ -645,9 +707,9 @@ pub struct CompiledResourcesCollection<'a> {
 }
 
 impl<'a> CompiledResourcesCollection<'a> {
-    /// Write resources to packed resources data, version 1.
+    /// Write resources to packed resources data, version 4.
     pub fn write_packed_resources<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
-        python_packed_resources::write_packed_resources_v3(
+        python_packed_resources::write_packed_resources_v4(
             &self
                 .resources
                 .values()
@@ -673,6 +735,55 @@ impl<'a> CompiledResourcesCollection<'a> {
     }
 }
 
+/// Represents a finalized collection of Python resources for streaming.
+///
+/// This is like [CompiledResourcesCollection] except the content of in-memory
+/// payloads backed by files isn't loaded. It is copied when packed resources
+/// data is written instead. This keeps memory use bounded when packing large
+/// resources.
+#[derive(Clone, Debug, Default)]
+pub struct CompiledStreamingResourcesCollection<'a> {
+    /// All indexes resources.
+    pub resources: BTreeMap<String, StreamingResource<'a>>,
+
+    /// Extra file installs that must be performed so referenced files are available.
+    pub extra_files: Vec<FileInstall>,
+}
+
+impl<'a> CompiledStreamingResourcesCollection<'a> {
+    /// Write resources to packed resources data, version 4.
+    ///
+    /// Payloads backed by files are copied in chunks.
+    pub fn write_packed_resources<W: std::io::Write>(&self, writer: &mut W) -> Result<()> {
+        python_packed_resources::write_packed_resources_v4_streaming(
+            &self
+                .resources
+                .values()
+                .cloned()
+                .collect::<Vec<StreamingResource<'a>>>(),
+            writer,
+            None,
+        )
+    }
+
+    /// Convert the file installs to a [FileManifest].
+    ///
+    /// Unlike [CompiledResourcesCollection::extra_files_manifest()], file-backed
+    /// installs aren't read into memory.
+    pub fn extra_files_manifest(&self) -> Result<FileManifest> {
+        let mut m = FileManifest::default();
+
+        for (path, location, executable) in &self.extra_files {
+            m.add_file_entry(
+                path,
+                FileEntry::new_from_data(location.clone(), *executable),
+            )?;
+        }
+
+        Ok(m)
+    }
+}
+
 /// Type used to collect Python resources so they can be serialized.
 ///
 /// We often want to turn Python resource primitives (module source,
@@ -712,6 +823,11 @@ pub struct PythonResourceCollector {
 
     /// Collection of software components which are licensed.
     licensed_components: LicensedComponents,
+
+    /// Prefix of virtual filenames that compiled bytecode refers to.
+    ///
+    /// If `None`, bytecode is compiled with the module name as its filename.
+    bytecode_filename_prefix: Option<String>,
 }
 
 impl PythonResourceCollector {
@@ -735,6 +851,7 @@ impl PythonResourceCollector {
             allow_files,
             resources: BTreeMap::new(),
             licensed_components: LicensedComponents::default(),
+            bytecode_filename_prefix: None,
         }
     }
 
@@ -743,6 +860,22 @@ impl PythonResourceCollector {
         &self.allowed_locations
     }
 
+    /// Obtain the prefix of virtual filenames that compiled bytecode refers to.
+    pub fn bytecode_filename_prefix(&self) -> Option<&str> {
+        self.bytecode_filename_prefix.as_deref()
+    }
+
+    /// Set the prefix of virtual filenames that compiled bytecode refers to.
+    ///
+    /// When set, bytecode compiled from source has its `co_filename` set to
+    /// a stable path like `<prefix>/foo/bar.py` instead of the module name.
+    /// Tracebacks then show the same paths regardless of where the build
+    /// ran. The importer serves in-memory source for these paths to
+    /// `linecache`, and replaces them with `__file__` for modules having one.
+    pub fn set_bytecode_filename_prefix(&mut self, prefix: Option<String>) {
+        self.bytecode_filename_prefix = prefix;
+    }
+
     /// Obtain a set of all top-level Python module names registered with the collector.
     ///
     /// The returned values correspond to packages or single file modules without
@@ -800,6 +933,53 @@ impl PythonResourceCollector {
         Ok(())
     }
 
+    /// Remove Python modules not recorded as loaded by any of the given manifests.
+    ///
+    /// See [LoadedModulesFilter] for which resources are retained.
+    pub fn retain_loaded_modules<'a>(
+        &mut self,
+        manifests: impl IntoIterator<Item = &'a LoadedModulesManifest>,
+        startup_only: bool,
+    ) -> Result<()> {
+        let filter = LoadedModulesFilter::new(manifests, startup_only);
+
+        self.filter_resources_mut(|resource| filter.retain(resource))
+    }
+
+    /// Remove Python modules unreachable from entry points through imports.
+    ///
+    /// Reachability is determined by static analysis of module source or
+    /// bytecode. See [ImportGraph]. Modules only reachable through import
+    /// functions called with literal names are retained and reported. Builtin
+    /// and frozen modules and non-module resources are always retained.
+    pub fn prune_unreachable_modules(
+        &mut self,
+        policy: &ImportGraphPruningPolicy,
+    ) -> Result<ImportGraphPruneReport> {
+        let graph = ImportGraph::from_resources(self.resources.values())?;
+        let reachability = graph.reachable(policy.entry_points.iter().map(|x| x.as_str()));
+
+        let removed = self
+            .resources
+            .values()
+            .filter(|resource| {
+                (resource.is_module || resource.is_extension_module)
+                    && !(resource.is_builtin_extension_module || resource.is_frozen_module)
+                    && !policy.is_retained(&resource.name)
+                    && !reachability.is_reachable(&resource.name)
+            })
+            .map(|resource| resource.name.clone())
+            .collect::<BTreeSet<_>>();
+
+        self.filter_resources_mut(|resource| !removed.contains(&resource.name))?;
+
+        Ok(ImportGraphPruneReport {
+            removed,
+            dynamic_only: reachability.dynamic_only,
+            unresolved_dynamic_importers: reachability.unresolved_dynamic_importers,
+        })
+    }
+
     /// Obtain an iterator over the resources in this collector.
     pub fn iter_resources(&self) -> impl Iterator<Item = (&String, &PrePackagedResource)> {
         Box::new(self.resources.iter())
@@ -1684,6 +1864,60 @@ impl PythonResourceCollector {
         )
     }
 
+    /// Add Python resources as directed by a packaging policy.
+    ///
+    /// Packages with compatibility issues are first registered as file
+    /// dependent with the policy, so every resource of such a package is
+    /// placed at the policy's file dependent packages location. Packages of
+    /// distributions having metadata in `resources` are registered as
+    /// third-party, so the policy's third-party test patterns apply to them.
+    /// Then each resource is added using the add context the policy derives
+    /// for it.
+    pub fn add_python_resources_with_policy<'a>(
+        &mut self,
+        policy: &mut PythonPackagingPolicy,
+        resources: &'a [PythonResource<'a>],
+    ) -> Result<Vec<AddResourceAction>> {
+        policy.register_file_dependent_packages_from_resources(resources.iter())?;
+        policy.register_third_party_distributions_from_resources(resources.iter())?;
+
+        let mut actions = vec![];
+
+        for resource in resources {
+            let add_context = policy.derive_add_collection_context(resource);
+
+            actions.extend(match resource {
+                PythonResource::ModuleSource(module) => {
+                    self.add_python_module_source_with_context(module, &add_context)?
+                }
+                PythonResource::ModuleBytecodeRequest(module) => {
+                    self.add_python_module_bytecode_from_source_with_context(module, &add_context)?
+                }
+                PythonResource::ModuleBytecode(module) => {
+                    self.add_python_module_bytecode_with_context(module, &add_context)?
+                }
+                PythonResource::PackageResource(resource) => {
+                    self.add_python_package_resource_with_context(resource, &add_context)?
+                }
+                PythonResource::PackageDistributionResource(resource) => self
+                    .add_python_package_distribution_resource_with_context(
+                        resource,
+                        &add_context,
+                    )?,
+                PythonResource::ExtensionModule(module) => {
+                    self.add_python_extension_module_with_context(module, &add_context)?
+                        .0
+                }
+                PythonResource::File(file) => {
+                    self.add_file_data_with_context(file, &add_context)?
+                }
+                _ => return Err(anyhow!("PythonResource variant not yet supported")),
+            });
+        }
+
+        Ok(actions)
+    }
+
     fn add_python_resource_with_locations(
         &mut self,
         resource: &PythonResource,
@@ -1834,6 +2068,119 @@ impl PythonResourceCollector {
         Ok(res)
     }
 
+    /// Searches Python sources for patterns incompatible with in-memory importing.
+    ///
+    /// Only modules with source or bytecode derived from source stored in
+    /// memory are analyzed. See [crate::compatibility] for what is detected.
+    pub fn analyze_compatibility(&self) -> Result<CompatibilityReport> {
+        let mut report = CompatibilityReport::default();
+
+        for (name, module) in &self.resources {
+            let source = module.in_memory_source.as_ref().or_else(|| {
+                [
+                    &module.in_memory_bytecode,
+                    &module.in_memory_bytecode_opt1,
+                    &module.in_memory_bytecode_opt2,
+                ]
+                .into_iter()
+                .find_map(|provider| match provider {
+                    Some(PythonModuleBytecodeProvider::FromSource(location)) => Some(location),
+                    _ => None,
+                })
+            });
+
+            if let Some(source) = source {
+                report.add_module(name, find_compatibility_issues(&source.resolve_content()?));
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// Moves in-memory modules and package resources of packages to a filesystem-relative location.
+    ///
+    /// `packages` are matched against top-level package names, so all
+    /// submodules of a named package are moved. Module source, bytecode, and
+    /// package resources are moved. Bytecode files are tagged with
+    /// `cache_tag`. Extension modules and distribution resources are left as
+    /// is.
+    ///
+    /// Returns the names of resources that were moved.
+    pub fn relocate_packages(
+        &mut self,
+        packages: &[String],
+        prefix: &str,
+        cache_tag: &str,
+    ) -> Result<Vec<String>> {
+        self.check_policy(AbstractResourceLocation::RelativePath)?;
+
+        let mut res = vec![];
+
+        for (name, entry) in self.resources.iter_mut() {
+            let top_level = name.split('.').next().unwrap_or(name);
+
+            if !entry.is_module || !packages.iter().any(|p| p == top_level) {
+                continue;
+            }
+
+            let mut moved = false;
+
+            if let Some(source) = entry.in_memory_source.take() {
+                entry.relative_path_module_source = Some((prefix.to_string(), source));
+                moved = true;
+            }
+
+            for (in_memory, relative_path) in [
+                (
+                    &mut entry.in_memory_bytecode,
+                    &mut entry.relative_path_bytecode,
+                ),
+                (
+                    &mut entry.in_memory_bytecode_opt1,
+                    &mut entry.relative_path_bytecode_opt1,
+                ),
+                (
+                    &mut entry.in_memory_bytecode_opt2,
+                    &mut entry.relative_path_bytecode_opt2,
+                ),
+            ] {
+                if let Some(bytecode) = in_memory.take() {
+                    *relative_path = Some((prefix.to_string(), cache_tag.to_string(), bytecode));
+                    moved = true;
+                }
+            }
+
+            if let Some(resources) = entry.in_memory_resources.take() {
+                let relocated = entry
+                    .relative_path_package_resources
+                    .get_or_insert_with(BTreeMap::new);
+
+                for (relative_name, data) in resources {
+                    let resource = PythonPackageResource {
+                        leaf_package: name.clone(),
+                        relative_name,
+                        data,
+                        is_stdlib: false,
+                        is_test: false,
+                    };
+
+                    relocated.insert(
+                        resource.relative_name.clone(),
+                        (resource.resolve_path(prefix), resource.data),
+                    );
+                }
+
+                moved = true;
+            }
+
+            if moved {
+                res.push(name.clone());
+            }
+        }
+
+        Ok(res)
+    }
+
     /// Compiles resources into a finalized collection.
     ///
     /// This will take all resources collected so far and convert them into
@@ -1844,6 +2191,53 @@ impl PythonResourceCollector {
         &self,
         compiler: &mut dyn PythonBytecodeCompiler,
     ) -> Result<CompiledResourcesCollection> {
+        let compiled = self.compile_resources_with_file_payloads(compiler, false)?;
+
+        Ok(CompiledResourcesCollection {
+            resources: compiled
+                .resources
+                .into_iter()
+                .map(|(name, resource)| (name, resource.resource))
+                .collect(),
+            extra_files: compiled.extra_files,
+        })
+    }
+
+    /// Compiles resources into a finalized collection for streaming.
+    ///
+    /// This is like [Self::compile_resources()] except the content of in-memory
+    /// payloads backed by files isn't read. Use this to write packed resources
+    /// data for resources too large to hold in memory.
+    pub fn compile_resources_streaming(
+        &self,
+        compiler: &mut dyn PythonBytecodeCompiler,
+    ) -> Result<CompiledStreamingResourcesCollection<'_>> {
+        self.compile_resources_with_file_payloads(compiler, true)
+    }
+
+    /// Compiles resources and attributes their size to packages and distributions.
+    ///
+    /// Sizes reflect the resources as [Self::compile_resources()] would emit
+    /// them, including bytecode and files installed next to the binary.
+    pub fn size_report(&self, compiler: &mut dyn PythonBytecodeCompiler) -> Result<SizeReport> {
+        let compiled = self.compile_resources(compiler)?;
+
+        let files = compiled
+            .extra_files
+            .iter()
+            .map(|(path, data, _)| (path.as_path(), data))
+            .collect::<HashMap<_, _>>();
+
+        SizeReport::from_resources(compiled.resources.values(), |path| {
+            files.get(path).map(|data| (*data).clone())
+        })
+    }
+
+    fn compile_resources_with_file_payloads(
+        &self,
+        compiler: &mut dyn PythonBytecodeCompiler,
+        stream_files: bool,
+    ) -> Result<CompiledStreamingResourcesCollection<'_>> {
         let mut input_resources = self.resources.clone();
         populate_parent_packages(&mut input_resources).context("populating parent packages")?;
 
@@ -1852,7 +2246,11 @@ impl PythonResourceCollector {
 
         for (name, resource) in &input_resources {
             let (entry, installs) = resource
-                .to_resource(compiler)
+                .to_resource_with_file_payloads(
+                    compiler,
+                    stream_files,
+                    self.bytecode_filename_prefix.as_deref(),
+                )
                 .with_context(|| format!("converting {} to resource", name))?;
 
             for install in installs {
@@ -1862,7 +2260,7 @@ impl PythonResourceCollector {
             resources.insert(name.clone(), entry);
         }
 
-        Ok(CompiledResourcesCollection {
+        Ok(CompiledStreamingResourcesCollection {
             resources,
             extra_files,
         })
@@ -1874,7 +2272,9 @@ mod tests {
     use {
         super::*,
         crate::{
+            compatibility::CompatibilityIssueKind,
             resource::{LibraryDependency, PythonPackageDistributionResourceFlavor},
+            size_report::SizeKind,
             testutil::FakeBytecodeCompiler,
         },
         simple_file_manifest::FileEntry,
@@ -1882,6 +2282,38 @@ mod tests {
 
     const DEFAULT_CACHE_TAG: &str = "cpython-39";
 
+    /// Construct a collector holding in-memory source modules, as `(name, source)` pairs.
+    ///
+    /// The module named `app` is a package. The collector also allows resources
+    /// at relative paths.
+    fn collector_with_sources(modules: &[(&str, &str)]) -> Result<PythonResourceCollector> {
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            false,
+        );
+
+        for (name, source) in modules {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(source.as_bytes().to_vec()),
+                    is_package: *name == "app",
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        Ok(r)
+    }
+
     #[test]
     fn test_resource_conversion_basic() -> Result<()> {
         let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
@@ -2978,6 +3410,195 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_retain_loaded_modules() -> Result<()> {
+        let mut r = collector_with_sources(&[("app.main", ""), ("app.unused", ""), ("other", "")])?;
+
+        let manifest = LoadedModulesManifest::parse(
+            "# loaded modules manifest v1\nmodule\tapp.main\tmemory\t-\tstartup\n",
+        )?;
+        r.retain_loaded_modules([&manifest], false)?;
+
+        assert_eq!(r.resources.keys().collect::<Vec<_>>(), vec!["app.main"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_unreachable_modules() -> Result<()> {
+        let mut r = collector_with_sources(&[
+            ("app", ""),
+            (
+                "app.main",
+                "from . import util\nimportlib.import_module('plugin')\n",
+            ),
+            ("app.util", "import json\n"),
+            ("app.unused", "import csv\n"),
+            ("plugin", ""),
+            ("json", ""),
+            ("csv", ""),
+            ("encodings", ""),
+        ])?;
+
+        let report = r.prune_unreachable_modules(&ImportGraphPruningPolicy {
+            entry_points: vec!["app.main".to_string()],
+            retain: vec!["encodings".to_string()],
+        })?;
+
+        assert_eq!(
+            r.resources.keys().collect::<Vec<_>>(),
+            vec!["app", "app.main", "app.util", "encodings", "json", "plugin"]
+        );
+        assert_eq!(
+            report.removed.into_iter().collect::<Vec<_>>(),
+            vec!["app.unused", "csv"]
+        );
+        assert_eq!(
+            report.dynamic_only.into_iter().collect::<Vec<_>>(),
+            vec!["plugin"]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_size_report() -> Result<()> {
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            false,
+        );
+
+        for (name, location) in [
+            ("app", ConcreteResourceLocation::InMemory),
+            (
+                "app.util",
+                ConcreteResourceLocation::RelativePath("lib".to_string()),
+            ),
+        ] {
+            r.add_python_module_source(
+                &PythonModuleSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(vec![0; 10]),
+                    is_package: name == "app",
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &location,
+            )?;
+        }
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+        let report = r.size_report(&mut compiler)?;
+
+        assert_eq!(report.packages.keys().collect::<Vec<_>>(), vec!["app"]);
+        assert_eq!(report.packages["app"].get(SizeKind::Source), 20);
+        assert!(report.distributions.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_python_resources_with_policy() -> Result<()> {
+        let mut r = collector_with_sources(&[])?;
+        let mut policy = PythonPackagingPolicy::default();
+
+        let module = |name: &str, source: &str| -> PythonResource<'static> {
+            PythonModuleSource {
+                name: name.to_string(),
+                source: FileData::Memory(source.as_bytes().to_vec()),
+                is_package: name == "app",
+                cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                is_stdlib: false,
+                is_test: false,
+            }
+            .into()
+        };
+
+        let resources = [
+            module("app", "import sys\n"),
+            module("app.data", "HERE = os.path.dirname(__file__)\n"),
+            module("other", "import sys\n"),
+        ];
+
+        r.add_python_resources_with_policy(&mut policy, &resources)?;
+
+        assert_eq!(
+            policy.file_dependent_packages().iter().collect::<Vec<_>>(),
+            vec!["app"]
+        );
+        for name in ["app", "app.data"] {
+            let resource = r.resources.get(name).unwrap();
+            assert!(resource.in_memory_source.is_none());
+            assert!(resource.relative_path_module_source.is_some());
+        }
+        let other = r.resources.get("other").unwrap();
+        assert!(other.in_memory_source.is_some());
+        assert!(other.relative_path_module_source.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_analyze_compatibility() -> Result<()> {
+        let mut r = collector_with_sources(&[
+            ("app", "import sys\n"),
+            ("app.data", "HERE = os.path.dirname(__file__)\n"),
+            ("other", "sys.path.insert(0, HERE)\n"),
+        ])?;
+        r.add_python_package_resource(
+            &PythonPackageResource {
+                leaf_package: "app".to_string(),
+                relative_name: "data.json".to_string(),
+                data: FileData::Memory(vec![42]),
+                is_stdlib: false,
+                is_test: false,
+            },
+            &ConcreteResourceLocation::InMemory,
+        )?;
+
+        let report = r.analyze_compatibility()?;
+        assert_eq!(
+            report.packages.keys().collect::<Vec<_>>(),
+            vec!["app", "other"]
+        );
+        assert_eq!(report.packages["app"]["app.data"][0].line, 1);
+
+        let flagged = report.flagged_packages(&[CompatibilityIssueKind::DunderFile]);
+        assert_eq!(flagged, vec!["app"]);
+
+        let moved = r.relocate_packages(&flagged, "lib", DEFAULT_CACHE_TAG)?;
+        assert_eq!(moved, vec!["app", "app.data"]);
+
+        let app = r.resources.get("app").unwrap();
+        assert!(app.in_memory_source.is_none());
+        assert!(app.in_memory_resources.is_none());
+        assert_eq!(
+            app.relative_path_package_resources,
+            Some(
+                [(
+                    "data.json".to_string(),
+                    (
+                        PathBuf::from("lib/app/data.json"),
+                        FileData::Memory(vec![42])
+                    )
+                )]
+                .into_iter()
+                .collect()
+            )
+        );
+        assert!(r.resources.get("other").unwrap().in_memory_source.is_some());
+        assert!(r.analyze_compatibility()?.packages.contains_key("other"));
+        assert!(!r.analyze_compatibility()?.packages.contains_key("app"));
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_in_memory_source_module_parents() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -3357,6 +3978,69 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_bytecode_filename_prefix() -> Result<()> {
+        /// Records the filenames bytecode is compiled with.
+        #[derive(Default)]
+        struct FilenameCompiler {
+            filenames: Vec<String>,
+        }
+
+        impl PythonBytecodeCompiler for FilenameCompiler {
+            fn get_magic_number(&self) -> u32 {
+                42
+            }
+
+            fn compile(
+                &mut self,
+                _source: &[u8],
+                filename: &str,
+                _optimize: BytecodeOptimizationLevel,
+                _output_mode: CompileMode,
+            ) -> Result<Vec<u8>> {
+                self.filenames.push(filename.to_string());
+
+                Ok(vec![])
+            }
+        }
+
+        let mut r = PythonResourceCollector::new(
+            vec![AbstractResourceLocation::InMemory],
+            vec![],
+            false,
+            false,
+        );
+
+        for (name, is_package) in [("foo", true), ("foo.bar", false)] {
+            r.add_python_module_bytecode_from_source(
+                &PythonModuleBytecodeFromSource {
+                    name: name.to_string(),
+                    source: FileData::Memory(vec![42]),
+                    optimize_level: BytecodeOptimizationLevel::Zero,
+                    is_package,
+                    cache_tag: DEFAULT_CACHE_TAG.to_string(),
+                    is_stdlib: false,
+                    is_test: false,
+                },
+                &ConcreteResourceLocation::InMemory,
+            )?;
+        }
+
+        let mut compiler = FilenameCompiler::default();
+        r.compile_resources(&mut compiler)?;
+        assert_eq!(compiler.filenames, vec!["foo", "foo.bar"]);
+
+        r.set_bytecode_filename_prefix(Some("<exe>".to_string()));
+        let mut compiler = FilenameCompiler::default();
+        r.compile_resources(&mut compiler)?;
+        assert_eq!(
+            compiler.filenames,
+            vec!["<exe>/foo/__init__.py", "<exe>/foo/bar.py"]
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_module_bytecode_with_context() -> Result<()> {
         let mut r = PythonResourceCollector::new(
@@ -4567,6 +5251,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_compile_resources_streaming() -> Result<()> {
+        let td = tempfile::Builder::new()
+            .prefix("python-packaging-test")
+            .tempdir()?;
+        let data_path = td.path().join("data.bin");
+        std::fs::write(&data_path, vec![42; 65536])?;
+
+        let mut r = PythonResourceCollector::new(
+            vec![
+                AbstractResourceLocation::InMemory,
+                AbstractResourceLocation::RelativePath,
+            ],
+            vec![],
+            false,
+            true,
+        );
+        r.add_file_data(
+            &File::new("foo/data.bin", FileEntry::new_from_path(&data_path, false)),
+            &ConcreteResourceLocation::InMemory,
+        )?;
+        r.add_file_data(
+            &File::new("foo/other.bin", FileEntry::new_from_path(&data_path, false)),
+            &ConcreteResourceLocation::RelativePath("prefix".to_string()),
+        )?;
+
+        let mut compiler = FakeBytecodeCompiler { magic_number: 42 };
+
+        let streaming = r.compile_resources_streaming(&mut compiler)?;
+        assert_eq!(streaming.resources.len(), 2);
+
+        let resource = streaming.resources.get("foo/data.bin").unwrap();
+        assert_eq!(
+            resource.resource.file_data_embedded,
+            Some(Cow::Owned(vec![]))
+        );
+        assert_eq!(
+            resource.file_payloads.get(&PayloadKey::FileDataEmbedded),
+            Some(&FilePayload {
+                path: data_path.clone(),
+                length: 65536,
+            })
+        );
+        assert!(streaming.resources["foo/other.bin"]
+            .file_payloads
+            .is_empty());
+
+        let manifest = streaming.extra_files_manifest()?;
+        assert_eq!(
+            manifest
+                .get("prefix/foo/other.bin")
+                .unwrap()
+                .file_data()
+                .backing_path(),
+            Some(data_path.as_path())
+        );
+
+        let mut expected = Vec::new();
+        r.compile_resources(&mut compiler)?
+            .write_packed_resources(&mut expected)?;
+
+        let mut data = Vec::new();
+        streaming.write_packed_resources(&mut data)?;
+
+        assert_eq!(data, expected);
+
+        Ok(())
+    }
+
     #[test]
     fn test_add_file_data_with_context() -> Result<()> {
         let mut r = PythonResourceCollector::new(
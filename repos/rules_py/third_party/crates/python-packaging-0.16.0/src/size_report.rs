@@ -0,0 +1,775 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Attribution of packaged bytes to Python packages and distributions.
+
+A [SizeReport] breaks down the size of packaged resources by top-level
+package, by distribution, and by [SizeKind]. Reports can be obtained from
+compiled resources or from packed resources data, rendered as text for
+humans, and serialized to a line-oriented, tab-delimited format for
+machines. The first line of the serialized form identifies the format.
+Subsequent lines are:
+
+```text
+<package|distribution> <name> <kind> <bytes>
+```
+
+Two reports can be compared with [SizeReport::diff()] to find what grew
+between builds.
+
+A package belongs to a distribution if the distribution's `top_level.txt`
+lists it. Distributions lacking `top_level.txt` claim the package whose name
+matches their normalized name. Distribution metadata is attributed to the
+first package the distribution claims.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::Resource,
+    simple_file_manifest::FileData,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fmt::Write,
+        path::Path,
+    },
+};
+
+/// Header line identifying the serialized report format.
+const HEADER: &str = "# size report v1";
+
+/// Describes what packaged bytes are used for.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum SizeKind {
+    /// Python module source code.
+    Source,
+    /// Python module bytecode, not optimized.
+    Bytecode,
+    /// Python module bytecode at optimization level 1.
+    BytecodeOpt1,
+    /// Python module bytecode at optimization level 2.
+    BytecodeOpt2,
+    /// Non-module resource files in packages.
+    PackageData,
+    /// Files in `.dist-info` or `.egg-info` directories.
+    DistributionMetadata,
+    /// Extension module shared libraries.
+    ExtensionModule,
+    /// Shared libraries that aren't extension modules.
+    SharedLibrary,
+    /// Arbitrary files.
+    File,
+}
+
+impl SizeKind {
+    /// All kinds, in display order.
+    pub const ALL: [SizeKind; 9] = [
+        SizeKind::Source,
+        SizeKind::Bytecode,
+        SizeKind::BytecodeOpt1,
+        SizeKind::BytecodeOpt2,
+        SizeKind::PackageData,
+        SizeKind::DistributionMetadata,
+        SizeKind::ExtensionModule,
+        SizeKind::SharedLibrary,
+        SizeKind::File,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Source => "source",
+            Self::Bytecode => "bytecode",
+            Self::BytecodeOpt1 => "bytecode-opt1",
+            Self::BytecodeOpt2 => "bytecode-opt2",
+            Self::PackageData => "package-data",
+            Self::DistributionMetadata => "distribution-metadata",
+            Self::ExtensionModule => "extension-module",
+            Self::SharedLibrary => "shared-library",
+            Self::File => "file",
+        }
+    }
+}
+
+impl TryFrom<&str> for SizeKind {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == value)
+            .ok_or_else(|| format!("{} is not a valid size kind", value))
+    }
+}
+
+/// Bytes attributed to an entity, by [SizeKind].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeBreakdown {
+    sizes: BTreeMap<SizeKind, u64>,
+}
+
+impl SizeBreakdown {
+    /// Attribute bytes of a given kind.
+    pub fn add(&mut self, kind: SizeKind, size: u64) {
+        if size == 0 {
+            return;
+        }
+
+        *self.sizes.entry(kind).or_default() += size;
+    }
+
+    /// Obtain the bytes of a given kind.
+    pub fn get(&self, kind: SizeKind) -> u64 {
+        self.sizes.get(&kind).copied().unwrap_or_default()
+    }
+
+    /// Obtain the bytes of all kinds.
+    pub fn total(&self) -> u64 {
+        self.sizes.values().sum()
+    }
+
+    /// Iterate over kinds having bytes attributed, in display order.
+    pub fn iter(&self) -> impl Iterator<Item = (SizeKind, u64)> + '_ {
+        self.sizes.iter().map(|(kind, size)| (*kind, *size))
+    }
+
+    fn merge(&mut self, other: &SizeBreakdown) {
+        for (kind, size) in other.iter() {
+            self.add(kind, size);
+        }
+    }
+}
+
+/// Sizes of packaged resources, attributed to packages and distributions.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeReport {
+    /// Sizes by top-level package name.
+    ///
+    /// Non-module resources are attributed to their own name or, for files,
+    /// to the first component of their path.
+    pub packages: BTreeMap<String, SizeBreakdown>,
+
+    /// Sizes by distribution name.
+    pub distributions: BTreeMap<String, SizeBreakdown>,
+}
+
+/// Obtain the size of file data, without reading files.
+fn file_data_size(data: &FileData) -> Result<u64> {
+    match data {
+        FileData::Memory(data) => Ok(data.len() as u64),
+        FileData::Path(path) => Ok(std::fs::metadata(path)
+            .with_context(|| format!("obtaining size of {}", path.display()))?
+            .len()),
+    }
+}
+
+/// Normalize a distribution name to the package name it conventionally provides.
+pub(crate) fn normalize_distribution_name(name: &str) -> String {
+    name.to_lowercase().replace(['-', '.'], "_")
+}
+
+fn top_level_name(name: &str, separator: char) -> &str {
+    name.split(separator).next().unwrap_or(name)
+}
+
+impl SizeReport {
+    /// Construct an instance from resources.
+    ///
+    /// `relative_file` resolves paths of files installed relative to resources.
+    /// Files it can't resolve are an error.
+    pub fn from_resources<'a, 'r: 'a>(
+        resources: impl IntoIterator<Item = &'a Resource<'r, u8>>,
+        relative_file: impl Fn(&Path) -> Option<FileData>,
+    ) -> Result<Self> {
+        let mut packages = BTreeMap::<String, SizeBreakdown>::new();
+        let mut metadata = BTreeMap::<String, SizeBreakdown>::new();
+        let mut top_levels = BTreeMap::<String, Vec<String>>::new();
+
+        let relative_size = |path: &Path| -> Result<u64> {
+            let data = relative_file(path)
+                .ok_or_else(|| anyhow!("unable to resolve file {}", path.display()))?;
+            file_data_size(&data)
+        };
+
+        for resource in resources {
+            let top_level = if resource.file_data_embedded.is_some()
+                || resource.file_data_utf8_relative_path.is_some()
+            {
+                top_level_name(&resource.name, '/')
+            } else {
+                top_level_name(&resource.name, '.')
+            };
+
+            let entry = packages.entry(top_level.to_string()).or_default();
+
+            let extension_kind = if resource.is_python_extension_module {
+                SizeKind::ExtensionModule
+            } else {
+                SizeKind::SharedLibrary
+            };
+
+            for (kind, data) in [
+                (SizeKind::Source, &resource.in_memory_source),
+                (SizeKind::Bytecode, &resource.in_memory_bytecode),
+                (SizeKind::BytecodeOpt1, &resource.in_memory_bytecode_opt1),
+                (SizeKind::BytecodeOpt2, &resource.in_memory_bytecode_opt2),
+                (
+                    SizeKind::ExtensionModule,
+                    &resource.in_memory_extension_module_shared_library,
+                ),
+                (extension_kind, &resource.in_memory_shared_library),
+                (SizeKind::File, &resource.file_data_embedded),
+            ] {
+                if let Some(data) = data {
+                    entry.add(kind, data.len() as u64);
+                }
+            }
+
+            for (kind, path) in [
+                (SizeKind::Source, &resource.relative_path_module_source),
+                (SizeKind::Bytecode, &resource.relative_path_module_bytecode),
+                (
+                    SizeKind::BytecodeOpt1,
+                    &resource.relative_path_module_bytecode_opt1,
+                ),
+                (
+                    SizeKind::BytecodeOpt2,
+                    &resource.relative_path_module_bytecode_opt2,
+                ),
+                (
+                    SizeKind::ExtensionModule,
+                    &resource.relative_path_extension_module_shared_library,
+                ),
+            ] {
+                if let Some(path) = path {
+                    entry.add(kind, relative_size(path)?);
+                }
+            }
+
+            if let Some(path) = &resource.file_data_utf8_relative_path {
+                entry.add(SizeKind::File, relative_size(Path::new(path.as_ref()))?);
+            }
+
+            if let Some(resources) = &resource.in_memory_package_resources {
+                for data in resources.values() {
+                    entry.add(SizeKind::PackageData, data.len() as u64);
+                }
+            }
+            if let Some(resources) = &resource.relative_path_package_resources {
+                for path in resources.values() {
+                    entry.add(SizeKind::PackageData, relative_size(path)?);
+                }
+            }
+
+            let mut distribution = SizeBreakdown::default();
+            let mut top_level_txt = None;
+
+            if let Some(resources) = &resource.in_memory_distribution_resources {
+                for (name, data) in resources {
+                    distribution.add(SizeKind::DistributionMetadata, data.len() as u64);
+
+                    if name == "top_level.txt" {
+                        top_level_txt = Some(data.to_vec());
+                    }
+                }
+            }
+            if let Some(resources) = &resource.relative_path_distribution_resources {
+                for (name, path) in resources {
+                    distribution.add(SizeKind::DistributionMetadata, relative_size(path)?);
+
+                    if name == "top_level.txt" {
+                        let data = relative_file(path)
+                            .ok_or_else(|| anyhow!("unable to resolve file {}", path.display()))?;
+                        top_level_txt = Some(data.resolve_content()?);
+                    }
+                }
+            }
+
+            if distribution.total() > 0 {
+                let names = match top_level_txt {
+                    Some(data) => String::from_utf8_lossy(&data)
+                        .lines()
+                        .map(|line| top_level_name(line.trim(), '/').to_string())
+                        .filter(|line| !line.is_empty())
+                        .collect::<Vec<_>>(),
+                    None => vec![normalize_distribution_name(&resource.name)],
+                };
+
+                top_levels.insert(resource.name.to_string(), names);
+                metadata
+                    .entry(resource.name.to_string())
+                    .or_default()
+                    .merge(&distribution);
+            }
+        }
+
+        // Distribution metadata is stored under the distribution's name, which
+        // generally isn't a package. Move it to the packages it describes.
+        let mut distributions = BTreeMap::<String, SizeBreakdown>::new();
+
+        for (name, sizes) in metadata {
+            let claimed = &top_levels[&name];
+
+            let entry = distributions.entry(name.clone()).or_default();
+            entry.merge(&sizes);
+
+            let owner = claimed.first().cloned().unwrap_or_else(|| name.clone());
+            packages.entry(owner).or_default().merge(&sizes);
+        }
+
+        let claimed = top_levels
+            .iter()
+            .flat_map(|(distribution, names)| names.iter().map(move |name| (name, distribution)))
+            .collect::<BTreeMap<_, _>>();
+
+        for (package, sizes) in &packages {
+            if let Some(distribution) = claimed.get(package) {
+                let mut sizes = sizes.clone();
+                sizes.sizes.remove(&SizeKind::DistributionMetadata);
+
+                distributions
+                    .entry(distribution.to_string())
+                    .or_default()
+                    .merge(&sizes);
+            }
+        }
+
+        packages.retain(|_, sizes| sizes.total() > 0);
+
+        Ok(Self {
+            packages,
+            distributions,
+        })
+    }
+
+    /// Construct an instance from packed resources data.
+    ///
+    /// Files installed relative to resources are resolved against `origin`. If
+    /// `origin` is [None], they are an error.
+    pub fn from_packed_resources(data: &[u8], origin: Option<&Path>) -> Result<Self> {
+        let resources = python_packed_resources::load_resources(data)
+            .context("parsing packed resources")?
+            .collect::<Result<Vec<_>, _>>()
+            .context("parsing packed resources")?;
+
+        Self::from_resources(&resources, |path| {
+            origin.map(|origin| FileData::Path(origin.join(path)))
+        })
+    }
+
+    /// Obtain the bytes of all packages.
+    pub fn total(&self) -> u64 {
+        self.packages.values().map(|x| x.total()).sum()
+    }
+
+    /// Parse a report from its serialized form.
+    pub fn parse(data: &str) -> Result<Self> {
+        let mut lines = data.lines();
+
+        if lines.next() != Some(HEADER) {
+            return Err(anyhow!("not a size report"));
+        }
+
+        let mut res = Self::default();
+
+        for (i, line) in lines.enumerate() {
+            if line.is_empty() {
+                continue;
+            }
+
+            let fields = line.split('\t').collect::<Vec<_>>();
+
+            let (map, name, kind, size) = match fields.as_slice() {
+                ["package", name, kind, size] => (&mut res.packages, name, kind, size),
+                ["distribution", name, kind, size] => (&mut res.distributions, name, kind, size),
+                _ => return Err(anyhow!("line {}: malformed entry: {}", i + 2, line)),
+            };
+
+            let kind = SizeKind::try_from(*kind).map_err(|e| anyhow!("line {}: {}", i + 2, e))?;
+            let size = size
+                .parse::<u64>()
+                .with_context(|| format!("line {}: invalid size", i + 2))?;
+
+            map.entry(name.to_string()).or_default().add(kind, size);
+        }
+
+        Ok(res)
+    }
+
+    /// Parse a report from a file.
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let data =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+
+        Self::parse(&data).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    /// Serialize the report.
+    pub fn to_report_string(&self) -> String {
+        let mut res = format!("{}\n", HEADER);
+
+        for (entity, map) in [
+            ("package", &self.packages),
+            ("distribution", &self.distributions),
+        ] {
+            for (name, sizes) in map {
+                for (kind, size) in sizes.iter() {
+                    writeln!(res, "{}\t{}\t{}\t{}", entity, name, kind.as_str(), size).unwrap();
+                }
+            }
+        }
+
+        res
+    }
+
+    /// Render the report as text tables, largest entries first.
+    pub fn to_text(&self) -> String {
+        let mut res = String::new();
+
+        for (title, map) in [
+            ("package", &self.packages),
+            ("distribution", &self.distributions),
+        ] {
+            let mut entries = map.iter().collect::<Vec<_>>();
+            entries.sort_by(|a, b| b.1.total().cmp(&a.1.total()).then(a.0.cmp(b.0)));
+
+            let width = entries
+                .iter()
+                .map(|(name, _)| name.len())
+                .chain(std::iter::once(title.len()))
+                .max()
+                .unwrap_or_default();
+
+            writeln!(res, "{:width$}  {:>10}  breakdown", title, "total").unwrap();
+
+            for (name, sizes) in entries {
+                let breakdown = sizes
+                    .iter()
+                    .map(|(kind, size)| format!("{} {}", kind.as_str(), format_size(size)))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                writeln!(
+                    res,
+                    "{:width$}  {:>10}  {}",
+                    name,
+                    format_size(sizes.total()),
+                    breakdown
+                )
+                .unwrap();
+            }
+
+            res.push('\n');
+        }
+
+        writeln!(res, "total: {}", format_size(self.total())).unwrap();
+
+        res
+    }
+
+    /// Compare this report, of an older build, against a report of a newer build.
+    pub fn diff(&self, newer: &SizeReport) -> SizeReportDiff {
+        let compare = |old: &BTreeMap<String, SizeBreakdown>,
+                       new: &BTreeMap<String, SizeBreakdown>| {
+            old.keys()
+                .chain(new.keys())
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .filter_map(|name| {
+                    let before = old.get(name).cloned().unwrap_or_default();
+                    let after = new.get(name).cloned().unwrap_or_default();
+
+                    if before == after {
+                        None
+                    } else {
+                        Some(SizeChange {
+                            name: name.clone(),
+                            before,
+                            after,
+                        })
+                    }
+                })
+                .collect::<Vec<_>>()
+        };
+
+        SizeReportDiff {
+            before_total: self.total(),
+            after_total: newer.total(),
+            packages: compare(&self.packages, &newer.packages),
+            distributions: compare(&self.distributions, &newer.distributions),
+        }
+    }
+}
+
+/// Describes how the size of a package or distribution changed.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SizeChange {
+    pub name: String,
+    pub before: SizeBreakdown,
+    pub after: SizeBreakdown,
+}
+
+impl SizeChange {
+    /// The change in total bytes. Positive values are growth.
+    pub fn delta(&self) -> i64 {
+        self.after.total() as i64 - self.before.total() as i64
+    }
+}
+
+/// Differences between two [SizeReport].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SizeReportDiff {
+    pub before_total: u64,
+    pub after_total: u64,
+
+    /// Changed packages, sorted by name.
+    pub packages: Vec<SizeChange>,
+
+    /// Changed distributions, sorted by name.
+    pub distributions: Vec<SizeChange>,
+}
+
+impl SizeReportDiff {
+    /// The change in total bytes. Positive values are growth.
+    pub fn delta(&self) -> i64 {
+        self.after_total as i64 - self.before_total as i64
+    }
+
+    /// Packages that grew by more than `threshold` bytes.
+    pub fn regressions(&self, threshold: u64) -> impl Iterator<Item = &SizeChange> {
+        self.packages
+            .iter()
+            .filter(move |change| change.delta() > threshold as i64)
+    }
+
+    /// Render the differences as text, largest growth first.
+    pub fn to_text(&self) -> String {
+        let mut res = String::new();
+
+        for (title, changes) in [
+            ("package", &self.packages),
+            ("distribution", &self.distributions),
+        ] {
+            if changes.is_empty() {
+                continue;
+            }
+
+            let mut changes = changes.iter().collect::<Vec<_>>();
+            changes.sort_by(|a, b| b.delta().cmp(&a.delta()).then(a.name.cmp(&b.name)));
+
+            writeln!(res, "{} changes:", title).unwrap();
+
+            for change in changes {
+                writeln!(
+                    res,
+                    "  {} {} ({} -> {})",
+                    change.name,
+                    format_delta(change.delta()),
+                    format_size(change.before.total()),
+                    format_size(change.after.total())
+                )
+                .unwrap();
+
+                for kind in SizeKind::ALL {
+                    let delta = change.after.get(kind) as i64 - change.before.get(kind) as i64;
+
+                    if delta != 0 {
+                        writeln!(res, "    {} {}", kind.as_str(), format_delta(delta)).unwrap();
+                    }
+                }
+            }
+
+            res.push('\n');
+        }
+
+        writeln!(
+            res,
+            "total: {} ({} -> {})",
+            format_delta(self.delta()),
+            format_size(self.before_total),
+            format_size(self.after_total)
+        )
+        .unwrap();
+
+        res
+    }
+}
+
+/// Format a byte count for humans.
+fn format_size(size: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+
+    if size < 1024 {
+        return format!("{} B", size);
+    }
+
+    let mut value = size as f64 / 1024.0;
+    let mut unit = 0;
+
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{:.1} {}", value, UNITS[unit])
+}
+
+fn format_delta(delta: i64) -> String {
+    let sign = if delta < 0 { "-" } else { "+" };
+
+    format!("{}{}", sign, format_size(delta.unsigned_abs()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::borrow::Cow, std::collections::HashMap};
+
+    fn module(name: &str, source: usize, bytecode: usize) -> Resource<'static, u8> {
+        Resource {
+            name: Cow::Owned(name.to_string()),
+            is_python_module: true,
+            in_memory_source: Some(Cow::Owned(vec![0; source])),
+            in_memory_bytecode: Some(Cow::Owned(vec![0; bytecode])),
+            ..Resource::default()
+        }
+    }
+
+    fn resources() -> Vec<Resource<'static, u8>> {
+        let mut distribution = HashMap::new();
+        distribution.insert(Cow::Borrowed("METADATA"), Cow::Owned(vec![0; 30]));
+        distribution.insert(
+            Cow::Borrowed("top_level.txt"),
+            Cow::Owned(b"yaml\n".to_vec()),
+        );
+
+        let mut package_data = HashMap::new();
+        package_data.insert(Cow::Borrowed("data.json"), Cow::Owned(vec![0; 7]));
+
+        vec![
+            module("yaml", 100, 50),
+            Resource {
+                in_memory_package_resources: Some(package_data),
+                ..module("yaml.loader", 20, 10)
+            },
+            Resource {
+                name: Cow::Borrowed("PyYAML"),
+                is_python_package: true,
+                in_memory_distribution_resources: Some(distribution),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::Borrowed("yaml._yaml"),
+                is_python_extension_module: true,
+                relative_path_extension_module_shared_library: Some(Cow::Borrowed(Path::new(
+                    "yaml/_yaml.so",
+                ))),
+                ..Resource::default()
+            },
+            module("json", 5, 3),
+        ]
+    }
+
+    #[test]
+    fn test_from_resources() -> Result<()> {
+        let report = SizeReport::from_resources(&resources(), |path| {
+            if path == Path::new("yaml/_yaml.so") {
+                Some(FileData::Memory(vec![0; 1000]))
+            } else {
+                None
+            }
+        })?;
+
+        assert_eq!(
+            report.packages.keys().collect::<Vec<_>>(),
+            vec!["json", "yaml"]
+        );
+
+        let yaml = &report.packages["yaml"];
+        assert_eq!(yaml.get(SizeKind::Source), 120);
+        assert_eq!(yaml.get(SizeKind::Bytecode), 60);
+        assert_eq!(yaml.get(SizeKind::PackageData), 7);
+        assert_eq!(yaml.get(SizeKind::DistributionMetadata), 35);
+        assert_eq!(yaml.get(SizeKind::ExtensionModule), 1000);
+        assert_eq!(yaml.total(), 1222);
+
+        assert_eq!(
+            report.distributions.keys().collect::<Vec<_>>(),
+            vec!["PyYAML"]
+        );
+        assert_eq!(report.distributions["PyYAML"], *yaml);
+        assert_eq!(report.total(), 1230);
+
+        assert!(SizeReport::from_resources(&resources(), |_| None).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_serialization() -> Result<()> {
+        let report = SizeReport::from_resources(&resources(), |_| Some(FileData::Memory(vec![])))?;
+
+        let serialized = report.to_report_string();
+        assert!(serialized.contains("package\tyaml\tsource\t120\n"));
+        assert!(serialized.contains("distribution\tPyYAML\tbytecode\t60\n"));
+        assert_eq!(SizeReport::parse(&serialized)?, report);
+
+        assert!(SizeReport::parse("package\tfoo\tsource\t1\n").is_err());
+        assert!(SizeReport::parse(&format!("{}\npackage\tfoo\tbogus\t1\n", HEADER)).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff() -> Result<()> {
+        let before = SizeReport::parse(&format!(
+            "{}\npackage\tfoo\tsource\t100\npackage\tgone\tbytecode\t10\n",
+            HEADER
+        ))?;
+        let after = SizeReport::parse(&format!(
+            "{}\npackage\tfoo\tsource\t2148\npackage\tnew\tbytecode\t5\n",
+            HEADER
+        ))?;
+
+        let diff = before.diff(&after);
+
+        assert_eq!(diff.delta(), 2043);
+        assert_eq!(
+            diff.packages
+                .iter()
+                .map(|c| (c.name.as_str(), c.delta()))
+                .collect::<Vec<_>>(),
+            vec![("foo", 2048), ("gone", -10), ("new", 5)]
+        );
+        assert_eq!(
+            diff.regressions(1024)
+                .map(|c| c.name.as_str())
+                .collect::<Vec<_>>(),
+            vec!["foo"]
+        );
+        assert_eq!(
+            diff.to_text(),
+            "package changes:\n  \
+             foo +2.0 KiB (100 B -> 2.1 KiB)\n    \
+             source +2.0 KiB\n  \
+             new +5 B (0 B -> 5 B)\n    \
+             bytecode +5 B\n  \
+             gone -10 B (10 B -> 0 B)\n    \
+             bytecode -10 B\n\n\
+             total: +2.0 KiB (110 B -> 2.1 KiB)\n"
+        );
+
+        assert!(before.diff(&before).packages.is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+}
@@ -0,0 +1,91 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Extract the bytecode of bootstrap modules to register as frozen modules.
+
+Usage: `extract-frozen-modules [--no-default-modules] [--module NAME]... --output PATH INPUT...`
+
+The modules in `DEFAULT_BOOTSTRAP_MODULES` are extracted unless
+`--no-default-modules` is given. `--module` adds additional modules. The output
+is packed resources data holding only the bytecode of extracted modules.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::{write_frozen_modules, DEFAULT_BOOTSTRAP_MODULES},
+    std::path::PathBuf,
+};
+
+const USAGE: &str =
+    "usage: extract-frozen-modules [--no-default-modules] [--module NAME]... --output PATH INPUT...";
+
+fn run() -> Result<()> {
+    let mut modules = DEFAULT_BOOTSTRAP_MODULES
+        .iter()
+        .map(|x| x.to_string())
+        .collect::<Vec<_>>();
+    let mut extra_modules = vec![];
+    let mut output = None;
+    let mut inputs = vec![];
+
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--no-default-modules") => {
+                modules.clear();
+            }
+            Some("--module") | Some("-m") => {
+                extra_modules.push(
+                    args.next()
+                        .and_then(|v| v.into_string().ok())
+                        .ok_or_else(|| anyhow!("--module requires a value"))?,
+                );
+            }
+            Some("--output") | Some("-o") => {
+                output = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--output requires a value"))?,
+                ));
+            }
+            Some("--help") | Some("-h") => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            Some(option) if option.starts_with('-') => {
+                return Err(anyhow!("unrecognized option {}\n{}", option, USAGE));
+            }
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    let output = output.ok_or_else(|| anyhow!("{}", USAGE))?;
+    modules.extend(extra_modules);
+
+    let data = inputs
+        .iter()
+        .map(|path| std::fs::read(path).with_context(|| format!("reading {}", path.display())))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut frozen = Vec::new();
+    write_frozen_modules(
+        &data.iter().map(|x| x.as_slice()).collect::<Vec<_>>(),
+        &modules.iter().map(|x| x.as_str()).collect::<Vec<_>>(),
+        &mut frozen,
+    )?;
+
+    std::fs::write(&output, frozen).with_context(|| format!("writing {}", output.display()))?;
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,135 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Merge multiple packed resources files into one.
+
+Usage: `merge-packed-resources [--conflict-policy POLICY] --output PATH INPUT...`
+
+`POLICY` is one of `error` (the default), `first-wins`, or `last-wins`. Conflicts
+resolved by the policy are reported on stderr.
+
+An `INPUT` of `-` reads from stdin and an output `PATH` of `-` writes to stdout.
+*/
+
+use {
+    anyhow::{anyhow, Context, Result},
+    python_packed_resources::{ConflictPolicy, ResourcesMerger},
+    std::{
+        io::{Read, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+const USAGE: &str =
+    "usage: merge-packed-resources [--conflict-policy error|first-wins|last-wins] --output PATH INPUT...\n\
+     an INPUT of - reads stdin and an output PATH of - writes stdout";
+
+/// Path standing for stdin as an input and for stdout as the output.
+const STDIO: &str = "-";
+
+/// Read an input, from stdin if it is [STDIO].
+fn read_input(path: &Path) -> Result<Vec<u8>> {
+    if path == Path::new(STDIO) {
+        let mut data = Vec::new();
+        std::io::stdin()
+            .lock()
+            .read_to_end(&mut data)
+            .context("reading stdin")?;
+
+        Ok(data)
+    } else {
+        std::fs::read(path).with_context(|| format!("reading {}", path.display()))
+    }
+}
+
+fn run() -> Result<()> {
+    let mut policy = ConflictPolicy::default();
+    let mut output = None;
+    let mut inputs = vec![];
+
+    let mut args = std::env::args_os().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.to_str() {
+            Some("--conflict-policy") => {
+                let value = args
+                    .next()
+                    .and_then(|v| v.into_string().ok())
+                    .ok_or_else(|| anyhow!("--conflict-policy requires a value"))?;
+                policy = value.parse().map_err(|e: String| anyhow!(e))?;
+            }
+            Some("--output") | Some("-o") => {
+                output = Some(PathBuf::from(
+                    args.next()
+                        .ok_or_else(|| anyhow!("--output requires a value"))?,
+                ));
+            }
+            Some("--help") | Some("-h") => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            Some(option) if option.starts_with('-') && option != STDIO => {
+                return Err(anyhow!("unrecognized option {}\n{}", option, USAGE));
+            }
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+
+    let output = output.ok_or_else(|| anyhow!("{}", USAGE))?;
+
+    if inputs
+        .iter()
+        .filter(|path| path.as_path() == Path::new(STDIO))
+        .count()
+        > 1
+    {
+        return Err(anyhow!("stdin can only be given as an input once"));
+    }
+
+    let data = inputs
+        .iter()
+        .map(|path| read_input(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut merger = ResourcesMerger::new(policy);
+    for (path, data) in inputs.iter().zip(&data) {
+        merger
+            .add_packed_resources(data)
+            .with_context(|| format!("merging {}", path.display()))?;
+    }
+
+    for conflict in merger.conflicts() {
+        eprintln!(
+            "warning: {} field of {} in {} conflicts with {}",
+            conflict.field,
+            conflict.name,
+            inputs[conflict.input].display(),
+            inputs[conflict.first_input].display()
+        );
+    }
+
+    let mut merged = Vec::new();
+    merger.write_packed_resources(&mut merged)?;
+
+    if output == Path::new(STDIO) {
+        std::io::stdout()
+            .lock()
+            .write_all(&merged)
+            .context("writing stdout")?;
+    } else {
+        std::fs::write(&output, merged).with_context(|| format!("writing {}", output.display()))?;
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("error: {:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,360 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Error types for parsing packed resources data. */
+
+use {
+    crate::serialization::ResourceField,
+    std::fmt::{Display, Formatter},
+};
+
+/// Identifies the resource entry an error occurred in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ResourceContext {
+    /// 0-based index of the resource within the resources index.
+    pub index: usize,
+
+    /// The resource name, if it had been read when the error occurred.
+    pub name: Option<String>,
+}
+
+impl Display for ResourceContext {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(name) = &self.name {
+            write!(f, "resource #{} ({})", self.index, name)
+        } else {
+            write!(f, "resource #{}", self.index)
+        }
+    }
+}
+
+/// Represents an error encountered when parsing packed resources data.
+///
+/// All `offset` values are byte offsets from the beginning of the data passed
+/// to [crate::load_resources()] or [crate::validate()], header included.
+///
+/// Lengths are reported as an `expected` number of bytes the format called for
+/// versus the `actual` number of bytes that were available.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// Data is too short to hold the format header.
+    HeaderTooShort { actual: usize },
+
+    /// The format header isn't recognized.
+    UnrecognizedHeader,
+
+    /// The data requires features this implementation doesn't support.
+    UnsupportedRequiredFeatures { required: u64, supported: u64 },
+
+    /// Data ended before a value in the global header or an index could be read.
+    Truncated {
+        offset: usize,
+        resource: Option<ResourceContext>,
+        context: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// The blob index contains an unknown field type.
+    InvalidBlobSectionField { offset: usize, value: u8 },
+
+    /// A blob index entry is missing a required field.
+    IncompleteBlobSection {
+        offset: usize,
+        missing: &'static str,
+    },
+
+    /// A blob index entry declares an unknown interior padding value.
+    InvalidInteriorPadding { offset: usize, value: u8 },
+
+    /// The number of blob index entries differs from the advertised count.
+    BlobSectionCountMismatch { expected: usize, actual: usize },
+
+    /// Multiple blob index entries describe the same resource field.
+    DuplicateBlobSection { offset: usize, field: u8 },
+
+    /// Blob sections extend past the end of the data.
+    BlobSectionsOutOfBounds {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// The advertised length of an index differs from its parsed length.
+    IndexLengthMismatch {
+        index: &'static str,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// More resources are advertised than the resources index could hold.
+    ImplausibleResourcesCount { count: usize, index_length: usize },
+
+    /// The resources index contains an unknown field type.
+    InvalidResourceField {
+        offset: usize,
+        resource: ResourceContext,
+        value: u8,
+    },
+
+    /// A resource field's advertised length differs from its parsed length.
+    FieldLengthMismatch {
+        offset: usize,
+        resource: ResourceContext,
+        field: ResourceField,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// A resource entry doesn't define a name.
+    MissingResourceName {
+        offset: usize,
+        resource: ResourceContext,
+    },
+
+    /// The number of resources differs from the advertised count.
+    ResourcesCountMismatch {
+        offset: usize,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// A resource field references a blob section which doesn't exist.
+    MissingBlobSection {
+        offset: usize,
+        resource: ResourceContext,
+        field: ResourceField,
+    },
+
+    /// A resource field's data extends past the end of its blob section.
+    BlobOutOfBounds {
+        offset: usize,
+        resource: ResourceContext,
+        field: ResourceField,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// A byte that should be NULL interior padding isn't.
+    InvalidPadding {
+        offset: usize,
+        resource: ResourceContext,
+        field: ResourceField,
+        value: u8,
+    },
+
+    /// String data isn't valid UTF-8.
+    InvalidUtf8 {
+        offset: usize,
+        resource: ResourceContext,
+        field: ResourceField,
+    },
+
+    /// Filesystem path data can't be decoded on this platform.
+    InvalidPath {
+        offset: usize,
+        resource: ResourceContext,
+        field: ResourceField,
+    },
+
+    /// A blob section wasn't fully consumed by the resources referencing it.
+    BlobSectionLengthMismatch {
+        field: u8,
+        expected: usize,
+        actual: usize,
+    },
+
+    /// Data exists after the last blob section.
+    TrailingData { offset: usize, length: usize },
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HeaderTooShort { actual } => {
+                write!(f, "error reading 8 byte header: only {} bytes available", actual)
+            }
+            Self::UnrecognizedHeader => f.write_str("unrecognized file format"),
+            Self::UnsupportedRequiredFeatures {
+                required,
+                supported,
+            } => write!(
+                f,
+                "data requires unsupported features {:#x} (supported: {:#x})",
+                required & !supported,
+                supported
+            ),
+            Self::Truncated {
+                offset,
+                resource,
+                context,
+                expected,
+                actual,
+            } => {
+                write!(f, "failed reading {} at offset {}", context, offset)?;
+                if let Some(resource) = resource {
+                    write!(f, " in {}", resource)?;
+                }
+                write!(
+                    f,
+                    ": needed {} bytes; {} available",
+                    expected, actual
+                )
+            }
+            Self::InvalidBlobSectionField { offset, value } => write!(
+                f,
+                "invalid blob index field type {:#04x} at offset {}",
+                value, offset
+            ),
+            Self::IncompleteBlobSection { offset, missing } => write!(
+                f,
+                "blob index entry ending at offset {} is missing {}",
+                offset, missing
+            ),
+            Self::InvalidInteriorPadding { offset, value } => write!(
+                f,
+                "invalid value {:#04x} for interior padding field at offset {}",
+                value, offset
+            ),
+            Self::BlobSectionCountMismatch { expected, actual } => write!(
+                f,
+                "mismatch between blob sections count: advertised {}; found {}",
+                expected, actual
+            ),
+            Self::DuplicateBlobSection { offset, field } => write!(
+                f,
+                "duplicate blob section for resource field {:#04x} at offset {}",
+                field, offset
+            ),
+            Self::BlobSectionsOutOfBounds {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "blob sections starting at offset {} need {} bytes; {} available",
+                offset, expected, actual
+            ),
+            Self::IndexLengthMismatch {
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} index length mismatch: advertised {} bytes; parsed {}",
+                index, expected, actual
+            ),
+            Self::ImplausibleResourcesCount {
+                count,
+                index_length,
+            } => write!(
+                f,
+                "{} resources cannot fit in a {} byte resources index",
+                count, index_length
+            ),
+            Self::InvalidResourceField {
+                offset,
+                resource,
+                value,
+            } => write!(
+                f,
+                "invalid field type {:#04x} at offset {} in {}",
+                value, offset, resource
+            ),
+            Self::FieldLengthMismatch {
+                offset,
+                resource,
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{:?} field at offset {} in {} advertised {} bytes; parsed {}",
+                field, offset, resource, expected, actual
+            ),
+            Self::MissingResourceName { offset, resource } => write!(
+                f,
+                "resource name field is required; {} ending at offset {} has none",
+                resource, offset
+            ),
+            Self::ResourcesCountMismatch {
+                offset,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "mismatch between advertised index count and actual at offset {}: advertised {}; found {}",
+                offset, expected, actual
+            ),
+            Self::MissingBlobSection {
+                offset,
+                resource,
+                field,
+            } => write!(
+                f,
+                "no blob section for field {:?} referenced at offset {} by {}",
+                field, offset, resource
+            ),
+            Self::BlobOutOfBounds {
+                offset,
+                resource,
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{:?} data for {} at offset {} needs {} bytes; {} remain in blob section",
+                field, resource, offset, expected, actual
+            ),
+            Self::InvalidPadding {
+                offset,
+                resource,
+                field,
+                value,
+            } => write!(
+                f,
+                "expected NULL interior padding after {:?} data for {} at offset {}; found {:#04x}",
+                field, resource, offset, value
+            ),
+            Self::InvalidUtf8 {
+                offset,
+                resource,
+                field,
+            } => write!(
+                f,
+                "{:?} data for {} at offset {} is not valid UTF-8",
+                field, resource, offset
+            ),
+            Self::InvalidPath {
+                offset,
+                resource,
+                field,
+            } => write!(
+                f,
+                "{:?} data for {} at offset {} is not a valid path",
+                field, resource, offset
+            ),
+            Self::BlobSectionLengthMismatch {
+                field,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "blob section for resource field {:#04x} advertised {} bytes; resources consumed {}",
+                field, expected, actual
+            ),
+            Self::TrailingData { offset, length } => write!(
+                f,
+                "{} bytes of trailing data at offset {}",
+                length, offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
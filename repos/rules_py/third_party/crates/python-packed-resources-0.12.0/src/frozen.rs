@@ -0,0 +1,177 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Extraction of modules to register as frozen modules.
+
+CPython imports a handful of modules, most notably `encodings`, while it
+initializes. Some of these imports happen before a custom meta path importer
+can be installed, so they must be serviced by the filesystem or by CPython's
+own frozen modules. An embedder can instead register the bytecode of these
+*bootstrap* modules as frozen modules so interpreter startup doesn't need a
+standard library on the filesystem.
+
+The functions in this module select the bootstrap modules from packed
+resources data and write them out as a standalone packed resources data
+structure, suitable for embedding in an executable.
+*/
+
+use {
+    crate::{parser::load_resources, resource::Resource, writer::write_packed_resources_v4},
+    anyhow::{anyhow, Context, Result},
+    std::{collections::BTreeMap, io::Write},
+};
+
+/// Modules imported during interpreter initialization.
+///
+/// This covers the codecs for the default UTF-8 filesystem and standard
+/// streams encodings. Applications using other encodings need to add the
+/// corresponding `encodings.*` modules.
+pub const DEFAULT_BOOTSTRAP_MODULES: &[&str] = &[
+    "abc",
+    "codecs",
+    "encodings",
+    "encodings.aliases",
+    "encodings.ascii",
+    "encodings.latin_1",
+    "encodings.utf_8",
+    "io",
+];
+
+/// Extract frozen module definitions from packed resources data.
+///
+/// Each named module and its parent packages are searched for in `inputs`, in
+/// order. The first resource defining in-memory bytecode for a module is used.
+/// It is an error for a module to lack in-memory bytecode in all inputs.
+///
+/// Returned resources only define the module name, whether it is a package,
+/// and its bytecode. They are sorted by name.
+pub fn extract_frozen_modules<'a>(
+    inputs: &[&'a [u8]],
+    modules: &[&str],
+) -> Result<Vec<Resource<'a, u8>>> {
+    let mut wanted = BTreeMap::new();
+    for module in modules {
+        let mut name = *module;
+        wanted.insert(name.to_string(), None);
+
+        while let Some((parent, _)) = name.rsplit_once('.') {
+            wanted.insert(parent.to_string(), None);
+            name = parent;
+        }
+    }
+
+    for (input, data) in inputs.iter().enumerate() {
+        let resources = load_resources(data)
+            .with_context(|| format!("parsing packed resources input #{}", input))?;
+
+        for resource in resources {
+            let resource =
+                resource.with_context(|| format!("parsing packed resources input #{}", input))?;
+
+            let bytecode = match &resource.in_memory_bytecode {
+                Some(bytecode) => bytecode.clone(),
+                None => continue,
+            };
+
+            if let Some(entry @ None) = wanted.get_mut(resource.name.as_ref()) {
+                *entry = Some(Resource {
+                    name: resource.name.clone(),
+                    is_python_module: true,
+                    is_python_package: resource.is_python_package,
+                    in_memory_bytecode: Some(bytecode),
+                    ..Resource::default()
+                });
+            }
+        }
+    }
+
+    wanted
+        .into_iter()
+        .map(|(name, resource)| {
+            resource.ok_or_else(|| anyhow!("no in-memory bytecode found for module {}", name))
+        })
+        .collect()
+}
+
+/// Write frozen module definitions as packed resources data, version 4.
+///
+/// See [extract_frozen_modules()] for how modules are selected.
+pub fn write_frozen_modules<W: Write>(
+    inputs: &[&[u8]],
+    modules: &[&str],
+    dest: &mut W,
+) -> Result<()> {
+    let resources = extract_frozen_modules(inputs, modules)?;
+
+    write_packed_resources_v4(&resources, dest, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::borrow::Cow};
+
+    fn packed(resources: &[Resource<u8>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_packed_resources_v4(resources, &mut data, None).unwrap();
+        data
+    }
+
+    fn module(
+        name: &str,
+        is_package: bool,
+        bytecode: Option<&'static [u8]>,
+    ) -> Resource<'static, u8> {
+        Resource {
+            name: Cow::Owned(name.to_string()),
+            is_python_module: true,
+            is_python_package: is_package,
+            in_memory_source: Some(Cow::Borrowed(b"source")),
+            in_memory_bytecode: bytecode.map(Cow::Borrowed),
+            ..Resource::default()
+        }
+    }
+
+    #[test]
+    fn test_extract_frozen_modules() -> Result<()> {
+        let first = packed(&[
+            module("encodings", true, None),
+            module("encodings.utf_8", false, Some(b"utf8")),
+        ]);
+        let second = packed(&[
+            module("encodings", true, Some(b"encodings")),
+            module("encodings.utf_8", false, Some(b"shadowed")),
+            module("unused", false, Some(b"unused")),
+        ]);
+
+        let resources = extract_frozen_modules(&[&first, &second], &["encodings.utf_8"])?;
+
+        assert_eq!(
+            resources,
+            vec![
+                Resource {
+                    name: Cow::Borrowed("encodings"),
+                    is_python_module: true,
+                    is_python_package: true,
+                    in_memory_bytecode: Some(Cow::Borrowed(b"encodings")),
+                    ..Resource::default()
+                },
+                Resource {
+                    name: Cow::Borrowed("encodings.utf_8"),
+                    is_python_module: true,
+                    in_memory_bytecode: Some(Cow::Borrowed(b"utf8")),
+                    ..Resource::default()
+                },
+            ]
+        );
+
+        assert!(extract_frozen_modules(&[&first], &["encodings.utf_8"]).is_err());
+        assert!(extract_frozen_modules(&[&second], &["missing"]).is_err());
+
+        Ok(())
+    }
+}
@@ -29,14 +29,25 @@ it can be used outside the PyOxidizer project. See the aforementioned docs
 for the canonical specification of this format.
 */
 
+mod error;
+mod frozen;
+mod merge;
 mod parser;
 mod resource;
 mod serialization;
 mod writer;
 
 pub use crate::{
-    parser::{load_resources, ResourceParserIterator},
+    error::{ParseError, ResourceContext},
+    frozen::{extract_frozen_modules, write_frozen_modules, DEFAULT_BOOTSTRAP_MODULES},
+    merge::{
+        conflicting_fields, merge_packed_resources, ConflictPolicy, MergeConflict, ResourcesMerger,
+    },
+    parser::{load_resources, validate, ResourceParserIterator},
     resource::Resource,
-    serialization::HEADER_V3,
-    writer::write_packed_resources_v3,
+    serialization::{HEADER_V3, HEADER_V4, SUPPORTED_REQUIRED_FEATURES},
+    writer::{
+        write_packed_resources_v3, write_packed_resources_v4, write_packed_resources_v4_streaming,
+        FilePayload, PayloadKey, StreamingResource,
+    },
 };
@@ -0,0 +1,388 @@
+// Copyright 2022 Gregory Szorc.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// https://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or https://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/*! Merging of multiple packed resources data structures. */
+
+use {
+    crate::{parser::load_resources, resource::Resource, writer::write_packed_resources_v4},
+    anyhow::{anyhow, Context, Result},
+    std::{collections::BTreeMap, fmt::Display, io::Write, str::FromStr},
+};
+
+/// Describes how to resolve fields defined by multiple resources having the same name.
+///
+/// Resources having the same name are always merged field by field, as
+/// [Resource::merge_from()] does. Boolean fields are combined. A conflict
+/// occurs when multiple inputs define the same data field with different values.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ConflictPolicy {
+    /// Conflicting fields are an error.
+    #[default]
+    Error,
+
+    /// The value from the first input defining a field is kept.
+    FirstWins,
+
+    /// The value from the last input defining a field is kept.
+    LastWins,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Self::Error),
+            "first-wins" => Ok(Self::FirstWins),
+            "last-wins" => Ok(Self::LastWins),
+            _ => Err(format!(
+                "invalid conflict policy {}; expected error, first-wins, or last-wins",
+                s
+            )),
+        }
+    }
+}
+
+/// Describes a resource field defined with different values by multiple inputs.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeConflict {
+    /// Name of the resource.
+    pub name: String,
+
+    /// Name of the conflicting [Resource] field.
+    pub field: &'static str,
+
+    /// Index of the first input defining the resource.
+    pub first_input: usize,
+
+    /// Index of the input whose value conflicted.
+    pub input: usize,
+}
+
+impl Display for MergeConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} field of {} defined by input #{} conflicts with input #{}",
+            self.field, self.name, self.input, self.first_input
+        )
+    }
+}
+
+/// Obtain the names of data fields defined with different values by both resources.
+///
+/// These are the fields whose value would be lost by merging one resource into
+/// the other. Boolean fields are combined by a merge and never conflict.
+pub fn conflicting_fields<'a>(a: &Resource<'a, u8>, b: &Resource<'a, u8>) -> Vec<&'static str> {
+    let mut fields = vec![];
+
+    macro_rules! check {
+        ($field:ident) => {
+            if let (Some(x), Some(y)) = (&a.$field, &b.$field) {
+                if x != y {
+                    fields.push(stringify!($field));
+                }
+            }
+        };
+    }
+
+    check!(in_memory_source);
+    check!(in_memory_bytecode);
+    check!(in_memory_bytecode_opt1);
+    check!(in_memory_bytecode_opt2);
+    check!(in_memory_extension_module_shared_library);
+    check!(in_memory_package_resources);
+    check!(in_memory_distribution_resources);
+    check!(in_memory_shared_library);
+    check!(shared_library_dependency_names);
+    check!(relative_path_module_source);
+    check!(relative_path_module_bytecode);
+    check!(relative_path_module_bytecode_opt1);
+    check!(relative_path_module_bytecode_opt2);
+    check!(relative_path_extension_module_shared_library);
+    check!(relative_path_package_resources);
+    check!(relative_path_distribution_resources);
+    check!(file_data_embedded);
+    check!(file_data_utf8_relative_path);
+
+    fields
+}
+
+/// Merges resources from multiple packed resources data structures.
+///
+/// Resource data is borrowed from the inputs. So nothing is recompiled or
+/// copied until the merged result is written.
+#[derive(Clone, Debug)]
+pub struct ResourcesMerger<'a> {
+    policy: ConflictPolicy,
+    inputs_count: usize,
+    /// Resources by name and the index of the first input defining them.
+    resources: BTreeMap<String, (usize, Resource<'a, u8>)>,
+    conflicts: Vec<MergeConflict>,
+}
+
+impl<'a> ResourcesMerger<'a> {
+    /// Construct an instance using the specified conflict policy.
+    pub fn new(policy: ConflictPolicy) -> Self {
+        Self {
+            policy,
+            inputs_count: 0,
+            resources: BTreeMap::new(),
+            conflicts: vec![],
+        }
+    }
+
+    /// Add packed resources data as the next input.
+    ///
+    /// Returns the index of the input.
+    ///
+    /// With [ConflictPolicy::Error], an error is returned if resources in this
+    /// input conflict with earlier inputs. The merger state is undefined after
+    /// an error.
+    pub fn add_packed_resources(&mut self, data: &'a [u8]) -> Result<usize> {
+        let input = self.inputs_count;
+        self.inputs_count += 1;
+
+        let resources = load_resources(data)
+            .with_context(|| format!("parsing packed resources input #{}", input))?;
+
+        for resource in resources {
+            let resource =
+                resource.with_context(|| format!("parsing packed resources input #{}", input))?;
+
+            self.add_resource(input, resource)?;
+        }
+
+        Ok(input)
+    }
+
+    /// Add a resource originating from the specified input index.
+    pub fn add_resource(&mut self, input: usize, resource: Resource<'a, u8>) -> Result<()> {
+        self.inputs_count = self.inputs_count.max(input + 1);
+
+        let (first_input, existing) = match self.resources.get_mut(resource.name.as_ref()) {
+            Some(entry) => entry,
+            None => {
+                self.resources
+                    .insert(resource.name.to_string(), (input, resource));
+                return Ok(());
+            }
+        };
+
+        let conflicts = conflicting_fields(existing, &resource)
+            .into_iter()
+            .map(|field| MergeConflict {
+                name: resource.name.to_string(),
+                field,
+                first_input: *first_input,
+                input,
+            })
+            .collect::<Vec<_>>();
+
+        if self.policy == ConflictPolicy::Error && !conflicts.is_empty() {
+            return Err(anyhow!(
+                "conflicting resources: {}",
+                conflicts
+                    .iter()
+                    .map(|c| c.to_string())
+                    .collect::<Vec<_>>()
+                    .join("; ")
+            ));
+        }
+
+        self.conflicts.extend(conflicts);
+
+        match self.policy {
+            ConflictPolicy::Error | ConflictPolicy::LastWins => {
+                existing.merge_from(resource).map_err(|e| anyhow!(e))?;
+            }
+            ConflictPolicy::FirstWins => {
+                // Merging the existing resource into the new one gives precedence
+                // to values already seen.
+                let mut merged = resource;
+                merged
+                    .merge_from(std::mem::take(existing))
+                    .map_err(|e| anyhow!(e))?;
+                *existing = merged;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Conflicts encountered so far.
+    pub fn conflicts(&self) -> &[MergeConflict] {
+        &self.conflicts
+    }
+
+    /// Iterate over merged resources, sorted by name.
+    pub fn iter_resources(&self) -> impl Iterator<Item = &Resource<'a, u8>> {
+        self.resources.values().map(|(_, resource)| resource)
+    }
+
+    /// Write merged resources as packed resources data, version 4.
+    pub fn write_packed_resources<W: Write>(&self, dest: &mut W) -> Result<()> {
+        write_packed_resources_v4(&self.iter_resources().collect::<Vec<_>>(), dest, None)
+    }
+}
+
+/// Merge multiple packed resources data structures into one.
+///
+/// Inputs are merged in order using the specified conflict policy and the
+/// result is written to `dest` as packed resources data, version 4.
+///
+/// Returns conflicts that were resolved by the policy.
+pub fn merge_packed_resources<W: Write>(
+    inputs: &[&[u8]],
+    dest: &mut W,
+    policy: ConflictPolicy,
+) -> Result<Vec<MergeConflict>> {
+    let mut merger = ResourcesMerger::new(policy);
+
+    for data in inputs {
+        merger.add_packed_resources(data)?;
+    }
+
+    merger.write_packed_resources(dest)?;
+
+    Ok(merger.conflicts)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, std::borrow::Cow};
+
+    fn packed(resources: &[Resource<u8>]) -> Vec<u8> {
+        let mut data = Vec::new();
+        write_packed_resources_v4(resources, &mut data, None).unwrap();
+        data
+    }
+
+    fn module(
+        name: &str,
+        source: Option<&'static [u8]>,
+        bytecode: Option<&'static [u8]>,
+    ) -> Resource<'static, u8> {
+        Resource {
+            name: Cow::Owned(name.to_string()),
+            is_python_module: true,
+            in_memory_source: source.map(Cow::Borrowed),
+            in_memory_bytecode: bytecode.map(Cow::Borrowed),
+            ..Resource::default()
+        }
+    }
+
+    fn merged(inputs: &[&[u8]], policy: ConflictPolicy) -> Result<(Vec<u8>, Vec<MergeConflict>)> {
+        let mut data = Vec::new();
+        let conflicts = merge_packed_resources(inputs, &mut data, policy)?;
+
+        Ok((data, conflicts))
+    }
+
+    fn parsed(data: &[u8]) -> Vec<Resource<'_, u8>> {
+        load_resources(data)
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_merge_disjoint() -> Result<()> {
+        let a = packed(&[module("foo", Some(b"foo"), None)]);
+        let b = packed(&[module("bar", Some(b"bar"), None)]);
+
+        let (data, conflicts) = merged(&[&a, &b], ConflictPolicy::Error)?;
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            parsed(&data),
+            vec![
+                module("bar", Some(b"bar"), None),
+                module("foo", Some(b"foo"), None)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_complementary_fields() -> Result<()> {
+        let a = packed(&[module("foo", Some(b"source"), None)]);
+        let b = packed(&[Resource {
+            is_python_package: true,
+            ..module("foo", None, Some(b"bytecode"))
+        }]);
+
+        let (data, conflicts) = merged(&[&a, &b], ConflictPolicy::Error)?;
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            parsed(&data),
+            vec![Resource {
+                is_python_package: true,
+                ..module("foo", Some(b"source"), Some(b"bytecode"))
+            }]
+        );
+
+        // Identical values don't conflict.
+        let (data, conflicts) = merged(&[&a, &a], ConflictPolicy::Error)?;
+        assert!(conflicts.is_empty());
+        assert_eq!(parsed(&data), vec![module("foo", Some(b"source"), None)]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_conflicts() -> Result<()> {
+        let a = packed(&[module("foo", Some(b"first"), Some(b"bytecode"))]);
+        let b = packed(&[module("foo", Some(b"second"), None)]);
+
+        let err = merged(&[&a, &b], ConflictPolicy::Error).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "conflicting resources: in_memory_source field of foo defined by input #1 conflicts with input #0"
+        );
+
+        let expected_conflicts = vec![MergeConflict {
+            name: "foo".to_string(),
+            field: "in_memory_source",
+            first_input: 0,
+            input: 1,
+        }];
+
+        let (data, conflicts) = merged(&[&a, &b], ConflictPolicy::FirstWins)?;
+        assert_eq!(conflicts, expected_conflicts);
+        assert_eq!(
+            parsed(&data),
+            vec![module("foo", Some(b"first"), Some(b"bytecode"))]
+        );
+
+        let (data, conflicts) = merged(&[&a, &b], ConflictPolicy::LastWins)?;
+        assert_eq!(conflicts, expected_conflicts);
+        assert_eq!(
+            parsed(&data),
+            vec![module("foo", Some(b"second"), Some(b"bytecode"))]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_invalid_input() {
+        let a = packed(&[module("foo", Some(b"foo"), None)]);
+
+        let err = merged(&[&a, b"garbage"], ConflictPolicy::Error).unwrap_err();
+        assert_eq!(err.to_string(), "parsing packed resources input #1");
+    }
+
+    #[test]
+    fn test_conflict_policy_from_str() {
+        assert_eq!("error".parse(), Ok(ConflictPolicy::Error));
+        assert_eq!("first-wins".parse(), Ok(ConflictPolicy::FirstWins));
+        assert_eq!("last-wins".parse(), Ok(ConflictPolicy::LastWins));
+        assert!("bogus".parse::<ConflictPolicy>().is_err());
+    }
+}
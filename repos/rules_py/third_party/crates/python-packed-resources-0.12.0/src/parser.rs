@@ -10,8 +10,12 @@
 
 use {
     crate::{
+        error::{ParseError, ResourceContext},
         resource::Resource,
-        serialization::{BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3},
+        serialization::{
+            BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3, HEADER_V4,
+            SUPPORTED_REQUIRED_FEATURES,
+        },
     },
     byteorder::{LittleEndian, ReadBytesExt},
     std::{borrow::Cow, collections::HashMap, io::Cursor, path::Path},
@@ -22,6 +26,16 @@ use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
 #[cfg(windows)]
 use {std::ffi::OsString, std::os::windows::ffi::OsStringExt, std::path::PathBuf};
 
+/// Length of the version 3 global header following the format header.
+///
+/// Blob section count + blob index length + resources count + resources index length.
+const GLOBAL_HEADER_V3_LENGTH: usize = 1 + 4 + 4 + 4;
+
+/// Length of the version 4 global header following the format header.
+///
+/// Version 3 global header + required features.
+const GLOBAL_HEADER_V4_LENGTH: usize = GLOBAL_HEADER_V3_LENGTH + 8;
+
 /// Represents a blob section in the blob index.
 #[derive(Debug)]
 struct BlobSection {
@@ -33,7 +47,9 @@ struct BlobSection {
 /// Holds state used to read an individual blob section.
 #[derive(Clone, Copy, Debug)]
 struct BlobSectionReadState {
+    start: usize,
     offset: usize,
+    end: usize,
     interior_padding: BlobInteriorPadding,
 }
 
@@ -41,13 +57,19 @@ struct BlobSectionReadState {
 ///
 /// The iterator emits [Resource] instances. The index data for a given resource is
 /// not read or validated until the iterator attempts to deserialize it.
+///
+/// Iteration stops after the first error.
 pub struct ResourceParserIterator<'a> {
     done: bool,
     data: &'a [u8],
     reader: Cursor<&'a [u8]>,
     blob_sections: [Option<BlobSectionReadState>; 256],
+    resources_index_start: usize,
+    blobs_end: usize,
     claimed_resources_count: usize,
     read_resources_count: usize,
+    /// Whether resources index fields are prefixed by their length (version 4).
+    field_lengths: bool,
 }
 
 impl<'a> ResourceParserIterator<'a> {
@@ -56,64 +78,238 @@ impl<'a> ResourceParserIterator<'a> {
         self.claimed_resources_count
     }
 
+    /// Resolve an offset within the parsed data to an offset within the original data.
+    fn absolute_offset(offset: usize) -> usize {
+        HEADER_V3.len() + offset
+    }
+
+    fn resource_context(&self, name: Option<&str>) -> ResourceContext {
+        ResourceContext {
+            index: self.read_resources_count.saturating_sub(1),
+            name: name.map(|name| name.to_string()),
+        }
+    }
+
+    /// Read an unsigned little endian integer of `width` bytes from the resources index.
+    fn read_length(
+        &mut self,
+        width: usize,
+        name: Option<&str>,
+        context: &'static str,
+    ) -> Result<usize, ParseError> {
+        let position = self.reader.position() as usize;
+
+        match self.reader.read_uint::<LittleEndian>(width) {
+            // Values not representable as usize can never be in bounds. So saturate
+            // and let bounds checking reject them.
+            Ok(value) => Ok(usize::try_from(value).unwrap_or(usize::MAX)),
+            Err(_) => Err(ParseError::Truncated {
+                offset: Self::absolute_offset(position),
+                resource: Some(self.resource_context(name)),
+                context,
+                expected: width,
+                actual: self.reader.get_ref().len().saturating_sub(position),
+            }),
+        }
+    }
+
+    /// Compute a safe capacity for a collection holding `count` index entries.
+    ///
+    /// Counts come from untrusted data. So we cap them by the number of entries
+    /// the remainder of the index could hold.
+    fn bounded_capacity(&self, count: usize, entry_length: usize) -> usize {
+        let remaining = self
+            .reader
+            .get_ref()
+            .len()
+            .saturating_sub(self.reader.position() as usize);
+
+        count.min(remaining / entry_length)
+    }
+
     /// Resolve a slice to an individual blob's data.
     ///
-    /// This accepts a reference to the original blobs payload, an array of
-    /// current blob section offsets, the resource field being accessed, and the
-    /// length of the blob and returns a slice to that blob.
-    fn resolve_blob_data(&mut self, resource_field: ResourceField, length: usize) -> &'a [u8] {
-        let mut state = self.blob_sections[resource_field as usize]
-            .as_mut()
-            .expect("blob state not found");
-
-        let blob = &self.data[state.offset..state.offset + length];
-
-        let increment = match &state.interior_padding {
-            BlobInteriorPadding::None => length,
-            BlobInteriorPadding::Null => length + 1,
+    /// This accepts the resource field being accessed and the length of the blob and
+    /// returns the offset of that blob and a slice to it. The blob section's read
+    /// offset is advanced past the blob and any interior padding.
+    fn resolve_blob(
+        &mut self,
+        name: Option<&str>,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<(usize, &'a [u8]), ParseError> {
+        let mut state = if let Some(state) = self.blob_sections[resource_field as usize] {
+            state
+        } else {
+            return Err(ParseError::MissingBlobSection {
+                offset: Self::absolute_offset(self.reader.position() as usize),
+                resource: self.resource_context(name),
+                field: resource_field,
+            });
+        };
+
+        let padding = match state.interior_padding {
+            BlobInteriorPadding::None => 0,
+            BlobInteriorPadding::Null => 1,
         };
 
+        let available = state.end - state.offset;
+
+        let increment = match length.checked_add(padding) {
+            Some(increment) if increment <= available => increment,
+            _ => {
+                return Err(ParseError::BlobOutOfBounds {
+                    offset: Self::absolute_offset(state.offset),
+                    resource: self.resource_context(name),
+                    field: resource_field,
+                    expected: length.saturating_add(padding),
+                    actual: available,
+                });
+            }
+        };
+
+        let offset = state.offset;
+        let blob = &self.data[offset..offset + length];
+
+        if padding == 1 && self.data[offset + length] != b'\0' {
+            return Err(ParseError::InvalidPadding {
+                offset: Self::absolute_offset(offset + length),
+                resource: self.resource_context(name),
+                field: resource_field,
+                value: self.data[offset + length],
+            });
+        }
+
         state.offset += increment;
+        self.blob_sections[resource_field as usize] = Some(state);
+
+        Ok((offset, blob))
+    }
 
-        blob
+    fn resolve_blob_data(
+        &mut self,
+        name: Option<&str>,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a [u8], ParseError> {
+        Ok(self.resolve_blob(name, resource_field, length)?.1)
+    }
+
+    fn resolve_str(
+        &mut self,
+        name: Option<&str>,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<&'a str, ParseError> {
+        let (offset, data) = self.resolve_blob(name, resource_field, length)?;
+
+        std::str::from_utf8(data).map_err(|_| ParseError::InvalidUtf8 {
+            offset: Self::absolute_offset(offset),
+            resource: self.resource_context(name),
+            field: resource_field,
+        })
     }
 
     #[cfg(unix)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let path_str = OsStr::from_bytes(self.resolve_blob_data(resource_field, length));
-        Cow::Borrowed(Path::new(path_str))
+    fn resolve_path(
+        &mut self,
+        name: Option<&str>,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, ParseError> {
+        let path_str = OsStr::from_bytes(self.resolve_blob_data(name, resource_field, length)?);
+        Ok(Cow::Borrowed(Path::new(path_str)))
     }
 
     #[cfg(windows)]
-    fn resolve_path(&mut self, resource_field: ResourceField, length: usize) -> Cow<'a, Path> {
-        let raw = self.resolve_blob_data(resource_field, length);
-        let raw = unsafe { std::slice::from_raw_parts(raw.as_ptr() as *const u16, raw.len() / 2) };
+    fn resolve_path(
+        &mut self,
+        name: Option<&str>,
+        resource_field: ResourceField,
+        length: usize,
+    ) -> Result<Cow<'a, Path>, ParseError> {
+        let (offset, raw) = self.resolve_blob(name, resource_field, length)?;
+
+        // Paths are stored as UTF-16 code units. An odd number of bytes can't be one.
+        if raw.len() % 2 != 0 {
+            return Err(ParseError::InvalidPath {
+                offset: Self::absolute_offset(offset),
+                resource: self.resource_context(name),
+                field: resource_field,
+            });
+        }
+
+        // The blob isn't guaranteed to be aligned for u16. So decode code units
+        // individually rather than reinterpreting the slice.
+        let raw = raw
+            .chunks_exact(2)
+            .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
 
         // There isn't an API that lets us get a OsStr from &[u16]. So we need to use
         // owned types.
-        let path_string = OsString::from_wide(raw);
+        let path_string = OsString::from_wide(&raw);
 
-        Cow::Owned(PathBuf::from(path_string))
+        Ok(Cow::Owned(PathBuf::from(path_string)))
     }
 
-    fn parse_next(&mut self) -> Result<Option<Resource<'a, u8>>, &'static str> {
+    fn parse_next(&mut self) -> Result<Option<Resource<'a, u8>>, ParseError> {
         let mut current_resource = Resource::default();
         let mut current_resource_name = None;
 
         loop {
-            let field_type = self
-                .reader
-                .read_u8()
-                .map_err(|_| "failed reading field type")?;
+            let position = self.reader.position() as usize;
+
+            let field_type = self.read_length(1, current_resource_name, "field type")? as u8;
+
+            // Version 4 prefixes every non-structural field with the length of its
+            // index data. This allows us to skip fields we don't know about.
+            let field_end = if self.field_lengths && !matches!(field_type, 0x00 | 0x01 | 0xff) {
+                let length = self.read_length(4, current_resource_name, "field length")?;
+                let start = self.reader.position() as usize;
+                let available = self.reader.get_ref().len() - start;
+
+                if length > available {
+                    return Err(ParseError::Truncated {
+                        offset: Self::absolute_offset(start),
+                        resource: Some(self.resource_context(current_resource_name)),
+                        context: "field data",
+                        expected: length,
+                        actual: available,
+                    });
+                }
 
-            let field_type = ResourceField::try_from(field_type)?;
+                Some((start, start + length))
+            } else {
+                None
+            };
+
+            let field_type = match ResourceField::try_from(field_type) {
+                Ok(field_type) => field_type,
+                Err(_) => {
+                    if let Some((_, end)) = field_end {
+                        self.reader.set_position(end as u64);
+                        continue;
+                    }
+
+                    return Err(ParseError::InvalidResourceField {
+                        offset: Self::absolute_offset(position),
+                        resource: self.resource_context(current_resource_name),
+                        value: field_type,
+                    });
+                }
+            };
 
             match field_type {
                 ResourceField::EndOfIndex => {
                     self.done = true;
 
                     if self.read_resources_count != self.claimed_resources_count {
-                        return Err("mismatch between advertised index count and actual");
+                        return Err(ParseError::ResourcesCountMismatch {
+                            offset: Self::absolute_offset(position),
+                            expected: self.claimed_resources_count,
+                            actual: self.read_resources_count,
+                        });
                     }
 
                     return Ok(None);
@@ -127,21 +323,18 @@ impl<'a> ResourceParserIterator<'a> {
                     let res = if current_resource_name.is_some() {
                         Ok(Some(current_resource))
                     } else {
-                        Err("resource name field is required")
+                        Err(ParseError::MissingResourceName {
+                            offset: Self::absolute_offset(position),
+                            resource: self.resource_context(None),
+                        })
                     };
 
                     return res;
                 }
                 ResourceField::Name => {
-                    let l = self
-                        .reader
-                        .read_u16::<LittleEndian>()
-                        .map_err(|_| "failed reading resource name length")?
-                        as usize;
-
-                    let name = unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
-                    };
+                    let l = self.read_length(2, None, "resource name length")?;
+
+                    let name = self.resolve_str(None, field_type, l)?;
 
                     current_resource_name = Some(name);
                     current_resource.name = Cow::Borrowed(name);
@@ -153,85 +346,70 @@ impl<'a> ResourceParserIterator<'a> {
                     current_resource.is_python_namespace_package = true;
                 }
                 ResourceField::InMemorySource => {
-                    let l = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading source length")?
-                        as usize;
-
-                    current_resource.in_memory_source =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                    let l = self.read_length(4, current_resource_name, "source length")?;
+
+                    current_resource.in_memory_source = Some(Cow::Borrowed(
+                        self.resolve_blob_data(current_resource_name, field_type, l)?,
+                    ));
                 }
                 ResourceField::InMemoryBytecode => {
-                    let l = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading bytecode length")?
-                        as usize;
-
-                    current_resource.in_memory_bytecode =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                    let l = self.read_length(4, current_resource_name, "bytecode length")?;
+
+                    current_resource.in_memory_bytecode = Some(Cow::Borrowed(
+                        self.resolve_blob_data(current_resource_name, field_type, l)?,
+                    ));
                 }
                 ResourceField::InMemoryBytecodeOpt1 => {
-                    let l = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading bytecode length")?
-                        as usize;
-
-                    current_resource.in_memory_bytecode_opt1 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                    let l = self.read_length(4, current_resource_name, "bytecode length")?;
+
+                    current_resource.in_memory_bytecode_opt1 = Some(Cow::Borrowed(
+                        self.resolve_blob_data(current_resource_name, field_type, l)?,
+                    ));
                 }
                 ResourceField::InMemoryBytecodeOpt2 => {
-                    let l = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading bytecode length")?
-                        as usize;
-
-                    current_resource.in_memory_bytecode_opt2 =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                    let l = self.read_length(4, current_resource_name, "bytecode length")?;
+
+                    current_resource.in_memory_bytecode_opt2 = Some(Cow::Borrowed(
+                        self.resolve_blob_data(current_resource_name, field_type, l)?,
+                    ));
                 }
                 ResourceField::InMemoryExtensionModuleSharedLibrary => {
-                    let l = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading extension module length")?
-                        as usize;
+                    let l =
+                        self.read_length(4, current_resource_name, "extension module length")?;
 
                     current_resource.in_memory_extension_module_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                        Some(Cow::Borrowed(self.resolve_blob_data(
+                            current_resource_name,
+                            field_type,
+                            l,
+                        )?));
                 }
 
                 ResourceField::InMemoryResourcesData => {
-                    let resource_count = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading resources length")?
-                        as usize;
+                    let resource_count =
+                        self.read_length(4, current_resource_name, "resources length")?;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources =
+                        HashMap::with_capacity(self.bounded_capacity(resource_count, 10));
 
                     for _ in 0..resource_count {
-                        let resource_name_length = self
-                            .reader
-                            .read_u16::<LittleEndian>()
-                            .map_err(|_| "failed reading resource name")?
-                            as usize;
-
-                        let resource_name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
-                            )
-                        };
-
-                        let resource_length = self
-                            .reader
-                            .read_u64::<LittleEndian>()
-                            .map_err(|_| "failed reading resource length")?
-                            as usize;
-
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let resource_name_length =
+                            self.read_length(2, current_resource_name, "resource name")?;
+
+                        let resource_name = self.resolve_str(
+                            current_resource_name,
+                            field_type,
+                            resource_name_length,
+                        )?;
+
+                        let resource_length =
+                            self.read_length(8, current_resource_name, "resource length")?;
+
+                        let resource_data = self.resolve_blob_data(
+                            current_resource_name,
+                            field_type,
+                            resource_length,
+                        )?;
 
                         resources
                             .insert(Cow::Borrowed(resource_name), Cow::Borrowed(resource_data));
@@ -241,33 +419,33 @@ impl<'a> ResourceParserIterator<'a> {
                 }
 
                 ResourceField::InMemoryDistributionResource => {
-                    let resource_count = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading package distribution length")?
-                        as usize;
+                    let resource_count =
+                        self.read_length(4, current_resource_name, "package distribution length")?;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources =
+                        HashMap::with_capacity(self.bounded_capacity(resource_count, 10));
 
                     for _ in 0..resource_count {
-                        let name_length = self
-                            .reader
-                            .read_u16::<LittleEndian>()
-                            .map_err(|_| "failed reading distribution metadata name")?
-                            as usize;
-
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
-
-                        let resource_length =
-                            self.reader.read_u64::<LittleEndian>().map_err(|_| {
-                                "failed reading package distribution resource length"
-                            })? as usize;
-
-                        let resource_data = self.resolve_blob_data(field_type, resource_length);
+                        let name_length = self.read_length(
+                            2,
+                            current_resource_name,
+                            "distribution metadata name",
+                        )?;
+
+                        let name =
+                            self.resolve_str(current_resource_name, field_type, name_length)?;
+
+                        let resource_length = self.read_length(
+                            8,
+                            current_resource_name,
+                            "package distribution resource length",
+                        )?;
+
+                        let resource_data = self.resolve_blob_data(
+                            current_resource_name,
+                            field_type,
+                            resource_length,
+                        )?;
 
                         resources.insert(Cow::Borrowed(name), Cow::Borrowed(resource_data));
                     }
@@ -276,36 +454,35 @@ impl<'a> ResourceParserIterator<'a> {
                 }
 
                 ResourceField::InMemorySharedLibrary => {
-                    let l = self
-                        .reader
-                        .read_u64::<LittleEndian>()
-                        .map_err(|_| "failed reading in-memory shared library length")?
-                        as usize;
-
-                    current_resource.in_memory_shared_library =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                    let l = self.read_length(
+                        8,
+                        current_resource_name,
+                        "in-memory shared library length",
+                    )?;
+
+                    current_resource.in_memory_shared_library = Some(Cow::Borrowed(
+                        self.resolve_blob_data(current_resource_name, field_type, l)?,
+                    ));
                 }
 
                 ResourceField::SharedLibraryDependencyNames => {
-                    let names_count = self
-                        .reader
-                        .read_u16::<LittleEndian>()
-                        .map_err(|_| "failed reading shared library dependency names length")?
-                        as usize;
+                    let names_count = self.read_length(
+                        2,
+                        current_resource_name,
+                        "shared library dependency names length",
+                    )?;
 
-                    let mut names = Vec::new();
+                    let mut names = Vec::with_capacity(self.bounded_capacity(names_count, 2));
 
                     for _ in 0..names_count {
-                        let name_length =
-                            self.reader.read_u16::<LittleEndian>().map_err(|_| {
-                                "failed reading shared library dependency name length"
-                            })? as usize;
+                        let name_length = self.read_length(
+                            2,
+                            current_resource_name,
+                            "shared library dependency name length",
+                        )?;
 
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
+                        let name =
+                            self.resolve_str(current_resource_name, field_type, name_length)?;
 
                         names.push(Cow::Borrowed(name));
                     }
@@ -314,86 +491,90 @@ impl<'a> ResourceParserIterator<'a> {
                 }
 
                 ResourceField::RelativeFilesystemModuleSource => {
-                    let path_length = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading Python module relative path length")?
-                        as usize;
+                    let path_length = self.read_length(
+                        4,
+                        current_resource_name,
+                        "Python module relative path length",
+                    )?;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(current_resource_name, field_type, path_length)?;
 
                     current_resource.relative_path_module_source = Some(path);
                 }
 
                 ResourceField::RelativeFilesystemModuleBytecode => {
-                    let path_length =
-                        self.reader.read_u32::<LittleEndian>().map_err(|_| {
-                            "failed reading Python module bytecode relative path length"
-                        })? as usize;
+                    let path_length = self.read_length(
+                        4,
+                        current_resource_name,
+                        "Python module bytecode relative path length",
+                    )?;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(current_resource_name, field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode = Some(path);
                 }
 
                 ResourceField::RelativeFilesystemModuleBytecodeOpt1 => {
-                    let path_length = self.reader.read_u32::<LittleEndian>().map_err(|_| {
-                        "failed reading Python module bytecode opt 1 relative path length"
-                    })? as usize;
+                    let path_length = self.read_length(
+                        4,
+                        current_resource_name,
+                        "Python module bytecode opt 1 relative path length",
+                    )?;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(current_resource_name, field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt1 = Some(path);
                 }
 
                 ResourceField::RelativeFilesystemModuleBytecodeOpt2 => {
-                    let path_length = self.reader.read_u32::<LittleEndian>().map_err(|_| {
-                        "failed reading Python module bytecode opt 2 relative path length"
-                    })? as usize;
+                    let path_length = self.read_length(
+                        4,
+                        current_resource_name,
+                        "Python module bytecode opt 2 relative path length",
+                    )?;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(current_resource_name, field_type, path_length)?;
 
                     current_resource.relative_path_module_bytecode_opt2 = Some(path);
                 }
 
                 ResourceField::RelativeFilesystemExtensionModuleSharedLibrary => {
-                    let path_length = self.reader.read_u32::<LittleEndian>().map_err(|_| {
-                        "failed reading Python extension module shared library relative path length"
-                    })? as usize;
+                    let path_length = self.read_length(
+                        4,
+                        current_resource_name,
+                        "Python extension module shared library relative path length",
+                    )?;
 
-                    let path = self.resolve_path(field_type, path_length);
+                    let path = self.resolve_path(current_resource_name, field_type, path_length)?;
 
                     current_resource.relative_path_extension_module_shared_library = Some(path);
                 }
 
                 ResourceField::RelativeFilesystemPackageResources => {
-                    let resource_count =
-                        self.reader.read_u32::<LittleEndian>().map_err(|_| {
-                            "failed reading package resources relative path item count"
-                        })? as usize;
+                    let resource_count = self.read_length(
+                        4,
+                        current_resource_name,
+                        "package resources relative path item count",
+                    )?;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources =
+                        HashMap::with_capacity(self.bounded_capacity(resource_count, 6));
 
                     for _ in 0..resource_count {
-                        let resource_name_length = self
-                            .reader
-                            .read_u16::<LittleEndian>()
-                            .map_err(|_| "failed reading resource name")?
-                            as usize;
-
-                        let resource_name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, resource_name_length),
-                            )
-                        };
-
-                        let path_length = self
-                            .reader
-                            .read_u32::<LittleEndian>()
-                            .map_err(|_| "failed reading resource path length")?
-                            as usize;
-
-                        let path = self.resolve_path(field_type, path_length);
+                        let resource_name_length =
+                            self.read_length(2, current_resource_name, "resource name")?;
+
+                        let resource_name = self.resolve_str(
+                            current_resource_name,
+                            field_type,
+                            resource_name_length,
+                        )?;
+
+                        let path_length =
+                            self.read_length(4, current_resource_name, "resource path length")?;
+
+                        let path =
+                            self.resolve_path(current_resource_name, field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(resource_name), path);
                     }
@@ -402,32 +583,33 @@ impl<'a> ResourceParserIterator<'a> {
                 }
 
                 ResourceField::RelativeFilesystemDistributionResource => {
-                    let resource_count = self.reader.read_u32::<LittleEndian>().map_err(|_| {
-                        "failed reading package distribution relative path item count"
-                    })? as usize;
+                    let resource_count = self.read_length(
+                        4,
+                        current_resource_name,
+                        "package distribution relative path item count",
+                    )?;
 
-                    let mut resources = HashMap::with_capacity(resource_count);
+                    let mut resources =
+                        HashMap::with_capacity(self.bounded_capacity(resource_count, 6));
 
                     for _ in 0..resource_count {
-                        let name_length = self
-                            .reader
-                            .read_u16::<LittleEndian>()
-                            .map_err(|_| "failed reading package distribution metadata name")?
-                            as usize;
-
-                        let name = unsafe {
-                            std::str::from_utf8_unchecked(
-                                self.resolve_blob_data(field_type, name_length),
-                            )
-                        };
-
-                        let path_length = self
-                            .reader
-                            .read_u32::<LittleEndian>()
-                            .map_err(|_| "failed reading package distribution path length")?
-                            as usize;
-
-                        let path = self.resolve_path(field_type, path_length);
+                        let name_length = self.read_length(
+                            2,
+                            current_resource_name,
+                            "package distribution metadata name",
+                        )?;
+
+                        let name =
+                            self.resolve_str(current_resource_name, field_type, name_length)?;
+
+                        let path_length = self.read_length(
+                            4,
+                            current_resource_name,
+                            "package distribution path length",
+                        )?;
+
+                        let path =
+                            self.resolve_path(current_resource_name, field_type, path_length)?;
 
                         resources.insert(Cow::Borrowed(name), path);
                     }
@@ -464,34 +646,88 @@ impl<'a> ResourceParserIterator<'a> {
                 }
 
                 ResourceField::FileDataEmbedded => {
-                    let l = self
-                        .reader
-                        .read_u64::<LittleEndian>()
-                        .map_err(|_| "failed reading embedded file data length")?
-                        as usize;
+                    let l =
+                        self.read_length(8, current_resource_name, "embedded file data length")?;
 
-                    current_resource.file_data_embedded =
-                        Some(Cow::Borrowed(self.resolve_blob_data(field_type, l)));
+                    current_resource.file_data_embedded = Some(Cow::Borrowed(
+                        self.resolve_blob_data(current_resource_name, field_type, l)?,
+                    ));
                 }
 
                 ResourceField::FileDataUtf8RelativePath => {
-                    let l = self
-                        .reader
-                        .read_u32::<LittleEndian>()
-                        .map_err(|_| "failed reading file data relative path length")?
-                        as usize;
-
-                    current_resource.file_data_utf8_relative_path = Some(Cow::Borrowed(unsafe {
-                        std::str::from_utf8_unchecked(self.resolve_blob_data(field_type, l))
-                    }));
+                    let l = self.read_length(
+                        4,
+                        current_resource_name,
+                        "file data relative path length",
+                    )?;
+
+                    current_resource.file_data_utf8_relative_path = Some(Cow::Borrowed(
+                        self.resolve_str(current_resource_name, field_type, l)?,
+                    ));
+                }
+            }
+
+            if let Some((start, end)) = field_end {
+                let consumed = self.reader.position() as usize;
+
+                if consumed != end {
+                    return Err(ParseError::FieldLengthMismatch {
+                        offset: Self::absolute_offset(position),
+                        resource: self.resource_context(current_resource_name),
+                        field: field_type,
+                        expected: end - start,
+                        actual: consumed - start,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Verify that parsing consumed exactly the advertised data.
+    ///
+    /// Must only be called after the iterator has been exhausted without error.
+    fn verify_consumed(&self) -> Result<(), ParseError> {
+        let index_end = self.reader.get_ref().len();
+        let position = self.reader.position() as usize;
+
+        if position != index_end {
+            return Err(ParseError::IndexLengthMismatch {
+                index: "resources",
+                expected: index_end - self.resources_index_start,
+                actual: position - self.resources_index_start,
+            });
+        }
+
+        for (field, state) in self.blob_sections.iter().enumerate() {
+            // Blob data for fields we skipped is never consumed.
+            if self.field_lengths && ResourceField::try_from(field as u8).is_err() {
+                continue;
+            }
+
+            if let Some(state) = state {
+                if state.offset != state.end {
+                    return Err(ParseError::BlobSectionLengthMismatch {
+                        field: field as u8,
+                        expected: state.end - state.start,
+                        actual: state.offset - state.start,
+                    });
                 }
             }
         }
+
+        if self.blobs_end != self.data.len() {
+            return Err(ParseError::TrailingData {
+                offset: Self::absolute_offset(self.blobs_end),
+                length: self.data.len() - self.blobs_end,
+            });
+        }
+
+        Ok(())
     }
 }
 
 impl<'a> Iterator for ResourceParserIterator<'a> {
-    type Item = Result<Resource<'a, u8>, &'static str>;
+    type Item = Result<Resource<'a, u8>, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.done {
@@ -500,7 +736,11 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
 
         match self.parse_next() {
             Ok(res) => res.map(Ok),
-            Err(e) => Some(Err(e)),
+            Err(e) => {
+                // Index state is undefined after an error. So stop iterating.
+                self.done = true;
+                Some(Err(e))
+            }
         }
     }
 }
@@ -510,40 +750,149 @@ impl<'a> Iterator for ResourceParserIterator<'a> {
 /// The data structure is parsed lazily via an iterator that emits reconstructed
 /// [Resource] instances.
 ///
+/// Every length is bounds checked against the data it refers to, so malformed
+/// input results in a [ParseError] rather than a panic. However, only the parts
+/// of the data referenced by emitted resources are examined. Use [validate()]
+/// to check an entire data structure up front.
+///
 /// Performance note: we once attempted to switch to anyhow for error handling and
 /// this decreased performance by ~15%. Given the performance sensitivity of this
-/// code, we need to keep error handling primitive.
-pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+/// code, we need to keep error handling primitive. [ParseError] is a plain enum
+/// that is only populated when an error occurs.
+pub fn load_resources<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, ParseError> {
     if data.len() < HEADER_V3.len() {
-        return Err("error reading 8 byte header");
+        return Err(ParseError::HeaderTooShort { actual: data.len() });
     }
 
     let header = &data[0..8];
 
     if header == HEADER_V3 {
-        load_resources_v3(&data[8..])
+        load_resources_v3(&data[8..], false)
+    } else if header == HEADER_V4 {
+        load_resources_v3(&data[8..], true)
     } else {
-        Err("unrecognized file format")
+        Err(ParseError::UnrecognizedHeader)
+    }
+}
+
+/// Validate a packed resources data structure in its entirety.
+///
+/// In addition to parsing every resource, this verifies that the advertised
+/// index lengths match their content, that every blob section is consumed
+/// exactly by the resources referencing it, that interior padding is well-formed,
+/// and that no data follows the last blob section.
+///
+/// String fields, including [Resource::file_data_utf8_relative_path], must be
+/// valid UTF-8. Filesystem path fields are stored in the platform's native
+/// encoding and are not required to be UTF-8: on Windows they must consist of
+/// whole UTF-16 code units and elsewhere any bytes are accepted.
+///
+/// This never panics, regardless of input. On success, returns the number of
+/// resources in the data structure.
+pub fn validate(data: &[u8]) -> Result<usize, ParseError> {
+    let mut resources = load_resources(data)?;
+
+    let mut count = 0;
+    for resource in &mut resources {
+        resource?;
+        count += 1;
     }
+
+    resources.verify_consumed()?;
+
+    Ok(count)
 }
 
-fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &'static str> {
+/// Parse version 3 or version 4 data following the format header.
+///
+/// Version 4 is a superset of version 3. So we share the implementation.
+fn load_resources_v3<'a>(
+    data: &'a [u8],
+    v4: bool,
+) -> Result<ResourceParserIterator<'a>, ParseError> {
     let mut reader = Cursor::new(data);
 
+    let global_header_length = if v4 {
+        GLOBAL_HEADER_V4_LENGTH
+    } else {
+        GLOBAL_HEADER_V3_LENGTH
+    };
+
+    let truncated = |context, expected| ParseError::Truncated {
+        offset: HEADER_V3.len(),
+        resource: None,
+        context,
+        expected,
+        actual: data.len(),
+    };
+
+    if data.len() < global_header_length {
+        return Err(truncated("global header", global_header_length));
+    }
+
     let blob_section_count = reader
         .read_u8()
-        .map_err(|_| "failed reading blob section count")?;
+        .map_err(|_| truncated("blob section count", 1))?;
     let blob_index_length = reader
         .read_u32::<LittleEndian>()
-        .map_err(|_| "failed reading blob index length")? as usize;
+        .map_err(|_| truncated("blob index length", 4))? as usize;
     let resources_count = reader
         .read_u32::<LittleEndian>()
-        .map_err(|_| "failed reading resources count")? as usize;
+        .map_err(|_| truncated("resources count", 4))? as usize;
     let resources_index_length = reader
         .read_u32::<LittleEndian>()
-        .map_err(|_| "failed reading resources index length")?
+        .map_err(|_| truncated("resources index length", 4))?
         as usize;
 
+    if v4 {
+        let required_features = reader
+            .read_u64::<LittleEndian>()
+            .map_err(|_| truncated("required features", 8))?;
+
+        if required_features & !SUPPORTED_REQUIRED_FEATURES != 0 {
+            return Err(ParseError::UnsupportedRequiredFeatures {
+                required: required_features,
+                supported: SUPPORTED_REQUIRED_FEATURES,
+            });
+        }
+    }
+
+    // Global payload offset where blobs data starts.
+    let blob_start_offset = global_header_length
+        .checked_add(blob_index_length)
+        .and_then(|v| v.checked_add(resources_index_length))
+        .filter(|end| *end <= data.len())
+        .ok_or(ParseError::Truncated {
+            offset: HEADER_V3.len() + global_header_length,
+            resource: None,
+            context: "indices",
+            expected: blob_index_length.saturating_add(resources_index_length),
+            actual: data.len() - global_header_length,
+        })?;
+
+    // Every resource entry occupies at least 1 byte of the index. So a larger
+    // count can only come from corrupt data. Rejecting it prevents consumers from
+    // making huge allocations based on the advertised count.
+    if resources_count > resources_index_length {
+        return Err(ParseError::ImplausibleResourcesCount {
+            count: resources_count,
+            index_length: resources_index_length,
+        });
+    }
+
+    // Reading the blob index must not run into the resources index.
+    let blob_index_end = global_header_length + blob_index_length;
+    let mut blob_reader = Cursor::new(&data[..blob_index_end]);
+    blob_reader.set_position(global_header_length as u64);
+
+    let blob_truncated = |position: u64, context, expected| ParseError::Truncated {
+        offset: HEADER_V3.len() + position as usize,
+        resource: None,
+        context,
+        expected,
+        actual: blob_index_end.saturating_sub(position as usize),
+    };
+
     let mut current_blob_field = None;
     let mut current_blob_raw_payload_length = None;
     let mut current_blob_interior_padding = None;
@@ -552,11 +901,18 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
 
     if blob_section_count != 0 || blob_index_length != 0 {
         loop {
-            let field_type = reader
+            let position = blob_reader.position();
+
+            let field_type = blob_reader
                 .read_u8()
-                .map_err(|_| "failed reading blob section field type")?;
+                .map_err(|_| blob_truncated(position, "blob section field type", 1))?;
 
-            let field_type = BlobSectionField::try_from(field_type)?;
+            let field_type = BlobSectionField::try_from(field_type).map_err(|_| {
+                ParseError::InvalidBlobSectionField {
+                    offset: HEADER_V3.len() + position as usize,
+                    value: field_type,
+                }
+            })?;
 
             match field_type {
                 BlobSectionField::EndOfIndex => break,
@@ -567,16 +923,19 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                     current_blob_interior_padding = None;
                 }
                 BlobSectionField::EndOfEntry => {
-                    if current_blob_field.is_none() {
-                        return Err("blob resource field is required");
-                    }
-                    if current_blob_raw_payload_length.is_none() {
-                        return Err("blob raw payload length is required");
-                    }
+                    let incomplete = |missing| ParseError::IncompleteBlobSection {
+                        offset: HEADER_V3.len() + position as usize,
+                        missing,
+                    };
+
+                    let resource_field =
+                        current_blob_field.ok_or_else(|| incomplete("resource field"))?;
+                    let raw_payload_length = current_blob_raw_payload_length
+                        .ok_or_else(|| incomplete("raw payload length"))?;
 
                     blob_sections.push(BlobSection {
-                        resource_field: current_blob_field.unwrap(),
-                        raw_payload_length: current_blob_raw_payload_length.unwrap(),
+                        resource_field,
+                        raw_payload_length,
                         interior_padding: current_blob_interior_padding,
                     });
 
@@ -585,26 +944,32 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
                     current_blob_interior_padding = None;
                 }
                 BlobSectionField::ResourceFieldType => {
-                    let field = reader
-                        .read_u8()
-                        .map_err(|_| "failed reading blob resource field value")?;
+                    let field = blob_reader.read_u8().map_err(|_| {
+                        blob_truncated(position + 1, "blob resource field value", 1)
+                    })?;
                     current_blob_field = Some(field);
                 }
                 BlobSectionField::RawPayloadLength => {
-                    let l = reader
+                    let l = blob_reader
                         .read_u64::<LittleEndian>()
-                        .map_err(|_| "failed reading raw payload length")?;
-                    current_blob_raw_payload_length = Some(l as usize);
+                        .map_err(|_| blob_truncated(position + 1, "raw payload length", 8))?;
+                    current_blob_raw_payload_length =
+                        Some(usize::try_from(l).unwrap_or(usize::MAX));
                 }
                 BlobSectionField::InteriorPadding => {
-                    let padding = reader
-                        .read_u8()
-                        .map_err(|_| "failed reading interior padding field value")?;
+                    let padding = blob_reader.read_u8().map_err(|_| {
+                        blob_truncated(position + 1, "interior padding field value", 1)
+                    })?;
 
                     current_blob_interior_padding = Some(match padding {
                         0x01 => BlobInteriorPadding::None,
                         0x02 => BlobInteriorPadding::Null,
-                        _ => return Err("invalid value for interior padding field"),
+                        _ => {
+                            return Err(ParseError::InvalidInteriorPadding {
+                                offset: HEADER_V3.len() + position as usize + 1,
+                                value: padding,
+                            })
+                        }
                     });
                 }
             }
@@ -612,41 +977,74 @@ fn load_resources_v3<'a>(data: &'a [u8]) -> Result<ResourceParserIterator<'a>, &
     }
 
     if blob_entry_count != blob_section_count {
-        return Err("mismatch between blob sections count");
+        return Err(ParseError::BlobSectionCountMismatch {
+            expected: blob_section_count as usize,
+            actual: blob_entry_count as usize,
+        });
+    }
+
+    if blob_reader.position() as usize != blob_index_end {
+        return Err(ParseError::IndexLengthMismatch {
+            index: "blob",
+            expected: blob_index_length,
+            actual: blob_reader.position() as usize - global_header_length,
+        });
     }
 
     // Array indexing resource field to current payload offset within that section.
     let mut blob_offsets: [Option<BlobSectionReadState>; 256] = [None; 256];
 
-    // Global payload offset where blobs data starts.
-    let blob_start_offset: usize =
-            // Global header.
-            1 + 4 + 4 + 4
-            + blob_index_length
-            + resources_index_length
-        ;
     // Current offset from start of blobs data.
-    let mut current_blob_offset = 0;
+    let mut current_blob_offset = blob_start_offset;
 
     for section in &blob_sections {
-        let section_start_offset = blob_start_offset + current_blob_offset;
-        blob_offsets[section.resource_field as usize] = Some(BlobSectionReadState {
-            offset: section_start_offset,
+        let section_end = current_blob_offset
+            .checked_add(section.raw_payload_length)
+            .filter(|end| *end <= data.len())
+            .ok_or(ParseError::BlobSectionsOutOfBounds {
+                offset: HEADER_V3.len() + blob_start_offset,
+                expected: blob_sections
+                    .iter()
+                    .fold(0usize, |acc, s| acc.saturating_add(s.raw_payload_length)),
+                actual: data.len() - blob_start_offset,
+            })?;
+
+        let state = &mut blob_offsets[section.resource_field as usize];
+
+        if state.is_some() {
+            return Err(ParseError::DuplicateBlobSection {
+                offset: HEADER_V3.len() + global_header_length,
+                field: section.resource_field,
+            });
+        }
+
+        state.replace(BlobSectionReadState {
+            start: current_blob_offset,
+            offset: current_blob_offset,
+            end: section_end,
             interior_padding: match section.interior_padding {
                 Some(padding) => padding,
                 None => BlobInteriorPadding::None,
             },
         });
-        current_blob_offset += section.raw_payload_length;
+        current_blob_offset = section_end;
     }
 
+    // The resources index reader is bounded by the end of the resources index so
+    // a malformed index can't run into blob data.
+    let mut reader = Cursor::new(&data[..blob_start_offset]);
+    reader.set_position(blob_index_end as u64);
+
     Ok(ResourceParserIterator {
-        done: resources_index_length == 0 || resources_count == 0,
+        done: resources_index_length == 0,
         data,
         reader,
         blob_sections: blob_offsets,
+        resources_index_start: blob_index_end,
+        blobs_end: current_blob_offset,
         claimed_resources_count: resources_count,
         read_resources_count: 0,
+        field_lengths: v4,
     })
 }
 
@@ -655,8 +1053,9 @@ mod tests {
     use {
         super::*,
         crate::{
-            resource::Resource, serialization::BlobInteriorPadding,
-            writer::write_packed_resources_v3,
+            resource::Resource,
+            serialization::BlobInteriorPadding,
+            writer::{write_packed_resources_v3, write_packed_resources_v4},
         },
     };
 
@@ -665,18 +1064,18 @@ mod tests {
         let data = b"foo";
 
         let res = load_resources(data);
-        assert_eq!(res.err(), Some("error reading 8 byte header"));
+        assert_eq!(res.err(), Some(ParseError::HeaderTooShort { actual: 3 }));
     }
 
     #[test]
     fn test_unrecognized_header() {
         let data = b"pyembed\x00";
         let res = load_resources(data);
-        assert_eq!(res.err(), Some("unrecognized file format"));
+        assert_eq!(res.err(), Some(ParseError::UnrecognizedHeader));
 
-        let data = b"pyembed\x04";
+        let data = b"pyembed\x05";
         let res = load_resources(data);
-        assert_eq!(res.err(), Some("unrecognized file format"));
+        assert_eq!(res.err(), Some(ParseError::UnrecognizedHeader));
     }
 
     #[test]
@@ -709,7 +1108,11 @@ mod tests {
         let mut res = load_resources(data).unwrap();
         assert_eq!(
             res.next(),
-            Some(Err("mismatch between advertised index count and actual"))
+            Some(Err(ParseError::ResourcesCountMismatch {
+                offset: 21,
+                expected: 1,
+                actual: 0
+            }))
         );
         assert_eq!(res.next(), None);
     }
@@ -719,7 +1122,16 @@ mod tests {
         let data =
             b"pyembed\x03\x00\x01\x00\x00\x00\x01\x00\x00\x00\x03\x00\x00\x00\x00\x01\xff\x00";
         let mut res = load_resources(data).unwrap();
-        assert_eq!(res.next(), Some(Err("resource name field is required")));
+        assert_eq!(
+            res.next(),
+            Some(Err(ParseError::MissingResourceName {
+                offset: 23,
+                resource: ResourceContext {
+                    index: 0,
+                    name: None
+                }
+            }))
+        );
         assert_eq!(res.next(), None);
     }
 
@@ -735,7 +1147,7 @@ mod tests {
 
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -766,7 +1178,7 @@ mod tests {
         write_packed_resources_v3(&[resource1, resource2], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 2);
@@ -812,7 +1224,7 @@ mod tests {
         .unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 2);
@@ -848,7 +1260,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -879,7 +1291,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -913,7 +1325,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -947,7 +1359,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -981,7 +1393,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1025,7 +1437,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1054,7 +1466,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1079,7 +1491,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1115,7 +1527,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1140,7 +1552,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1169,7 +1581,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1198,7 +1610,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1227,7 +1639,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1256,7 +1668,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1291,7 +1703,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1326,7 +1738,7 @@ mod tests {
         write_packed_resources_v3(&[resource], &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1408,10 +1820,10 @@ mod tests {
         };
 
         let mut data = Vec::new();
-        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+        write_packed_resources_v3(std::slice::from_ref(&resource), &mut data, None).unwrap();
         let resources = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources.len(), 1);
@@ -1519,6 +1931,16 @@ mod tests {
             entry.file_data_utf8_relative_path.as_ref().unwrap(),
             "file_data_utf8_relative_path"
         );
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(&[resource], &mut data, None).unwrap();
+        assert_eq!(validate(&data), Ok(1));
+        let resources_v4 = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
+            .unwrap();
+
+        assert_eq!(resources_v4, vec![entry.clone()]);
     }
 
     #[test]
@@ -1542,9 +1964,345 @@ mod tests {
         write_packed_resources_v3(&resources, &mut data, None).unwrap();
         let loaded = load_resources(&data)
             .unwrap()
-            .collect::<Result<Vec<Resource<u8>>, &'static str>>()
+            .collect::<Result<Vec<Resource<u8>>, ParseError>>()
             .unwrap();
 
         assert_eq!(resources, loaded);
     }
+
+    fn all_fields_resources() -> Vec<Resource<'static, u8>> {
+        let mut in_memory_resources = HashMap::new();
+        in_memory_resources.insert(Cow::from("foo"), Cow::from(b"foovalue".to_vec()));
+
+        let mut relative_path_resources = HashMap::new();
+        relative_path_resources.insert(Cow::from("foo.txt"), Cow::from(Path::new("foo.txt")));
+
+        vec![
+            Resource {
+                name: Cow::from("module"),
+                is_python_module: true,
+                is_python_package: true,
+                in_memory_source: Some(Cow::from(b"source".to_vec())),
+                in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+                in_memory_package_resources: Some(in_memory_resources),
+                shared_library_dependency_names: Some(vec![Cow::from("libfoo")]),
+                relative_path_module_source: Some(Cow::from(Path::new("module.py"))),
+                relative_path_package_resources: Some(relative_path_resources),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::from("file.txt"),
+                is_utf8_filename_data: true,
+                file_data_embedded: Some(Cow::from(b"data".to_vec())),
+                file_data_utf8_relative_path: Some(Cow::from("lib/file.txt")),
+                ..Resource::default()
+            },
+        ]
+    }
+
+    /// Produce data exercising every field with null interior padding.
+    fn all_fields_data() -> Vec<u8> {
+        let mut data = Vec::new();
+        write_packed_resources_v3(
+            &all_fields_resources(),
+            &mut data,
+            Some(BlobInteriorPadding::Null),
+        )
+        .unwrap();
+
+        data
+    }
+
+    /// Produce version 4 data exercising every field with null interior padding.
+    fn all_fields_data_v4() -> Vec<u8> {
+        let mut data = Vec::new();
+        write_packed_resources_v4(
+            &all_fields_resources(),
+            &mut data,
+            Some(BlobInteriorPadding::Null),
+        )
+        .unwrap();
+
+        data
+    }
+
+    #[test]
+    fn test_validate_valid() {
+        let mut data = Vec::new();
+        write_packed_resources_v3::<Resource<u8>, _>(&[], &mut data, None).unwrap();
+        assert_eq!(validate(&data), Ok(0));
+
+        assert_eq!(validate(&all_fields_data()), Ok(2));
+    }
+
+    #[test]
+    fn test_validate_trailing_data() {
+        let mut data = all_fields_data();
+        let offset = data.len();
+        data.extend_from_slice(b"extra");
+
+        // Lazy parsing doesn't notice.
+        assert!(load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<_>, ParseError>>()
+            .is_ok());
+
+        assert_eq!(
+            validate(&data),
+            Err(ParseError::TrailingData { offset, length: 5 })
+        );
+    }
+
+    #[test]
+    fn test_invalid_padding() {
+        let mut data = all_fields_data();
+        // The first blob is the first resource's name, followed by a NULL.
+        let offset = data
+            .windows(b"module\0".len())
+            .position(|w| w == b"module\0")
+            .unwrap()
+            + b"module".len();
+        data[offset] = b'x';
+
+        assert_eq!(
+            validate(&data),
+            Err(ParseError::InvalidPadding {
+                offset,
+                resource: ResourceContext {
+                    index: 0,
+                    name: None
+                },
+                field: ResourceField::Name,
+                value: b'x',
+            })
+        );
+    }
+
+    #[test]
+    fn test_invalid_utf8() {
+        let mut data = all_fields_data();
+        let offset = data
+            .windows(b"lib/file.txt".len())
+            .position(|w| w == b"lib/file.txt")
+            .unwrap();
+        data[offset] = 0xff;
+
+        assert_eq!(
+            validate(&data),
+            Err(ParseError::InvalidUtf8 {
+                offset,
+                resource: ResourceContext {
+                    index: 1,
+                    name: Some("file.txt".to_string())
+                },
+                field: ResourceField::FileDataUtf8RelativePath,
+            })
+        );
+    }
+
+    #[test]
+    fn test_blob_out_of_bounds() {
+        let resource = Resource {
+            name: Cow::from("foo"),
+            in_memory_source: Some(Cow::from(b"source".to_vec())),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v3(&[resource], &mut data, None).unwrap();
+
+        // Claim the source is longer than the blob section holding it.
+        let length_offset = data.len() - b"foosource".len() - 2 - 4;
+        data[length_offset] = 7;
+
+        let mut res = load_resources(&data).unwrap();
+        assert_eq!(
+            res.next(),
+            Some(Err(ParseError::BlobOutOfBounds {
+                offset: data.len() - 6,
+                resource: ResourceContext {
+                    index: 0,
+                    name: Some("foo".to_string())
+                },
+                field: ResourceField::InMemorySource,
+                expected: 7,
+                actual: 6,
+            }))
+        );
+        assert_eq!(res.next(), None);
+    }
+
+    #[test]
+    fn test_implausible_resources_count() {
+        let data = b"pyembed\x03\x00\x00\x00\x00\x00\xff\xff\xff\xff\x01\x00\x00\x00\x00";
+        assert_eq!(
+            load_resources(data).err(),
+            Some(ParseError::ImplausibleResourcesCount {
+                count: u32::MAX as usize,
+                index_length: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_v4_round_trip() {
+        let data = all_fields_data_v4();
+        assert_eq!(&data[0..8], HEADER_V4);
+        assert_eq!(validate(&data), Ok(2));
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<_>, ParseError>>()
+            .unwrap();
+        assert_eq!(resources, all_fields_resources());
+
+        let mut data = Vec::new();
+        write_packed_resources_v4::<Resource<u8>, _>(&[], &mut data, None).unwrap();
+        // Global header, then end of blob index + end of resources index.
+        assert_eq!(data.len(), 8 + GLOBAL_HEADER_V4_LENGTH + 2);
+        assert_eq!(validate(&data), Ok(0));
+    }
+
+    /// Produce version 4 data for a resource named `foo` with an unknown field
+    /// holding `field_data` following the name.
+    fn unknown_field_data(field_data: &[u8]) -> Vec<u8> {
+        let resource = Resource::<u8> {
+            name: Cow::from("foo"),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(&[resource], &mut data, None).unwrap();
+
+        // The resources index ends with end of entry + end of index, followed
+        // by the name blob.
+        let insert = data.len() - 5;
+        assert_eq!(&data[insert..], b"\xff\x00foo");
+
+        let mut field = vec![0x7f];
+        field.extend_from_slice(&(field_data.len() as u32).to_le_bytes());
+        field.extend_from_slice(field_data);
+        data.splice(insert..insert, field.iter().copied());
+
+        // Adjust the advertised resources index length.
+        let length = u32::from_le_bytes(data[17..21].try_into().unwrap()) + field.len() as u32;
+        data[17..21].copy_from_slice(&length.to_le_bytes());
+
+        data
+    }
+
+    #[test]
+    fn test_v4_unknown_field_skipped() {
+        let data = unknown_field_data(b"future");
+        assert_eq!(validate(&data), Ok(1));
+
+        let resources = load_resources(&data)
+            .unwrap()
+            .collect::<Result<Vec<_>, ParseError>>()
+            .unwrap();
+        assert_eq!(
+            resources,
+            vec![Resource {
+                name: Cow::from("foo"),
+                ..Resource::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_v4_unknown_field_truncated() {
+        let mut data = unknown_field_data(b"future");
+        let offset = data.len() - 3 - 2 - 6 - 4;
+        assert_eq!(data[offset - 1], 0x7f);
+        // Claim more field data than the resources index holds.
+        data[offset..offset + 4].copy_from_slice(&100u32.to_le_bytes());
+
+        assert_eq!(
+            validate(&data),
+            Err(ParseError::Truncated {
+                offset: offset + 4,
+                resource: Some(ResourceContext {
+                    index: 0,
+                    name: Some("foo".to_string())
+                }),
+                context: "field data",
+                expected: 100,
+                actual: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn test_v4_field_length_mismatch() {
+        let resource = Resource::<u8> {
+            name: Cow::from("foo"),
+            ..Resource::default()
+        };
+
+        let mut data = Vec::new();
+        write_packed_resources_v4(&[resource], &mut data, None).unwrap();
+
+        // Start of entry, then the name field type and its length.
+        let offset = data.len() - 3 - 2 - 2 - 4 - 1;
+        assert_eq!(&data[offset - 1..offset + 5], b"\x01\x03\x02\x00\x00\x00");
+        data[offset + 1] = 0x03;
+
+        assert_eq!(
+            validate(&data),
+            Err(ParseError::FieldLengthMismatch {
+                offset,
+                resource: ResourceContext {
+                    index: 0,
+                    name: Some("foo".to_string())
+                },
+                field: ResourceField::Name,
+                expected: 3,
+                actual: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_v4_unsupported_required_features() {
+        let mut data = Vec::new();
+        write_packed_resources_v4::<Resource<u8>, _>(&[], &mut data, None).unwrap();
+        data[21..29].copy_from_slice(&0x5u64.to_le_bytes());
+
+        let err = load_resources(&data).err().unwrap();
+        assert_eq!(
+            err,
+            ParseError::UnsupportedRequiredFeatures {
+                required: 0x5,
+                supported: SUPPORTED_REQUIRED_FEATURES,
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "data requires unsupported features 0x5 (supported: 0x0)"
+        );
+    }
+
+    #[test]
+    fn test_hostile_input_does_not_panic() {
+        for data in [all_fields_data(), all_fields_data_v4()] {
+            // Every truncation must produce an error.
+            for length in 0..data.len() {
+                assert!(validate(&data[0..length]).is_err(), "length {}", length);
+            }
+
+            // Corrupting any byte must not panic. Corruption in blob data may still
+            // result in valid data.
+            for offset in 0..data.len() {
+                for value in [0x00, 0x01, 0x02, 0x7f, 0x80, 0xff] {
+                    let mut corrupt = data.clone();
+                    corrupt[offset] = value;
+
+                    let _ = validate(&corrupt);
+                    if let Ok(resources) = load_resources(&corrupt) {
+                        for _ in resources {}
+                    }
+                }
+            }
+        }
+    }
 }
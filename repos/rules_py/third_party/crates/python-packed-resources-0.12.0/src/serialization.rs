@@ -11,6 +11,19 @@
 /// Header value for version 2 of resources payload.
 pub const HEADER_V3: &[u8] = b"pyembed\x03";
 
+/// Header value for version 4 of resources payload.
+pub const HEADER_V4: &[u8] = b"pyembed\x04";
+
+/// Bitset of required features this implementation supports.
+///
+/// Version 4 payloads advertise a bitset of features a reader must support
+/// in order to interpret the payload correctly. A payload requiring a feature
+/// not in this set is rejected. Fields not tied to a required feature can be
+/// skipped by readers which don't know about them.
+///
+/// No required features are currently defined.
+pub const SUPPORTED_REQUIRED_FEATURES: u64 = 0;
+
 /// Defines interior padding mechanism between entries in blob sections.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum BlobInteriorPadding {
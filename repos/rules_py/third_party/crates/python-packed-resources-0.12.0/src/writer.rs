@@ -11,11 +11,17 @@
 use {
     crate::{
         resource::Resource,
-        serialization::{BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3},
+        serialization::{
+            BlobInteriorPadding, BlobSectionField, ResourceField, HEADER_V3, HEADER_V4,
+        },
     },
     anyhow::{anyhow, Context, Result},
     byteorder::{LittleEndian, WriteBytesExt},
-    std::{collections::BTreeMap, io::Write, path::Path},
+    std::{
+        collections::BTreeMap,
+        io::{Read, Write},
+        path::{Path, PathBuf},
+    },
 };
 
 #[cfg(unix)]
@@ -51,6 +57,229 @@ fn path_to_bytes(p: &Path) -> Vec<u8> {
     }
 }
 
+/// Identifies a payload held by a [Resource].
+#[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum PayloadKey {
+    /// [Resource::in_memory_source].
+    InMemorySource,
+    /// [Resource::in_memory_bytecode].
+    InMemoryBytecode,
+    /// [Resource::in_memory_bytecode_opt1].
+    InMemoryBytecodeOpt1,
+    /// [Resource::in_memory_bytecode_opt2].
+    InMemoryBytecodeOpt2,
+    /// [Resource::in_memory_extension_module_shared_library].
+    InMemoryExtensionModuleSharedLibrary,
+    /// The named entry in [Resource::in_memory_package_resources].
+    InMemoryPackageResource(String),
+    /// The named entry in [Resource::in_memory_distribution_resources].
+    InMemoryDistributionResource(String),
+    /// [Resource::in_memory_shared_library].
+    InMemorySharedLibrary,
+    /// [Resource::file_data_embedded].
+    FileDataEmbedded,
+}
+
+impl PayloadKey {
+    /// The resource field holding this payload.
+    fn resource_field(&self) -> ResourceField {
+        match self {
+            Self::InMemorySource => ResourceField::InMemorySource,
+            Self::InMemoryBytecode => ResourceField::InMemoryBytecode,
+            Self::InMemoryBytecodeOpt1 => ResourceField::InMemoryBytecodeOpt1,
+            Self::InMemoryBytecodeOpt2 => ResourceField::InMemoryBytecodeOpt2,
+            Self::InMemoryExtensionModuleSharedLibrary => {
+                ResourceField::InMemoryExtensionModuleSharedLibrary
+            }
+            Self::InMemoryPackageResource(_) => ResourceField::InMemoryResourcesData,
+            Self::InMemoryDistributionResource(_) => ResourceField::InMemoryDistributionResource,
+            Self::InMemorySharedLibrary => ResourceField::InMemorySharedLibrary,
+            Self::FileDataEmbedded => ResourceField::FileDataEmbedded,
+        }
+    }
+
+    /// Resolve the in-memory data for this payload in a resource.
+    fn resolve<'r>(&self, resource: &'r Resource<u8>) -> Option<&'r [u8]> {
+        match self {
+            Self::InMemorySource => resource.in_memory_source.as_deref(),
+            Self::InMemoryBytecode => resource.in_memory_bytecode.as_deref(),
+            Self::InMemoryBytecodeOpt1 => resource.in_memory_bytecode_opt1.as_deref(),
+            Self::InMemoryBytecodeOpt2 => resource.in_memory_bytecode_opt2.as_deref(),
+            Self::InMemoryExtensionModuleSharedLibrary => resource
+                .in_memory_extension_module_shared_library
+                .as_deref(),
+            Self::InMemoryPackageResource(name) => resource
+                .in_memory_package_resources
+                .as_ref()
+                .and_then(|resources| resources.get(name.as_str()))
+                .map(|data| data.as_ref()),
+            Self::InMemoryDistributionResource(name) => resource
+                .in_memory_distribution_resources
+                .as_ref()
+                .and_then(|resources| resources.get(name.as_str()))
+                .map(|data| data.as_ref()),
+            Self::InMemorySharedLibrary => resource.in_memory_shared_library.as_deref(),
+            Self::FileDataEmbedded => resource.file_data_embedded.as_deref(),
+        }
+    }
+}
+
+/// A payload whose data is copied from a file when it is written.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FilePayload {
+    /// Path of the file holding the payload data.
+    pub path: PathBuf,
+    /// Length of the payload data in bytes.
+    pub length: usize,
+}
+
+impl FilePayload {
+    /// Construct an instance from a filesystem path.
+    ///
+    /// The length is obtained from the file's metadata. The file is not read.
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path)
+            .with_context(|| format!("obtaining metadata of {}", path.display()))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            length: usize::try_from(metadata.len())
+                .with_context(|| format!("converting length of {} to usize", path.display()))?,
+        })
+    }
+
+    /// Copy the payload data to a writer.
+    ///
+    /// Data is copied in fixed size chunks so memory use is bounded regardless
+    /// of the file size. An error occurs if the file length changed since
+    /// this instance was constructed.
+    fn copy_to<W: Write>(&self, dest: &mut W) -> Result<()> {
+        let fh = std::fs::File::open(&self.path)
+            .with_context(|| format!("opening {}", self.path.display()))?;
+
+        // Read 1 byte beyond the expected length so we can detect growth.
+        let copied = std::io::copy(&mut fh.take(self.length as u64 + 1), dest)
+            .with_context(|| format!("copying {}", self.path.display()))?;
+
+        if copied != self.length as u64 {
+            return Err(anyhow!(
+                "length of {} changed while writing: expected {} bytes",
+                self.path.display(),
+                self.length
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// A [Resource] whose payloads can be copied from files as they are written.
+///
+/// For each entry in `file_payloads`, the corresponding payload in `resource`
+/// must be defined and empty. It acts as a placeholder and the payload data is
+/// copied from the file instead. This allows writing packed resources data
+/// without holding the content of file-backed payloads in memory.
+#[derive(Clone, Debug, Default)]
+pub struct StreamingResource<'a> {
+    /// Resource metadata and in-memory payloads.
+    pub resource: Resource<'a, u8>,
+    /// Payloads whose data is copied from files.
+    pub file_payloads: BTreeMap<PayloadKey, FilePayload>,
+}
+
+impl<'a> From<Resource<'a, u8>> for StreamingResource<'a> {
+    fn from(resource: Resource<'a, u8>) -> Self {
+        Self {
+            resource,
+            file_payloads: BTreeMap::new(),
+        }
+    }
+}
+
+impl<'a> StreamingResource<'a> {
+    /// Ensure every file payload has an empty placeholder in the resource.
+    fn validate_placeholders(&self) -> Result<()> {
+        for key in self.file_payloads.keys() {
+            match key.resolve(&self.resource) {
+                Some([]) => {}
+                Some(_) => {
+                    return Err(anyhow!(
+                        "{:?} payload of {} is backed by a file but holds in-memory data",
+                        key,
+                        self.resource.name
+                    ));
+                }
+                None => {
+                    return Err(anyhow!(
+                        "{:?} payload of {} is backed by a file but not defined in resource",
+                        key,
+                        self.resource.name
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A resource that can be serialized by [write_packed_resources()].
+trait SerializableResource<'a> {
+    fn resource(&self) -> &Resource<'a, u8>;
+
+    fn file_payloads(&self) -> Option<&BTreeMap<PayloadKey, FilePayload>>;
+}
+
+impl<'a, T: AsRef<Resource<'a, u8>>> SerializableResource<'a> for T {
+    fn resource(&self) -> &Resource<'a, u8> {
+        self.as_ref()
+    }
+
+    fn file_payloads(&self) -> Option<&BTreeMap<PayloadKey, FilePayload>> {
+        None
+    }
+}
+
+impl<'a> SerializableResource<'a> for StreamingResource<'a> {
+    fn resource(&self) -> &Resource<'a, u8> {
+        &self.resource
+    }
+
+    fn file_payloads(&self) -> Option<&BTreeMap<PayloadKey, FilePayload>> {
+        if self.file_payloads.is_empty() {
+            None
+        } else {
+            Some(&self.file_payloads)
+        }
+    }
+}
+
+/// Resolve the length of a payload, taking file-backed payloads into account.
+fn payload_length(
+    file_payloads: Option<&BTreeMap<PayloadKey, FilePayload>>,
+    data_length: usize,
+    key: impl FnOnce() -> PayloadKey,
+) -> usize {
+    match file_payloads.and_then(|payloads| payloads.get(&key())) {
+        Some(payload) => payload.length,
+        None => data_length,
+    }
+}
+
+/// Write a payload, copying it from a file if it is backed by one.
+fn write_payload<W: Write>(
+    dest: &mut W,
+    file_payloads: Option<&BTreeMap<PayloadKey, FilePayload>>,
+    data: &[u8],
+    key: impl FnOnce() -> PayloadKey,
+) -> Result<()> {
+    match file_payloads.and_then(|payloads| payloads.get(&key())) {
+        Some(payload) => payload.copy_to(dest),
+        None => Ok(dest.write_all(data)?),
+    }
+}
+
 #[derive(Debug)]
 struct BlobSection {
     resource_field: ResourceField,
@@ -270,6 +499,196 @@ where
         index
     }
 
+    /// Compute length of index entry for version 4 payload format.
+    pub fn index_v4_length(&self) -> usize {
+        // Start of index entry + end of index entry.
+        let mut index = 2;
+
+        for field in self.index_fields() {
+            // Field type + field length + field data.
+            index += 1 + 4 + self.field_index_length(field);
+        }
+
+        index
+    }
+
+    /// Obtain the fields written to the index entry for this resource.
+    ///
+    /// Start and end of entry markers are not included.
+    fn index_fields(&self) -> Vec<ResourceField> {
+        [
+            (ResourceField::Name, true),
+            (ResourceField::IsPythonPackage, self.is_python_package),
+            (
+                ResourceField::IsPythonNamespacePackage,
+                self.is_python_namespace_package,
+            ),
+            (
+                ResourceField::InMemorySource,
+                self.in_memory_source.is_some(),
+            ),
+            (
+                ResourceField::InMemoryBytecode,
+                self.in_memory_bytecode.is_some(),
+            ),
+            (
+                ResourceField::InMemoryBytecodeOpt1,
+                self.in_memory_bytecode_opt1.is_some(),
+            ),
+            (
+                ResourceField::InMemoryBytecodeOpt2,
+                self.in_memory_bytecode_opt2.is_some(),
+            ),
+            (
+                ResourceField::InMemoryExtensionModuleSharedLibrary,
+                self.in_memory_extension_module_shared_library.is_some(),
+            ),
+            (
+                ResourceField::InMemoryResourcesData,
+                self.in_memory_package_resources.is_some(),
+            ),
+            (
+                ResourceField::InMemoryDistributionResource,
+                self.in_memory_distribution_resources.is_some(),
+            ),
+            (
+                ResourceField::InMemorySharedLibrary,
+                self.in_memory_shared_library.is_some(),
+            ),
+            (
+                ResourceField::SharedLibraryDependencyNames,
+                self.shared_library_dependency_names.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemModuleSource,
+                self.relative_path_module_source.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemModuleBytecode,
+                self.relative_path_module_bytecode.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemModuleBytecodeOpt1,
+                self.relative_path_module_bytecode_opt1.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemModuleBytecodeOpt2,
+                self.relative_path_module_bytecode_opt2.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+                self.relative_path_extension_module_shared_library.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemPackageResources,
+                self.relative_path_package_resources.is_some(),
+            ),
+            (
+                ResourceField::RelativeFilesystemDistributionResource,
+                self.relative_path_distribution_resources.is_some(),
+            ),
+            (ResourceField::IsPythonModule, self.is_python_module),
+            (
+                ResourceField::IsPythonBuiltinExtensionModule,
+                self.is_python_builtin_extension_module,
+            ),
+            (
+                ResourceField::IsPythonFrozenModule,
+                self.is_python_frozen_module,
+            ),
+            (
+                ResourceField::IsPythonExtensionModule,
+                self.is_python_extension_module,
+            ),
+            (ResourceField::IsSharedLibrary, self.is_shared_library),
+            (
+                ResourceField::IsUtf8FilenameData,
+                self.is_utf8_filename_data,
+            ),
+            (ResourceField::FileExecutable, self.file_executable),
+            (
+                ResourceField::FileDataEmbedded,
+                self.file_data_embedded.is_some(),
+            ),
+            (
+                ResourceField::FileDataUtf8RelativePath,
+                self.file_data_utf8_relative_path.is_some(),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(field, present)| if present { Some(field) } else { None })
+        .collect()
+    }
+
+    /// Compute the length of a field's data in the resources index.
+    ///
+    /// The field type and field length preceding the data are not part of the
+    /// returned length.
+    pub fn field_index_length(&self, field: ResourceField) -> usize {
+        match field {
+            ResourceField::EndOfIndex => 0,
+            ResourceField::StartOfEntry => 0,
+            ResourceField::EndOfEntry => 0,
+            ResourceField::Name => 2,
+            ResourceField::IsPythonPackage => 0,
+            ResourceField::IsPythonNamespacePackage => 0,
+            ResourceField::InMemorySource => 4,
+            ResourceField::InMemoryBytecode => 4,
+            ResourceField::InMemoryBytecodeOpt1 => 4,
+            ResourceField::InMemoryBytecodeOpt2 => 4,
+            ResourceField::InMemoryExtensionModuleSharedLibrary => 4,
+            ResourceField::InMemoryResourcesData => {
+                // u16 + u64 for resource name and data.
+                4 + 10
+                    * self
+                        .in_memory_package_resources
+                        .as_ref()
+                        .map_or(0, |resources| resources.len())
+            }
+            ResourceField::InMemoryDistributionResource => {
+                4 + 10
+                    * self
+                        .in_memory_distribution_resources
+                        .as_ref()
+                        .map_or(0, |metadata| metadata.len())
+            }
+            ResourceField::InMemorySharedLibrary => 8,
+            ResourceField::SharedLibraryDependencyNames => {
+                2 + 2 * self
+                    .shared_library_dependency_names
+                    .as_ref()
+                    .map_or(0, |names| names.len())
+            }
+            ResourceField::RelativeFilesystemModuleSource => 4,
+            ResourceField::RelativeFilesystemModuleBytecode => 4,
+            ResourceField::RelativeFilesystemModuleBytecodeOpt1 => 4,
+            ResourceField::RelativeFilesystemModuleBytecodeOpt2 => 4,
+            ResourceField::RelativeFilesystemExtensionModuleSharedLibrary => 4,
+            ResourceField::RelativeFilesystemPackageResources => {
+                // u16 + u32 for resource name + path length.
+                4 + 6 * self
+                    .relative_path_package_resources
+                    .as_ref()
+                    .map_or(0, |resources| resources.len())
+            }
+            ResourceField::RelativeFilesystemDistributionResource => {
+                4 + 6 * self
+                    .relative_path_distribution_resources
+                    .as_ref()
+                    .map_or(0, |metadata| metadata.len())
+            }
+            ResourceField::IsPythonModule => 0,
+            ResourceField::IsPythonBuiltinExtensionModule => 0,
+            ResourceField::IsPythonFrozenModule => 0,
+            ResourceField::IsPythonExtensionModule => 0,
+            ResourceField::IsSharedLibrary => 0,
+            ResourceField::IsUtf8FilenameData => 0,
+            ResourceField::FileExecutable => 0,
+            ResourceField::FileDataEmbedded => 8,
+            ResourceField::FileDataUtf8RelativePath => 4,
+        }
+    }
+
     /// Compute the length of a field.
     ///
     /// Interior padding is not part of the returned length.
@@ -587,68 +1006,106 @@ where
 
     /// Write the version 1 index entry for a resource instance.
     pub fn write_index_v1<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.write_index(dest, false, None)
+    }
+
+    /// Write the version 4 index entry for a resource instance.
+    ///
+    /// This is the version 1 entry with the index data length of every field
+    /// following the field type.
+    pub fn write_index_v4<W: Write>(&self, dest: &mut W) -> Result<()> {
+        self.write_index(dest, true, None)
+    }
+
+    fn write_index<W: Write>(
+        &self,
+        dest: &mut W,
+        field_lengths: bool,
+        file_payloads: Option<&BTreeMap<PayloadKey, FilePayload>>,
+    ) -> Result<()> {
+        let write_field = |dest: &mut W, field: ResourceField| -> std::io::Result<()> {
+            dest.write_u8(field.into())?;
+
+            if field_lengths {
+                // Index data for a field is at most a few bytes per entry in a collection
+                // whose count is itself stored in a u32.
+                dest.write_u32::<LittleEndian>(self.field_index_length(field) as u32)?;
+            }
+
+            Ok(())
+        };
+
         let name_len =
             u16::try_from(self.name.as_bytes().len()).context("converting name to u16")?;
 
         dest.write_u8(ResourceField::StartOfEntry.into())
             .context("writing start of index entry")?;
 
-        dest.write_u8(ResourceField::Name.into())
-            .context("writing resource name field")?;
+        write_field(dest, ResourceField::Name).context("writing resource name field")?;
 
         dest.write_u16::<LittleEndian>(name_len)
             .context("writing resource name length")?;
 
         if self.is_python_package {
-            dest.write_u8(ResourceField::IsPythonPackage.into())
+            write_field(dest, ResourceField::IsPythonPackage)
                 .context("writing is_package field")?;
         }
 
         if self.is_python_namespace_package {
-            dest.write_u8(ResourceField::IsPythonNamespacePackage.into())
+            write_field(dest, ResourceField::IsPythonNamespacePackage)
                 .context("writing is_namespace field")?;
         }
 
         if let Some(source) = &self.in_memory_source {
-            let l =
-                u32::try_from(source.len()).context("converting in-memory source length to u32")?;
-            dest.write_u8(ResourceField::InMemorySource.into())
+            let l = u32::try_from(payload_length(file_payloads, source.len(), || {
+                PayloadKey::InMemorySource
+            }))
+            .context("converting in-memory source length to u32")?;
+            write_field(dest, ResourceField::InMemorySource)
                 .context("writing in-memory source length field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory source length")?;
         }
 
         if let Some(bytecode) = &self.in_memory_bytecode {
-            let l = u32::try_from(bytecode.len())
-                .context("converting in-memory bytecode length to u32")?;
-            dest.write_u8(ResourceField::InMemoryBytecode.into())
+            let l = u32::try_from(payload_length(file_payloads, bytecode.len(), || {
+                PayloadKey::InMemoryBytecode
+            }))
+            .context("converting in-memory bytecode length to u32")?;
+            write_field(dest, ResourceField::InMemoryBytecode)
                 .context("writing in-memory bytecode length field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory bytecode length")?;
         }
 
         if let Some(bytecode) = &self.in_memory_bytecode_opt1 {
-            let l = u32::try_from(bytecode.len())
-                .context("converting in-memory bytecode opt 1 length to u32")?;
-            dest.write_u8(ResourceField::InMemoryBytecodeOpt1.into())
+            let l = u32::try_from(payload_length(file_payloads, bytecode.len(), || {
+                PayloadKey::InMemoryBytecodeOpt1
+            }))
+            .context("converting in-memory bytecode opt 1 length to u32")?;
+            write_field(dest, ResourceField::InMemoryBytecodeOpt1)
                 .context("writing in-memory bytecode opt 1 length field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory bytecode opt 1 length")?;
         }
 
         if let Some(bytecode) = &self.in_memory_bytecode_opt2 {
-            let l = u32::try_from(bytecode.len())
-                .context("converting in-memory bytecode opt 2 length to u32")?;
-            dest.write_u8(ResourceField::InMemoryBytecodeOpt2.into())
+            let l = u32::try_from(payload_length(file_payloads, bytecode.len(), || {
+                PayloadKey::InMemoryBytecodeOpt2
+            }))
+            .context("converting in-memory bytecode opt 2 length to u32")?;
+            write_field(dest, ResourceField::InMemoryBytecodeOpt2)
                 .context("writing in-memory bytecode opt 2 field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory bytecode opt 2 length")?;
         }
 
         if let Some(library) = &self.in_memory_extension_module_shared_library {
-            let l = u32::try_from(library.len())
-                .context("converting in-memory library length to u32")?;
-            dest.write_u8(ResourceField::InMemoryExtensionModuleSharedLibrary.into())
+            let l = u32::try_from(payload_length(file_payloads, library.len(), || {
+                PayloadKey::InMemoryExtensionModuleSharedLibrary
+            }))
+            .context("converting in-memory library length to u32")?;
+            write_field(dest, ResourceField::InMemoryExtensionModuleSharedLibrary)
                 .context("writing in-memory extension module shared library field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory extension module shared library length")?;
@@ -657,7 +1114,7 @@ where
         if let Some(resources) = &self.in_memory_package_resources {
             let l = u32::try_from(resources.len())
                 .context("converting in-memory resources data length to u32")?;
-            dest.write_u8(ResourceField::InMemoryResourcesData.into())
+            write_field(dest, ResourceField::InMemoryResourcesData)
                 .context("writing in-memory resources field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory resources data length")?;
@@ -667,7 +1124,10 @@ where
                     .context("converting resource name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing resource name length")?;
-                dest.write_u64::<LittleEndian>(value.len() as u64)
+                let l = payload_length(file_payloads, value.len(), || {
+                    PayloadKey::InMemoryPackageResource(name.to_string())
+                });
+                dest.write_u64::<LittleEndian>(l as u64)
                     .context("writing resource data length")?;
             }
         }
@@ -675,7 +1135,7 @@ where
         if let Some(metadata) = &self.in_memory_distribution_resources {
             let l = u32::try_from(metadata.len())
                 .context("converting in-memory distribution metadata length to u32")?;
-            dest.write_u8(ResourceField::InMemoryDistributionResource.into())
+            write_field(dest, ResourceField::InMemoryDistributionResource)
                 .context("writing in-memory package distribution field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing in-memory package distribution length")?;
@@ -685,15 +1145,20 @@ where
                     .context("converting distribution name length to u16")?;
                 dest.write_u16::<LittleEndian>(name_length)
                     .context("writing distribution name length")?;
-                dest.write_u64::<LittleEndian>(value.len() as u64)
+                let l = payload_length(file_payloads, value.len(), || {
+                    PayloadKey::InMemoryDistributionResource(name.to_string())
+                });
+                dest.write_u64::<LittleEndian>(l as u64)
                     .context("writing distribution data length")?;
             }
         }
 
         if let Some(library) = &self.in_memory_shared_library {
-            let l = u64::try_from(library.len())
-                .context("converting in-memory shared library length to u64")?;
-            dest.write_u8(ResourceField::InMemorySharedLibrary.into())
+            let l = u64::try_from(payload_length(file_payloads, library.len(), || {
+                PayloadKey::InMemorySharedLibrary
+            }))
+            .context("converting in-memory shared library length to u64")?;
+            write_field(dest, ResourceField::InMemorySharedLibrary)
                 .context("writing in-memory shared library field")?;
             dest.write_u64::<LittleEndian>(l)
                 .context("writing in-memory shared library length")?;
@@ -702,7 +1167,7 @@ where
         if let Some(names) = &self.shared_library_dependency_names {
             let l = u16::try_from(names.len())
                 .context("converting shared library dependency names to u16")?;
-            dest.write_u8(ResourceField::SharedLibraryDependencyNames.into())
+            write_field(dest, ResourceField::SharedLibraryDependencyNames)
                 .context("writing shared library dependency names field")?;
             dest.write_u16::<LittleEndian>(l)
                 .context("writing shared library dependency names length")?;
@@ -718,7 +1183,7 @@ where
         if let Some(path) = &self.relative_path_module_source {
             let l = u32::try_from(path_bytes_length(path))
                 .context("converting module source relative path length to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemModuleSource.into())
+            write_field(dest, ResourceField::RelativeFilesystemModuleSource)
                 .context("writing relative path module source field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module source length")?;
@@ -727,7 +1192,7 @@ where
         if let Some(path) = &self.relative_path_module_bytecode {
             let l = u32::try_from(path_bytes_length(path))
                 .context("converting module bytecode relative path to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemModuleBytecode.into())
+            write_field(dest, ResourceField::RelativeFilesystemModuleBytecode)
                 .context("writing relative path module bytecode field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module bytecode length")?;
@@ -736,7 +1201,7 @@ where
         if let Some(path) = &self.relative_path_module_bytecode_opt1 {
             let l = u32::try_from(path_bytes_length(path))
                 .context("converting module bytecode opt1 relative path to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemModuleBytecodeOpt1.into())
+            write_field(dest, ResourceField::RelativeFilesystemModuleBytecodeOpt1)
                 .context("writing relative path module bytecode opt1 field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module bytecode opt1 length")?;
@@ -745,7 +1210,7 @@ where
         if let Some(path) = &self.relative_path_module_bytecode_opt2 {
             let l = u32::try_from(path_bytes_length(path))
                 .context("converting module bytecode opt2 relative path to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemModuleBytecodeOpt2.into())
+            write_field(dest, ResourceField::RelativeFilesystemModuleBytecodeOpt2)
                 .context("writing relative path module bytecode opt2 field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path module bytecode opt2 length")?;
@@ -754,8 +1219,11 @@ where
         if let Some(path) = &self.relative_path_extension_module_shared_library {
             let l = u32::try_from(path_bytes_length(path))
                 .context("converting extension module shared library relative path to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemExtensionModuleSharedLibrary.into())
-                .context("writing relative path extension module shared library field")?;
+            write_field(
+                dest,
+                ResourceField::RelativeFilesystemExtensionModuleSharedLibrary,
+            )
+            .context("writing relative path extension module shared library field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path extension module shared library length")?;
         }
@@ -763,7 +1231,7 @@ where
         if let Some(resources) = &self.relative_path_package_resources {
             let l = u32::try_from(resources.len())
                 .context("converting relative path resources data length to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemPackageResources.into())
+            write_field(dest, ResourceField::RelativeFilesystemPackageResources)
                 .context("writing relative path resources resources field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path resources resources data length")?;
@@ -783,7 +1251,7 @@ where
         if let Some(metadata) = &self.relative_path_distribution_resources {
             let l = u32::try_from(metadata.len())
                 .context("converting relative path distribution length to u32")?;
-            dest.write_u8(ResourceField::RelativeFilesystemDistributionResource.into())
+            write_field(dest, ResourceField::RelativeFilesystemDistributionResource)
                 .context("writing relative path resources resources field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing relative path distribution data length")?;
@@ -801,44 +1269,45 @@ where
         }
 
         if self.is_python_module {
-            dest.write_u8(ResourceField::IsPythonModule.into())
-                .context("writing is_module field")?;
+            write_field(dest, ResourceField::IsPythonModule).context("writing is_module field")?;
         }
 
         if self.is_python_builtin_extension_module {
-            dest.write_u8(ResourceField::IsPythonBuiltinExtensionModule.into())
+            write_field(dest, ResourceField::IsPythonBuiltinExtensionModule)
                 .context("writing is_builtin_extension_module field")?;
         }
 
         if self.is_python_frozen_module {
-            dest.write_u8(ResourceField::IsPythonFrozenModule.into())
+            write_field(dest, ResourceField::IsPythonFrozenModule)
                 .context("writing is_frozen_module field")?;
         }
 
         if self.is_python_extension_module {
-            dest.write_u8(ResourceField::IsPythonExtensionModule.into())
+            write_field(dest, ResourceField::IsPythonExtensionModule)
                 .context("writing is_extension_module field")?;
         }
 
         if self.is_shared_library {
-            dest.write_u8(ResourceField::IsSharedLibrary.into())
+            write_field(dest, ResourceField::IsSharedLibrary)
                 .context("writing is_shared_library field")?;
         }
 
         if self.is_utf8_filename_data {
-            dest.write_u8(ResourceField::IsUtf8FilenameData.into())
+            write_field(dest, ResourceField::IsUtf8FilenameData)
                 .context("writing is_utf8_filename_data field")?;
         }
 
         if self.file_executable {
-            dest.write_u8(ResourceField::FileExecutable.into())
+            write_field(dest, ResourceField::FileExecutable)
                 .context("writing file_executable field")?;
         }
 
         if let Some(data) = &self.file_data_embedded {
-            let l =
-                u64::try_from(data.len()).context("converting embedded file data length to u64")?;
-            dest.write_u8(ResourceField::FileDataEmbedded.into())
+            let l = u64::try_from(payload_length(file_payloads, data.len(), || {
+                PayloadKey::FileDataEmbedded
+            }))
+            .context("converting embedded file data length to u64")?;
+            write_field(dest, ResourceField::FileDataEmbedded)
                 .context("writing file_data_embedded field")?;
             dest.write_u64::<LittleEndian>(l)
                 .context("writing file_data_embedded length")?;
@@ -847,7 +1316,7 @@ where
         if let Some(path) = &self.file_data_utf8_relative_path {
             let l = u32::try_from(path.as_bytes().len())
                 .context("converting embedded file data relative path length to u32")?;
-            dest.write_u8(ResourceField::FileDataUtf8RelativePath.into())
+            write_field(dest, ResourceField::FileDataUtf8RelativePath)
                 .context("writing file_data_utf8_relative_path field")?;
             dest.write_u32::<LittleEndian>(l)
                 .context("writing file_data_utf_relative_path field")?;
@@ -861,11 +1330,50 @@ where
 }
 
 /// Write packed resources data, version 3.
-#[allow(clippy::cognitive_complexity)]
 pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     resources: &[T],
     dest: &mut W,
     interior_padding: Option<BlobInteriorPadding>,
+) -> Result<()> {
+    write_packed_resources(resources, dest, interior_padding, false)
+}
+
+/// Write packed resources data, version 4.
+///
+/// Version 4 is version 3 plus a required features bitset in the global header
+/// and a length for every field in the resources index. Readers use the
+/// lengths to skip fields they don't know about.
+pub fn write_packed_resources_v4<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
+    resources: &[T],
+    dest: &mut W,
+    interior_padding: Option<BlobInteriorPadding>,
+) -> Result<()> {
+    write_packed_resources(resources, dest, interior_padding, true)
+}
+
+/// Write packed resources data, version 4, streaming file-backed payloads.
+///
+/// This is like [write_packed_resources_v4()] except payloads backed by files are
+/// copied into `dest` in chunks instead of being held in memory. The index is
+/// computed from the lengths recorded in each [FilePayload].
+pub fn write_packed_resources_v4_streaming<W: Write>(
+    resources: &[StreamingResource],
+    dest: &mut W,
+    interior_padding: Option<BlobInteriorPadding>,
+) -> Result<()> {
+    for resource in resources {
+        resource.validate_placeholders()?;
+    }
+
+    write_packed_resources(resources, dest, interior_padding, true)
+}
+
+#[allow(clippy::cognitive_complexity)]
+fn write_packed_resources<'a, T: SerializableResource<'a>, W: Write>(
+    resources: &[T],
+    dest: &mut W,
+    interior_padding: Option<BlobInteriorPadding>,
+    v4: bool,
 ) -> Result<()> {
     let mut blob_sections = BTreeMap::new();
 
@@ -876,16 +1384,9 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     // 1 for end of index field.
     let mut resource_index_length = 1;
 
-    let process_field = |blob_sections: &mut BTreeMap<ResourceField, BlobSection>,
-                         resource: &Resource<u8>,
-                         field: ResourceField| {
-        let padding = match &interior_padding {
-            Some(padding) => *padding,
-            None => BlobInteriorPadding::None,
-        };
-
-        let l = resource.field_blob_length(field)
-            + resource.field_blob_interior_padding_length(field, padding);
+    let add_section_length = |blob_sections: &mut BTreeMap<ResourceField, BlobSection>,
+                              field: ResourceField,
+                              l: usize| {
         if l > 0 {
             blob_sections
                 .entry(field)
@@ -898,6 +1399,22 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         }
     };
 
+    let process_field = |blob_sections: &mut BTreeMap<ResourceField, BlobSection>,
+                         resource: &Resource<u8>,
+                         field: ResourceField| {
+        let padding = match &interior_padding {
+            Some(padding) => *padding,
+            None => BlobInteriorPadding::None,
+        };
+
+        add_section_length(
+            blob_sections,
+            field,
+            resource.field_blob_length(field)
+                + resource.field_blob_interior_padding_length(field, padding),
+        );
+    };
+
     let add_interior_padding = |dest: &mut W| -> Result<()> {
         if interior_padding == Some(BlobInteriorPadding::Null) {
             dest.write_all(b"\0")?;
@@ -907,8 +1424,20 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     };
 
     for resource in resources {
-        let resource = resource.as_ref();
-        resource_index_length += resource.index_v1_length();
+        // Placeholders for file-backed payloads are empty. So their data needs
+        // to be accounted for separately.
+        if let Some(file_payloads) = resource.file_payloads() {
+            for (key, payload) in file_payloads {
+                add_section_length(&mut blob_sections, key.resource_field(), payload.length);
+            }
+        }
+
+        let resource = resource.resource();
+        resource_index_length += if v4 {
+            resource.index_v4_length()
+        } else {
+            resource.index_v1_length()
+        };
 
         process_field(&mut blob_sections, resource, ResourceField::Name);
         process_field(&mut blob_sections, resource, ResourceField::InMemorySource);
@@ -1004,13 +1533,18 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
         blob_index_length += section.index_v1_length();
     }
 
-    dest.write_all(HEADER_V3)?;
+    dest.write_all(if v4 { HEADER_V4 } else { HEADER_V3 })?;
 
     dest.write_u8(blob_section_count)?;
     dest.write_u32::<LittleEndian>(blob_index_length as u32)?;
     dest.write_u32::<LittleEndian>(resources.len() as u32)?;
     dest.write_u32::<LittleEndian>(resource_index_length as u32)?;
 
+    if v4 {
+        // None of the fields we write require reader support beyond version 4.
+        dest.write_u64::<LittleEndian>(0)?;
+    }
+
     // Write the blob index.
     for section in blob_sections.values() {
         section.write_index_v1(dest)?;
@@ -1019,82 +1553,103 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     // Write the resources index.
     for resource in resources {
-        resource.as_ref().write_index_v1(dest)?;
+        resource
+            .resource()
+            .write_index(dest, v4, resource.file_payloads())?;
     }
     dest.write_u8(ResourceField::EndOfIndex.into())?;
 
     // Write blob data, one field at a time.
     for resource in resources {
-        dest.write_all(resource.as_ref().name.as_bytes())?;
+        dest.write_all(resource.resource().name.as_bytes())?;
         add_interior_padding(dest)?;
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_source {
-            dest.write_all(data)?;
+        if let Some(data) = &resource.resource().in_memory_source {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::InMemorySource
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_bytecode {
-            dest.write_all(data)?;
+        if let Some(data) = &resource.resource().in_memory_bytecode {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::InMemoryBytecode
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_bytecode_opt1 {
-            dest.write_all(data)?;
+        if let Some(data) = &resource.resource().in_memory_bytecode_opt1 {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::InMemoryBytecodeOpt1
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_bytecode_opt2 {
-            dest.write_all(data)?;
+        if let Some(data) = &resource.resource().in_memory_bytecode_opt2 {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::InMemoryBytecodeOpt2
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_extension_module_shared_library {
-            dest.write_all(data)?;
+        if let Some(data) = &resource
+            .resource()
+            .in_memory_extension_module_shared_library
+        {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::InMemoryExtensionModuleSharedLibrary
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(resources) = &resource.as_ref().in_memory_package_resources {
+        if let Some(resources) = &resource.resource().in_memory_package_resources {
             for (key, value) in resources.iter() {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
-                dest.write_all(value)?;
+                write_payload(dest, resource.file_payloads(), value, || {
+                    PayloadKey::InMemoryPackageResource(key.to_string())
+                })?;
                 add_interior_padding(dest)?;
             }
         }
     }
 
     for resource in resources {
-        if let Some(resources) = &resource.as_ref().in_memory_distribution_resources {
+        if let Some(resources) = &resource.resource().in_memory_distribution_resources {
             for (key, value) in resources {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
-                dest.write_all(value)?;
+                write_payload(dest, resource.file_payloads(), value, || {
+                    PayloadKey::InMemoryDistributionResource(key.to_string())
+                })?;
                 add_interior_padding(dest)?;
             }
         }
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().in_memory_shared_library {
-            dest.write_all(data)?;
+        if let Some(data) = &resource.resource().in_memory_shared_library {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::InMemorySharedLibrary
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(names) = &resource.as_ref().shared_library_dependency_names {
+        if let Some(names) = &resource.resource().shared_library_dependency_names {
             for name in names {
                 dest.write_all(name.as_bytes())?;
                 add_interior_padding(dest)?;
@@ -1103,28 +1658,28 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     }
 
     for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_source {
+        if let Some(path) = &resource.resource().relative_path_module_source {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_bytecode {
+        if let Some(path) = &resource.resource().relative_path_module_bytecode {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_bytecode_opt1 {
+        if let Some(path) = &resource.resource().relative_path_module_bytecode_opt1 {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(path) = &resource.as_ref().relative_path_module_bytecode_opt2 {
+        if let Some(path) = &resource.resource().relative_path_module_bytecode_opt2 {
             dest.write_all(&path_to_bytes(path))?;
             add_interior_padding(dest)?;
         }
@@ -1132,7 +1687,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
 
     for resource in resources {
         if let Some(path) = &resource
-            .as_ref()
+            .resource()
             .relative_path_extension_module_shared_library
         {
             dest.write_all(&path_to_bytes(path))?;
@@ -1141,7 +1696,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     }
 
     for resource in resources {
-        if let Some(resources) = &resource.as_ref().relative_path_package_resources {
+        if let Some(resources) = &resource.resource().relative_path_package_resources {
             for (key, path) in resources.iter() {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
@@ -1152,7 +1707,7 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     }
 
     for resource in resources {
-        if let Some(resources) = &resource.as_ref().relative_path_distribution_resources {
+        if let Some(resources) = &resource.resource().relative_path_distribution_resources {
             for (key, path) in resources {
                 dest.write_all(key.as_bytes())?;
                 add_interior_padding(dest)?;
@@ -1163,14 +1718,16 @@ pub fn write_packed_resources_v3<'a, T: AsRef<Resource<'a, u8>>, W: Write>(
     }
 
     for resource in resources {
-        if let Some(data) = &resource.as_ref().file_data_embedded {
-            dest.write_all(data)?;
+        if let Some(data) = &resource.resource().file_data_embedded {
+            write_payload(dest, resource.file_payloads(), data, || {
+                PayloadKey::FileDataEmbedded
+            })?;
             add_interior_padding(dest)?;
         }
     }
 
     for resource in resources {
-        if let Some(path) = &resource.as_ref().file_data_utf8_relative_path {
+        if let Some(path) = &resource.resource().file_data_utf8_relative_path {
             dest.write_all(path.as_bytes())?;
             add_interior_padding(dest)?;
         }
@@ -1245,6 +1802,104 @@ mod tests {
 
         assert_eq!(data, expected);
 
+        Ok(())
+    }
+    /// Write `data` to a file in a temporary directory unique to this process.
+    fn temp_file(name: &str, data: &[u8]) -> Result<PathBuf> {
+        let path = std::env::temp_dir().join(format!(
+            "python-packed-resources-{}-{}",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, data)?;
+
+        Ok(path)
+    }
+
+    #[test]
+    fn test_write_streaming() -> Result<()> {
+        let source_path = temp_file("streaming-source", b"import os")?;
+        let resource_path = temp_file("streaming-resource", b"resource data")?;
+        let embedded_path = temp_file("streaming-embedded", &[0x42; 100_000])?;
+
+        let mut package_resources = std::collections::HashMap::new();
+        package_resources.insert(Cow::from("memory.txt"), Cow::from(b"memory".to_vec()));
+        package_resources.insert(Cow::from("file.txt"), Cow::from(b"resource data".to_vec()));
+
+        let in_memory = vec![
+            Resource {
+                name: Cow::from("foo"),
+                is_python_module: true,
+                in_memory_source: Some(Cow::from(b"import os".to_vec())),
+                in_memory_bytecode: Some(Cow::from(b"bytecode".to_vec())),
+                in_memory_package_resources: Some(package_resources.clone()),
+                ..Resource::default()
+            },
+            Resource {
+                name: Cow::from("file.bin"),
+                file_data_embedded: Some(Cow::from(vec![0x42; 100_000])),
+                ..Resource::default()
+            },
+        ];
+
+        package_resources.insert(Cow::from("file.txt"), Cow::from(vec![]));
+
+        let streaming = vec![
+            StreamingResource {
+                resource: Resource {
+                    in_memory_source: Some(Cow::from(vec![])),
+                    in_memory_package_resources: Some(package_resources),
+                    ..in_memory[0].clone()
+                },
+                file_payloads: [
+                    (PayloadKey::InMemorySource, FilePayload::new(&source_path)?),
+                    (
+                        PayloadKey::InMemoryPackageResource("file.txt".to_string()),
+                        FilePayload::new(&resource_path)?,
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            },
+            StreamingResource {
+                resource: Resource {
+                    file_data_embedded: Some(Cow::from(vec![])),
+                    ..in_memory[1].clone()
+                },
+                file_payloads: [(
+                    PayloadKey::FileDataEmbedded,
+                    FilePayload::new(&embedded_path)?,
+                )]
+                .into_iter()
+                .collect(),
+            },
+        ];
+
+        for padding in [None, Some(BlobInteriorPadding::Null)] {
+            let mut expected = Vec::new();
+            write_packed_resources_v4(&in_memory, &mut expected, padding)?;
+
+            let mut data = Vec::new();
+            write_packed_resources_v4_streaming(&streaming, &mut data, padding)?;
+
+            assert_eq!(data, expected);
+        }
+
+        // Files changing length after being indexed are detected.
+        std::fs::write(&source_path, b"import sys")?;
+        let mut data = Vec::new();
+        assert!(write_packed_resources_v4_streaming(&streaming, &mut data, None).is_err());
+
+        // Placeholders must be empty.
+        let mut invalid = streaming[0].clone();
+        invalid.resource.in_memory_source = Some(Cow::from(b"import os".to_vec()));
+        let mut data = Vec::new();
+        assert!(write_packed_resources_v4_streaming(&[invalid], &mut data, None).is_err());
+
+        for path in [source_path, resource_path, embedded_path] {
+            std::fs::remove_file(path)?;
+        }
+
         Ok(())
     }
 }